@@ -0,0 +1,82 @@
+//! ANN index vs. the linear scan `LocalVectorMemory::search` used before it.
+//!
+//! Run with `cargo bench --bench memory_search`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+use rust_agency::memory::entry::MemorySource;
+use rust_agency::memory::hnsw::DEFAULT_EF_SEARCH;
+use rust_agency::memory::{HnswIndex, IndexFilter, MemoryEntry};
+use std::collections::HashMap;
+
+/// all-MiniLM-L6-v2 embedding width
+const DIMS: usize = 384;
+const TOP_K: usize = 10;
+
+fn random_unit(rng: &mut StdRng) -> Vec<f32> {
+    let mut v: Vec<f32> = (0..DIMS).map(|_| rng.gen_range(-1.0..1.0)).collect();
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    v.iter_mut().for_each(|x| *x /= norm);
+    v
+}
+
+fn entries(n: usize, rng: &mut StdRng) -> Vec<MemoryEntry> {
+    (0..n)
+        .map(|i| {
+            let mut entry = MemoryEntry::new(format!("Memory {} {}", i, "lorem ipsum ".repeat(40)), "bench", MemorySource::User);
+            entry.metadata.context = if i % 4 == 0 { "Project".to_string() } else { "General".to_string() };
+            entry.embedding = Some(random_unit(rng));
+            entry
+        })
+        .collect()
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
+/// The pre-index search: score and clone every matching entry, sort, truncate
+fn linear_scan(entries: &[MemoryEntry], query: &[f32], context: Option<&str>) -> Vec<MemoryEntry> {
+    let mut all: Vec<(f32, MemoryEntry)> = entries
+        .par_iter()
+        .filter(|e| context.is_none_or(|c| e.metadata.context == c))
+        .filter_map(|e| e.embedding.as_ref().map(|emb| (dot(query, emb), e.clone())))
+        .collect();
+    all.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+    all.into_iter().take(TOP_K).map(|(_, e)| e).collect()
+}
+
+/// Index walk plus materializing only the hits, as `LocalVectorMemory::search` does
+fn indexed(index: &HnswIndex, entries: &[MemoryEntry], query: &[f32], context: Option<&str>) -> Vec<MemoryEntry> {
+    let hits = index.search(query, TOP_K, DEFAULT_EF_SEARCH, &IndexFilter { context, ..Default::default() }, &());
+    let mut scores: HashMap<&str, f32> = hits.iter().map(|(id, s)| (id.as_str(), *s)).collect();
+    entries.iter().filter(|e| scores.remove(e.id.as_str()).is_some()).cloned().collect()
+}
+
+fn bench_search(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(42);
+    let mut group = c.benchmark_group("memory_search");
+    group.sample_size(20);
+
+    for n in [1_000, 10_000, 50_000] {
+        let entries = entries(n, &mut rng);
+        let index = HnswIndex::build(&entries);
+        let queries: Vec<Vec<f32>> = (0..32).map(|_| random_unit(&mut rng)).collect();
+
+        for (label, context) in [("all", None), ("context", Some("Project"))] {
+            group.bench_with_input(BenchmarkId::new(format!("linear_scan/{}", label), n), &n, |b, _| {
+                let mut q = queries.iter().cycle();
+                b.iter(|| black_box(linear_scan(&entries, q.next().unwrap(), context)))
+            });
+            group.bench_with_input(BenchmarkId::new(format!("hnsw/{}", label), n), &n, |b, _| {
+                let mut q = queries.iter().cycle();
+                b.iter(|| black_box(indexed(&index, &entries, q.next().unwrap(), context)))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_search);
+criterion_main!(benches);
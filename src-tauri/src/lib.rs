@@ -9,7 +9,7 @@ use rust_agency::tools::{
     ToolRegistry, WebSearchTool, CodeExecTool, MemoryQueryTool, 
     KnowledgeGraphTool, ArtifactTool, SandboxTool, CodebaseTool, 
     SystemTool, ForgeTool, VisualizationTool, 
-    SpeakerRsTool, ScienceTool, VisionTool, ModelManager
+    SpeakerRsTool, ScienceTool, VisionTool, ModelManager, DEFAULT_ARTIFACT_DIR
 };
 
 struct AgencyState {
@@ -143,7 +143,10 @@ pub fn run() {
             tools.register_instance(CodeExecTool::new()).await;
             tools.register_instance(MemoryQueryTool::new(memory.clone())).await;
             tools.register_instance(KnowledgeGraphTool::new(memory.clone())).await;
-            tools.register_instance(ArtifactTool::default()).await;
+            match ArtifactTool::new(DEFAULT_ARTIFACT_DIR) {
+                Ok(artifacts) => tools.register_instance(artifacts).await,
+                Err(e) => eprintln!("Artifacts unavailable: {:#}", e),
+            }
             tools.register_instance(SandboxTool::default()).await;
             tools.register_instance(CodebaseTool::default()).await;
             tools.register_instance(ModelManager).await;
@@ -6,7 +6,7 @@ use tracing::{info, warn};
 use crate::agent::{ReActAgent, ReActStep, AgentConfig, AgentType, AgentResponse, Agent, LLMCache, LLMProvider, OllamaProvider, CachedProvider, NQDPortfolio};
 use crate::agent::rl::{Experience, ExperienceBuffer};
 use crate::orchestrator::profile::AgencyProfile;
-use crate::orchestrator::{Objective, MethodDescription, AutonomyLedger};
+use crate::orchestrator::{Objective, MethodDescription, AutonomyLedger, SessionUsage};
 use crate::orchestrator::aggregation::{RewardModel, Candidate};
 use crate::tools::ToolRegistry;
 
@@ -82,6 +82,11 @@ impl AutonomousMachine {
         self
     }
 
+    pub fn with_usage_meter(mut self, meter: SessionUsage) -> Self {
+        self.agent = self.agent.with_usage_meter(meter);
+        self
+    }
+
     pub fn get_method_id(&self) -> String {
         self.method.id.clone()
     }
@@ -169,7 +174,13 @@ impl AutonomousMachine {
         });
 
         // FPF Integration: Update Ledger and Portfolio
-        self.autonomy_ledger.record_tokens(final_query.len() as u32 / 4 + response.answer.len() as u32 / 4);
+        // Backends that report no usage fall back to the ~4 chars/token estimate
+        let spent = if response.cost_tokens > 0 {
+            response.cost_tokens
+        } else {
+            final_query.len() as u32 / 4 + response.answer.len() as u32 / 4
+        };
+        self.autonomy_ledger.record_tokens(spent);
         
         for step in &response.steps {
             for _ in &step.actions {
@@ -1,66 +1,333 @@
 //! LLM Response Cache
-//! 
-//! Provides a simple in-memory cache for LLM responses to avoid redundant computations.
+//!
+//! Caches LLM responses in SQLite to avoid redundant computations across runs.
+//! Entries are bounded by count and size (least recently used go first), expire
+//! after a per-model TTL, and keep streamed chunks so cached streams replay as streams.
 
 use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
 use async_trait::async_trait;
-use crate::agent::LLMProvider;
+use tracing::warn;
+use crate::agent::{ChatCompletion, ChatMessage, ChatStream, GenerationOptions, LLMProvider, ToolCompletion};
+use crate::tools::ToolDefinition;
 use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct CacheKey {
-    model: String,
-    prompt_hash: [u8; 32],
-    system_hash: [u8; 32],
+/// Limits and expiry rules for the cache
+#[derive(Debug, Clone)]
+pub struct CachePolicy {
+    /// Maximum number of entries kept
+    pub max_entries: usize,
+    /// Maximum total size of cached responses in bytes
+    pub max_bytes: u64,
+    /// Expiry for models without an entry in `model_ttls` (`None` = never)
+    pub default_ttl: Option<Duration>,
+    /// Per-model expiry overrides
+    pub model_ttls: HashMap<String, Duration>,
+    /// Calls sampled above this temperature without a fixed seed are not
+    /// reproducible, so they bypass the cache
+    pub max_temperature: f32,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self {
+            max_entries: 10_000,
+            max_bytes: 64 * 1024 * 1024,
+            default_ttl: Some(Duration::from_secs(7 * 24 * 3600)),
+            model_ttls: HashMap::new(),
+            max_temperature: 0.0,
+        }
+    }
+}
+
+impl CachePolicy {
+    /// Default policy adjusted by `AGENCY_LLM_CACHE_MAX_TEMPERATURE` and
+    /// `AGENCY_LLM_CACHE_TTLS` (`model=seconds,...`)
+    pub fn from_env() -> Self {
+        let mut policy = Self::default();
+        if let Ok(value) = std::env::var("AGENCY_LLM_CACHE_MAX_TEMPERATURE") {
+            match value.trim().parse::<f32>() {
+                Ok(max_temperature) => policy.max_temperature = max_temperature,
+                Err(_) => warn!("Ignoring invalid AGENCY_LLM_CACHE_MAX_TEMPERATURE '{}'", value),
+            }
+        }
+        if let Ok(spec) = std::env::var("AGENCY_LLM_CACHE_TTLS") {
+            policy.model_ttls.extend(parse_model_ttls(&spec));
+        }
+        policy
+    }
+
+    pub fn with_max_temperature(mut self, max_temperature: f32) -> Self {
+        self.max_temperature = max_temperature;
+        self
+    }
+
+    pub fn with_model_ttl(mut self, model: impl Into<String>, ttl: Duration) -> Self {
+        self.model_ttls.insert(model.into(), ttl);
+        self
+    }
+
+    fn ttl(&self, model: &str) -> Option<Duration> {
+        self.model_ttls.get(model).copied().or(self.default_ttl)
+    }
+
+    /// Whether a call with these options is deterministic enough to cache
+    pub fn is_cacheable(&self, options: &GenerationOptions) -> bool {
+        match options.temperature {
+            Some(t) => t <= self.max_temperature || options.seed.is_some(),
+            None => true,
+        }
+    }
 }
 
+/// Parse `model=seconds` pairs; malformed entries are skipped with a warning
+fn parse_model_ttls(spec: &str) -> HashMap<String, Duration> {
+    let mut ttls = HashMap::new();
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        match entry.rsplit_once('=').map(|(model, secs)| (model.trim(), secs.trim().parse::<u64>())) {
+            Some((model, Ok(secs))) if !model.is_empty() => {
+                ttls.insert(model.to_string(), Duration::from_secs(secs));
+            }
+            _ => warn!("Ignoring malformed cache TTL entry '{}'", entry),
+        }
+    }
+    ttls
+}
+
+/// Hit/miss counters plus current occupancy
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Calls that skipped the cache because of a non-deterministic temperature
+    pub bypassed: u64,
+    pub evictions: u64,
+    pub entries: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    bypassed: AtomicU64,
+    evictions: AtomicU64,
+}
+
+/// A cached response; `chunks` holds the original stream segmentation
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedEntry {
+    pub response: String,
+    pub chunks: Vec<String>,
+}
+
+/// Default location of the persistent cache, next to the other state in `data/`
+pub const DEFAULT_CACHE_DB: &str = "data/agency_llm_cache.db";
+
 /// A cache for LLM responses
 pub struct LLMCache {
-    responses: Arc<RwLock<HashMap<CacheKey, String>>>,
+    conn: Arc<Mutex<Connection>>,
+    policy: CachePolicy,
+    counters: Arc<Counters>,
 }
 
 impl LLMCache {
+    /// In-memory cache, dropped with the process
     pub fn new() -> Self {
-        Self {
-            responses: Arc::new(RwLock::new(HashMap::new())),
+        let conn = Connection::open_in_memory().expect("in-memory SQLite is always available");
+        Self::with_connection(conn, CachePolicy::default()).expect("in-memory cache schema")
+    }
+
+    /// Disk-backed cache shared across runs
+    pub fn open(path: impl AsRef<Path>, policy: CachePolicy) -> anyhow::Result<Self> {
+        if let Some(parent) = path.as_ref().parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
         }
+        let conn = Connection::open(path)?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        Self::with_connection(conn, policy)
+    }
+
+    /// Open `AGENCY_LLM_CACHE_DB` (default `DEFAULT_CACHE_DB`) with
+    /// `CachePolicy::from_env`, falling back to memory
+    pub fn from_env() -> Self {
+        let path = std::env::var("AGENCY_LLM_CACHE_DB").unwrap_or_else(|_| DEFAULT_CACHE_DB.to_string());
+        let policy = CachePolicy::from_env();
+        Self::open(&path, policy.clone()).unwrap_or_else(|e| {
+            warn!("LLM cache at '{}' unavailable ({}); using in-memory cache", path, e);
+            Self::new().with_policy(policy)
+        })
+    }
+
+    fn with_connection(conn: Connection, policy: CachePolicy) -> anyhow::Result<Self> {
+        conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS llm_cache (
+                key BLOB PRIMARY KEY,
+                model TEXT NOT NULL,
+                response TEXT NOT NULL,
+                chunks TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                created_at INTEGER NOT NULL,
+                last_access INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_llm_cache_access ON llm_cache(last_access);
+            "#,
+        )?;
+        Ok(Self { conn: Arc::new(Mutex::new(conn)), policy, counters: Arc::default() })
+    }
+
+    pub fn with_policy(mut self, policy: CachePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn policy(&self) -> &CachePolicy {
+        &self.policy
     }
 
-    fn hash(text: &str) -> [u8; 32] {
+    fn hash(model: &str, prompt: &str, system: Option<&str>) -> [u8; 32] {
         let mut hasher = Sha256::new();
-        hasher.update(text.as_bytes());
+        // Length prefixes keep ("ab", "c") and ("a", "bc") apart
+        for part in [model, system.unwrap_or(""), prompt] {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
         hasher.finalize().into()
     }
 
+    fn now_ms() -> i64 {
+        chrono::Utc::now().timestamp_millis()
+    }
+
+    /// Run a blocking closure against the connection off the async runtime
+    async fn with_conn<T, F>(&self, f: F) -> anyhow::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> anyhow::Result<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap_or_else(|e| e.into_inner());
+            f(&conn)
+        }).await?
+    }
+
+    /// Look up an entry, refreshing its LRU position and dropping it if expired
+    pub async fn get_entry(&self, model: &str, prompt: &str, system: Option<&str>) -> Option<CachedEntry> {
+        let key = Self::hash(model, prompt, system);
+        let ttl_ms = self.policy.ttl(model).map(|ttl| ttl.as_millis() as i64);
+
+        let result = self.with_conn(move |conn| {
+            let row: Option<(String, String, i64)> = conn.query_row(
+                "SELECT response, chunks, created_at FROM llm_cache WHERE key = ?1",
+                params![&key[..]],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            ).optional()?;
+            let Some((response, chunks, created_at)) = row else { return Ok(None) };
+
+            if ttl_ms.is_some_and(|ttl| Self::now_ms() - created_at > ttl) {
+                conn.execute("DELETE FROM llm_cache WHERE key = ?1", params![&key[..]])?;
+                return Ok(None);
+            }
+            conn.execute(
+                "UPDATE llm_cache SET last_access = (SELECT COALESCE(MAX(last_access), 0) + 1 FROM llm_cache) WHERE key = ?1",
+                params![&key[..]],
+            )?;
+            let chunks: Vec<String> = serde_json::from_str(&chunks).unwrap_or_else(|_| vec![response.clone()]);
+            Ok(Some(CachedEntry { response, chunks }))
+        }).await;
+
+        let entry = result.unwrap_or_else(|e| {
+            warn!("LLM cache read failed: {}", e);
+            None
+        });
+        let counter = if entry.is_some() { &self.counters.hits } else { &self.counters.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        entry
+    }
+
     pub async fn get(&self, model: &str, prompt: &str, system: Option<&str>) -> Option<String> {
-        let key = CacheKey {
-            model: model.to_string(),
-            prompt_hash: Self::hash(prompt),
-            system_hash: Self::hash(system.unwrap_or("")),
-        };
-        
-        let responses = self.responses.read().await;
-        responses.get(&key).cloned()
+        self.get_entry(model, prompt, system).await.map(|entry| entry.response)
     }
 
     pub async fn set(&self, model: &str, prompt: &str, system: Option<&str>, response: String) {
-        let key = CacheKey {
-            model: model.to_string(),
-            prompt_hash: Self::hash(prompt),
-            system_hash: Self::hash(system.unwrap_or("")),
-        };
-        
-        let mut responses = self.responses.write().await;
-        responses.insert(key, response);
+        self.set_chunks(model, prompt, system, vec![response]).await;
+    }
+
+    /// Store a streamed response, keeping its chunk boundaries for replay
+    pub async fn set_chunks(&self, model: &str, prompt: &str, system: Option<&str>, chunks: Vec<String>) {
+        let key = Self::hash(model, prompt, system);
+        let model = model.to_string();
+        let response = chunks.concat();
+        let (max_entries, max_bytes) = (self.policy.max_entries as i64, self.policy.max_bytes as i64);
+
+        let result = self.with_conn(move |conn| {
+            let now = Self::now_ms();
+            conn.execute(
+                "INSERT OR REPLACE INTO llm_cache (key, model, response, chunks, size, created_at, last_access)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, (SELECT COALESCE(MAX(last_access), 0) + 1 FROM llm_cache))",
+                params![&key[..], model, response, serde_json::to_string(&chunks)?, response.len() as i64, now],
+            )?;
+
+            // Evict least recently used entries until both limits hold
+            let mut evicted = 0u64;
+            loop {
+                let (count, bytes): (i64, i64) = conn.query_row(
+                    "SELECT COUNT(*), COALESCE(SUM(size), 0) FROM llm_cache", [], |row| Ok((row.get(0)?, row.get(1)?)),
+                )?;
+                if (count <= max_entries && bytes <= max_bytes) || count <= 1 {
+                    break;
+                }
+                conn.execute(
+                    "DELETE FROM llm_cache WHERE key = (SELECT key FROM llm_cache ORDER BY last_access ASC LIMIT 1)",
+                    [],
+                )?;
+                evicted += 1;
+            }
+            Ok(evicted)
+        }).await;
+
+        match result {
+            Ok(evicted) => { self.counters.evictions.fetch_add(evicted, Ordering::Relaxed); }
+            Err(e) => warn!("LLM cache write failed: {}", e),
+        }
+    }
+
+    /// Count a call that skipped the cache
+    pub fn record_bypass(&self) {
+        self.counters.bypassed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub async fn stats(&self) -> CacheStats {
+        let (entries, bytes) = self.with_conn(|conn| {
+            Ok(conn.query_row("SELECT COUNT(*), COALESCE(SUM(size), 0) FROM llm_cache", [], |row| {
+                Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64))
+            })?)
+        }).await.unwrap_or_default();
+
+        CacheStats {
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            bypassed: self.counters.bypassed.load(Ordering::Relaxed),
+            evictions: self.counters.evictions.load(Ordering::Relaxed),
+            entries,
+            bytes,
+        }
     }
 
     #[allow(dead_code)]
     pub async fn clear(&self) {
-        let mut responses = self.responses.write().await;
-        responses.clear();
+        if let Err(e) = self.with_conn(|conn| Ok(conn.execute("DELETE FROM llm_cache", [])?)).await {
+            warn!("LLM cache clear failed: {}", e);
+        }
     }
 }
 
@@ -70,6 +337,40 @@ impl Default for LLMCache {
     }
 }
 
+/// Replay cached chunks as a token stream
+fn replay(entry: CachedEntry) -> BoxStream<'static, anyhow::Result<String>> {
+    Box::pin(futures_util::stream::iter(entry.chunks.into_iter().map(Ok)))
+}
+
+/// Pass a live stream through, storing its chunks once it completes cleanly.
+/// Streams that error or are dropped early are not cached.
+fn record_stream(
+    inner: BoxStream<'static, anyhow::Result<String>>,
+    cache: Arc<LLMCache>,
+    model: String,
+    key: String,
+    system: Option<String>,
+) -> BoxStream<'static, anyhow::Result<String>> {
+    let state = (inner, Some(Vec::new()), cache, model, key, system);
+    Box::pin(futures_util::stream::unfold(state, |(mut inner, mut chunks, cache, model, key, system)| async move {
+        match inner.next().await {
+            Some(Ok(chunk)) => {
+                if let Some(ref mut chunks) = chunks {
+                    chunks.push(chunk.clone());
+                }
+                Some((Ok(chunk), (inner, chunks, cache, model, key, system)))
+            }
+            Some(Err(e)) => Some((Err(e), (inner, None, cache, model, key, system))),
+            None => {
+                if let Some(chunks) = chunks.filter(|c| !c.is_empty()) {
+                    cache.set_chunks(&model, &key, system.as_deref(), chunks).await;
+                }
+                None
+            }
+        }
+    }))
+}
+
 /// Provider that wraps another provider with a cache
 pub struct CachedProvider {
     inner: Arc<dyn LLMProvider>,
@@ -96,18 +397,62 @@ impl LLMProvider for CachedProvider {
     }
 
     async fn generate_stream(&self, model: &str, prompt: String, system: Option<String>) -> anyhow::Result<BoxStream<'static, anyhow::Result<String>>> {
-        if let Some(cached) = self.cache.get(model, &prompt, system.as_deref()).await {
+        if let Some(entry) = self.cache.get_entry(model, &prompt, system.as_deref()).await {
             tracing::debug!("LLM Cache Hit for model {}", model);
-            return Ok(Box::pin(futures_util::stream::once(async move { Ok(cached) })));
+            return Ok(replay(entry));
         }
 
-        let model = model.to_string();
-        
-        let stream = self.inner.generate_stream(&model, prompt, system).await?;
+        let stream = self.inner.generate_stream(model, prompt.clone(), system.clone()).await?;
+        Ok(record_stream(stream, self.cache.clone(), model.to_string(), prompt, system))
+    }
+
+    async fn chat(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions) -> anyhow::Result<ChatCompletion> {
+        if !self.cache.policy().is_cacheable(&options) {
+            self.cache.record_bypass();
+            return self.inner.chat(model, messages, options).await;
+        }
+
+        // The whole conversation plus options forms the cache key
+        let key = serde_json::to_string(&(&messages, &options))?;
+        if let Some(cached) = self.cache.get(model, &key, None).await {
+            tracing::debug!("LLM Cache Hit for model {}", model);
+            // Cache hits cost nothing, so they report zero usage
+            return Ok(ChatCompletion { content: cached, ..Default::default() });
+        }
 
-        // For now, CachedProvider::generate_stream will just not cache the result of a miss
-        // to avoid complexity with collecting chunks in a stream.
-        Ok(Box::pin(stream))
+        let completion = self.inner.chat(model, messages, options).await?;
+        self.cache.set(model, &key, None, completion.content.clone()).await;
+        Ok(completion)
+    }
+
+    async fn chat_stream(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions) -> anyhow::Result<ChatStream> {
+        if !self.cache.policy().is_cacheable(&options) {
+            self.cache.record_bypass();
+            return self.inner.chat_stream(model, messages, options).await;
+        }
+
+        let key = serde_json::to_string(&(&messages, &options))?;
+        if let Some(entry) = self.cache.get_entry(model, &key, None).await {
+            tracing::debug!("LLM Cache Hit for model {}", model);
+            return Ok(ChatStream::without_usage(replay(entry)));
+        }
+
+        let stream = self.inner.chat_stream(model, messages, options).await?;
+        let (cache, model) = (self.cache.clone(), model.to_string());
+        Ok(stream.map_inner(move |inner| record_stream(inner, cache, model, key, None)))
+    }
+
+    fn supports_native_tools(&self) -> bool {
+        self.inner.supports_native_tools()
+    }
+
+    async fn chat_with_tools(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions, tools: &[ToolDefinition]) -> anyhow::Result<ToolCompletion> {
+        // Tool-call turns depend on registry state, so they bypass the text cache
+        self.inner.chat_with_tools(model, messages, options, tools).await
+    }
+
+    async fn chat_stream_with_tools(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions, tools: &[ToolDefinition]) -> anyhow::Result<ChatStream> {
+        self.inner.chat_stream_with_tools(model, messages, options, tools).await
     }
 
     fn get_lock(&self) -> Arc<tokio::sync::Mutex<()>> {
@@ -151,4 +496,135 @@ mod tests {
         let cached = cache.get("m", "p", None).await;
         assert!(cached.is_none());
     }
+
+    struct CountingProvider {
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LLMProvider for CountingProvider {
+        async fn generate(&self, _model: &str, prompt: String, _system: Option<String>) -> anyhow::Result<String> {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(prompt)
+        }
+        async fn generate_stream(&self, model: &str, prompt: String, system: Option<String>) -> anyhow::Result<BoxStream<'static, anyhow::Result<String>>> {
+            let text = self.generate(model, prompt, system).await?;
+            Ok(Box::pin(futures_util::stream::once(async move { Ok(text) })))
+        }
+        fn get_lock(&self) -> Arc<tokio::sync::Mutex<()>> {
+            Arc::new(tokio::sync::Mutex::new(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cached_chat_keys_on_history() -> anyhow::Result<()> {
+        let inner = Arc::new(CountingProvider { calls: Default::default() });
+        let provider = CachedProvider::new(inner.clone(), Arc::new(LLMCache::new()));
+        let first = vec![ChatMessage::user("a"), ChatMessage::assistant("b"), ChatMessage::user("c")];
+        let mut second = first.clone();
+        second[1] = ChatMessage::assistant("different");
+
+        provider.chat("m", first.clone(), GenerationOptions::default()).await?;
+        provider.chat("m", first, GenerationOptions::default()).await?;
+        provider.chat("m", second, GenerationOptions::default()).await?;
+
+        assert_eq!(inner.calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lru_eviction_by_count_and_size() {
+        let cache = LLMCache::new().with_policy(CachePolicy { max_entries: 2, ..Default::default() });
+        cache.set("m", "a", None, "1".into()).await;
+        cache.set("m", "b", None, "2".into()).await;
+        // Touch "a" so "b" becomes least recently used
+        assert!(cache.get("m", "a", None).await.is_some());
+        cache.set("m", "c", None, "3".into()).await;
+
+        assert!(cache.get("m", "b", None).await.is_none());
+        assert!(cache.get("m", "a", None).await.is_some());
+        let stats = cache.stats().await;
+        assert_eq!((stats.entries, stats.evictions), (2, 1));
+
+        let cache = LLMCache::new().with_policy(CachePolicy { max_bytes: 10, ..Default::default() });
+        cache.set("m", "a", None, "x".repeat(6)).await;
+        cache.set("m", "b", None, "y".repeat(6)).await;
+        assert!(cache.get("m", "a", None).await.is_none());
+        assert_eq!(cache.stats().await.bytes, 6);
+    }
+
+    #[tokio::test]
+    async fn test_per_model_ttl() {
+        let policy = CachePolicy::default().with_model_ttl("fast", Duration::from_millis(20));
+        let cache = LLMCache::new().with_policy(policy);
+        cache.set("fast", "p", None, "r".into()).await;
+        cache.set("slow", "p", None, "r".into()).await;
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert!(cache.get("fast", "p", None).await.is_none());
+        assert!(cache.get("slow", "p", None).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_persists_across_reopen() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("data").join("cache.db");
+        LLMCache::open(&path, CachePolicy::default())?.set("m", "p", Some("s"), "kept".into()).await;
+
+        let reopened = LLMCache::open(&path, CachePolicy::default())?;
+        assert_eq!(reopened.get("m", "p", Some("s")).await.as_deref(), Some("kept"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_replays_chunks_and_counts_hits() -> anyhow::Result<()> {
+        let inner = Arc::new(CountingProvider { calls: Default::default() });
+        let cache = Arc::new(LLMCache::new());
+        let provider = CachedProvider::new(inner.clone(), cache.clone());
+        let messages = vec![ChatMessage::user("stream me")];
+
+        let first = provider.chat_stream("m", messages.clone(), GenerationOptions::default()).await?;
+        let first: Vec<String> = first.map(|c| c.unwrap()).collect().await;
+        let replayed = provider.chat_stream("m", messages, GenerationOptions::default()).await?;
+        let replayed: Vec<String> = replayed.map(|c| c.unwrap()).collect().await;
+
+        assert_eq!(first, replayed);
+        assert_eq!(inner.calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+        let stats = cache.stats().await;
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sampled_calls_bypass_cache() -> anyhow::Result<()> {
+        let inner = Arc::new(CountingProvider { calls: Default::default() });
+        let cache = Arc::new(LLMCache::new());
+        let provider = CachedProvider::new(inner.clone(), cache.clone());
+        let messages = vec![ChatMessage::user("hi")];
+        let sampled = GenerationOptions::default().with_temperature(0.7);
+
+        provider.chat("m", messages.clone(), sampled.clone()).await?;
+        provider.chat("m", messages.clone(), sampled.clone()).await?;
+        assert_eq!(inner.calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert_eq!(cache.stats().await.bypassed, 2);
+
+        // A fixed seed makes sampling reproducible again
+        let seeded = sampled.with_seed(7);
+        provider.chat("m", messages.clone(), seeded.clone()).await?;
+        provider.chat("m", messages, seeded).await?;
+        assert_eq!(inner.calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_temperature_gate_and_model_ttls_are_configurable() {
+        let sampled = GenerationOptions::default().with_temperature(0.7);
+        assert!(!CachePolicy::default().is_cacheable(&sampled));
+        assert!(CachePolicy::default().with_max_temperature(0.7).is_cacheable(&sampled));
+
+        let ttls = parse_model_ttls("glm-4=60, qwen2.5:7b=3600,broken,=5");
+        assert_eq!(ttls.len(), 2);
+        assert_eq!(ttls["glm-4"], Duration::from_secs(60));
+        assert_eq!(ttls["qwen2.5:7b"], Duration::from_secs(3600));
+    }
 }
@@ -0,0 +1,558 @@
+//! Provider Failover
+//!
+//! Wraps an ordered list of providers. Each call goes to the first healthy
+//! backend; failures are retried on the next one. Backends that fail
+//! repeatedly are taken out of rotation (circuit open) for a cooldown, after
+//! which a single probe decides whether they rejoin.
+//!
+//! Only failures of the backend itself (transport errors, 5xx, 429) count
+//! against it and move on to the next one. Any other error means the request
+//! itself was rejected, and is returned to the caller as is.
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures_util::stream::BoxStream;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::agent::provider::HttpStatusError;
+use crate::agent::{ChatCompletion, ChatMessage, ChatStream, GenerationOptions, LLMProvider, ToolCompletion};
+use crate::orchestrator::AgencyEvent;
+use crate::tools::ToolDefinition;
+
+/// Tuning for failover and circuit breaking
+#[derive(Debug, Clone)]
+pub struct FailoverPolicy {
+    /// Consecutive failures that open a backend's circuit
+    pub failure_threshold: u32,
+    /// How long an open circuit stays open before a probe is allowed
+    pub cooldown: Duration,
+    /// Extra attempts on the same backend before moving on
+    pub retries_per_backend: u32,
+    /// Number of recent calls the error rate is computed over
+    pub window: usize,
+}
+
+impl Default for FailoverPolicy {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            cooldown: Duration::from_secs(30),
+            retries_per_backend: 0,
+            window: 20,
+        }
+    }
+}
+
+/// Circuit state of a backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Healthy, receives traffic
+    Closed,
+    /// Failing, skipped until the cooldown expires
+    Open,
+    /// Cooldown expired, the next call is a probe
+    HalfOpen,
+}
+
+/// Health snapshot of one backend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackendHealth {
+    pub name: String,
+    pub circuit: CircuitState,
+    pub requests: u64,
+    pub failures: u64,
+    /// Failure ratio over the last `FailoverPolicy::window` calls
+    pub error_rate: f32,
+    /// Exponentially weighted latency of successful calls
+    pub avg_latency_ms: Option<f64>,
+}
+
+#[derive(Debug)]
+struct HealthState {
+    requests: u64,
+    failures: u64,
+    consecutive_failures: u32,
+    recent: VecDeque<bool>,
+    avg_latency_ms: Option<f64>,
+    open_until: Option<Instant>,
+}
+
+impl HealthState {
+    fn new() -> Self {
+        Self {
+            requests: 0,
+            failures: 0,
+            consecutive_failures: 0,
+            recent: VecDeque::new(),
+            avg_latency_ms: None,
+            open_until: None,
+        }
+    }
+
+    fn circuit(&self, now: Instant) -> CircuitState {
+        match self.open_until {
+            None => CircuitState::Closed,
+            Some(until) if now < until => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    fn push_outcome(&mut self, ok: bool, window: usize) {
+        self.requests += 1;
+        self.recent.push_back(ok);
+        while self.recent.len() > window.max(1) {
+            self.recent.pop_front();
+        }
+    }
+
+    fn error_rate(&self) -> f32 {
+        if self.recent.is_empty() {
+            return 0.0;
+        }
+        self.recent.iter().filter(|ok| !**ok).count() as f32 / self.recent.len() as f32
+    }
+}
+
+struct Backend {
+    name: String,
+    provider: Arc<dyn LLMProvider>,
+    /// Model to request from this backend instead of the caller's
+    model: Option<String>,
+    health: Mutex<HealthState>,
+}
+
+/// Whether `error` says the backend is unavailable (worth failing over), as
+/// opposed to the request being refused
+pub fn is_backend_failure(error: &anyhow::Error) -> bool {
+    let retryable_status = |status: u16| status >= 500 || status == 429;
+    error.chain().find_map(|cause| {
+        if let Some(e) = cause.downcast_ref::<HttpStatusError>() {
+            return Some(retryable_status(e.status));
+        }
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            return Some(match e.status() {
+                Some(status) => retryable_status(status.as_u16()),
+                None => !e.is_builder(),
+            });
+        }
+        (cause.is::<std::io::Error>() || cause.is::<tokio::time::error::Elapsed>()).then_some(true)
+    }).unwrap_or(false)
+}
+
+/// Provider that fails over across an ordered chain of backends
+pub struct FallbackProvider {
+    backends: Vec<Backend>,
+    policy: FailoverPolicy,
+}
+
+impl FallbackProvider {
+    /// Build a chain from `(name, provider)` pairs, highest priority first
+    pub fn new(backends: Vec<(String, Arc<dyn LLMProvider>)>) -> Self {
+        assert!(!backends.is_empty(), "FallbackProvider needs at least one backend");
+        Self {
+            backends: backends.into_iter().map(|(name, provider)| Backend {
+                name,
+                provider,
+                model: None,
+                health: Mutex::new(HealthState::new()),
+            }).collect(),
+            policy: FailoverPolicy::default(),
+        }
+    }
+
+    pub fn with_policy(mut self, policy: FailoverPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Request `model` whenever the call lands on `backend`; model names
+    /// rarely carry over between providers
+    pub fn with_model(mut self, backend: &str, model: impl Into<String>) -> Self {
+        let model = model.into();
+        for b in self.backends.iter_mut().filter(|b| b.name == backend) {
+            b.model = Some(model.clone());
+        }
+        self
+    }
+
+    /// Health of every backend, in priority order
+    pub fn health(&self) -> Vec<BackendHealth> {
+        let now = Instant::now();
+        self.backends.iter().map(|backend| {
+            let state = backend.health.lock().unwrap_or_else(|e| e.into_inner());
+            BackendHealth {
+                name: backend.name.clone(),
+                circuit: state.circuit(now),
+                requests: state.requests,
+                failures: state.failures,
+                error_rate: state.error_rate(),
+                avg_latency_ms: state.avg_latency_ms,
+            }
+        }).collect()
+    }
+
+    /// Indices of backends to try, in order. Open circuits are skipped; if every
+    /// circuit is open the whole chain is probed rather than failing outright.
+    fn candidates(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let available: Vec<usize> = (0..self.backends.len())
+            .filter(|&i| self.backends[i].health.lock().map(|s| s.circuit(now) != CircuitState::Open).unwrap_or(true))
+            .collect();
+        if available.is_empty() {
+            warn!("All provider circuits are open; probing the full chain");
+            return (0..self.backends.len()).collect();
+        }
+        available
+    }
+
+    fn record_success(&self, index: usize, latency: Duration) {
+        let backend = &self.backends[index];
+        let mut state = backend.health.lock().unwrap_or_else(|e| e.into_inner());
+        state.push_outcome(true, self.policy.window);
+        state.consecutive_failures = 0;
+        let ms = latency.as_secs_f64() * 1000.0;
+        state.avg_latency_ms = Some(match state.avg_latency_ms {
+            Some(avg) => avg * 0.8 + ms * 0.2,
+            None => ms,
+        });
+        if state.open_until.take().is_some() {
+            info!("Provider '{}' recovered; circuit closed", backend.name);
+            crate::emit_event!(AgencyEvent::ProviderCircuitChanged { provider: backend.name.clone(), open: false });
+        }
+    }
+
+    fn record_failure(&self, index: usize) {
+        let backend = &self.backends[index];
+        let mut state = backend.health.lock().unwrap_or_else(|e| e.into_inner());
+        state.push_outcome(false, self.policy.window);
+        state.failures += 1;
+        state.consecutive_failures += 1;
+        let now = Instant::now();
+        // A failed probe re-opens immediately; otherwise wait for the threshold
+        let trip = state.circuit(now) == CircuitState::HalfOpen
+            || state.consecutive_failures >= self.policy.failure_threshold;
+        if trip {
+            let was_closed = state.open_until.is_none();
+            state.open_until = Some(now + self.policy.cooldown);
+            if was_closed {
+                warn!("Provider '{}' failed {} times in a row; circuit opened", backend.name, state.consecutive_failures);
+                crate::emit_event!(AgencyEvent::ProviderCircuitChanged { provider: backend.name.clone(), open: true });
+            }
+        }
+    }
+
+    /// Run `call` against the chain until one backend succeeds. `call` gets
+    /// the backend and the model to ask it for.
+    async fn run<T, F, Fut>(&self, operation: &str, model: &str, mut call: F) -> Result<T>
+    where
+        F: FnMut(Arc<dyn LLMProvider>, String) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let candidates = self.candidates();
+        let mut errors = Vec::new();
+
+        for (position, &index) in candidates.iter().enumerate() {
+            let backend = &self.backends[index];
+            for attempt in 0..=self.policy.retries_per_backend {
+                let started = Instant::now();
+                let model = backend.model.clone().unwrap_or_else(|| model.to_string());
+                match call(backend.provider.clone(), model).await {
+                    Ok(value) => {
+                        self.record_success(index, started.elapsed());
+                        return Ok(value);
+                    }
+                    Err(e) if !is_backend_failure(&e) => {
+                        // The backend answered; another one would refuse the request too
+                        warn!("Provider '{}' rejected {}: {}", backend.name, operation, e);
+                        return Err(e);
+                    }
+                    Err(e) => {
+                        warn!("Provider '{}' {} attempt {} failed: {}", backend.name, operation, attempt + 1, e);
+                        self.record_failure(index);
+                        errors.push(format!("{}: {}", backend.name, e));
+                    }
+                }
+            }
+
+            if let Some(&next) = candidates.get(position + 1) {
+                crate::emit_event!(AgencyEvent::ProviderFailover {
+                    from: backend.name.clone(),
+                    to: self.backends[next].name.clone(),
+                    reason: errors.last().cloned().unwrap_or_default(),
+                });
+            }
+        }
+
+        Err(anyhow::anyhow!("All {} providers failed for {}: {}", candidates.len(), operation, errors.join("; ")))
+    }
+
+    /// First backend currently taking traffic
+    fn primary(&self) -> &Arc<dyn LLMProvider> {
+        &self.backends[self.candidates()[0]].provider
+    }
+}
+
+#[async_trait]
+impl LLMProvider for FallbackProvider {
+    async fn generate(&self, model: &str, prompt: String, system: Option<String>) -> Result<String> {
+        self.run("generate", model, |p, model| {
+            let (prompt, system) = (prompt.clone(), system.clone());
+            async move { p.generate(&model, prompt, system).await }
+        }).await
+    }
+
+    /// Failover covers opening the stream; errors after the first token reach the caller
+    async fn generate_stream(&self, model: &str, prompt: String, system: Option<String>) -> Result<BoxStream<'static, Result<String>>> {
+        self.run("generate_stream", model, |p, model| {
+            let (prompt, system) = (prompt.clone(), system.clone());
+            async move { p.generate_stream(&model, prompt, system).await }
+        }).await
+    }
+
+    async fn chat(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions) -> Result<ChatCompletion> {
+        self.run("chat", model, |p, model| {
+            let (messages, options) = (messages.clone(), options.clone());
+            async move { p.chat(&model, messages, options).await }
+        }).await
+    }
+
+    /// Failover covers opening the stream; errors after the first token reach the caller
+    async fn chat_stream(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions) -> Result<ChatStream> {
+        self.run("chat_stream", model, |p, model| {
+            let (messages, options) = (messages.clone(), options.clone());
+            async move { p.chat_stream(&model, messages, options).await }
+        }).await
+    }
+
+    fn supports_native_tools(&self) -> bool {
+        // Backends without native tools fall back to plain chat, which the text parser handles
+        self.primary().supports_native_tools()
+    }
+
+    async fn chat_with_tools(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions, tools: &[ToolDefinition]) -> Result<ToolCompletion> {
+        self.run("chat_with_tools", model, |p, model| {
+            let (messages, options) = (messages.clone(), options.clone());
+            async move { p.chat_with_tools(&model, messages, options, tools).await }
+        }).await
+    }
+
+    /// Failover covers opening the stream; errors after the first token reach the caller
+    async fn chat_stream_with_tools(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions, tools: &[ToolDefinition]) -> Result<ChatStream> {
+        self.run("chat_stream_with_tools", model, |p, model| {
+            let (messages, options) = (messages.clone(), options.clone());
+            async move { p.chat_stream_with_tools(&model, messages, options, tools).await }
+        }).await
+    }
+
+    fn get_lock(&self) -> Arc<tokio::sync::Mutex<()>> {
+        self.primary().get_lock()
+    }
+
+    async fn notify(&self, message: &str) -> Result<()> {
+        self.primary().notify(message).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    struct FlakyProvider {
+        reply: &'static str,
+        failing: AtomicBool,
+        /// HTTP status returned while failing
+        status: u16,
+        calls: AtomicUsize,
+        last_model: Mutex<String>,
+    }
+
+    impl FlakyProvider {
+        fn new(reply: &'static str, failing: bool) -> Arc<Self> {
+            Self::with_status(reply, failing, 503)
+        }
+
+        fn with_status(reply: &'static str, failing: bool, status: u16) -> Arc<Self> {
+            Arc::new(Self {
+                reply,
+                failing: AtomicBool::new(failing),
+                status,
+                calls: AtomicUsize::new(0),
+                last_model: Mutex::new(String::new()),
+            })
+        }
+
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait]
+    impl LLMProvider for FlakyProvider {
+        async fn generate(&self, model: &str, _prompt: String, _system: Option<String>) -> Result<String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            *self.last_model.lock().unwrap() = model.to_string();
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(HttpStatusError { status: self.status, message: format!("{} is down", self.reply) }.into());
+            }
+            Ok(self.reply.to_string())
+        }
+
+        async fn generate_stream(&self, model: &str, prompt: String, system: Option<String>) -> Result<BoxStream<'static, Result<String>>> {
+            let text = self.generate(model, prompt, system).await?;
+            Ok(Box::pin(futures_util::stream::once(async move { Ok(text) })))
+        }
+
+        fn get_lock(&self) -> Arc<tokio::sync::Mutex<()>> {
+            Arc::new(tokio::sync::Mutex::new(()))
+        }
+    }
+
+    fn chain(backends: &[(&str, &Arc<FlakyProvider>)], policy: FailoverPolicy) -> FallbackProvider {
+        FallbackProvider::new(backends.iter().map(|(name, p)| (name.to_string(), (*p).clone() as Arc<dyn LLMProvider>)).collect())
+            .with_policy(policy)
+    }
+
+    #[tokio::test]
+    async fn test_fails_over_and_emits_event() -> Result<()> {
+        let mut events = crate::orchestrator::event_bus::AGENCY_EVENT_BUS.subscribe();
+        let primary = FlakyProvider::new("failover-primary", true);
+        let backup = FlakyProvider::new("failover-backup", false);
+        let provider = chain(&[("failover-primary", &primary), ("failover-backup", &backup)], FailoverPolicy::default());
+
+        assert_eq!(provider.generate("m", "hi".into(), None).await?, "failover-backup");
+
+        let health = provider.health();
+        assert_eq!(health[0].failures, 1);
+        assert_eq!(health[0].error_rate, 1.0);
+        assert_eq!(health[1].requests, 1);
+        assert!(health[1].avg_latency_ms.is_some());
+
+        // The bus is global; skip events from concurrently running tests
+        loop {
+            if let AgencyEvent::ProviderFailover { from, to, reason } = events.recv().await? {
+                if from == "failover-primary" {
+                    assert_eq!(to, "failover-backup");
+                    assert!(reason.contains("is down"));
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_circuit_opens_then_recovers_after_probe() -> Result<()> {
+        let primary = FlakyProvider::new("primary", true);
+        let backup = FlakyProvider::new("backup", false);
+        let policy = FailoverPolicy { failure_threshold: 2, cooldown: Duration::from_millis(50), ..Default::default() };
+        let provider = chain(&[("primary", &primary), ("backup", &backup)], policy);
+
+        for _ in 0..2 {
+            provider.generate("m", "hi".into(), None).await?;
+        }
+        assert_eq!(provider.health()[0].circuit, CircuitState::Open);
+
+        // Open circuit: primary is skipped entirely
+        provider.generate("m", "hi".into(), None).await?;
+        assert_eq!(primary.calls(), 2);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(provider.health()[0].circuit, CircuitState::HalfOpen);
+        primary.failing.store(false, Ordering::SeqCst);
+
+        assert_eq!(provider.generate("m", "hi".into(), None).await?, "primary");
+        assert_eq!(provider.health()[0].circuit, CircuitState::Closed);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failed_probe_reopens_circuit() -> Result<()> {
+        let primary = FlakyProvider::new("primary", true);
+        let backup = FlakyProvider::new("backup", false);
+        let policy = FailoverPolicy { failure_threshold: 1, cooldown: Duration::from_millis(20), ..Default::default() };
+        let provider = chain(&[("primary", &primary), ("backup", &backup)], policy);
+
+        provider.generate("m", "hi".into(), None).await?;
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        provider.generate("m", "hi".into(), None).await?;
+
+        assert_eq!(primary.calls(), 2);
+        assert_eq!(provider.health()[0].circuit, CircuitState::Open);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_retries_before_failover_and_reports_all_errors() {
+        let a = FlakyProvider::new("a", true);
+        let b = FlakyProvider::new("b", true);
+        let policy = FailoverPolicy { retries_per_backend: 1, ..Default::default() };
+        let provider = chain(&[("a", &a), ("b", &b)], policy);
+
+        let err = provider.chat("m", vec![ChatMessage::user("hi")], GenerationOptions::default()).await.unwrap_err();
+        assert_eq!(a.calls(), 2);
+        assert_eq!(b.calls(), 2);
+        let message = err.to_string();
+        assert!(message.contains("a is down") && message.contains("b is down"));
+    }
+
+    #[tokio::test]
+    async fn test_all_open_circuits_still_probe() -> Result<()> {
+        let only = FlakyProvider::new("only", true);
+        let policy = FailoverPolicy { failure_threshold: 1, cooldown: Duration::from_secs(60), ..Default::default() };
+        let provider = chain(&[("only", &only)], policy);
+
+        assert!(provider.generate("m", "hi".into(), None).await.is_err());
+        only.failing.store(false, Ordering::SeqCst);
+        assert_eq!(provider.generate("m", "hi".into(), None).await?, "only");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rejected_request_is_returned_without_failover() {
+        let primary = FlakyProvider::with_status("primary", true, 400);
+        let backup = FlakyProvider::new("backup", false);
+        let policy = FailoverPolicy { failure_threshold: 1, ..Default::default() };
+        let provider = chain(&[("primary", &primary), ("backup", &backup)], policy);
+
+        let err = provider.generate("m", "hi".into(), None).await.unwrap_err();
+        assert!(err.to_string().contains("primary is down"));
+        assert_eq!(backup.calls(), 0);
+        let health = provider.health();
+        assert_eq!(health[0].failures, 0);
+        assert_eq!(health[0].circuit, CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_and_transport_errors_fail_over() -> Result<()> {
+        let limited = FlakyProvider::with_status("limited", true, 429);
+        let backup = FlakyProvider::new("backup", false);
+        let provider = chain(&[("limited", &limited), ("backup", &backup)], FailoverPolicy::default());
+        assert_eq!(provider.generate("m", "hi".into(), None).await?, "backup");
+
+        let io: anyhow::Error = std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "refused").into();
+        assert!(is_backend_failure(&io.context("Ollama stream error")));
+        assert!(!is_backend_failure(&anyhow::anyhow!("could not parse tool call")));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_backend_model_override() -> Result<()> {
+        let primary = FlakyProvider::new("primary", true);
+        let backup = FlakyProvider::new("backup", false);
+        let provider = chain(&[("primary", &primary), ("backup", &backup)], FailoverPolicy::default())
+            .with_model("backup", "qwen2.5:7b");
+
+        provider.generate("glm-4.6", "hi".into(), None).await?;
+        assert_eq!(*primary.last_model.lock().unwrap(), "glm-4.6");
+        assert_eq!(*backup.last_model.lock().unwrap(), "qwen2.5:7b");
+        Ok(())
+    }
+}
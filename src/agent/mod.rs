@@ -10,6 +10,9 @@ mod background;
 pub mod provider;
 mod ctm;
 mod cache;
+mod fallback;
+mod replay;
+pub mod sse;
 pub mod nqd;
 pub mod speaker_rs;
 pub mod rl;
@@ -22,8 +25,10 @@ pub use types::{AgentType, AgentConfig};
 pub use autonomous::AutonomousMachine;
 pub use background::BackgroundThoughtMachine;
 pub use ctm::ContinuousThoughtMachine;
-pub use provider::{LLMProvider, OllamaProvider, OpenAICompatibleProvider, CandleProvider, RemoteNexusProvider, PublishingProvider};
-pub use cache::{LLMCache, CachedProvider};
+pub use provider::{LLMProvider, ChatMessage, ChatRole, GenerationOptions, ToolCompletion, TokenUsage, StreamTrailer, ChatStream, ChatCompletion, OllamaProvider, OpenAICompatibleProvider, CandleProvider, RemoteNexusProvider, PublishingProvider};
+pub use cache::{LLMCache, CachedProvider, CachePolicy, CacheStats};
+pub use fallback::{FallbackProvider, FailoverPolicy, BackendHealth, CircuitState};
+pub use replay::{RecordingProvider, ReplayProvider, Fixture, FixtureEntry};
 pub use nqd::NQDPortfolio;
 pub use provider::dynamic_provider;
 pub use pai_core::uap::{SovereignAgent, UapTask, UapStep, UapTaskStatus, UapStepStatus, UapArtifact};
@@ -50,6 +55,10 @@ pub enum AgentError {
     Pai(String),
     #[error("Execution failed: {0}")]
     Execution(String),
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+    #[error("Timed out: {0}")]
+    Timeout(String),
 }
 
 /// Specialized Result for Agent operations
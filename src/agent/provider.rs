@@ -5,7 +5,8 @@ use serde_json::json;
 use reqwest::Client;
 use std::sync::Arc;
 use tokio::sync::{Mutex, RwLock};
-use tracing::{info, debug, error};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, debug, error, warn};
 use lazy_static::lazy_static;
 use std::fs::File;
 use std::collections::HashMap;
@@ -17,6 +18,8 @@ use candle_transformers::generation::LogitsProcessor;
 use candle_transformers::models::llama as llama_model;
 use candle_transformers::models::quantized_llama;
 use crate::models::reasoner::{ReasonerModel, Config as ReasonerConfig};
+use crate::tools::{ToolCall, ToolDefinition};
+use super::sse::{ChatDelta, ChatDeltaDecoder};
 use tokenizers::Tokenizer;
 
 // Truly global lock to protect hardware across all instances
@@ -24,10 +27,354 @@ lazy_static! {
     static ref GLOBAL_HW_LOCK: Arc<Mutex<()>> = Arc::new(Mutex::new(()));
 }
 
+/// Role of a message in a multi-turn conversation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    System,
+    User,
+    Assistant,
+    Tool,
+}
+
+impl ChatRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChatRole::System => "system",
+            ChatRole::User => "user",
+            ChatRole::Assistant => "assistant",
+            ChatRole::Tool => "tool",
+        }
+    }
+}
+
+/// A single message in a multi-turn conversation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub content: String,
+    /// Tool calls requested by the assistant in this message
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// Name of the tool whose result this message carries (role = tool)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>,
+}
+
+impl ChatMessage {
+    pub fn new(role: ChatRole, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+            tool_calls: Vec::new(),
+            tool_name: None,
+        }
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new(ChatRole::System, content)
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new(ChatRole::User, content)
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new(ChatRole::Assistant, content)
+    }
+
+    /// A tool result, attributed to the tool that produced it
+    pub fn tool(tool_name: impl Into<String>, content: impl Into<String>) -> Self {
+        let mut message = Self::new(ChatRole::Tool, content);
+        message.tool_name = Some(tool_name.into());
+        message
+    }
+
+    pub fn with_tool_calls(mut self, tool_calls: Vec<ToolCall>) -> Self {
+        self.tool_calls = tool_calls;
+        self
+    }
+}
+
+/// Per-call generation options. Unset fields fall back to the backend's defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationOptions {
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    /// Sequences that end generation (not included in the output)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    /// Sampling seed; the same seed, prompt and options reproduce the same output
+    pub seed: Option<u64>,
+    /// Context window in tokens (local backends only)
+    pub num_ctx: Option<u32>,
+}
+
+impl GenerationOptions {
+    /// Options carrying only the process-wide seed (`AGENCY_SEED`), if any
+    pub fn from_env() -> Self {
+        Self {
+            seed: std::env::var("AGENCY_SEED").ok().and_then(|s| s.parse().ok()),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_stop(mut self, stop: Vec<String>) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn with_num_ctx(mut self, num_ctx: u32) -> Self {
+        self.num_ctx = Some(num_ctx);
+        self
+    }
+}
+
+/// Token counts reported by a backend for one or more calls
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+}
+
+impl TokenUsage {
+    pub fn new(prompt_tokens: u32, completion_tokens: u32) -> Self {
+        Self { prompt_tokens, completion_tokens }
+    }
+
+    pub fn total_tokens(&self) -> u32 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    /// Usage from an OpenAI-style `usage` object
+    pub(crate) fn from_openai(usage: &serde_json::Value) -> Option<Self> {
+        Some(Self::new(
+            usage["prompt_tokens"].as_u64()? as u32,
+            usage["completion_tokens"].as_u64().unwrap_or(0) as u32,
+        ))
+    }
+
+    /// Usage from an Ollama final response (`prompt_eval_count` / `eval_count`)
+    fn from_ollama(response: &serde_json::Value) -> Option<Self> {
+        let prompt = response["prompt_eval_count"].as_u64();
+        let completion = response["eval_count"].as_u64();
+        if prompt.is_none() && completion.is_none() {
+            return None;
+        }
+        Some(Self::new(prompt.unwrap_or(0) as u32, completion.unwrap_or(0) as u32))
+    }
+}
+
+impl std::ops::Add for TokenUsage {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.prompt_tokens + other.prompt_tokens, self.completion_tokens + other.completion_tokens)
+    }
+}
+
+impl std::ops::AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+/// Metadata a backend reports at the end of a streamed call: usage, finish
+/// reason and any tool calls. Filled by the provider, read once drained.
+#[derive(Debug, Clone, Default)]
+pub struct StreamTrailer(Arc<std::sync::Mutex<TrailerData>>);
+
+#[derive(Debug, Default)]
+struct TrailerData {
+    usage: TokenUsage,
+    finish_reason: Option<String>,
+    tool_calls: Vec<ToolCall>,
+}
+
+impl StreamTrailer {
+    pub fn set_usage(&self, usage: TokenUsage) {
+        if let Ok(mut data) = self.0.lock() {
+            data.usage = usage;
+        }
+    }
+
+    pub fn usage(&self) -> TokenUsage {
+        self.0.lock().map(|data| data.usage).unwrap_or_default()
+    }
+
+    pub fn set_finish_reason(&self, reason: impl Into<String>) {
+        if let Ok(mut data) = self.0.lock() {
+            data.finish_reason = Some(reason.into());
+        }
+    }
+
+    pub fn finish_reason(&self) -> Option<String> {
+        self.0.lock().ok().and_then(|data| data.finish_reason.clone())
+    }
+
+    pub fn push_tool_calls(&self, calls: Vec<ToolCall>) {
+        if let Ok(mut data) = self.0.lock() {
+            data.tool_calls.extend(calls);
+        }
+    }
+
+    pub fn tool_calls(&self) -> Vec<ToolCall> {
+        self.0.lock().map(|data| data.tool_calls.clone()).unwrap_or_default()
+    }
+}
+
+/// A streamed chat response. Usage, finish reason and streamed tool calls are
+/// available once the stream is drained; backends that report none leave them empty.
+pub struct ChatStream {
+    inner: BoxStream<'static, Result<String>>,
+    trailer: StreamTrailer,
+}
+
+impl ChatStream {
+    pub fn new(inner: BoxStream<'static, Result<String>>, trailer: StreamTrailer) -> Self {
+        Self { inner, trailer }
+    }
+
+    /// Wrap a stream whose backend reports no trailer
+    pub fn without_usage(inner: BoxStream<'static, Result<String>>) -> Self {
+        Self::new(inner, StreamTrailer::default())
+    }
+
+    /// A finished completion replayed as a single chunk, tool calls and usage in the trailer
+    pub fn from_completion(completion: ToolCompletion) -> Self {
+        let trailer = StreamTrailer::default();
+        trailer.set_usage(completion.usage);
+        trailer.push_tool_calls(completion.tool_calls);
+        let chunks = Some(completion.content).filter(|c| !c.is_empty()).into_iter().map(Ok);
+        Self::new(Box::pin(futures_util::stream::iter(chunks)), trailer)
+    }
+
+    pub fn usage(&self) -> TokenUsage {
+        self.trailer.usage()
+    }
+
+    pub fn finish_reason(&self) -> Option<String> {
+        self.trailer.finish_reason()
+    }
+
+    pub fn tool_calls(&self) -> Vec<ToolCall> {
+        self.trailer.tool_calls()
+    }
+
+    /// A handle to the trailer, readable after the stream has been consumed elsewhere
+    pub fn trailer(&self) -> StreamTrailer {
+        self.trailer.clone()
+    }
+
+    /// Replace the token stream, keeping the trailer
+    pub fn map_inner(self, f: impl FnOnce(BoxStream<'static, Result<String>>) -> BoxStream<'static, Result<String>>) -> Self {
+        Self { inner: f(self.inner), trailer: self.trailer }
+    }
+
+    /// End the stream with an error as soon as `token` is cancelled. The inner
+    /// stream is dropped at that point, which closes the backend connection.
+    pub fn with_cancellation(self, token: CancellationToken) -> Self {
+        self.map_inner(|inner| Box::pin(futures_util::stream::unfold(Some((inner, token)), |state| async move {
+            let (mut inner, token) = state?;
+            tokio::select! {
+                biased;
+                _ = token.cancelled() => Some((Err(anyhow::anyhow!("stream cancelled")), None)),
+                item = inner.next() => item.map(|item| (item, Some((inner, token)))),
+            }
+        })))
+    }
+
+    /// Drop the trailer and return a plain token stream
+    pub fn boxed(self) -> BoxStream<'static, Result<String>> {
+        self.inner
+    }
+
+    /// Drain the stream into a full completion
+    pub async fn collect(mut self) -> Result<ChatCompletion> {
+        let mut content = String::new();
+        while let Some(chunk) = self.inner.next().await {
+            content.push_str(&chunk?);
+        }
+        Ok(ChatCompletion { content, usage: self.trailer.usage(), finish_reason: self.trailer.finish_reason() })
+    }
+}
+
+impl futures_util::Stream for ChatStream {
+    type Item = Result<String>;
+
+    fn poll_next(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+/// Result of a chat call
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChatCompletion {
+    pub content: String,
+    pub usage: TokenUsage,
+    /// Why generation stopped (`stop`, `length`, ...), when the backend says
+    pub finish_reason: Option<String>,
+}
+
+/// Result of a generation that may contain native (typed) tool calls
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ToolCompletion {
+    /// Free-text content produced alongside (or instead of) tool calls
+    pub content: String,
+    /// Tool calls returned by the backend's structured tool-calling interface
+    pub tool_calls: Vec<ToolCall>,
+    /// Tokens consumed by the call
+    pub usage: TokenUsage,
+}
+
 #[async_trait]
 pub trait LLMProvider: Send + Sync {
     async fn generate(&self, model: &str, prompt: String, system: Option<String>) -> Result<String>;
     async fn generate_stream(&self, model: &str, prompt: String, system: Option<String>) -> Result<BoxStream<'static, Result<String>>>;
+    /// Multi-turn chat with real role boundaries.
+    /// Providers without a native chat endpoint receive the conversation flattened into one prompt.
+    async fn chat(&self, model: &str, messages: Vec<ChatMessage>, _options: GenerationOptions) -> Result<ChatCompletion> {
+        let (system, prompt) = flatten_messages(&messages);
+        let content = self.generate(model, prompt, system).await?;
+        Ok(ChatCompletion { content, ..Default::default() })
+    }
+    /// Streaming variant of `chat`
+    async fn chat_stream(&self, model: &str, messages: Vec<ChatMessage>, _options: GenerationOptions) -> Result<ChatStream> {
+        let (system, prompt) = flatten_messages(&messages);
+        Ok(ChatStream::without_usage(self.generate_stream(model, prompt, system).await?))
+    }
+    /// Whether the backend accepts tool schemas as native function definitions
+    fn supports_native_tools(&self) -> bool {
+        false
+    }
+    /// Chat with tool schemas passed as native function definitions.
+    /// Providers without native support fall back to plain chat and leave
+    /// tool-call recovery to the caller's text parser.
+    async fn chat_with_tools(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions, _tools: &[ToolDefinition]) -> Result<ToolCompletion> {
+        let completion = self.chat(model, messages, options).await?;
+        Ok(ToolCompletion { content: completion.content, tool_calls: Vec::new(), usage: completion.usage })
+    }
+    /// Streaming variant of `chat_with_tools`: content arrives as it is
+    /// generated and typed tool calls land in the trailer. Backends without a
+    /// streaming tool-call endpoint replay `chat_with_tools` as one chunk.
+    async fn chat_stream_with_tools(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions, tools: &[ToolDefinition]) -> Result<ChatStream> {
+        Ok(ChatStream::from_completion(self.chat_with_tools(model, messages, options, tools).await?))
+    }
     /// Get a clone of the hardware lock
     fn get_lock(&self) -> Arc<Mutex<()>>;
     /// Send a notification message back to the user/UI
@@ -36,6 +383,261 @@ pub trait LLMProvider: Send + Sync {
     }
 }
 
+/// Build the two-message conversation equivalent to a `generate(prompt, system)` call
+pub fn messages_from_prompt(prompt: String, system: Option<String>) -> Vec<ChatMessage> {
+    let mut messages = Vec::new();
+    if let Some(sys) = system {
+        messages.push(ChatMessage::system(sys));
+    }
+    messages.push(ChatMessage::user(prompt));
+    messages
+}
+
+/// Flatten a conversation into `(system, prompt)` for single-prompt backends.
+/// A lone user message is passed through untouched.
+pub fn flatten_messages(messages: &[ChatMessage]) -> (Option<String>, String) {
+    let system = messages.iter()
+        .filter(|m| m.role == ChatRole::System)
+        .map(|m| m.content.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let turns: Vec<&ChatMessage> = messages.iter().filter(|m| m.role != ChatRole::System).collect();
+
+    let prompt = match turns.as_slice() {
+        [only] if only.role == ChatRole::User => only.content.clone(),
+        _ => turns.iter()
+            .map(|m| match m.role {
+                ChatRole::User => format!("User: {}", m.content),
+                ChatRole::Assistant => format!("Assistant: {}", m.content),
+                ChatRole::Tool => format!("Tool ({}): {}", m.tool_name.as_deref().unwrap_or("unknown"), m.content),
+                ChatRole::System => unreachable!(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+    };
+
+    ((!system.is_empty()).then_some(system), prompt)
+}
+
+/// Render messages in the OpenAI chat format.
+/// Tool results are paired with the preceding assistant tool calls by generated ids;
+/// a tool result with no pending call is downgraded to a user message.
+fn openai_messages(messages: &[ChatMessage]) -> Vec<serde_json::Value> {
+    let mut rendered = Vec::new();
+    let mut pending_ids = std::collections::VecDeque::new();
+    let mut next_id = 0usize;
+
+    for message in messages {
+        match message.role {
+            ChatRole::Assistant if !message.tool_calls.is_empty() => {
+                let calls: Vec<serde_json::Value> = message.tool_calls.iter().map(|call| {
+                    let id = format!("call_{}", next_id);
+                    next_id += 1;
+                    pending_ids.push_back(id.clone());
+                    json!({
+                        "id": id,
+                        "type": "function",
+                        "function": { "name": call.name, "arguments": call.parameters.to_string() }
+                    })
+                }).collect();
+                rendered.push(json!({ "role": "assistant", "content": message.content, "tool_calls": calls }));
+            }
+            ChatRole::Tool => match pending_ids.pop_front() {
+                Some(id) => rendered.push(json!({ "role": "tool", "tool_call_id": id, "content": message.content })),
+                None => rendered.push(json!({
+                    "role": "user",
+                    "content": format!("[Tool result: {}]\n{}", message.tool_name.as_deref().unwrap_or("unknown"), message.content)
+                })),
+            },
+            role => rendered.push(json!({ "role": role.as_str(), "content": message.content })),
+        }
+    }
+    rendered
+}
+
+/// Render messages in the Ollama chat format (tool calls carry object arguments, no ids)
+fn ollama_messages(messages: &[ChatMessage]) -> Vec<serde_json::Value> {
+    messages.iter().map(|message| {
+        let mut rendered = json!({ "role": message.role.as_str(), "content": message.content });
+        if !message.tool_calls.is_empty() {
+            rendered["tool_calls"] = json!(message.tool_calls.iter()
+                .map(|call| json!({ "function": { "name": call.name, "arguments": call.parameters } }))
+                .collect::<Vec<_>>());
+        }
+        if let Some(ref name) = message.tool_name {
+            rendered["tool_name"] = json!(name);
+        }
+        rendered
+    }).collect()
+}
+
+/// Non-success HTTP response from a backend. The status lets failover tell a
+/// broken backend (5xx, 429) from a request it will never accept (other 4xx).
+#[derive(Debug, thiserror::Error)]
+#[error("{message}")]
+pub struct HttpStatusError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl HttpStatusError {
+    pub fn new(status: reqwest::StatusCode, message: String) -> Self {
+        Self { status: status.as_u16(), message }
+    }
+}
+
+/// Decode an OpenAI-compatible SSE response body into a chat stream.
+/// Server errors end the stream with an `Err`; usage, finish reason and
+/// streamed tool calls land in the trailer.
+fn openai_sse_stream(res: reqwest::Response, label: &'static str) -> ChatStream {
+    let trailer = StreamTrailer::default();
+    let sink = trailer.clone();
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+
+    tokio::task::spawn(async move {
+        let mut body = res.bytes_stream();
+        let mut decoder = ChatDeltaDecoder::new();
+        loop {
+            // Stop reading (and drop the connection) once the consumer goes away
+            let next = tokio::select! {
+                _ = tx.closed() => return,
+                next = body.next() => next,
+            };
+            let (deltas, eof) = match next {
+                Some(Ok(bytes)) => (decoder.push(&bytes), false),
+                Some(Err(e)) => {
+                    let _ = tx.send(Err(anyhow::anyhow!("{} stream error: {}", label, e)));
+                    return;
+                }
+                None => (decoder.finish(), true),
+            };
+            for delta in deltas {
+                match delta {
+                    ChatDelta::Content(text) => {
+                        if tx.send(Ok(text)).is_err() {
+                            return; // receiver dropped
+                        }
+                    }
+                    ChatDelta::ToolCalls(calls) => sink.push_tool_calls(calls),
+                    ChatDelta::Finish(reason) => {
+                        if reason == "length" {
+                            warn!("{} stream truncated at max_tokens", label);
+                        }
+                        sink.set_finish_reason(reason);
+                    }
+                    ChatDelta::Usage(usage) => sink.set_usage(usage),
+                    ChatDelta::Error(message) => {
+                        let _ = tx.send(Err(anyhow::anyhow!("{} stream error: {}", label, message)));
+                        return;
+                    }
+                    ChatDelta::Done => {}
+                }
+            }
+            if eof || decoder.is_done() {
+                return;
+            }
+        }
+    });
+
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|val| (val, rx))
+    });
+    ChatStream::new(Box::pin(stream), trailer)
+}
+
+/// Decode an Ollama `/api/chat` NDJSON response body into a chat stream.
+/// Lines are framed on raw bytes, so characters split across network chunks
+/// survive; usage, finish reason and tool calls land in the trailer.
+fn ollama_ndjson_stream(res: reqwest::Response, label: &'static str) -> ChatStream {
+    let trailer = StreamTrailer::default();
+    let sink = trailer.clone();
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+
+    tokio::task::spawn(async move {
+        let mut body = res.bytes_stream();
+        let mut buffer: Vec<u8> = Vec::new();
+        loop {
+            let next = tokio::select! {
+                _ = tx.closed() => return,
+                next = body.next() => next,
+            };
+            let eof = match next {
+                Some(Ok(bytes)) => {
+                    buffer.extend_from_slice(&bytes);
+                    false
+                }
+                Some(Err(e)) => {
+                    error!("❌ {} stream network error: {}", label, e);
+                    let _ = tx.send(Err(anyhow::anyhow!("{} stream error: {}", label, e)));
+                    return;
+                }
+                None => true,
+            };
+            let mut lines: Vec<Vec<u8>> = Vec::new();
+            while let Some(newline) = buffer.iter().position(|&b| b == b'\n') {
+                lines.push(buffer.drain(..=newline).collect());
+            }
+            if eof {
+                lines.push(std::mem::take(&mut buffer));
+            }
+
+            for line in lines {
+                let line = String::from_utf8_lossy(&line);
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                let json = match serde_json::from_str::<serde_json::Value>(line) {
+                    Ok(json) => json,
+                    Err(e) => {
+                        error!("❌ {} Parse Error: {} (Content: {})", label, e, line);
+                        continue;
+                    }
+                };
+                if let Some(message) = json["error"].as_str() {
+                    let _ = tx.send(Err(anyhow::anyhow!("{} stream error: {}", label, message)));
+                    return;
+                }
+                let calls = parse_tool_calls(&json["message"]);
+                if !calls.is_empty() {
+                    sink.push_tool_calls(calls);
+                }
+                if let Some(content) = json["message"]["content"].as_str().filter(|c| !c.is_empty()) {
+                    if tx.send(Ok(content.to_string())).is_err() {
+                        return; // receiver dropped
+                    }
+                }
+                if json["done"].as_bool().unwrap_or(false) {
+                    if let Some(usage) = TokenUsage::from_ollama(&json) {
+                        sink.set_usage(usage);
+                    }
+                    if let Some(reason) = json["done_reason"].as_str() {
+                        sink.set_finish_reason(reason);
+                    }
+                    return;
+                }
+            }
+            if eof {
+                return;
+            }
+        }
+    });
+
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|val| (val, rx))
+    });
+    ChatStream::new(Box::pin(stream), trailer)
+}
+
+/// Collect a token stream into the full response text
+async fn collect_stream(mut stream: BoxStream<'static, Result<String>>) -> Result<String> {
+    let mut full_text = String::new();
+    while let Some(chunk) = stream.next().await {
+        full_text.push_str(&chunk?);
+    }
+    Ok(full_text)
+}
+
 /// Provider that wraps another provider and publishes tokens/notifications to a broadcast channel
 pub struct PublishingProvider {
     inner: Arc<dyn LLMProvider>,
@@ -46,21 +648,9 @@ impl PublishingProvider {
     pub fn new(inner: Arc<dyn LLMProvider>, tx: tokio::sync::broadcast::Sender<String>) -> Self {
         Self { inner, tx }
     }
-}
-
-#[async_trait]
-impl LLMProvider for PublishingProvider {
-    async fn generate(&self, model: &str, prompt: String, system: Option<String>) -> Result<String> {
-        let mut stream = self.generate_stream(model, prompt, system).await?;
-        let mut full_text = String::new();
-        while let Some(chunk) = stream.next().await {
-            full_text.push_str(&chunk?);
-        }
-        Ok(full_text)
-    }
 
-    async fn generate_stream(&self, model: &str, prompt: String, system: Option<String>) -> Result<BoxStream<'static, Result<String>>> {
-        let stream = self.inner.generate_stream(model, prompt, system).await?;
+    /// Mirror every token of `stream` onto the broadcast channel
+    fn publish(&self, stream: BoxStream<'static, Result<String>>) -> BoxStream<'static, Result<String>> {
         let tx = self.tx.clone();
 
         let mapped_stream = futures_util::stream::unfold((stream, String::new(), false), move |(mut s, mut buffer, mut answer_detected)| {
@@ -88,7 +678,45 @@ impl LLMProvider for PublishingProvider {
             }
         });
 
-        Ok(Box::pin(mapped_stream))
+        Box::pin(mapped_stream)
+    }
+}
+
+#[async_trait]
+impl LLMProvider for PublishingProvider {
+    async fn generate(&self, model: &str, prompt: String, system: Option<String>) -> Result<String> {
+        collect_stream(self.generate_stream(model, prompt, system).await?).await
+    }
+
+    async fn generate_stream(&self, model: &str, prompt: String, system: Option<String>) -> Result<BoxStream<'static, Result<String>>> {
+        let stream = self.inner.generate_stream(model, prompt, system).await?;
+        Ok(self.publish(stream))
+    }
+
+    async fn chat(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions) -> Result<ChatCompletion> {
+        self.chat_stream(model, messages, options).await?.collect().await
+    }
+
+    async fn chat_stream(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions) -> Result<ChatStream> {
+        let stream = self.inner.chat_stream(model, messages, options).await?;
+        Ok(stream.map_inner(|inner| self.publish(inner)))
+    }
+
+    fn supports_native_tools(&self) -> bool {
+        self.inner.supports_native_tools()
+    }
+
+    async fn chat_with_tools(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions, tools: &[ToolDefinition]) -> Result<ToolCompletion> {
+        let completion = self.inner.chat_with_tools(model, messages, options, tools).await?;
+        if !completion.content.is_empty() {
+            let _ = self.tx.send(format!("TOKEN:{}", completion.content));
+        }
+        Ok(completion)
+    }
+
+    async fn chat_stream_with_tools(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions, tools: &[ToolDefinition]) -> Result<ChatStream> {
+        let stream = self.inner.chat_stream_with_tools(model, messages, options, tools).await?;
+        Ok(stream.map_inner(|inner| self.publish(inner)))
     }
 
     fn get_lock(&self) -> Arc<Mutex<()>> {
@@ -101,6 +729,78 @@ impl LLMProvider for PublishingProvider {
     }
 }
 
+/// Apply options to an OpenAI-style request body. `num_ctx` has no OpenAI equivalent.
+fn apply_openai_options(body: &mut serde_json::Value, options: &GenerationOptions) {
+    if let Some(temperature) = options.temperature {
+        body["temperature"] = json!(temperature);
+    }
+    if let Some(max_tokens) = options.max_tokens {
+        body["max_tokens"] = json!(max_tokens);
+    }
+    if !options.stop.is_empty() {
+        body["stop"] = json!(options.stop);
+    }
+    if let Some(seed) = options.seed {
+        body["seed"] = json!(seed);
+    }
+}
+
+/// Ollama `options` object for a raw `/api/chat` call
+fn ollama_options(options: &GenerationOptions) -> serde_json::Value {
+    let mut model_options = json!({});
+    if let Some(temperature) = options.temperature {
+        model_options["temperature"] = json!(temperature);
+    }
+    if let Some(max_tokens) = options.max_tokens {
+        model_options["num_predict"] = json!(max_tokens);
+    }
+    if !options.stop.is_empty() {
+        model_options["stop"] = json!(options.stop);
+    }
+    if let Some(seed) = options.seed {
+        model_options["seed"] = json!(seed);
+    }
+    if let Some(num_ctx) = options.num_ctx {
+        model_options["num_ctx"] = json!(num_ctx);
+    }
+    model_options
+}
+
+/// Convert a tool definition into the OpenAI `tools` entry format (also accepted by Ollama)
+fn function_spec(tool: &ToolDefinition) -> serde_json::Value {
+    json!({
+        "type": "function",
+        "function": {
+            "name": tool.name,
+            "description": tool.description,
+            "parameters": tool.parameters,
+        }
+    })
+}
+
+/// Extract typed tool calls from an assistant message.
+/// OpenAI encodes `arguments` as a JSON string, Ollama as an object; both are accepted.
+pub(crate) fn parse_tool_calls(message: &serde_json::Value) -> Vec<ToolCall> {
+    let Some(calls) = message["tool_calls"].as_array() else {
+        return Vec::new();
+    };
+
+    calls.iter().filter_map(|call| {
+        let function = &call["function"];
+        let name = function["name"].as_str()?.to_string();
+        let parameters = match &function["arguments"] {
+            serde_json::Value::String(raw) if raw.trim().is_empty() => json!({}),
+            serde_json::Value::String(raw) => serde_json::from_str(raw).unwrap_or_else(|e| {
+                warn!("Tool call '{}' has malformed arguments ({}); passing raw string", name, e);
+                serde_json::Value::String(raw.clone())
+            }),
+            serde_json::Value::Null => json!({}),
+            other => other.clone(),
+        };
+        Some(ToolCall { name, parameters })
+    }).collect()
+}
+
 enum LoadedModel {
     Llama(llama_model::Llama, Arc<Mutex<llama_model::Cache>>, Tokenizer),
     Quantized(Arc<Mutex<quantized_llama::ModelWeights>>, Tokenizer),
@@ -315,26 +1015,159 @@ impl CandleProvider {
     }
 }
 
-#[async_trait]
-impl LLMProvider for CandleProvider {
-    async fn generate(&self, model_name: &str, prompt: String, system: Option<String>) -> Result<String> {
-        let mut stream = self.generate_stream(model_name, prompt, system).await?;
-        let mut full_text = String::new();
-        while let Some(chunk) = stream.next().await {
-            full_text.push_str(&chunk?);
+/// Sampler for native inference: seeded, greedy at temperature 0
+fn logits_processor(options: &GenerationOptions) -> LogitsProcessor {
+    let temperature = options.temperature.unwrap_or(0.7) as f64;
+    let temperature = (temperature > 0.0).then_some(temperature);
+    LogitsProcessor::new(options.seed.unwrap_or(42), temperature, Some(0.9))
+}
+
+/// Tokens opening each turn of the chat templates above
+const TURN_MARKERS: [&str; 2] = ["<|start_header_id|>", "<|im_start|>"];
+
+/// Tokenize a formatted prompt, clipped to `num_ctx` tokens
+fn encode_prompt(tokenizer: &Tokenizer, prompt: String, add_bos: bool, options: &GenerationOptions) -> Result<Vec<u32>> {
+    let mut tokens = tokenizer.encode(prompt.as_str(), add_bos).map_err(anyhow::Error::msg)?.get_ids().to_vec();
+    if options.num_ctx.is_some_and(|n| tokens.len() > n as usize) {
+        let keep = match system_block_len(&prompt) {
+            0 => add_bos as usize,
+            len => tokenizer.encode(&prompt[..len], add_bos).map_err(anyhow::Error::msg)?.get_ids().len(),
+        };
+        let turn_start = TURN_MARKERS.iter().find(|m| prompt.contains(*m)).and_then(|m| tokenizer.token_to_id(m));
+        clip_context(&mut tokens, keep, turn_start, options);
+    }
+    Ok(tokens)
+}
+
+/// Byte length of a formatted prompt's leading system block (up to the
+/// next turn), 0 if it has none
+fn system_block_len(prompt: &str) -> usize {
+    const BLOCKS: [(&str, &str); 3] = [
+        ("<|start_header_id|>system<|end_header_id|>", "<|start_header_id|>"),
+        ("<|im_start|>system", "<|im_start|>"),
+        ("<|system|>", "<|user|>"),
+    ];
+    let body = prompt.trim_start_matches("<|begin_of_text|>");
+    let offset = prompt.len() - body.len();
+    BLOCKS
+        .iter()
+        .filter(|(open, _)| body.starts_with(open))
+        .find_map(|(open, next)| body[open.len()..].find(next).map(|i| offset + open.len() + i))
+        .unwrap_or(0)
+}
+
+/// Drop the oldest prompt tokens after the first `keep` (BOS and system
+/// block) until at most `num_ctx` remain. With a `turn_start` token the
+/// rest starts at a turn boundary instead of mid-turn; the final turn is
+/// never dropped that way.
+fn clip_context(tokens: &mut Vec<u32>, keep: usize, turn_start: Option<u32>, options: &GenerationOptions) {
+    let Some(num_ctx) = options.num_ctx.map(|n| n as usize) else { return };
+    if tokens.len() <= num_ctx {
+        return;
+    }
+    let keep = keep.min(num_ctx);
+    let mut cut = keep + tokens.len() - num_ctx;
+    if let Some(turn_start) = turn_start {
+        let last = tokens.iter().rposition(|&t| t == turn_start).unwrap_or(0);
+        if cut < last {
+            if let Some(next) = tokens[cut..last].iter().position(|&t| t == turn_start) {
+                cut += next;
+            }
         }
-        Ok(full_text)
     }
+    tokens.drain(keep..cut);
+}
 
-    async fn generate_stream(&self, model_name: &str, prompt: String, system: Option<String>) -> Result<BoxStream<'static, Result<String>>> {
-        let lock = self.lock.clone();
-        let device = self.device.clone();
-        
-        self.get_or_load_model(model_name).await?;
-        let mut models_guard = self.models.lock().await;
-        let loaded_model = models_guard.get_mut(model_name).context("Model failed to load")?;
+/// Byte offset of the earliest stop sequence in `text`
+fn find_stop(text: &str, stop: &[String]) -> Option<usize> {
+    stop.iter().filter(|s| !s.is_empty()).filter_map(|s| text.find(s.as_str())).min()
+}
 
-        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+/// Forwards decoded chunks up to the first stop sequence. The last
+/// `longest stop - 1` bytes are held back until the next chunk, since a
+/// stop sequence may be split across chunks.
+struct StopFilter {
+    stop: Vec<String>,
+    generated: String,
+    sent: usize,
+    stopped: bool,
+}
+
+impl StopFilter {
+    fn new(stop: Vec<String>) -> Self {
+        Self { stop, generated: String::new(), sent: 0, stopped: false }
+    }
+
+    /// Append a decoded chunk and forward what can no longer be part of a
+    /// stop sequence. Returns false once generation should end.
+    fn push(&mut self, chunk: &str, tx: &tokio::sync::mpsc::UnboundedSender<Result<String>>) -> bool {
+        self.generated.push_str(chunk);
+        if let Some(idx) = find_stop(&self.generated, &self.stop) {
+            self.stopped = true;
+            self.send_until(idx, tx);
+            return false;
+        }
+        let hold = self.stop.iter().map(String::len).max().unwrap_or(0).saturating_sub(1);
+        let mut end = self.generated.len().saturating_sub(hold);
+        while !self.generated.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.send_until(end, tx)
+    }
+
+    /// Forward the held-back tail once generation ended without a stop sequence
+    fn finish(&mut self, tx: &tokio::sync::mpsc::UnboundedSender<Result<String>>) {
+        if !self.stopped {
+            self.send_until(self.generated.len(), tx);
+        }
+    }
+
+    fn send_until(&mut self, end: usize, tx: &tokio::sync::mpsc::UnboundedSender<Result<String>>) -> bool {
+        if end <= self.sent {
+            return !tx.is_closed();
+        }
+        let sent = tx.send(Ok(self.generated[self.sent..end].to_string())).is_ok();
+        self.sent = end;
+        sent
+    }
+}
+
+#[async_trait]
+impl LLMProvider for CandleProvider {
+    async fn generate(&self, model_name: &str, prompt: String, system: Option<String>) -> Result<String> {
+        collect_stream(self.generate_stream(model_name, prompt, system).await?).await
+    }
+
+    async fn generate_stream(&self, model_name: &str, prompt: String, system: Option<String>) -> Result<BoxStream<'static, Result<String>>> {
+        Ok(self.sample_stream(model_name, prompt, system, GenerationOptions::default()).await?.boxed())
+    }
+
+    async fn chat(&self, model_name: &str, messages: Vec<ChatMessage>, options: GenerationOptions) -> Result<ChatCompletion> {
+        self.chat_stream(model_name, messages, options).await?.collect().await
+    }
+
+    async fn chat_stream(&self, model_name: &str, messages: Vec<ChatMessage>, options: GenerationOptions) -> Result<ChatStream> {
+        let (system, prompt) = flatten_messages(&messages);
+        self.sample_stream(model_name, prompt, system, options).await
+    }
+
+    fn get_lock(&self) -> Arc<Mutex<()>> {
+        self.lock.clone()
+    }
+}
+
+impl CandleProvider {
+    /// Run native sampling; usage is counted with the model's own tokenizer
+    async fn sample_stream(&self, model_name: &str, prompt: String, system: Option<String>, options: GenerationOptions) -> Result<ChatStream> {
+        let lock = self.lock.clone();
+        let usage = StreamTrailer::default();
+        let device = self.device.clone();
+        
+        self.get_or_load_model(model_name).await?;
+        let mut models_guard = self.models.lock().await;
+        let loaded_model = models_guard.get_mut(model_name).context("Model failed to load")?;
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
 
         match loaded_model {
             LoadedModel::Llama(model, cache_lock, tokenizer) => {
@@ -357,14 +1190,18 @@ impl LLMProvider for CandleProvider {
                 };
 
                 let add_bos = !model_name_lower.contains("qwen") && !has_bos;
-                let mut tokens = tokenizer.encode(full_prompt, add_bos).map_err(anyhow::Error::msg)?.get_ids().to_vec();
-                let mut lp = LogitsProcessor::new(42, Some(0.7), Some(0.9));
+                let mut tokens = encode_prompt(&tokenizer, full_prompt, add_bos, &options)?;
+                let mut lp = logits_processor(&options);
+                let max_tokens = options.max_tokens.map_or(1024, |m| m as usize);
+                let mut filter = StopFilter::new(options.stop.clone());
+                let prompt_tokens = tokens.len() as u32;
+                let usage_sink = usage.clone();
 
                 tokio::task::spawn_blocking(move || {
                     let mut cache = futures::executor::block_on(cache_lock.lock());
                     cache.clear();
                     
-                    for step in 0..1024 {
+                    for step in 0..max_tokens {
                         let _guard = futures::executor::block_on(lock.lock());
                         let context_size = if step > 0 { 1 } else { tokens.len() };
                         let start_pos = tokens.len().saturating_sub(context_size);
@@ -398,11 +1235,13 @@ impl LLMProvider for CandleProvider {
                         match tokenizer.decode(&[next_token], true) {
                             Ok(chunk) => {
                                 tokens.push(next_token);
-                                if tx.send(Ok(chunk)).is_err() { break; }
+                                if !filter.push(&chunk, &tx) { break; }
                             },
                             Err(e) => { let _ = tx.send(Err(anyhow::anyhow!("Decode error: {}", e))); break; }
                         }
                     }
+                    filter.finish(&tx);
+                    usage_sink.set_usage(TokenUsage::new(prompt_tokens, (tokens.len() as u32).saturating_sub(prompt_tokens)));
                 });
             },
             LoadedModel::Quantized(model_mutex, tokenizer) => {
@@ -445,13 +1284,17 @@ impl LLMProvider for CandleProvider {
                 };
 
                 let add_bos = !model_name_lower.contains("qwen") && !has_bos;
-                let mut tokens = tokenizer.encode(full_prompt, add_bos).map_err(anyhow::Error::msg)?.get_ids().to_vec();
-                let mut lp = LogitsProcessor::new(42, Some(0.7), Some(0.9));
+                let mut tokens = encode_prompt(&tokenizer, full_prompt, add_bos, &options)?;
+                let mut lp = logits_processor(&options);
+                let max_tokens = options.max_tokens.map_or(1024, |m| m as usize);
+                let mut filter = StopFilter::new(options.stop.clone());
+                let prompt_tokens = tokens.len() as u32;
+                let usage_sink = usage.clone();
 
                 tokio::task::spawn_blocking(move || {
                     let mut model = futures::executor::block_on(model_mutex.lock());
                     
-                    for step in 0..1024 {
+                    for step in 0..max_tokens {
                         let _guard = futures::executor::block_on(lock.lock());
                         let context_size = if step > 0 { 1 } else { tokens.len() };
                         let start_pos = tokens.len().saturating_sub(context_size);
@@ -493,11 +1336,13 @@ impl LLMProvider for CandleProvider {
                         match tokenizer.decode(&[next_token], true) {
                             Ok(chunk) => {
                                 tokens.push(next_token);
-                                if tx.send(Ok(chunk)).is_err() { break; }
+                                if !filter.push(&chunk, &tx) { break; }
                             },
                             Err(e) => { let _ = tx.send(Err(anyhow::anyhow!("Decode error: {}", e))); break; }
                         }
                     }
+                    filter.finish(&tx);
+                    usage_sink.set_usage(TokenUsage::new(prompt_tokens, (tokens.len() as u32).saturating_sub(prompt_tokens)));
                 });
             },
             LoadedModel::Reasoner(model_mutex, tokenizer) => {
@@ -513,14 +1358,18 @@ impl LLMProvider for CandleProvider {
                 };
 
                 let add_bos = false; // Qwen models don't use BOS
-                let mut tokens = tokenizer.encode(full_prompt, add_bos).map_err(anyhow::Error::msg)?.get_ids().to_vec();
-                let mut lp = LogitsProcessor::new(42, Some(0.7), Some(0.9));
+                let mut tokens = encode_prompt(&tokenizer, full_prompt, add_bos, &options)?;
+                let mut lp = logits_processor(&options);
+                let max_tokens = options.max_tokens.map_or(2048, |m| m as usize);
+                let mut filter = StopFilter::new(options.stop.clone());
+                let prompt_tokens = tokens.len() as u32;
+                let usage_sink = usage.clone();
 
                 tokio::task::spawn_blocking(move || {
                     let mut model = futures::executor::block_on(model_mutex.lock());
                     model.clear_cache();
                     
-                    for step in 0..2048 {
+                    for step in 0..max_tokens {
                         let _guard = futures::executor::block_on(lock.lock());
                         let context_size = if step > 0 { 1 } else { tokens.len() };
                         let start_pos = tokens.len().saturating_sub(context_size);
@@ -554,11 +1403,13 @@ impl LLMProvider for CandleProvider {
                         match tokenizer.decode(&[next_token], true) {
                             Ok(chunk) => {
                                 tokens.push(next_token);
-                                if tx.send(Ok(chunk)).is_err() { break; }
+                                if !filter.push(&chunk, &tx) { break; }
                             },
                             Err(e) => { let _ = tx.send(Err(anyhow::anyhow!("Decode error: {}", e))); break; }
                         }
                     }
+                    filter.finish(&tx);
+                    usage_sink.set_usage(TokenUsage::new(prompt_tokens, (tokens.len() as u32).saturating_sub(prompt_tokens)));
                 });
             }
         }
@@ -566,16 +1417,13 @@ impl LLMProvider for CandleProvider {
         let stream = futures_util::stream::unfold(rx, |mut rx| async move {{
             rx.recv().await.map(|val| (val, rx))
         }});
-        Ok(Box::pin(stream))
-    }
-
-    fn get_lock(&self) -> Arc<Mutex<()>> {
-        self.lock.clone()
+        Ok(ChatStream::new(Box::pin(stream), usage))
     }
 }
 
 pub struct OllamaProvider {
     client: ollama_rs::Ollama,
+    http: Client,
     lock: Arc<Mutex<()>>,
 }
 
@@ -583,6 +1431,7 @@ impl OllamaProvider {
     pub fn new(client: ollama_rs::Ollama) -> Self {
         Self {
             client,
+            http: Client::new(),
             lock: GLOBAL_HW_LOCK.clone(),
         }
     }
@@ -591,42 +1440,76 @@ impl OllamaProvider {
 #[async_trait]
 impl LLMProvider for OllamaProvider {
     async fn generate(&self, model: &str, prompt: String, system: Option<String>) -> Result<String> {
-        let mut stream = self.generate_stream(model, prompt, system).await?;
-        let mut full_text = String::new();
-        while let Some(chunk) = stream.next().await {
-            full_text.push_str(&chunk?);
-        }
-        Ok(full_text)
+        Ok(self.chat(model, messages_from_prompt(prompt, system), GenerationOptions::default()).await?.content)
     }
 
     async fn generate_stream(&self, model: &str, prompt: String, system: Option<String>) -> Result<BoxStream<'static, Result<String>>> {
-        use ollama_rs::generation::chat::{request::ChatMessageRequest, ChatMessage};
-        use ollama_rs::models::ModelOptions;        
-        let client = self.client.clone();
-        let model = model.to_string();
-
-        let mut messages = Vec::new();
-        if let Some(sys) = system {
-            messages.push(ChatMessage::system(sys));
+        Ok(self.chat_stream(model, messages_from_prompt(prompt, system), GenerationOptions::default()).await?.boxed())
+    }
+
+    async fn chat(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions) -> Result<ChatCompletion> {
+        self.chat_stream(model, messages, options).await?.collect().await
+    }
+
+    async fn chat_stream(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions) -> Result<ChatStream> {
+        // Same request as the tool-calling path, so history keeps its tool calls and tool names
+        self.chat_stream_with_tools(model, messages, options, &[]).await
+    }
+
+    fn supports_native_tools(&self) -> bool {
+        true
+    }
+
+    async fn chat_with_tools(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions, tools: &[ToolDefinition]) -> Result<ToolCompletion> {
+        let mut body = json!({
+            "model": model,
+            "messages": ollama_messages(&messages),
+            "stream": false,
+            "options": ollama_options(&options),
+        });
+        if !tools.is_empty() {
+            body["tools"] = json!(tools.iter().map(function_spec).collect::<Vec<_>>());
         }
-        messages.push(ChatMessage::user(prompt));
 
-        let mut options = ModelOptions::default();
-        options = options.num_ctx(4096);
-        options = options.num_thread(4);
+        let url = format!("{}/api/chat", self.client.url_str().trim_end_matches('/'));
+        let res = self.http.post(&url).json(&body).send().await?;
 
-        let request = ChatMessageRequest::new(model, messages).options(options);
+        if !res.status().is_success() {
+            let status = res.status();
+            let text = res.text().await.unwrap_or_else(|_| "Could not read error body".to_string());
+            return Err(HttpStatusError::new(status, format!("Ollama tool-call error ({}): {}", status, text)).into());
+        }
 
-        let stream = client.send_chat_messages_stream(request).await?;
+        let json = res.json::<serde_json::Value>().await?;
+        let message = &json["message"];
+        Ok(ToolCompletion {
+            content: message["content"].as_str().unwrap_or_default().to_string(),
+            tool_calls: parse_tool_calls(message),
+            usage: TokenUsage::from_ollama(&json).unwrap_or_default(),
+        })
+    }
 
-        let mapped_stream = stream.map(|res| {
-            match res {
-                Ok(chunk) => Ok(chunk.message.content),
-                Err(e) => Err(anyhow::anyhow!("Ollama stream error: {:?}", e)),
-            }
+    async fn chat_stream_with_tools(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions, tools: &[ToolDefinition]) -> Result<ChatStream> {
+        let mut body = json!({
+            "model": model,
+            "messages": ollama_messages(&messages),
+            "stream": true,
+            "options": ollama_options(&options),
         });
+        if !tools.is_empty() {
+            body["tools"] = json!(tools.iter().map(function_spec).collect::<Vec<_>>());
+        }
+
+        let url = format!("{}/api/chat", self.client.url_str().trim_end_matches('/'));
+        let res = self.http.post(&url).json(&body).send().await?;
 
-        Ok(Box::pin(mapped_stream))
+        if !res.status().is_success() {
+            let status = res.status();
+            let text = res.text().await.unwrap_or_else(|_| "Could not read error body".to_string());
+            return Err(HttpStatusError::new(status, format!("Ollama tool-call error ({}): {}", status, text)).into());
+        }
+
+        Ok(ollama_ndjson_stream(res, "Ollama"))
     }
 
     fn get_lock(&self) -> Arc<Mutex<()>> {
@@ -652,80 +1535,62 @@ impl RemoteNexusProvider {
 
 #[async_trait]
 impl LLMProvider for RemoteNexusProvider {
-    async fn generate(&self, _model: &str, prompt: String, system: Option<String>) -> Result<String> {
-        let mut messages = Vec::new();
-        if let Some(sys) = system {
-            messages.push(json!({ "role": "system", "content": sys }));
-        }
-        messages.push(json!({ "role": "user", "content": prompt }));
+    async fn generate(&self, model: &str, prompt: String, system: Option<String>) -> Result<String> {
+        Ok(self.chat(model, messages_from_prompt(prompt, system), GenerationOptions::default()).await?.content)
+    }
 
-        let body = json!({
-            "messages": messages,
-            "max_tokens": 1024,
+    async fn generate_stream(&self, model: &str, prompt: String, system: Option<String>) -> Result<BoxStream<'static, Result<String>>> {
+        Ok(self.chat_stream(model, messages_from_prompt(prompt, system), GenerationOptions::default()).await?.boxed())
+    }
+
+    async fn chat(&self, _model: &str, messages: Vec<ChatMessage>, options: GenerationOptions) -> Result<ChatCompletion> {
+        let mut body = json!({
+            "messages": openai_messages(&messages),
         });
+        apply_openai_options(&mut body, &options);
 
         let res = self.client.post(&self.url)
             .json(&body)
             .send()
-            .await?
-            .json::<serde_json::Value>()
             .await?;
 
+        if !res.status().is_success() {
+            let status = res.status();
+            let text = res.text().await.unwrap_or_else(|_| "Could not read error body".to_string());
+            return Err(HttpStatusError::new(status, format!("Remote Nexus error ({}): {}", status, text)).into());
+        }
+
+        let res = res.json::<serde_json::Value>().await?;
+
         // SOTA: The Nexus response is now strictly projected via MVPK logic.
-        Ok(res["choices"][0]["message"]["content"].as_str().map(|s| s.to_string()).unwrap_or_else(|| "No response from Remote Nexus".to_string()))
+        Ok(ChatCompletion {
+            content: res["choices"][0]["message"]["content"].as_str().map(|s| s.to_string()).unwrap_or_else(|| "No response from Remote Nexus".to_string()),
+            usage: TokenUsage::from_openai(&res["usage"]).unwrap_or_default(),
+            finish_reason: res["choices"][0]["finish_reason"].as_str().map(|s| s.to_string()),
+        })
     }
 
-    async fn generate_stream(&self, _model: &str, prompt: String, system: Option<String>) -> Result<BoxStream<'static, Result<String>>> {
-        let mut messages = Vec::new();
-        if let Some(sys) = system {
-            messages.push(json!({ "role": "system", "content": sys }));
-        }
-        messages.push(json!({ "role": "user", "content": prompt }));
-
-        let body = json!({
-            "messages": messages,
-            "max_tokens": 1024,
+    async fn chat_stream(&self, _model: &str, messages: Vec<ChatMessage>, options: GenerationOptions) -> Result<ChatStream> {
+        let mut body = json!({
+            "messages": openai_messages(&messages),
             "stream": true,
+            "stream_options": { "include_usage": true },
         });
+        apply_openai_options(&mut body, &options);
 
         let res = self.client.post(&self.url)
             .json(&body)
             .send()
             .await?;
 
-        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
-
-        tokio::task::spawn(async move {
-            let mut stream = res.bytes_stream();
-            while let Some(item) = stream.next().await {
-                match item {
-                    Ok(bytes) => {
-                        let text = String::from_utf8_lossy(&bytes);
-                        for line in text.lines() {
-                            if line.starts_with("data: ") {
-                                let data = &line[6..];
-                                if data == "[DONE]" { break; }
-                                if let Ok(json) = serde_json::from_str::<serde_json::Value>(data) {
-                                    if let Some(content) = json["choices"][0]["delta"]["content"].as_str() {
-                                        // SOTA: Forward raw tokens; higher-level agents will perform MVPK projection.
-                                        let _ = tx.send(Ok(content.to_string()));
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        let _ = tx.send(Err(anyhow::anyhow!("Remote stream error: {}", e)));
-                        break;
-                    }
-                }
-            }
-        });
+        if !res.status().is_success() {
+            let status = res.status();
+            let text = res.text().await.unwrap_or_else(|_| "Could not read error body".to_string());
+            return Err(HttpStatusError::new(status, format!("Remote Nexus error ({}): {}", status, text)).into());
+        }
 
-        let stream = futures_util::stream::unfold(rx, |mut rx| async move {
-            rx.recv().await.map(|val| (val, rx))
-        });
-        Ok(Box::pin(stream))
+        // SOTA: Forward raw tokens; higher-level agents will perform MVPK projection.
+        Ok(openai_sse_stream(res, "Remote"))
     }
 
     fn get_lock(&self) -> Arc<Mutex<()>> {
@@ -760,74 +1625,95 @@ impl OpenAICompatibleProvider {
             lock: GLOBAL_HW_LOCK.clone(),
         }
     }
+
+    /// Build an authenticated POST to the chat completions endpoint
+    fn completions_request(&self, body: &serde_json::Value) -> reqwest::RequestBuilder {
+        let mut request = self.client.post(format!("{}/chat/completions", self.base_url.trim_end_matches('/')))
+            .header("Accept-Language", "en-US,en")
+            .json(body);
+
+        if let Some(ref key) = self.api_key {
+            request = request.bearer_auth(key);
+        }
+        request
+    }
 }
 
 #[async_trait]
 impl LLMProvider for OpenAICompatibleProvider {
     async fn generate(&self, model: &str, prompt: String, system: Option<String>) -> Result<String> {
-        let mut stream = self.generate_stream(model, prompt, system).await?;
-        let mut full_text = String::new();
-        while let Some(chunk) = stream.next().await {
-            full_text.push_str(&chunk?);
-        }
-        Ok(full_text)
+        Ok(self.chat(model, messages_from_prompt(prompt, system), GenerationOptions::default()).await?.content)
     }
 
     async fn generate_stream(&self, model: &str, prompt: String, system: Option<String>) -> Result<BoxStream<'static, Result<String>>> {
-        let mut messages = Vec::new();
-        if let Some(sys) = system {
-            messages.push(json!({ "role": "system", "content": sys }));
-        }
-        messages.push(json!({ "role": "user", "content": prompt }));
+        Ok(self.chat_stream(model, messages_from_prompt(prompt, system), GenerationOptions::default()).await?.boxed())
+    }
+
+    async fn chat(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions) -> Result<ChatCompletion> {
+        self.chat_stream(model, messages, options).await?.collect().await
+    }
+
+    async fn chat_stream(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions) -> Result<ChatStream> {
+        self.chat_stream_with_tools(model, messages, options, &[]).await
+    }
 
-        let body = json!({
+    fn supports_native_tools(&self) -> bool {
+        true
+    }
+
+    /// Tool-call deltas are assembled by the SSE decoder and reach the trailer
+    async fn chat_stream_with_tools(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions, tools: &[ToolDefinition]) -> Result<ChatStream> {
+        let mut body = json!({
             "model": model,
-            "messages": messages,
-            "temperature": 0.7,
+            "messages": openai_messages(&messages),
             "stream": true,
+            "stream_options": { "include_usage": true },
         });
-
-        let mut request = self.client.post(format!("{}/chat/completions", self.base_url.trim_end_matches('/')))
-            .header("Accept-Language", "en-US,en")
-            .json(&body);
-
-        if let Some(ref key) = self.api_key {
-            request = request.bearer_auth(key);
+        apply_openai_options(&mut body, &options);
+        if !tools.is_empty() {
+            body["tools"] = json!(tools.iter().map(function_spec).collect::<Vec<_>>());
+            body["tool_choice"] = json!("auto");
         }
 
-        let res = request.send().await?;
+        let res = self.completions_request(&body).send().await?;
         
         if !res.status().is_success() {
             let status = res.status();
             let text = res.text().await.unwrap_or_else(|_| "Could not read error body".to_string());
             println!("❌ Z.ai/OpenAI API Error ({}): {}", status, text);
-            return Err(anyhow::anyhow!("HTTP status client error ({}) for url ({}): {}", status, self.base_url, text));
+            return Err(HttpStatusError::new(status, format!("HTTP status client error ({}) for url ({}): {}", status, self.base_url, text)).into());
         }
         
-        let stream = res.bytes_stream();
-        let mapped_stream = stream.map(|res| {
-            match res {
-                Ok(bytes) => {
-                    let text = String::from_utf8_lossy(&bytes);
-                    let mut content = String::new();
-                    for line in text.lines() {
-                        if line.starts_with("data: ") {
-                            let data = &line[6..];
-                            if data == "[DONE]" { continue; }
-                            if let Ok(json) = serde_json::from_str::<serde_json::Value>(data) {
-                                if let Some(chunk) = json["choices"][0]["delta"]["content"].as_str() {
-                                    content.push_str(chunk);
-                                }
-                            }
-                        }
-                    }
-                    Ok(content)
-                }
-                Err(e) => Err(anyhow::anyhow!("OpenAI stream error: {}", e)),
-            }
+        Ok(openai_sse_stream(res, "OpenAI"))
+    }
+
+    async fn chat_with_tools(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions, tools: &[ToolDefinition]) -> Result<ToolCompletion> {
+        let mut body = json!({
+            "model": model,
+            "messages": openai_messages(&messages),
+            "stream": false,
         });
+        apply_openai_options(&mut body, &options);
+        if !tools.is_empty() {
+            body["tools"] = json!(tools.iter().map(function_spec).collect::<Vec<_>>());
+            body["tool_choice"] = json!("auto");
+        }
+
+        let res = self.completions_request(&body).send().await?;
 
-        Ok(Box::pin(mapped_stream))
+        if !res.status().is_success() {
+            let status = res.status();
+            let text = res.text().await.unwrap_or_else(|_| "Could not read error body".to_string());
+            return Err(HttpStatusError::new(status, format!("HTTP status client error ({}) for url ({}): {}", status, self.base_url, text)).into());
+        }
+
+        let json = res.json::<serde_json::Value>().await?;
+        let message = &json["choices"][0]["message"];
+        Ok(ToolCompletion {
+            content: message["content"].as_str().unwrap_or_default().to_string(),
+            tool_calls: parse_tool_calls(message),
+            usage: TokenUsage::from_openai(&json["usage"]).unwrap_or_default(),
+        })
     }
 
     fn get_lock(&self) -> Arc<Mutex<()>> {
@@ -857,94 +1743,97 @@ impl OllamaCloudProvider {
             lock: GLOBAL_HW_LOCK.clone(),
         }
     }
+
+    /// Override the chat endpoint (e.g. a self-hosted gateway)
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.url = url.into();
+        self
+    }
+
+    /// Build an authenticated POST to the chat endpoint
+    fn chat_request(&self, body: &serde_json::Value) -> reqwest::RequestBuilder {
+        let mut request = self.client.post(&self.url)
+            .json(body);
+
+        if let Some(ref key) = self.api_key {
+            request = request.bearer_auth(key);
+        }
+        request
+    }
 }
 
 #[async_trait]
 impl LLMProvider for OllamaCloudProvider {
     async fn generate(&self, model: &str, prompt: String, system: Option<String>) -> Result<String> {
-        let mut stream = self.generate_stream(model, prompt, system).await?;
-        let mut full_text = String::new();
-        while let Some(chunk) = stream.next().await {
-            full_text.push_str(&chunk?);
-        }
-        Ok(full_text)
+        Ok(self.chat(model, messages_from_prompt(prompt, system), GenerationOptions::default()).await?.content)
     }
 
     async fn generate_stream(&self, model: &str, prompt: String, system: Option<String>) -> Result<BoxStream<'static, Result<String>>> {
-        let mut messages = Vec::new();
-        if let Some(sys) = system {
-            messages.push(json!({ "role": "system", "content": sys }));
-        }
-        messages.push(json!({ "role": "user", "content": prompt }));
+        Ok(self.chat_stream(model, messages_from_prompt(prompt, system), GenerationOptions::default()).await?.boxed())
+    }
+
+    async fn chat(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions) -> Result<ChatCompletion> {
+        self.chat_stream(model, messages, options).await?.collect().await
+    }
 
-        let body = json!({
+    async fn chat_stream(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions) -> Result<ChatStream> {
+        self.chat_stream_with_tools(model, messages, options, &[]).await
+    }
+
+    async fn chat_stream_with_tools(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions, tools: &[ToolDefinition]) -> Result<ChatStream> {
+        let mut body = json!({
             "model": model,
-            "messages": messages,
+            "messages": ollama_messages(&messages),
+            "options": ollama_options(&options),
             "stream": true,
         });
-
-        let mut request = self.client.post(&self.url)
-            .json(&body);
-
-        if let Some(ref key) = self.api_key {
-            request = request.bearer_auth(key);
+        if !tools.is_empty() {
+            body["tools"] = json!(tools.iter().map(function_spec).collect::<Vec<_>>());
         }
 
-        let res = request.send().await?;
+        let res = self.chat_request(&body).send().await?;
         
         if !res.status().is_success() {
             let status = res.status();
             let text = res.text().await.unwrap_or_else(|_| "Could not read error body".to_string());
             error!("❌ Ollama Cloud Error ({}): {}", status, text);
-            return Err(anyhow::anyhow!("Ollama Cloud Error ({}): {}", status, text));
+            return Err(HttpStatusError::new(status, format!("Ollama Cloud Error ({}): {}", status, text)).into());
         }
 
-        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        Ok(ollama_ndjson_stream(res, "Ollama Cloud"))
+    }
 
-        tokio::task::spawn(async move {
-            let mut stream = res.bytes_stream();
-            let mut buffer = String::new();
-
-            while let Some(item) = stream.next().await {
-                match item {
-                    Ok(bytes) => {
-                        let chunk = String::from_utf8_lossy(&bytes);
-                        buffer.push_str(&chunk);
-
-                        while let Some(newline_idx) = buffer.find('\n') {
-                            let line = buffer[..newline_idx].trim().to_string();
-                            buffer = buffer[newline_idx + 1..].to_string();
-
-                            if line.is_empty() { continue; }
-
-                            match serde_json::from_str::<serde_json::Value>(&line) {
-                                Ok(json) => {
-                                    if let Some(content) = json["message"]["content"].as_str() {
-                                        let _ = tx.send(Ok(content.to_string()));
-                                    }
-                                    if json["done"].as_bool().unwrap_or(false) {
-                                        return;
-                                    }
-                                }
-                                Err(e) => {
-                                    error!("❌ Ollama Cloud Parse Error: {} (Content: {})", e, line);
-                                }
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        error!("❌ Ollama Cloud stream network error: {}", e);
-                        let _ = tx.send(Err(anyhow::anyhow!("Ollama Cloud stream error: {}", e)));
-                        break;
-                    }
-                }
-            }
-        });
+    fn supports_native_tools(&self) -> bool {
+        true
+    }
 
-        let stream = futures_util::stream::unfold(rx, |mut rx| async move {
-            rx.recv().await.map(|val| (val, rx))
+    async fn chat_with_tools(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions, tools: &[ToolDefinition]) -> Result<ToolCompletion> {
+        let mut body = json!({
+            "model": model,
+            "messages": ollama_messages(&messages),
+            "options": ollama_options(&options),
+            "stream": false,
         });
-        Ok(Box::pin(stream))
+        if !tools.is_empty() {
+            body["tools"] = json!(tools.iter().map(function_spec).collect::<Vec<_>>());
+        }
+
+        let res = self.chat_request(&body).send().await?;
+
+        if !res.status().is_success() {
+            let status = res.status();
+            let text = res.text().await.unwrap_or_else(|_| "Could not read error body".to_string());
+            error!("❌ Ollama Cloud Error ({}): {}", status, text);
+            return Err(HttpStatusError::new(status, format!("Ollama Cloud Error ({}): {}", status, text)).into());
+        }
+
+        let json = res.json::<serde_json::Value>().await?;
+        let message = &json["message"];
+        Ok(ToolCompletion {
+            content: message["content"].as_str().unwrap_or_default().to_string(),
+            tool_calls: parse_tool_calls(message),
+            usage: TokenUsage::from_ollama(&json).unwrap_or_default(),
+        })
     }
 
     fn get_lock(&self) -> Arc<Mutex<()>> {
@@ -981,6 +1870,31 @@ impl LLMProvider for SwitchableProvider {
         provider.generate_stream(model, prompt, system).await
     }
 
+    async fn chat(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions) -> Result<ChatCompletion> {
+        let provider = self.inner.read().await.clone();
+        provider.chat(model, messages, options).await
+    }
+
+    async fn chat_stream(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions) -> Result<ChatStream> {
+        let provider = self.inner.read().await.clone();
+        provider.chat_stream(model, messages, options).await
+    }
+
+    fn supports_native_tools(&self) -> bool {
+        // A switch in progress holds the write lock; treat that moment as text-only
+        self.inner.try_read().map(|p| p.supports_native_tools()).unwrap_or(false)
+    }
+
+    async fn chat_with_tools(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions, tools: &[ToolDefinition]) -> Result<ToolCompletion> {
+        let provider = self.inner.read().await.clone();
+        provider.chat_with_tools(model, messages, options, tools).await
+    }
+
+    async fn chat_stream_with_tools(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions, tools: &[ToolDefinition]) -> Result<ChatStream> {
+        let provider = self.inner.read().await.clone();
+        provider.chat_stream_with_tools(model, messages, options, tools).await
+    }
+
     fn get_lock(&self) -> Arc<Mutex<()>> {
         GLOBAL_HW_LOCK.clone()
     }
@@ -1032,15 +1946,503 @@ pub fn create_provider_by_type(provider_type: &str) -> Arc<dyn LLMProvider> {
     }
 }
 
+/// Build `provider_type`, chained with the comma-separated `AGENCY_FALLBACK_PROVIDERS` when set.
+/// An entry may name the model to use on that backend (`ollama=qwen2.5:7b`);
+/// otherwise it receives the model the caller asked for.
+pub fn create_provider_with_fallbacks(provider_type: &str) -> Arc<dyn LLMProvider> {
+    let fallbacks = parse_fallbacks(&std::env::var("AGENCY_FALLBACK_PROVIDERS").unwrap_or_default(), provider_type);
+    if fallbacks.is_empty() {
+        return create_provider_by_type(provider_type);
+    }
+
+    let names: Vec<&str> = fallbacks.iter().map(|(name, _)| name.as_str()).collect();
+    println!("🔀 Provider failover chain: {} → {}", provider_type, names.join(" → "));
+    let chain = std::iter::once(provider_type.to_string()).chain(fallbacks.iter().map(|(name, _)| name.clone()))
+        .map(|name| { let provider = create_provider_by_type(&name); (name, provider) })
+        .collect();
+    let mut provider = super::FallbackProvider::new(chain);
+    for (name, model) in fallbacks {
+        if let Some(model) = model {
+            provider = provider.with_model(&name, model);
+        }
+    }
+    Arc::new(provider)
+}
+
+/// `(backend, model override)` pairs from an `AGENCY_FALLBACK_PROVIDERS` value, minus the primary
+fn parse_fallbacks(spec: &str, primary: &str) -> Vec<(String, Option<String>)> {
+    spec.split(',')
+        .filter_map(|entry| {
+            let (name, model) = match entry.split_once('=') {
+                Some((name, model)) => (name, Some(model.trim().to_string()).filter(|m| !m.is_empty())),
+                None => (entry, None),
+            };
+            let name = name.trim().to_lowercase();
+            (!name.is_empty() && name != primary.to_lowercase()).then_some((name, model))
+        })
+        .collect()
+}
+
 pub fn dynamic_provider() -> Arc<SwitchableProvider> {
     let provider_type = std::env::var("AGENCY_PROVIDER").unwrap_or_else(|_| "zai".to_string());
-    let initial = create_provider_by_type(&provider_type);
+    let initial = create_provider_with_fallbacks(&provider_type);
     Arc::new(SwitchableProvider::new(initial))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use axum::{routing::post, Json, Router};
+
+    /// Spawn a local HTTP server answering `path` with a canned JSON body.
+    /// Returns the base URL and a slot holding the last request body received.
+    async fn spawn_mock(path: &'static str, reply: serde_json::Value) -> (String, Arc<Mutex<Option<serde_json::Value>>>) {
+        let captured = Arc::new(Mutex::new(None));
+        let sink = captured.clone();
+        let app = Router::new().route(path, post(move |Json(body): Json<serde_json::Value>| {
+            let sink = sink.clone();
+            let reply = reply.clone();
+            async move {
+                *sink.lock().await = Some(body);
+                Json(reply)
+            }
+        }));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        (format!("http://{}", addr), captured)
+    }
+
+    fn weather_tool() -> ToolDefinition {
+        ToolDefinition {
+            name: "get_weather".to_string(),
+            description: "Look up the weather".to_string(),
+            parameters: json!({"type": "object", "properties": {"location": {"type": "string"}}, "required": ["location"]}),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_openai_native_tool_calls() -> Result<()> {
+        let reply = json!({
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "content": "Checking the forecast.",
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": { "name": "get_weather", "arguments": "{\"location\": \"Seattle\"}" }
+                    }]
+                }
+            }]
+        });
+        let (base_url, captured) = spawn_mock("/v1/chat/completions", reply).await;
+        let provider = OpenAICompatibleProvider::new(format!("{}/v1", base_url), None);
+
+        let completion = provider.chat_with_tools("glm-4", messages_from_prompt("Weather?".to_string(), Some("sys".to_string())), GenerationOptions::default(), &[weather_tool()]).await?;
+        assert_eq!(completion.content, "Checking the forecast.");
+        assert_eq!(completion.tool_calls, vec![ToolCall { name: "get_weather".to_string(), parameters: json!({"location": "Seattle"}) }]);
+
+        let body = captured.lock().await.clone().expect("request not captured");
+        assert_eq!(body["tools"][0]["type"], "function");
+        assert_eq!(body["tools"][0]["function"]["name"], "get_weather");
+        assert_eq!(body["tool_choice"], "auto");
+        assert_eq!(body["stream"], false);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ollama_native_tool_calls() -> Result<()> {
+        let reply = json!({
+            "message": {
+                "role": "assistant",
+                "content": "",
+                "tool_calls": [{ "function": { "name": "get_weather", "arguments": { "location": "Paris" } } }]
+            },
+            "done": true
+        });
+        let (base_url, captured) = spawn_mock("/api/chat", reply).await;
+        let port = base_url.rsplit(':').next().unwrap().parse::<u16>()?;
+        let provider = OllamaProvider::new(ollama_rs::Ollama::new("http://127.0.0.1", port));
+
+        let completion = provider.chat_with_tools("llama3.2", vec![ChatMessage::user("Weather?")], GenerationOptions::default(), &[weather_tool()]).await?;
+        assert!(completion.content.is_empty());
+        assert_eq!(completion.tool_calls[0].name, "get_weather");
+        assert_eq!(completion.tool_calls[0].parameters, json!({"location": "Paris"}));
+
+        let body = captured.lock().await.clone().expect("request not captured");
+        assert_eq!(body["tools"][0]["function"]["parameters"]["required"][0], "location");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ollama_stream_with_tools_fills_trailer() -> Result<()> {
+        let lines = [
+            json!({ "message": { "role": "assistant", "content": "Checking " }, "done": false }),
+            json!({ "message": { "role": "assistant", "content": "",
+                "tool_calls": [{ "function": { "name": "get_weather", "arguments": { "location": "Paris" } } }] }, "done": false }),
+            json!({ "message": { "role": "assistant", "content": "" }, "done": true, "done_reason": "stop", "prompt_eval_count": 20, "eval_count": 6 }),
+        ];
+        let body: String = lines.iter().map(|l| format!("{}\n", l)).collect();
+        let app = Router::new().route("/api/chat", post(move || {
+            let body = body.clone();
+            async move { body }
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+        let provider = OllamaCloudProvider::new(None).with_url(format!("http://{}/api/chat", addr));
+
+        let mut stream = provider.chat_stream_with_tools("gpt-oss:120b", vec![ChatMessage::user("Weather?")], GenerationOptions::default(), &[weather_tool()]).await?;
+        let mut content = String::new();
+        while let Some(chunk) = stream.next().await {
+            content.push_str(&chunk?);
+        }
+        assert_eq!(content, "Checking ");
+        assert_eq!(stream.tool_calls(), vec![ToolCall { name: "get_weather".to_string(), parameters: json!({"location": "Paris"}) }]);
+        assert_eq!(stream.usage(), TokenUsage::new(20, 6));
+        assert_eq!(stream.finish_reason().as_deref(), Some("stop"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ollama_stream_keeps_tool_history() -> Result<()> {
+        let reply = json!({ "message": { "role": "assistant", "content": "It is sunny." }, "done": true, "eval_count": 4 });
+        let (base_url, captured) = spawn_mock("/api/chat", reply).await;
+        let port = base_url.rsplit(':').next().unwrap().parse::<u16>()?;
+        let provider = OllamaProvider::new(ollama_rs::Ollama::new("http://127.0.0.1", port));
+        let history = vec![
+            ChatMessage::user("Weather?"),
+            ChatMessage::assistant("").with_tool_calls(vec![ToolCall { name: "get_weather".to_string(), parameters: json!({"location": "Paris"}) }]),
+            ChatMessage::tool("get_weather", "sunny"),
+        ];
+
+        let completion = provider.chat("llama3.2", history, GenerationOptions::default()).await?;
+        assert_eq!(completion.content, "It is sunny.");
+        let body = captured.lock().await.clone().expect("request not captured");
+        assert_eq!(body["messages"][1]["tool_calls"][0]["function"]["name"], "get_weather");
+        assert_eq!(body["messages"][2]["tool_name"], "get_weather");
+        assert!(body.get("tools").is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ollama_cloud_text_only_response() -> Result<()> {
+        let reply = json!({ "message": { "role": "assistant", "content": "🎯 It is sunny." }, "done": true });
+        let (base_url, _captured) = spawn_mock("/api/chat", reply).await;
+        let provider = OllamaCloudProvider::new(None).with_url(format!("{}/api/chat", base_url));
+
+        let completion = provider.chat_with_tools("gpt-oss:120b", vec![ChatMessage::user("Weather?")], GenerationOptions::default(), &[weather_tool()]).await?;
+        assert_eq!(completion.content, "🎯 It is sunny.");
+        assert!(completion.tool_calls.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_usage_reported_by_openai_and_ollama() -> Result<()> {
+        let reply = json!({
+            "choices": [{ "message": { "role": "assistant", "content": "ok" } }],
+            "usage": { "prompt_tokens": 31, "completion_tokens": 4, "total_tokens": 35 }
+        });
+        let (base_url, _captured) = spawn_mock("/v1/chat/completions", reply).await;
+        let provider = OpenAICompatibleProvider::new(format!("{}/v1", base_url), None);
+        let completion = provider.chat_with_tools("glm-4", vec![ChatMessage::user("hi")], GenerationOptions::default(), &[]).await?;
+        assert_eq!(completion.usage, TokenUsage::new(31, 4));
+        assert_eq!(completion.usage.total_tokens(), 35);
+
+        let reply = json!({ "message": { "role": "assistant", "content": "ok" }, "done": true, "prompt_eval_count": 12, "eval_count": 3 });
+        let (base_url, _captured) = spawn_mock("/api/chat", reply).await;
+        let provider = OllamaCloudProvider::new(None).with_url(format!("{}/api/chat", base_url));
+        let completion = provider.chat_with_tools("gpt-oss:120b", vec![ChatMessage::user("hi")], GenerationOptions::default(), &[]).await?;
+        assert_eq!(completion.usage, TokenUsage::new(12, 3));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_chat_stream_collect_reads_usage_after_drain() -> Result<()> {
+        let usage = StreamTrailer::default();
+        let sink = usage.clone();
+        let inner = futures_util::stream::iter(vec![Ok("a".to_string()), Ok("b".to_string())])
+            .inspect(move |_| sink.set_usage(TokenUsage::new(7, 2)));
+        let completion = ChatStream::new(Box::pin(inner), usage).collect().await?;
+        assert_eq!(completion.content, "ab");
+        assert_eq!(completion.usage, TokenUsage::new(7, 2));
+
+        let plain = ChatStream::without_usage(Box::pin(futures_util::stream::once(async { Ok("x".to_string()) })));
+        assert_eq!(plain.collect().await?.usage, TokenUsage::default());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_stream_ends_with_error() {
+        // A backend that sends one token and then hangs forever
+        let hung = futures_util::stream::once(async { Ok("partial".to_string()) })
+            .chain(futures_util::stream::pending());
+        let token = CancellationToken::new();
+        let mut stream = ChatStream::without_usage(Box::pin(hung)).with_cancellation(token.clone());
+
+        assert_eq!(stream.next().await.unwrap().unwrap(), "partial");
+        token.cancel();
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("cancelled"));
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_openai_stream_reports_trailer_and_errors() -> Result<()> {
+        async fn serve_sse(body: &'static str) -> String {
+            let app = Router::new().route("/v1/chat/completions", post(move || async move {
+                ([(axum::http::header::CONTENT_TYPE, "text/event-stream")], body)
+            }));
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                let _ = axum::serve(listener, app).await;
+            });
+            format!("http://{}/v1", addr)
+        }
+
+        let url = serve_sse(concat!(
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"lo\"},\"finish_reason\":\"length\"}]}\n\n",
+            "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":3,\"completion_tokens\":2}}\n\n",
+            "data: [DONE]\n\n",
+        )).await;
+        let provider = OpenAICompatibleProvider::new(url, None);
+        let completion = provider.chat("glm-4", vec![ChatMessage::user("hi")], GenerationOptions::default()).await?;
+        assert_eq!(completion.content, "Hello");
+        assert_eq!(completion.usage, TokenUsage::new(3, 2));
+        assert_eq!(completion.finish_reason.as_deref(), Some("length"));
+
+        let url = serve_sse("data: {\"choices\":[{\"delta\":{\"content\":\"a\"}}]}\n\nevent: error\ndata: {\"error\":{\"message\":\"overloaded\"}}\n\n").await;
+        let provider = OpenAICompatibleProvider::new(url, None);
+        let err = provider.chat("glm-4", vec![ChatMessage::user("hi")], GenerationOptions::default()).await.unwrap_err();
+        assert!(err.to_string().contains("overloaded"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_tool_calls_malformed_arguments() {
+        let message = json!({
+            "tool_calls": [
+                { "function": { "name": "a", "arguments": "" } },
+                { "function": { "name": "b", "arguments": "{not json" } },
+                { "function": { "arguments": "{}" } }
+            ]
+        });
+        let calls = parse_tool_calls(&message);
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].parameters, json!({}));
+        assert_eq!(calls[1].parameters, json!("{not json"));
+    }
+
+    #[test]
+    fn test_flatten_messages() {
+        let (system, prompt) = flatten_messages(&[ChatMessage::system("be brief"), ChatMessage::user("hi")]);
+        assert_eq!(system.as_deref(), Some("be brief"));
+        assert_eq!(prompt, "hi");
+
+        let (system, prompt) = flatten_messages(&[
+            ChatMessage::user("first"),
+            ChatMessage::assistant("reply"),
+            ChatMessage::user("second"),
+        ]);
+        assert!(system.is_none());
+        assert_eq!(prompt, "User: first\n\nAssistant: reply\n\nUser: second");
+    }
+
+    #[test]
+    fn test_openai_messages_pairs_tool_results() {
+        let call = ToolCall { name: "get_weather".to_string(), parameters: json!({"location": "Oslo"}) };
+        let wire = openai_messages(&[
+            ChatMessage::user("Weather?"),
+            ChatMessage::assistant("").with_tool_calls(vec![call]),
+            ChatMessage::tool("get_weather", "rain"),
+            ChatMessage::tool("orphan", "no matching call"),
+        ]);
+
+        assert_eq!(wire[1]["tool_calls"][0]["id"], "call_0");
+        assert_eq!(wire[1]["tool_calls"][0]["function"]["arguments"], "{\"location\":\"Oslo\"}");
+        assert_eq!(wire[2]["role"], "tool");
+        assert_eq!(wire[2]["tool_call_id"], "call_0");
+        assert_eq!(wire[3]["role"], "user");
+    }
+
+    #[tokio::test]
+    async fn test_openai_chat_sends_history() -> Result<()> {
+        let reply = json!({ "choices": [{ "message": { "role": "assistant", "content": "Still sunny." } }] });
+        let (base_url, captured) = spawn_mock("/v1/chat/completions", reply).await;
+        let provider = OpenAICompatibleProvider::new(format!("{}/v1", base_url), None);
+
+        let messages = vec![
+            ChatMessage::system("sys"),
+            ChatMessage::user("Weather?"),
+            ChatMessage::assistant("Sunny."),
+            ChatMessage::user("And now?"),
+        ];
+        provider.chat_with_tools("glm-4", messages, GenerationOptions::default(), &[]).await?;
+
+        let body = captured.lock().await.clone().expect("request not captured");
+        let roles: Vec<_> = body["messages"].as_array().unwrap().iter().map(|m| m["role"].as_str().unwrap().to_string()).collect();
+        assert_eq!(roles, vec!["system", "user", "assistant", "user"]);
+        assert!(body.get("tools").is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_generation_options_reach_openai_and_ollama() -> Result<()> {
+        let options = GenerationOptions::default()
+            .with_temperature(0.2)
+            .with_max_tokens(64)
+            .with_stop(vec!["</answer>".to_string()])
+            .with_seed(7)
+            .with_num_ctx(8192);
+
+        let reply = json!({ "choices": [{ "message": { "role": "assistant", "content": "ok" } }] });
+        let (base_url, captured) = spawn_mock("/v1/chat/completions", reply).await;
+        let provider = OpenAICompatibleProvider::new(format!("{}/v1", base_url), None);
+        provider.chat_with_tools("glm-4", vec![ChatMessage::user("hi")], options.clone(), &[]).await?;
+        let body = captured.lock().await.clone().expect("request not captured");
+        assert!((body["temperature"].as_f64().unwrap() - 0.2).abs() < 1e-6);
+        assert_eq!(body["max_tokens"], 64);
+        assert_eq!(body["stop"], json!(["</answer>"]));
+        assert_eq!(body["seed"], 7);
+        assert!(body.get("num_ctx").is_none());
+
+        let reply = json!({ "message": { "role": "assistant", "content": "ok" }, "done": true });
+        let (base_url, captured) = spawn_mock("/api/chat", reply).await;
+        let port = base_url.rsplit(':').next().unwrap().parse::<u16>()?;
+        let provider = OllamaProvider::new(ollama_rs::Ollama::new("http://127.0.0.1", port));
+        provider.chat_with_tools("llama3.2", vec![ChatMessage::user("hi")], options, &[]).await?;
+        let body = captured.lock().await.clone().expect("request not captured");
+        assert_eq!(body["options"]["num_ctx"], 8192);
+        assert_eq!(body["options"]["num_predict"], 64);
+        assert_eq!(body["options"]["seed"], 7);
+        assert!(body["options"].get("num_thread").is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_default_options_send_no_sampling_overrides() -> Result<()> {
+        let reply = json!({ "choices": [{ "message": { "role": "assistant", "content": "ok" } }] });
+        let (base_url, captured) = spawn_mock("/v1/chat/completions", reply).await;
+        let provider = OpenAICompatibleProvider::new(format!("{}/v1", base_url), None);
+        provider.chat_with_tools("glm-4", vec![ChatMessage::user("hi")], GenerationOptions::default(), &[]).await?;
+
+        let body = captured.lock().await.clone().expect("request not captured");
+        assert!(body.get("temperature").is_none());
+        assert!(body.get("max_tokens").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_seeded_sampling_is_reproducible() -> Result<()> {
+        let logits = Tensor::new(&[0.1f32, 0.5, 0.2, 0.9, 0.3, 0.4], &Device::Cpu)?;
+        let options = GenerationOptions::default().with_temperature(1.0).with_seed(1234);
+
+        let sample = |options: &GenerationOptions| -> Result<Vec<u32>> {
+            let mut lp = logits_processor(options);
+            (0..32).map(|_| lp.sample(&logits).map_err(anyhow::Error::from)).collect()
+        };
+        assert_eq!(sample(&options)?, sample(&options)?);
+
+        // Temperature 0 is greedy regardless of seed
+        let greedy = sample(&GenerationOptions::default().with_temperature(0.0))?;
+        assert!(greedy.iter().all(|&t| t == 3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_stop_filter_stops_before_stop_sequence() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut filter = StopFilter::new(vec!["###".to_string()]);
+
+        assert!(filter.push("Hello ", &tx));
+        assert!(!filter.push("world###more", &tx));
+        filter.finish(&tx);
+
+        let mut sent = String::new();
+        while let Ok(chunk) = rx.try_recv() {
+            sent.push_str(&chunk.unwrap());
+        }
+        assert_eq!(sent, "Hello world");
+    }
+
+    #[test]
+    fn test_stop_filter_holds_back_split_stop_sequence() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut filter = StopFilter::new(vec!["</answer>".to_string()]);
+        let drain = |rx: &mut tokio::sync::mpsc::UnboundedReceiver<Result<String>>| {
+            let mut sent = String::new();
+            while let Ok(chunk) = rx.try_recv() {
+                sent.push_str(&chunk.unwrap());
+            }
+            sent
+        };
+
+        assert!(filter.push("42 is it</ans", &tx));
+        assert_eq!(drain(&mut rx), "42 is");
+        assert!(!filter.push("wer> trailing", &tx));
+        filter.finish(&tx);
+        assert_eq!(drain(&mut rx), " it");
+
+        // Without a stop sequence the held-back tail is sent at the end
+        let mut filter = StopFilter::new(vec!["</answer>".to_string()]);
+        assert!(filter.push("a < b", &tx));
+        filter.finish(&tx);
+        assert_eq!(drain(&mut rx), "a < b");
+    }
+
+    #[test]
+    fn test_clip_context_keeps_tail() {
+        let mut tokens: Vec<u32> = (0..10).collect();
+        clip_context(&mut tokens, 0, None, &GenerationOptions::default().with_num_ctx(4));
+        assert_eq!(tokens, vec![6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_clip_context_keeps_system_block_and_whole_turns() {
+        // [system 1 2] [turn 100 3 4 5] [turn 100 6 7] [reply 100]
+        let prompt = vec![1, 2, 100, 3, 4, 5, 100, 6, 7, 100];
+        let options = GenerationOptions::default().with_num_ctx(7);
+
+        let mut tokens = prompt.clone();
+        clip_context(&mut tokens, 2, None, &options);
+        assert_eq!(tokens, vec![1, 2, 5, 100, 6, 7, 100]);
+
+        let mut tokens = prompt.clone();
+        clip_context(&mut tokens, 2, Some(100), &options);
+        assert_eq!(tokens, vec![1, 2, 100, 6, 7, 100]);
+
+        // The final turn is trimmed rather than dropped
+        let mut tokens = prompt;
+        clip_context(&mut tokens, 2, Some(100), &GenerationOptions::default().with_num_ctx(4));
+        assert_eq!(tokens, vec![1, 2, 7, 100]);
+    }
+
+    #[test]
+    fn test_system_block_len() {
+        let llama = "<|begin_of_text|><|start_header_id|>system<|end_header_id|>\n\nBe brief<|eot_id|><|start_header_id|>user<|end_header_id|>\n\nhi";
+        assert_eq!(&llama[..system_block_len(llama)], "<|begin_of_text|><|start_header_id|>system<|end_header_id|>\n\nBe brief<|eot_id|>");
+        let qwen = "<|im_start|>system\nBe brief<|im_end|>\n<|im_start|>user\nhi<|im_end|>\n";
+        assert_eq!(&qwen[..system_block_len(qwen)], "<|im_start|>system\nBe brief<|im_end|>\n");
+        assert_eq!(system_block_len("<|im_start|>user\nhi<|im_end|>\n"), 0);
+    }
+
+    #[test]
+    fn test_parse_fallbacks_with_models() {
+        let parsed = parse_fallbacks("ZAI, ollama=qwen2.5:7b ,candle,openai=", "zai");
+        assert_eq!(parsed, vec![
+            ("ollama".to_string(), Some("qwen2.5:7b".to_string())),
+            ("candle".to_string(), None),
+            ("openai".to_string(), None),
+        ]);
+    }
 
     #[tokio::test]
     async fn test_candle_provider_qwen_tiny() -> Result<()> {
@@ -9,10 +9,13 @@ use std::sync::Arc;
 use std::collections::HashMap;
 use tracing::{debug, info, warn};
 use futures_util::StreamExt;
+use tokio_util::sync::CancellationToken;
 
 use super::{Agent, AgentConfig, AgentType, is_action_query, LLMProvider, OllamaProvider, OpenAICompatibleProvider, AgentResult, AgentError};
-use crate::memory::Memory;
-use crate::tools::{ToolCall, ToolRegistry};
+use super::{ChatMessage, ChatStream, TokenUsage, ToolCompletion};
+use crate::memory::{ContextCompactor, Memory, Reinforcement};
+use crate::orchestrator::SessionUsage;
+use crate::tools::{ArtifactContext, ArtifactFilter, ArtifactStore, ToolCall, ToolDefinition, ToolRegistry, DEFAULT_ARTIFACT_DIR};
 use pai_core::{HookManager, HookEvent, HookEventType, HookAction};
 use pai_core::uap::{SovereignAgent, UapTask, UapStep, UapStepStatus, UapArtifact};
 
@@ -20,7 +23,7 @@ use pai_core::vcp::{ValueCommitment, CommitmentModality, EconomicImpact};
 use pai_core::sap::{AlignmentEngine, AlignmentAudit, AuditStatus};
 
 /// A single step in the ReAct loop
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ReActStep {
     /// The agent's thought/reasoning
     pub thought: String,
@@ -32,6 +35,9 @@ pub struct ReActStep {
     pub is_final: bool,
     /// The final answer (if is_final is true)
     pub answer: Option<String>,
+    /// Tokens spent by the LLM call that produced this step
+    #[serde(default)]
+    pub usage: TokenUsage,
 }
 
 impl ReActStep {
@@ -42,6 +48,7 @@ impl ReActStep {
             observations: Vec::new(),
             is_final: false,
             answer: None,
+            usage: TokenUsage::default(),
         }
     }
 
@@ -62,8 +69,14 @@ impl ReActStep {
             observations: Vec::new(),
             is_final: true,
             answer: Some(answer.into()),
+            usage: TokenUsage::default(),
         }
     }
+
+    pub fn with_usage(mut self, usage: TokenUsage) -> Self {
+        self.usage = usage;
+        self
+    }
 }
 
 /// Response from an agent execution
@@ -85,6 +98,9 @@ pub struct AgentResponse {
     pub reliability: f32,
     /// Token usage for this response
     pub cost_tokens: u32,
+    /// Prompt/completion breakdown of `cost_tokens`
+    #[serde(default)]
+    pub usage: TokenUsage,
     /// Pending approval for HITL
     pub pending_approval: Option<crate::safety::ApprovalRequest>,
 }
@@ -101,6 +117,7 @@ impl AgentResponse {
             error: None,
             reliability: 1.0,
             cost_tokens: 0,
+            usage: TokenUsage::default(),
             pending_approval: None,
         }
     }
@@ -120,6 +137,12 @@ impl AgentResponse {
         self
     }
 
+    pub fn with_usage(mut self, usage: TokenUsage) -> Self {
+        self.cost_tokens = usage.total_tokens();
+        self.usage = usage;
+        self
+    }
+
     pub fn failure(error: impl Into<String>, steps: Vec<ReActStep>, agent_type: AgentType) -> Self {
         let error = error.into();
         Self {
@@ -131,6 +154,7 @@ impl AgentResponse {
             error: Some(error),
             reliability: 0.0,
             cost_tokens: 0,
+            usage: TokenUsage::default(),
             pending_approval: None,
         }
     }
@@ -147,6 +171,14 @@ pub struct ReActAgent {
     pub pai_hooks: Option<Arc<HookManager>>,
     pub pai_memory: Option<Arc<pai_core::memory::TieredMemoryManager>>,
     pub recovery: Option<Arc<pai_core::recovery::RecoveryJournal>>,
+    /// Prior conversation turns, sent as real messages ahead of the ReAct prompt
+    history: Vec<ChatMessage>,
+    /// Cancelling this aborts the current turn, including in-flight streams and tools
+    cancel: CancellationToken,
+    /// Where artifacts produced by this agent's tools are catalogued
+    artifacts: Option<Arc<ArtifactStore>>,
+    /// Charged as each call's usage arrives, so failed and interrupted turns are counted too
+    usage_meter: Option<SessionUsage>,
 }
 
 impl ReActAgent {
@@ -170,6 +202,10 @@ impl ReActAgent {
             pai_hooks: None,
             pai_memory: None,
             recovery: None,
+            history: Vec::new(),
+            cancel: CancellationToken::new(),
+            artifacts: None,
+            usage_meter: None,
         }
     }
 
@@ -187,6 +223,10 @@ impl ReActAgent {
             pai_hooks: None,
             pai_memory: None,
             recovery: None,
+            history: Vec::new(),
+            cancel: CancellationToken::new(),
+            artifacts: None,
+            usage_meter: None,
         }
     }
 
@@ -219,6 +259,58 @@ impl ReActAgent {
         self.safety = Some(safety);
         self
     }
+
+    pub fn with_history(mut self, history: Vec<ChatMessage>) -> Self {
+        self.history = history;
+        self
+    }
+
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    pub fn with_artifacts(mut self, artifacts: Arc<ArtifactStore>) -> Self {
+        self.artifacts = Some(artifacts);
+        self
+    }
+
+    pub fn with_usage_meter(mut self, meter: SessionUsage) -> Self {
+        self.usage_meter = Some(meter);
+        self
+    }
+
+    fn meter_usage(&self, model: &str, usage: TokenUsage) {
+        if let Some(meter) = &self.usage_meter {
+            meter.record(model, &format!("{:?}", self.config.agent_type), usage);
+        }
+    }
+
+    /// The configured artifact store, or the default one under `artifacts/`
+    fn artifact_store(&self) -> anyhow::Result<Arc<ArtifactStore>> {
+        match &self.artifacts {
+            Some(store) => Ok(store.clone()),
+            None => ArtifactStore::open(DEFAULT_ARTIFACT_DIR),
+        }
+    }
+}
+
+/// Cancels a turn's token when its deadline passes; disarmed when dropped
+struct TurnDeadline(tokio::task::JoinHandle<()>);
+
+impl TurnDeadline {
+    fn arm(token: CancellationToken, after: std::time::Duration) -> Self {
+        Self(tokio::spawn(async move {
+            tokio::time::sleep(after).await;
+            token.cancel();
+        }))
+    }
+}
+
+impl Drop for TurnDeadline {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
 }
 
 #[async_trait]
@@ -280,11 +372,19 @@ impl SovereignAgent for ReActAgent {
         let query = input.and_then(|v| v.as_str().map(|s| s.to_string()))
             .unwrap_or_else(|| "Continue with task".to_string());
 
-        // Execute one iteration of the ReAct loop
-        let res = self.execute(&query, None).await
+        // Execute one iteration of the ReAct loop, attributing artifacts to this task and step
+        let mut context = ArtifactContext::current();
+        context.owner.task_id = Some(task_id.to_string());
+        context.step_id = Some(step.step_id.clone());
+        let res = context.scope(self.execute(&query, None)).await
             .map_err(|e| anyhow::anyhow!(e))?;
 
         step.output = Some(res.answer);
+        step.artifacts = self.artifact_store()?
+            .list(&ArtifactFilter { step_id: Some(step.step_id.clone()), ..ArtifactFilter::task(task_id) })
+            .iter()
+            .map(|r| r.to_uap())
+            .collect();
         step.status = UapStepStatus::Completed;
         step.is_last = true; // For now, we execute full turns as single UAP steps
 
@@ -299,25 +399,17 @@ impl SovereignAgent for ReActAgent {
         Ok(UapTask::new("Not found")) // Placeholder for persistence
     }
 
-    async fn list_artifacts(&self, _task_id: &str) -> anyhow::Result<Vec<UapArtifact>> {
-        Ok(vec![])
+    async fn list_artifacts(&self, task_id: &str) -> anyhow::Result<Vec<UapArtifact>> {
+        let store = self.artifact_store()?;
+        Ok(store.list(&ArtifactFilter::task(task_id)).iter().map(|r| r.to_uap()).collect())
     }
 }
 
 impl ReActAgent {
-    /// Build the ReAct prompt
-    async fn build_react_prompt(&self, query: &str, steps: &[
-ReActStep],
- context: Option<&str>) -> String {
+    /// Build the ReAct prompt (the final user turn of the conversation)
+    async fn build_react_prompt(&self, query: &str, steps: &[ReActStep]) -> String {
         let mut prompt = String::new();
 
-        if let Some(ctx) = context {
-            prompt.push_str(&format!("## Context
-{}
-
-", ctx));
-        }
-
         prompt.push_str("## Available Tools
 ");
         prompt.push_str("Standard Tools:\n");
@@ -325,18 +417,20 @@ ReActStep],
         
         // SOTA: Laboratory Surface (FPF Principle)
         // Show dynamic tools that are currently in the 'laboratory'
-        let lab_tools = self.tools.tool_names().await.into_iter()
-            .filter(|n| !self.config.allowed_tools.contains(n) && n != "forge_tool")
-            .collect::<Vec<_>>();
+        let lab_tools = self.laboratory_tool_names().await;
             
         if !lab_tools.is_empty() {
             prompt.push_str("\nLaboratory (Experimental) Tools:\n");
-            prompt.push_str("NOTE: These tools are currently in the laboratory. Successful use will promote them to the standard set.\n");
+            prompt.push_str("NOTE: These tools are currently in the laboratory. After a successful use they are promoted to the standard set if their declared tests pass.\n");
             prompt.push_str(&self.tools.generate_filtered_tools_prompt(&lab_tools).await);
         }
         
         prompt.push_str("\n");
 
+        if self.provider.supports_native_tools() {
+            prompt.push_str("Tools are also available through native function calling. Prefer calling them that way.\n\n");
+        }
+
         if self.config.reasoning_enabled {
             prompt.push_str(r###"## Response Format (SNS-Core)
 
@@ -399,6 +493,106 @@ RULES:
         prompt
     }
 
+    /// Build the full conversation for a ReAct turn: system prompt and context,
+    /// prior turns, then the ReAct prompt. With native tool calling the trace is
+    /// replayed as assistant tool calls and tool results instead of prompt text.
+    async fn build_react_messages(&self, query: &str, steps: &[ReActStep], context: Option<&str>) -> Vec<ChatMessage> {
+        let mut system = self.config.system_prompt.clone();
+        if let Some(ctx) = context {
+            system.push_str(&format!("\n\n## Context\n{}", ctx));
+        }
+
+        let native = self.provider.supports_native_tools();
+        let mut messages = vec![ChatMessage::system(system)];
+        messages.extend(self.history.iter().cloned());
+        let prompt_trace: &[ReActStep] = if native { &[] } else { steps };
+        messages.push(ChatMessage::user(self.build_react_prompt(query, prompt_trace).await));
+
+        if native && !steps.is_empty() {
+            for step in steps {
+                messages.push(ChatMessage::assistant(step.thought.clone()).with_tool_calls(step.actions.clone()));
+                for (i, action) in step.actions.iter().enumerate() {
+                    let observation = step.observations.get(i).cloned()
+                        .unwrap_or_else(|| "No observation recorded.".to_string());
+                    messages.push(ChatMessage::tool(action.name.clone(), observation));
+                }
+                // System hints ride on steps without a matching tool call
+                for hint in step.observations.iter().skip(step.actions.len()) {
+                    messages.push(ChatMessage::user(hint.clone()));
+                }
+            }
+            messages.push(ChatMessage::user("Continue:"));
+        }
+        messages
+    }
+
+    /// Dynamic tools that are currently in the 'laboratory' (not in the allowed set)
+    async fn laboratory_tool_names(&self) -> Vec<String> {
+        self.tools.tool_names().await.into_iter()
+            .filter(|n| !self.config.allowed_tools.contains(n) && n != "forge_tool")
+            .collect()
+    }
+
+    /// Schemas offered to native tool calling: the allowed set plus laboratory tools
+    async fn native_tool_definitions(&self) -> Vec<ToolDefinition> {
+        let mut visible = self.config.allowed_tools.clone();
+        visible.extend(self.laboratory_tool_names().await);
+        self.tools.tool_definitions(&visible).await
+    }
+
+    /// Execute a step through the provider's native tool-calling interface.
+    async fn step_native(&self, query: &str, messages: Vec<ChatMessage>) -> AgentResult<ReActStep> {
+        let definitions = self.native_tool_definitions().await;
+        let completion = self.provider.chat_with_tools(&self.config.model, messages, self.config.generation_options(), &definitions).await
+            .map_err(|e| AgentError::Provider(e.to_string()))?;
+        self.native_step(query, completion)
+    }
+
+    /// Streaming variant of `step_native`: tokens flow as they are generated
+    /// and the typed tool calls are read from the stream trailer.
+    async fn step_native_stream(&self, query: &str, messages: Vec<ChatMessage>) -> AgentResult<ReActStep> {
+        let definitions = self.native_tool_definitions().await;
+        let mut stream = self.provider.chat_stream_with_tools(&self.config.model, messages, self.config.generation_options(), &definitions).await
+            .map_err(|e| AgentError::Provider(e.to_string()))?
+            .with_cancellation(self.cancel.clone());
+        let content = self.drain_stream(&mut stream).await?;
+        self.native_step(query, ToolCompletion { content, tool_calls: stream.tool_calls(), usage: stream.usage() })
+    }
+
+    /// Read a streamed step to the end, telling cancellation apart from provider failures
+    async fn drain_stream(&self, stream: &mut ChatStream) -> AgentResult<String> {
+        let mut full_content = String::new();
+        while let Some(chunk_res) = stream.next().await {
+            let chunk = chunk_res.map_err(|e| if self.cancel.is_cancelled() {
+                AgentError::Cancelled(e.to_string())
+            } else {
+                AgentError::Provider(e.to_string())
+            })?;
+            full_content.push_str(&chunk);
+            // SOTA: No token-by-token printing to stdout to avoid IO bottlenecks.
+            // Tokens are streamed to the UI via the provider's internal tx channel.
+        }
+        Ok(full_content)
+    }
+
+    /// Turn a native tool-calling completion into a step. Typed tool calls are
+    /// used directly; the text parser only runs when the model answered
+    /// without calling a tool.
+    fn native_step(&self, query: &str, completion: ToolCompletion) -> AgentResult<ReActStep> {
+        debug!("Native tool-call response: {} calls, content:\n{}", completion.tool_calls.len(), completion.content);
+
+        if completion.tool_calls.is_empty() {
+            return Ok(self.parse_response(&completion.content, query)?.with_usage(completion.usage));
+        }
+
+        let thought = self.extract_tag(&completion.content, "⚡")
+            .or_else(|| self.extract_tag(&completion.content, "🧠"))
+            .or_else(|| Some(completion.content.trim().to_string()).filter(|c| !c.is_empty()))
+            .unwrap_or_else(|| "Executing task...".to_string());
+
+        Ok(ReActStep::thought(thought).with_actions(completion.tool_calls).with_usage(completion.usage))
+    }
+
     /// Parse the LLM response using strict Tags
     fn parse_response(&self, response: &str, _query: &str) -> AgentResult<ReActStep> {
         debug!("Raw LLM Response for parsing:\n{}", response);
@@ -671,43 +865,43 @@ RULES:
 
     /// Execute a single step of the ReAct loop with streaming
     pub async fn step_stream(&self, query: &str, steps: &[ReActStep], context: Option<&str>) -> AgentResult<ReActStep> {
-        let prompt = self.build_react_prompt(query, steps, context).await;
-        let system = Some(self.config.system_prompt.clone());
+        let messages = self.build_react_messages(query, steps, context).await;
         
-        debug!("ReAct prompt (streaming):\n{}", prompt);
+        debug!("ReAct messages (streaming): {:?}", messages);
         info!("   ⏳ Iteration starting (model: {})...", self.config.model);
 
-        let mut stream = self.provider.generate_stream(&self.config.model, prompt, system).await
-            .map_err(|e| AgentError::Provider(e.to_string()))?;
-        let mut full_content = String::new();
-
-        while let Some(chunk_res) = stream.next().await {
-            let chunk = chunk_res.map_err(|e| AgentError::Provider(e.to_string()))?;
-            full_content.push_str(&chunk);
-            // SOTA: No token-by-token printing to stdout to avoid IO bottlenecks.
-            // Tokens are streamed to the UI via the provider's internal tx channel.
+        if self.provider.supports_native_tools() {
+            return self.step_native_stream(query, messages).await;
         }
 
+        let mut stream = self.provider.chat_stream(&self.config.model, messages, self.config.generation_options()).await
+            .map_err(|e| AgentError::Provider(e.to_string()))?
+            .with_cancellation(self.cancel.clone());
+        let full_content = self.drain_stream(&mut stream).await?;
+
         debug!("Full streamed response:\n{}", full_content);
 
-        self.parse_response(&full_content, query)
+        Ok(self.parse_response(&full_content, query)?.with_usage(stream.usage()))
     }
 
     /// Execute a single step of the ReAct loop
     pub async fn step(&self, query: &str, steps: &[
 ReActStep],
  context: Option<&str>) -> AgentResult<ReActStep> {
-        let prompt = self.build_react_prompt(query, steps, context).await;
-        let system = Some(self.config.system_prompt.clone());
+        let messages = self.build_react_messages(query, steps, context).await;
         
-        debug!("ReAct prompt:\n{}", prompt);
+        debug!("ReAct messages: {:?}", messages);
 
-        let content = self.provider.generate(&self.config.model, prompt, system).await
+        if self.provider.supports_native_tools() {
+            return self.step_native(query, messages).await;
+        }
+
+        let completion = self.provider.chat(&self.config.model, messages, self.config.generation_options()).await
             .map_err(|e| AgentError::Provider(e.to_string()))?;
 
-        debug!("LLM response:\n{}", content);
+        debug!("LLM response:\n{}", completion.content);
 
-        self.parse_response(&content, query)
+        Ok(self.parse_response(&completion.content, query)?.with_usage(completion.usage))
     }
 }
 
@@ -746,14 +940,43 @@ impl ReActAgent {
         &self, 
         query: &str, 
         context: Option<&str>,
-        mut steering_rx: Option<tokio::sync::mpsc::Receiver<String>>
+        steering_rx: Option<tokio::sync::mpsc::Receiver<String>>
+    ) -> AgentResult<AgentResponse> {
+        let mut recalled = Vec::new();
+        let result = self.run_turn(query, context, steering_rx, &mut recalled).await;
+
+        // Memories recalled during the turn share its outcome; paused turns have none yet
+        if let (Some(memory), Ok(resp)) = (&self.memory, &result) {
+            if !recalled.is_empty() && resp.pending_approval.is_none() {
+                if let Err(e) = memory.reinforce(&recalled, Reinforcement::Outcome(resp.success)).await {
+                    warn!("Failed to reinforce recalled memories: {}", e);
+                }
+            }
+        }
+        result
+    }
+
+    async fn run_turn(
+        &self,
+        query: &str,
+        context: Option<&str>,
+        mut steering_rx: Option<tokio::sync::mpsc::Receiver<String>>,
+        recalled: &mut Vec<String>,
     ) -> AgentResult<AgentResponse> {
         info!("ReAct agent starting execution for query: {}", query);
         
         let mut steps = Vec::new();
+        // Accumulated separately: trace compression may drop steps
+        let mut usage = TokenUsage::default();
+        // The turn stops on an external cancel or once its deadline passes
+        let turn = self.cancel.child_token();
+        let _deadline = self.config.turn_timeout().map(|limit| TurnDeadline::arm(turn.clone(), limit));
         
         for iteration in 0..self.config.max_iterations {
             debug!("ReAct iteration {}", iteration + 1);
+            if turn.is_cancelled() {
+                return Ok(self.interrupted(steps, usage));
+            }
             
             // Check for steering messages BEFORE the turn
             if let Some(ref mut rx) = steering_rx {
@@ -768,7 +991,15 @@ impl ReActAgent {
             let _ = self.provider.notify(&format!("STATE:MODEL:{}", self.config.model)).await;
             let _ = self.provider.notify(&format!("\n[ITERATION {}]\n", iteration + 1)).await;
             
-            let mut step = match self.step_stream(query, &steps, context).await {
+            let outcome = tokio::select! {
+                biased;
+                _ = turn.cancelled() => None,
+                outcome = self.step_stream(query, &steps, context) => Some(outcome),
+            };
+            let Some(outcome) = outcome else {
+                return Ok(self.interrupted(steps, usage));
+            };
+            let mut step = match outcome {
                 Ok(s) => {
                     for action in &s.actions {
                         let msg = format!("🔧 Using Tool: {}...", action.name);
@@ -778,13 +1009,16 @@ impl ReActAgent {
                     }
                     s
                 },
+                Err(_) if turn.is_cancelled() => return Ok(self.interrupted(steps, usage)),
                 Err(e) => {
                     warn!("ReAct step parsing failed: {}", e);
                     let _ = self.provider.notify(&format!("\n❌ Parsing error: {}\n", e)).await;
                     steps.push(ReActStep::thought(format!("Parsing error: {}", e)));
-                    return Ok(AgentResponse::failure(e.to_string(), steps, self.config.agent_type));
+                    return Ok(AgentResponse::failure(e.to_string(), steps, self.config.agent_type).with_usage(usage));
                 }
             };
+            usage += step.usage;
+            self.meter_usage(&self.config.model, step.usage);
 
             // LAZINESS FILTER: Detect finishing without action for complex queries
             if step.is_final && steps.is_empty() && is_action_query(query) {
@@ -809,7 +1043,7 @@ impl ReActAgent {
                 self.normalize_steps(&mut steps);
                 
                 info!("ReAct agent completed in {} iterations", iteration + 1);
-                return Ok(AgentResponse::success(answer, steps, self.config.agent_type));
+                return Ok(AgentResponse::success(answer, steps, self.config.agent_type).with_usage(usage));
             }
 
             if !step.actions.is_empty() {
@@ -825,7 +1059,8 @@ impl ReActAgent {
                             self.normalize_steps(&mut steps);
                             
                             return Ok(AgentResponse::success("Awaiting human approval for sensitive operation.", steps, self.config.agent_type)
-                                .with_approval(request));
+                                .with_approval(request)
+                                .with_usage(usage));
                         }
                     }
                 }
@@ -841,18 +1076,6 @@ impl ReActAgent {
                     }
                 }
 
-                // Context Compression: If we have too many steps, summarize old ones
-                if steps.len() > 5 {
-                    info!("Trace compression active ({} steps). Summarizing early history.", steps.len());
-                    // Simple compression: Keep first step and last 3 steps, replace middle with summary
-                    let first = steps[0].clone();
-                    let last_three = steps[steps.len()-3..].to_vec();
-                    let mut compressed = vec![first];
-                    compressed.push(ReActStep::thought("[SYSTEM: Early history summarized to save context tokens]"));
-                    compressed.extend(last_three);
-                    steps = compressed;
-                }
-
                 debug!("Executing {} tools in parallel", step.actions.len());
                 
                 // PAI: Trigger PreToolUse Hooks
@@ -885,7 +1108,7 @@ impl ReActAgent {
                                     let _ = mem.log_event(&blocked_event);
                                 }
 
-                                return Ok(AgentResponse::failure(format!("Security Block: {}", reason), blocked_steps, self.config.agent_type));
+                                return Ok(AgentResponse::failure(format!("Security Block: {}", reason), blocked_steps, self.config.agent_type).with_usage(usage));
                             },
                             Ok(_) => {
                                 // Log the allowed event
@@ -911,7 +1134,14 @@ impl ReActAgent {
                     });
                 }
 
-                let results = self.tools.execute_parallel(&step.actions).await;
+                let artifact_context = ArtifactContext {
+                    step: Some(iteration),
+                    agent: Some(self.config.agent_type.to_string()),
+                    ..ArtifactContext::current()
+                };
+                let results = artifact_context
+                    .scope(self.tools.execute_parallel_with(&step.actions, &turn, self.config.tool_timeout()))
+                    .await;
                 
                 let mut observations = Vec::new();
                 for (i, res) in results.into_iter().enumerate() {
@@ -924,15 +1154,30 @@ impl ReActAgent {
                             });
                             let _ = self.provider.notify(&format!("\n👁️ Observation: {}\n", output.summary)).await;
                             
-                            // SOTA: Tool Promotion (Laboratory graduation)
-                            let _ = self.tools.promote_tool(&action.name).await;
+                            if output.success && action.name == "memory_query" {
+                                if let Some(memories) = output.data["memories"].as_array() {
+                                    recalled.extend(memories.iter().filter_map(|m| m["id"].as_str().map(str::to_string)));
+                                }
+                            }
+
+                            // SOTA: Tool Promotion (Laboratory graduation), gated on the tool's declared tests
+                            if output.success {
+                                if let Err(e) = self.tools.promote_tool(&action.name).await {
+                                    debug!("'{}' stays in the laboratory: {}", action.name, e);
+                                }
+                            }
                             
                             // SOTA: TOON Data Optimization (FPF Principle: Token Sovereignty)
                             // If the tool data is complex (not just summary), use TOON notation.
-                            if output.data.is_object() || output.data.is_array() {
+                            let body = if output.data.is_object() || output.data.is_array() {
                                 crate::utils::toon::ToonFormatter::format(&output.data)
                             } else {
                                 output.summary
+                            };
+                            // Let the model know it may be looking at an earlier result
+                            match output.cache_age_secs {
+                                Some(age) => format!("[cached result from {}s ago]\n{}", age, body),
+                                None => body,
                             }
                         },
                         Err(e) => {
@@ -957,8 +1202,28 @@ impl ReActAgent {
                     observations,
                     is_final: false,
                     answer: None,
+                    usage: step.usage,
                 };
                 steps.push(step_with_obs);
+                if turn.is_cancelled() {
+                    return Ok(self.interrupted(steps, usage));
+                }
+
+                // Context Compression: summarize early history once the trace outgrows its token budget
+                let compaction = tokio::select! {
+                    biased;
+                    _ = turn.cancelled() => None,
+                    compaction = ContextCompactor::compact_trace(&mut steps, self.provider.clone(), self.config.summary_model(), self.config.trace_budget()) => Some(compaction),
+                };
+                match compaction {
+                    None => return Ok(self.interrupted(steps, usage)),
+                    Some(Ok(Some(done))) => {
+                        usage += done.usage;
+                        self.meter_usage(self.config.summary_model(), done.usage);
+                    }
+                    Some(Ok(None)) => {}
+                    Some(Err(e)) => warn!("Trace compaction failed: {}", e),
+                }
             } else {
                 steps.push(step);
             }
@@ -971,7 +1236,20 @@ impl ReActAgent {
             format!("Reached maximum iterations ({})", self.config.max_iterations),
             steps,
             self.config.agent_type,
-        ))
+        ).with_usage(usage))
+    }
+
+    /// Close a cancelled or timed-out turn, keeping the steps taken so far
+    fn interrupted(&self, mut steps: Vec<ReActStep>, usage: TokenUsage) -> AgentResponse {
+        let reason = if self.cancel.is_cancelled() {
+            "Turn cancelled".to_string()
+        } else {
+            format!("Turn exceeded its {}s deadline", self.config.turn_timeout_secs.unwrap_or_default())
+        };
+        warn!("ReAct agent interrupted: {}", reason);
+        steps.push(ReActStep::thought(format!("[CANCELLED]: {}", reason)));
+        self.normalize_steps(&mut steps);
+        AgentResponse::failure(reason, steps, self.config.agent_type).with_usage(usage)
     }
 }
 
@@ -980,6 +1258,8 @@ impl ReActAgent {
 pub struct SimpleAgent {
     provider: Arc<dyn LLMProvider>,
     config: AgentConfig,
+    /// Prior conversation turns, sent as real messages before the query
+    history: Vec<ChatMessage>,
 }
 
 impl SimpleAgent {
@@ -990,11 +1270,11 @@ impl SimpleAgent {
             Arc::new(OllamaProvider::new(ollama)) as Arc<dyn LLMProvider>
         };
 
-        Self { provider, config }
+        Self { provider, config, history: Vec::new() }
     }
 
     pub fn new_with_provider(provider: Arc<dyn LLMProvider>, config: AgentConfig) -> Self {
-        Self { provider, config }
+        Self { provider, config, history: Vec::new() }
     }
 
     pub fn with_provider(mut self, provider: Arc<dyn LLMProvider>) -> Self {
@@ -1002,30 +1282,40 @@ impl SimpleAgent {
         self
     }
 
+    pub fn with_history(mut self, history: Vec<ChatMessage>) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// System prompt + guidance + context, prior turns, then the query
+    fn build_messages(&self, guidance: &str, query: &str, context: Option<&str>) -> Vec<ChatMessage> {
+        let mut system = format!("{}\n\n{}", self.config.system_prompt, guidance);
+        if let Some(ctx) = context {
+            system.push_str(&format!("\n\n## Context\n{}", ctx));
+        }
+
+        let mut messages = vec![ChatMessage::system(system)];
+        messages.extend(self.history.iter().cloned());
+        messages.push(ChatMessage::user(query));
+        messages
+    }
+
     pub async fn execute_simple(&self, query: &str, context: Option<&str>) -> AgentResult<AgentResponse> {
-        let mut prompt = String::new();
-        let system = Some(self.config.system_prompt.clone());
-        
         // FPF-SOTA (January 2026): Multi-View Publication with SLL/BLP
-        prompt.push_str("<|im_start|>system\nYou are a high-fidelity intelligence layer. \
+        let messages = self.build_messages("You are a high-fidelity intelligence layer. \
             Follow the Standard Notation System (SNS): Use symbols to minimize tokens. \
             Follow the First Principles Framework (FPF): \
             1. BLP (Bitter-Lesson Preference): Prefer general, scale-amenable solutions. \
             2. SLL (Scaling-Law Lens): Identify scale variables (S) and χ elasticity. \
-            Use ⚡ for internal thought and 🎯 for the final user response.<|im_end|>\n");
+            Use ⚡ for internal thought and 🎯 for the final user response.", query, context);
 
-        if let Some(ctx) = context {
-            prompt.push_str(ctx);
-            prompt.push_str("\n");
-        }
-        prompt.push_str(&format!("<|im_start|>user\n{}<|im_end|>\n<|im_start|>assistant\n⚡\n", query));
-
-        debug!("Simple conversational prompt:\n{}", prompt);
+        debug!("Simple conversational messages: {:?}", messages);
 
         let _ = self.provider.notify(&format!("STATE:MODEL:{}", self.config.model)).await;
 
-        let content = self.provider.generate(&self.config.model, prompt, system).await
+        let completion = self.provider.chat(&self.config.model, messages, self.config.generation_options()).await
             .map_err(|e| AgentError::Provider(e.to_string()))?;
+        let content = completion.content;
         
         // MVPK Projection: Extract Thought (TechView) and Answer (PlainView)
         let mut thought = "Processing...".to_string();
@@ -1058,10 +1348,11 @@ impl SimpleAgent {
             }
         }
 
-        let step = ReActStep::final_answer(thought.clone(), &answer);
+        let step = ReActStep::final_answer(thought.clone(), &answer).with_usage(completion.usage);
         Ok(AgentResponse::success(answer, vec![step], self.config.agent_type)
             .with_thought(thought)
-            .with_reliability(reliability))
+            .with_reliability(reliability)
+            .with_usage(completion.usage))
     }
 
     /// FPF Quality Scoring: Detect hallucinations and repetitive patterns
@@ -1121,26 +1412,16 @@ impl SimpleAgent {
     where
         F: FnMut(&str) + Send
     {
-        let mut prompt = String::new();
-        let system = Some(self.config.system_prompt.clone());
-        
         // FPF multi-view publication header
-        prompt.push_str("<|im_start|>system\nYou are a high-fidelity intelligence layer. \
-            Follow the First Principles Framework (FPF): ALWAYS start with [THOUGHT] to process, then [ANSWER] for the user.<|im_end|>\n");
+        let messages = self.build_messages("You are a high-fidelity intelligence layer. \
+            Follow the First Principles Framework (FPF): ALWAYS start with [THOUGHT] to process, then [ANSWER] for the user.", query, context);
 
-        if let Some(ctx) = context {
-            // Inject context directly (assumed ChatML formatted)
-            prompt.push_str(ctx);
-            prompt.push_str("\n");
-        }
-        prompt.push_str(&format!("<|im_start|>user\n{}<|im_end|>\n<|im_start|>assistant\n[THOUGHT]\n", query));
-
-        debug!("Simple conversational prompt (streaming):\n{}", prompt);
+        debug!("Simple conversational messages (streaming): {:?}", messages);
 
         let _ = self.provider.notify(&format!("STATE:MODEL:{}", self.config.model)).await;
 
         // Use streaming generation
-        let mut stream = self.provider.generate_stream(&self.config.model, prompt, system).await
+        let mut stream = self.provider.chat_stream(&self.config.model, messages, self.config.generation_options()).await
             .map_err(|e| AgentError::Provider(e.to_string()))?;
         let mut full_response = String::new();
         
@@ -1159,10 +1440,12 @@ impl SimpleAgent {
         if let Some(t) = self.extract_tag(&full_response, "[THOUGHT]") { thought = t; }
         if let Some(a) = self.extract_tag(&full_response, "[ANSWER]") { answer = a; }
 
-        let step = ReActStep::final_answer(thought.clone(), &answer);
+        let usage = stream.usage();
+        let step = ReActStep::final_answer(thought.clone(), &answer).with_usage(usage);
         Ok(AgentResponse::success(answer, vec![step], self.config.agent_type)
             .with_thought(thought)
-            .with_reliability(reliability))
+            .with_reliability(reliability)
+            .with_usage(usage))
     }
 
     fn extract_tag(&self, text: &str, tag: &str) -> Option<String> {
@@ -1208,6 +1491,171 @@ mod tests {
     use super::*;
     use crate::orchestrator::profile::AgencyProfile;
 
+    use crate::agent::{ChatRole, GenerationOptions, ToolCompletion};
+    use crate::tools::{Tool, ToolDefinition, ToolOutput};
+
+    #[derive(Default)]
+    struct StatusTool;
+
+    #[async_trait]
+    impl Tool for StatusTool {
+        fn name(&self) -> String { "system_monitor".to_string() }
+        fn description(&self) -> String { "Reports system status".to_string() }
+        fn parameters(&self) -> serde_json::Value { serde_json::json!({"type": "object"}) }
+        async fn execute(&self, _params: serde_json::Value) -> AgentResult<ToolOutput> {
+            Ok(ToolOutput::success_str("ok"))
+        }
+    }
+
+    struct NativeToolProvider;
+
+    #[async_trait]
+    impl LLMProvider for NativeToolProvider {
+        async fn generate(&self, _model: &str, _prompt: String, _system: Option<String>) -> anyhow::Result<String> {
+            Ok("🎯 text path".to_string())
+        }
+
+        async fn generate_stream(&self, _model: &str, _prompt: String, _system: Option<String>) -> anyhow::Result<futures_util::stream::BoxStream<'static, anyhow::Result<String>>> {
+            Ok(Box::pin(futures_util::stream::once(async { Ok("🎯 text path".to_string()) })))
+        }
+
+        fn supports_native_tools(&self) -> bool {
+            true
+        }
+
+        async fn chat_with_tools(&self, _model: &str, messages: Vec<ChatMessage>, _options: GenerationOptions, tools: &[ToolDefinition]) -> anyhow::Result<ToolCompletion> {
+            assert!(tools.iter().any(|t| t.name == "system_monitor"));
+            assert_eq!(messages[0].role, ChatRole::System);
+            Ok(ToolCompletion {
+                content: "Check the machine first.".to_string(),
+                tool_calls: vec![ToolCall { name: "system_monitor".to_string(), parameters: serde_json::json!({"action": "status"}) }],
+                usage: TokenUsage::new(120, 15),
+            })
+        }
+
+        fn get_lock(&self) -> Arc<tokio::sync::Mutex<()>> {
+            Arc::new(tokio::sync::Mutex::new(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_step_uses_native_tool_calls() {
+        let profile = AgencyProfile::default();
+        let config = AgentConfig::new(AgentType::GeneralChat, &profile);
+        let tools = Arc::new(ToolRegistry::default());
+        tools.register::<StatusTool>().await;
+        let agent = ReActAgent::new_with_provider(Arc::new(NativeToolProvider), config, tools);
+
+        let step = agent.step("How is the system doing?", &[], None).await.unwrap();
+        assert!(!step.is_final);
+        assert_eq!(step.thought, "Check the machine first.");
+        assert_eq!(step.actions.len(), 1);
+        assert_eq!(step.actions[0].name, "system_monitor");
+        assert_eq!(step.usage, TokenUsage::new(120, 15));
+    }
+
+    #[tokio::test]
+    async fn test_step_stream_reads_tool_calls_from_trailer() {
+        let profile = AgencyProfile::default();
+        let config = AgentConfig::new(AgentType::GeneralChat, &profile);
+        let tools = Arc::new(ToolRegistry::default());
+        tools.register::<StatusTool>().await;
+        let agent = ReActAgent::new_with_provider(Arc::new(NativeToolProvider), config, tools);
+
+        let step = agent.step_stream("How is the system doing?", &[], None).await.unwrap();
+        assert_eq!(step.thought, "Check the machine first.");
+        assert_eq!(step.actions.len(), 1);
+        assert_eq!(step.actions[0].name, "system_monitor");
+        assert_eq!(step.usage, TokenUsage::new(120, 15));
+    }
+
+    #[tokio::test]
+    async fn test_native_trace_replayed_as_messages() {
+        let profile = AgencyProfile::default();
+        let config = AgentConfig::new(AgentType::GeneralChat, &profile);
+        let agent = ReActAgent::new_with_provider(Arc::new(NativeToolProvider), config, Arc::new(ToolRegistry::default()))
+            .with_history(vec![ChatMessage::user("earlier question"), ChatMessage::assistant("earlier answer")]);
+
+        let call = ToolCall { name: "system_monitor".to_string(), parameters: serde_json::json!({"action": "status"}) };
+        let mut step = ReActStep::thought("Check status").with_actions(vec![call.clone()]);
+        step.observations.push("all good".to_string());
+
+        let messages = agent.build_react_messages("How is the system?", &[step], Some("project notes")).await;
+        let roles: Vec<_> = messages.iter().map(|m| m.role).collect();
+        assert_eq!(roles, vec![
+            ChatRole::System, ChatRole::User, ChatRole::Assistant, ChatRole::User,
+            ChatRole::Assistant, ChatRole::Tool, ChatRole::User,
+        ]);
+        assert!(messages[0].content.contains("project notes"));
+        assert!(!messages[3].content.contains("## Trace"));
+        assert_eq!(messages[4].tool_calls, vec![call]);
+        assert_eq!(messages[5].content, "all good");
+    }
+
+    #[derive(Default)]
+    struct HangingStatusTool;
+
+    #[async_trait]
+    impl Tool for HangingStatusTool {
+        fn name(&self) -> String { "system_monitor".to_string() }
+        fn description(&self) -> String { "Reports system status, eventually".to_string() }
+        fn parameters(&self) -> serde_json::Value { serde_json::json!({"type": "object"}) }
+        async fn execute(&self, _params: serde_json::Value) -> AgentResult<ToolOutput> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_turn_keeps_partial_trace() {
+        let profile = AgencyProfile::default();
+        let mut config = AgentConfig::new(AgentType::GeneralChat, &profile);
+        config.tool_timeout_secs = None;
+        let tools = Arc::new(ToolRegistry::default());
+        tools.register::<HangingStatusTool>().await;
+        let cancel = CancellationToken::new();
+        let agent = ReActAgent::new_with_provider(Arc::new(NativeToolProvider), config, tools)
+            .with_cancellation(cancel.clone());
+
+        let run = tokio::spawn(async move { agent.execute("How is the system doing?", None).await });
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        cancel.cancel();
+        let response = tokio::time::timeout(std::time::Duration::from_secs(5), run).await
+            .expect("cancellation must end the turn promptly").unwrap().unwrap();
+
+        assert!(!response.success);
+        assert_eq!(response.error.as_deref(), Some("Turn cancelled"));
+        assert_eq!(response.usage, TokenUsage::new(120, 15));
+        let tool_step = &response.steps[0];
+        assert_eq!(tool_step.actions[0].name, "system_monitor");
+        assert!(tool_step.observations[0].contains("cancelled"));
+        assert!(response.steps.last().unwrap().thought.starts_with("[CANCELLED]"));
+    }
+
+    #[tokio::test]
+    async fn test_turn_deadline_interrupts() {
+        let profile = AgencyProfile::default();
+        let mut config = AgentConfig::new(AgentType::GeneralChat, &profile);
+        config.turn_timeout_secs = Some(1);
+        config.tool_timeout_secs = None;
+        let tools = Arc::new(ToolRegistry::default());
+        tools.register::<HangingStatusTool>().await;
+        let agent = ReActAgent::new_with_provider(Arc::new(NativeToolProvider), config, tools);
+
+        let response = tokio::time::timeout(std::time::Duration::from_secs(5), agent.execute("How is the system doing?", None)).await
+            .expect("deadline must end the turn").unwrap();
+        assert!(!response.success);
+        assert_eq!(response.error.as_deref(), Some("Turn exceeded its 1s deadline"));
+        assert!(!response.steps.is_empty());
+    }
+
+    #[test]
+    fn test_with_usage_sets_cost_tokens() {
+        let response = AgentResponse::success("done", vec![], AgentType::GeneralChat)
+            .with_usage(TokenUsage::new(40, 2) + TokenUsage::new(10, 8));
+        assert_eq!(response.cost_tokens, 60);
+        assert_eq!(response.usage, TokenUsage::new(50, 10));
+    }
+
     #[test]
     fn test_extract_tag() {
         let profile = AgencyProfile::default();
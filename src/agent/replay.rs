@@ -0,0 +1,487 @@
+//! Record / Replay Providers
+//!
+//! `RecordingProvider` wraps a live backend and writes every prompt/response
+//! pair to a JSON fixture. `ReplayProvider` serves those pairs back, keyed by a
+//! hash of the normalized conversation, so whole pipelines run offline and
+//! deterministically. An unrecorded prompt is an error that prints the prompt
+//! and the closest recorded one, never a silent fallback.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+
+use super::provider::{messages_from_prompt, StreamTrailer};
+use crate::agent::{ChatCompletion, ChatMessage, ChatStream, GenerationOptions, LLMProvider, TokenUsage, ToolCompletion};
+use crate::tools::{ToolCall, ToolDefinition};
+
+/// Bumped when the normalization or file layout changes incompatibly
+pub const FIXTURE_VERSION: u32 = 1;
+
+lazy_static! {
+    static ref UUID_RE: Regex = Regex::new(r"(?i)\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b").unwrap();
+    static ref TIMESTAMP_RE: Regex = Regex::new(r"\b\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(\.\d+)?(Z|[+-]\d{2}:?\d{2})?").unwrap();
+    static ref WHITESPACE_RE: Regex = Regex::new(r"\s+").unwrap();
+}
+
+/// Strip what varies between otherwise identical runs: ids, wall-clock
+/// timestamps and whitespace layout
+pub fn normalize_text(text: &str) -> String {
+    let text = UUID_RE.replace_all(text, "<uuid>");
+    let text = TIMESTAMP_RE.replace_all(&text, "<timestamp>");
+    WHITESPACE_RE.replace_all(text.trim(), " ").into_owned()
+}
+
+/// Canonical rendering of a request, one line per message. The model is left
+/// out so a fixture recorded against one backend replays against any other.
+pub fn normalize_request(messages: &[ChatMessage], tools: &[ToolDefinition]) -> String {
+    let mut lines: Vec<String> = messages.iter().map(|m| {
+        let mut line = match m.tool_name {
+            Some(ref tool) => format!("{}({}): ", m.role.as_str(), tool),
+            None => format!("{}: ", m.role.as_str()),
+        };
+        line.push_str(&normalize_text(&m.content));
+        if !m.tool_calls.is_empty() {
+            line.push_str(&format!(" calls={}", serde_json::to_string(&m.tool_calls).unwrap_or_default()));
+        }
+        line
+    }).collect();
+    if !tools.is_empty() {
+        // Registries hand out definitions in hash order
+        let mut names: Vec<&str> = tools.iter().map(|t| t.name.as_str()).collect();
+        names.sort_unstable();
+        lines.push(format!("tools: {}", names.join(",")));
+    }
+    lines.join("\n")
+}
+
+/// Hex SHA-256 of a normalized request
+pub fn request_key(normalized: &str) -> String {
+    hex::encode(Sha256::digest(normalized.as_bytes()))
+}
+
+/// One recorded exchange
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FixtureEntry {
+    /// `request_key(request)`; recomputed on load, kept for readable diffs
+    pub key: String,
+    /// Model the request was sent to (informational)
+    pub model: String,
+    /// The normalized request
+    pub request: String,
+    pub response: String,
+    /// Chunks of a streamed response, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chunks: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    #[serde(default)]
+    pub usage: TokenUsage,
+}
+
+/// A recorded conversation, as checked into `tests/fixtures/`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Fixture {
+    pub version: u32,
+    /// Whether the recorded backend took native tool definitions; replaying
+    /// with the same answer keeps agents on the same code path
+    #[serde(default)]
+    pub native_tools: bool,
+    pub entries: Vec<FixtureEntry>,
+}
+
+impl Default for Fixture {
+    fn default() -> Self {
+        Self { version: FIXTURE_VERSION, native_tools: false, entries: Vec::new() }
+    }
+}
+
+impl Fixture {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let raw = std::fs::read_to_string(path).with_context(|| format!("reading fixture {:?}", path))?;
+        let fixture: Fixture = serde_json::from_str(&raw).with_context(|| format!("parsing fixture {:?}", path))?;
+        if fixture.version != FIXTURE_VERSION {
+            return Err(anyhow!("fixture {:?} has version {}, expected {}; re-record it", path, fixture.version, FIXTURE_VERSION));
+        }
+        Ok(fixture)
+    }
+
+    /// Write pretty JSON via a temporary file so a crash never leaves half a fixture
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)? + "\n")?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+/// Wraps a provider and records every exchange to a fixture file.
+/// The file is rewritten after each exchange, so an aborted run keeps what it saw.
+pub struct RecordingProvider {
+    inner: Arc<dyn LLMProvider>,
+    path: PathBuf,
+    fixture: Arc<Mutex<Fixture>>,
+}
+
+impl RecordingProvider {
+    /// Start a fresh recording; an existing file at `path` is replaced
+    pub fn new(inner: Arc<dyn LLMProvider>, path: impl Into<PathBuf>) -> Self {
+        let fixture = Fixture { native_tools: inner.supports_native_tools(), ..Default::default() };
+        Self { inner, path: path.into(), fixture: Arc::new(Mutex::new(fixture)) }
+    }
+
+    /// Everything recorded so far
+    pub async fn fixture(&self) -> Fixture {
+        self.fixture.lock().await.clone()
+    }
+
+    async fn record(&self, entry: FixtureEntry) -> Result<()> {
+        append(&self.fixture, &self.path, entry).await
+    }
+}
+
+async fn append(fixture: &Mutex<Fixture>, path: &Path, entry: FixtureEntry) -> Result<()> {
+    let mut fixture = fixture.lock().await;
+    fixture.entries.push(entry);
+    fixture.save(path)
+}
+
+fn entry(model: &str, request: String, response: String) -> FixtureEntry {
+    FixtureEntry {
+        key: request_key(&request),
+        model: model.to_string(),
+        request,
+        response,
+        chunks: Vec::new(),
+        tool_calls: Vec::new(),
+        usage: TokenUsage::default(),
+    }
+}
+
+/// Pass a live stream through and record it once it completes cleanly.
+/// Streams that error or are dropped early are not recorded.
+fn record_stream(
+    inner: BoxStream<'static, Result<String>>,
+    trailer: StreamTrailer,
+    fixture: Arc<Mutex<Fixture>>,
+    path: PathBuf,
+    pending: FixtureEntry,
+) -> BoxStream<'static, Result<String>> {
+    let state = (inner, Some(pending), trailer, fixture, path);
+    Box::pin(futures_util::stream::unfold(state, |(mut inner, mut pending, trailer, fixture, path)| async move {
+        match inner.next().await {
+            Some(Ok(chunk)) => {
+                if let Some(ref mut entry) = pending {
+                    entry.response.push_str(&chunk);
+                    entry.chunks.push(chunk.clone());
+                }
+                Some((Ok(chunk), (inner, pending, trailer, fixture, path)))
+            }
+            Some(Err(e)) => Some((Err(e), (inner, None, trailer, fixture, path))),
+            None => {
+                if let Some(mut entry) = pending {
+                    entry.usage = trailer.usage();
+                    entry.tool_calls = trailer.tool_calls();
+                    if let Err(e) = append(&fixture, &path, entry).await {
+                        tracing::warn!("RecordingProvider: failed to save {:?}: {}", path, e);
+                    }
+                }
+                None
+            }
+        }
+    }))
+}
+
+#[async_trait]
+impl LLMProvider for RecordingProvider {
+    async fn generate(&self, model: &str, prompt: String, system: Option<String>) -> Result<String> {
+        let request = normalize_request(&messages_from_prompt(prompt.clone(), system.clone()), &[]);
+        let response = self.inner.generate(model, prompt, system).await?;
+        self.record(entry(model, request, response.clone())).await?;
+        Ok(response)
+    }
+
+    async fn generate_stream(&self, model: &str, prompt: String, system: Option<String>) -> Result<BoxStream<'static, Result<String>>> {
+        let request = normalize_request(&messages_from_prompt(prompt.clone(), system.clone()), &[]);
+        let stream = self.inner.generate_stream(model, prompt, system).await?;
+        Ok(record_stream(stream, StreamTrailer::default(), self.fixture.clone(), self.path.clone(), entry(model, request, String::new())))
+    }
+
+    async fn chat(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions) -> Result<ChatCompletion> {
+        let request = normalize_request(&messages, &[]);
+        let completion = self.inner.chat(model, messages, options).await?;
+        let mut recorded = entry(model, request, completion.content.clone());
+        recorded.usage = completion.usage;
+        self.record(recorded).await?;
+        Ok(completion)
+    }
+
+    async fn chat_stream(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions) -> Result<ChatStream> {
+        let request = normalize_request(&messages, &[]);
+        let stream = self.inner.chat_stream(model, messages, options).await?;
+        let trailer = stream.trailer();
+        let (fixture, path) = (self.fixture.clone(), self.path.clone());
+        let pending = entry(model, request, String::new());
+        Ok(stream.map_inner(move |inner| record_stream(inner, trailer, fixture, path, pending)))
+    }
+
+    fn supports_native_tools(&self) -> bool {
+        self.inner.supports_native_tools()
+    }
+
+    async fn chat_with_tools(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions, tools: &[ToolDefinition]) -> Result<ToolCompletion> {
+        let request = normalize_request(&messages, tools);
+        let completion = self.inner.chat_with_tools(model, messages, options, tools).await?;
+        let mut recorded = entry(model, request, completion.content.clone());
+        recorded.tool_calls = completion.tool_calls.clone();
+        recorded.usage = completion.usage;
+        self.record(recorded).await?;
+        Ok(completion)
+    }
+
+    async fn chat_stream_with_tools(&self, model: &str, messages: Vec<ChatMessage>, options: GenerationOptions, tools: &[ToolDefinition]) -> Result<ChatStream> {
+        let request = normalize_request(&messages, tools);
+        let stream = self.inner.chat_stream_with_tools(model, messages, options, tools).await?;
+        let trailer = stream.trailer();
+        let (fixture, path) = (self.fixture.clone(), self.path.clone());
+        let pending = entry(model, request, String::new());
+        Ok(stream.map_inner(move |inner| record_stream(inner, trailer, fixture, path, pending)))
+    }
+
+    fn get_lock(&self) -> Arc<Mutex<()>> {
+        self.inner.get_lock()
+    }
+
+    async fn notify(&self, message: &str) -> Result<()> {
+        self.inner.notify(message).await
+    }
+}
+
+/// Serves a recorded fixture. Identical requests replay their recorded
+/// responses in order, repeating the last one once the recording runs out.
+pub struct ReplayProvider {
+    entries: HashMap<String, Vec<FixtureEntry>>,
+    /// How many times each key has been served
+    served: std::sync::Mutex<HashMap<String, usize>>,
+    native_tools: bool,
+    lock: Arc<Mutex<()>>,
+}
+
+impl ReplayProvider {
+    pub fn new(fixture: Fixture) -> Self {
+        let mut entries: HashMap<String, Vec<FixtureEntry>> = HashMap::new();
+        for entry in fixture.entries {
+            // Keys are derived, so a hand-edited request stays consistent
+            entries.entry(request_key(&entry.request)).or_default().push(entry);
+        }
+        Self {
+            entries,
+            served: std::sync::Mutex::new(HashMap::new()),
+            native_tools: fixture.native_tools,
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(Fixture::load(path)?))
+    }
+
+    /// Recorded requests that were never asked for; a non-empty result in a
+    /// golden test means the pipeline skipped a step it used to take
+    pub fn unused(&self) -> Vec<String> {
+        let served = self.served.lock().map(|s| s.clone()).unwrap_or_default();
+        let mut unused: Vec<String> = self.entries.iter()
+            .filter(|(key, _)| !served.contains_key(*key))
+            .flat_map(|(_, entries)| entries.iter().map(|e| e.request.clone()))
+            .collect();
+        unused.sort();
+        unused
+    }
+
+    fn lookup(&self, messages: &[ChatMessage], tools: &[ToolDefinition]) -> Result<FixtureEntry> {
+        let request = normalize_request(messages, tools);
+        let key = request_key(&request);
+        let Some(recorded) = self.entries.get(&key) else {
+            return Err(anyhow!(
+                "ReplayProvider: no recorded response for request {}\n--- request ---\n{}\n--- closest recorded request ---\n{}",
+                key,
+                request,
+                self.closest(&request).unwrap_or("<fixture is empty>"),
+            ));
+        };
+        let mut served = self.served.lock().map_err(|_| anyhow!("ReplayProvider: lock poisoned"))?;
+        let count = served.entry(key).or_insert(0);
+        let entry = recorded[(*count).min(recorded.len() - 1)].clone();
+        *count += 1;
+        Ok(entry)
+    }
+
+    /// The recorded request sharing the longest prefix with `request`
+    fn closest(&self, request: &str) -> Option<&str> {
+        self.entries.values()
+            .flatten()
+            .map(|e| e.request.as_str())
+            .max_by_key(|candidate| candidate.chars().zip(request.chars()).take_while(|(a, b)| a == b).count())
+    }
+
+    fn stream(entry: FixtureEntry) -> ChatStream {
+        let trailer = StreamTrailer::default();
+        trailer.set_usage(entry.usage);
+        trailer.push_tool_calls(entry.tool_calls);
+        trailer.set_finish_reason("stop");
+        let chunks = if entry.chunks.is_empty() { vec![entry.response] } else { entry.chunks };
+        ChatStream::new(Box::pin(futures_util::stream::iter(chunks.into_iter().map(Ok))), trailer)
+    }
+}
+
+#[async_trait]
+impl LLMProvider for ReplayProvider {
+    async fn generate(&self, _model: &str, prompt: String, system: Option<String>) -> Result<String> {
+        Ok(self.lookup(&messages_from_prompt(prompt, system), &[])?.response)
+    }
+
+    async fn generate_stream(&self, _model: &str, prompt: String, system: Option<String>) -> Result<BoxStream<'static, Result<String>>> {
+        Ok(Self::stream(self.lookup(&messages_from_prompt(prompt, system), &[])?).boxed())
+    }
+
+    async fn chat(&self, _model: &str, messages: Vec<ChatMessage>, _options: GenerationOptions) -> Result<ChatCompletion> {
+        let entry = self.lookup(&messages, &[])?;
+        Ok(ChatCompletion { content: entry.response, usage: entry.usage, finish_reason: Some("stop".to_string()) })
+    }
+
+    async fn chat_stream(&self, _model: &str, messages: Vec<ChatMessage>, _options: GenerationOptions) -> Result<ChatStream> {
+        Ok(Self::stream(self.lookup(&messages, &[])?))
+    }
+
+    fn supports_native_tools(&self) -> bool {
+        self.native_tools
+    }
+
+    async fn chat_with_tools(&self, _model: &str, messages: Vec<ChatMessage>, _options: GenerationOptions, tools: &[ToolDefinition]) -> Result<ToolCompletion> {
+        let entry = self.lookup(&messages, tools)?;
+        Ok(ToolCompletion { content: entry.response, tool_calls: entry.tool_calls, usage: entry.usage })
+    }
+
+    async fn chat_stream_with_tools(&self, _model: &str, messages: Vec<ChatMessage>, _options: GenerationOptions, tools: &[ToolDefinition]) -> Result<ChatStream> {
+        Ok(Self::stream(self.lookup(&messages, tools)?))
+    }
+
+    fn get_lock(&self) -> Arc<Mutex<()>> {
+        self.lock.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Answers with a fixed reply per call, streaming it word by word
+    struct Scripted;
+
+    #[async_trait]
+    impl LLMProvider for Scripted {
+        async fn generate(&self, _model: &str, prompt: String, _system: Option<String>) -> Result<String> {
+            Ok(format!("echo: {}", prompt))
+        }
+
+        async fn generate_stream(&self, _model: &str, prompt: String, _system: Option<String>) -> Result<BoxStream<'static, Result<String>>> {
+            let words: Vec<Result<String>> = format!("echo: {}", prompt).split_inclusive(' ').map(|w| Ok(w.to_string())).collect();
+            Ok(Box::pin(futures_util::stream::iter(words)))
+        }
+
+        async fn chat_with_tools(&self, _model: &str, _messages: Vec<ChatMessage>, _options: GenerationOptions, _tools: &[ToolDefinition]) -> Result<ToolCompletion> {
+            Ok(ToolCompletion {
+                content: String::new(),
+                tool_calls: vec![ToolCall { name: "lookup".to_string(), parameters: json!({ "q": "rust" }) }],
+                usage: TokenUsage::new(30, 5),
+            })
+        }
+
+        fn get_lock(&self) -> Arc<Mutex<()>> {
+            Arc::new(Mutex::new(()))
+        }
+    }
+
+    fn lookup_tool() -> ToolDefinition {
+        ToolDefinition { name: "lookup".to_string(), description: "Look things up".to_string(), parameters: json!({}) }
+    }
+
+    #[test]
+    fn test_normalization_ignores_volatile_details() {
+        let a = normalize_text("session 3f2b8c1e-1d2a-4f6b-9c0d-1234567890ab at 2026-01-02T10:11:12Z:\n  hello   world ");
+        let b = normalize_text("session 00000000-aaaa-4bbb-8ccc-dddddddddddd at 2031-12-31 23:59:59.123+02:00: hello world");
+        assert_eq!(a, b);
+        assert_eq!(a, "session <uuid> at <timestamp>: hello world");
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("golden/conversation.json");
+        let recorder = RecordingProvider::new(Arc::new(Scripted), &path);
+
+        let answer = recorder.generate("m", "hi there".to_string(), Some("be brief".to_string())).await.unwrap();
+        let mut stream = recorder.chat_stream("m", vec![ChatMessage::user("stream me")], GenerationOptions::default()).await.unwrap();
+        let mut streamed = String::new();
+        while let Some(chunk) = stream.next().await {
+            streamed.push_str(&chunk.unwrap());
+        }
+        let tools = recorder.chat_with_tools("m", vec![ChatMessage::user("find rust")], GenerationOptions::default(), &[lookup_tool()]).await.unwrap();
+
+        let replay = ReplayProvider::load(&path).unwrap();
+        assert_eq!(replay.generate("other-model", "hi  there".to_string(), Some("be brief".to_string())).await.unwrap(), answer);
+
+        let replayed = replay.chat_stream("m", vec![ChatMessage::user("stream me")], GenerationOptions::default()).await.unwrap();
+        let chunks: Vec<String> = replayed.map(|c| c.unwrap()).collect().await;
+        assert_eq!(chunks, vec!["echo: ", "stream ", "me"]);
+        assert_eq!(chunks.concat(), streamed);
+
+        let replayed_tools = replay.chat_with_tools("m", vec![ChatMessage::user("find rust")], GenerationOptions::default(), &[lookup_tool()]).await.unwrap();
+        assert_eq!(replayed_tools, tools);
+        assert!(replay.unused().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unexpected_prompt_fails_loudly() {
+        let mut fixture = Fixture::default();
+        let request = normalize_request(&[ChatMessage::user("what is rust?")], &[]);
+        fixture.entries.push(entry("m", request, "A language.".to_string()));
+        let replay = ReplayProvider::new(fixture);
+
+        let err = replay.generate("m", "what is go?".to_string(), None).await.unwrap_err().to_string();
+        assert!(err.contains("no recorded response"));
+        assert!(err.contains("user: what is go?"));
+        assert!(err.contains("user: what is rust?"), "closest recorded request should be shown: {}", err);
+        assert_eq!(replay.unused(), vec!["user: what is rust?".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_prompts_replay_in_order() {
+        let mut fixture = Fixture::default();
+        let request = normalize_request(&[ChatMessage::user("roll")], &[]);
+        for answer in ["1", "2"] {
+            fixture.entries.push(entry("m", request.clone(), answer.to_string()));
+        }
+        let replay = ReplayProvider::new(fixture);
+        let mut answers = Vec::new();
+        for _ in 0..3 {
+            answers.push(replay.chat("m", vec![ChatMessage::user("roll")], GenerationOptions::default()).await.unwrap().content);
+        }
+        assert_eq!(answers, vec!["1", "2", "2"]);
+    }
+}
@@ -0,0 +1,343 @@
+//! Server-Sent Events decoding for OpenAI-compatible streaming endpoints.
+//!
+//! `SseDecoder` frames raw network chunks into events. Bytes are buffered until
+//! a full line is available, so events and multi-byte UTF-8 characters split
+//! across TCP chunks are reassembled intact. `ChatDeltaDecoder` interprets those
+//! events as OpenAI `chat.completion.chunk` payloads.
+
+use std::collections::BTreeMap;
+
+use serde_json::json;
+use tracing::warn;
+
+use super::provider::{parse_tool_calls, TokenUsage};
+use crate::tools::ToolCall;
+
+/// A single dispatched SSE event
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    /// The `event:` field, if the server named the event
+    pub event: Option<String>,
+    /// All `data:` lines of the event, joined with `\n`
+    pub data: String,
+    /// The `id:` field, if present
+    pub id: Option<String>,
+}
+
+/// Incremental SSE framing decoder
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    /// Bytes of the current, not yet terminated line
+    line: Vec<u8>,
+    /// The previous chunk ended on `\r`; a leading `\n` belongs to that line break
+    skip_lf: bool,
+    event: Option<String>,
+    data: Vec<String>,
+    id: Option<String>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a network chunk and return every event it completed
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+        for &byte in bytes {
+            if std::mem::take(&mut self.skip_lf) && byte == b'\n' {
+                continue;
+            }
+            match byte {
+                b'\n' => self.end_line(&mut events),
+                b'\r' => {
+                    self.end_line(&mut events);
+                    self.skip_lf = true;
+                }
+                _ => self.line.push(byte),
+            }
+        }
+        events
+    }
+
+    /// Flush at end of stream. A trailing event without its blank line is
+    /// still dispatched; some servers close the connection right after `data:`.
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        let mut events = Vec::new();
+        if !self.line.is_empty() {
+            self.end_line(&mut events);
+        }
+        self.dispatch(&mut events);
+        events.pop()
+    }
+
+    fn end_line(&mut self, events: &mut Vec<SseEvent>) {
+        let line = std::mem::take(&mut self.line);
+        if line.is_empty() {
+            self.dispatch(events);
+            return;
+        }
+        let line = String::from_utf8_lossy(&line);
+        if line.starts_with(':') {
+            return; // comment / keep-alive
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line.as_ref(), ""),
+        };
+        match field {
+            "data" => self.data.push(value.to_string()),
+            "event" => self.event = Some(value.to_string()),
+            "id" => self.id = Some(value.to_string()),
+            _ => {} // `retry` and unknown fields are ignored
+        }
+    }
+
+    fn dispatch(&mut self, events: &mut Vec<SseEvent>) {
+        if self.data.is_empty() {
+            self.event = None;
+            return;
+        }
+        events.push(SseEvent {
+            event: self.event.take(),
+            data: std::mem::take(&mut self.data).join("\n"),
+            id: self.id.clone(),
+        });
+    }
+}
+
+/// What a chat completion stream reported, in arrival order
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChatDelta {
+    /// A piece of assistant text
+    Content(String),
+    /// Tool calls assembled from their argument fragments
+    ToolCalls(Vec<ToolCall>),
+    /// The server's `finish_reason` (`stop`, `length`, `tool_calls`, ...)
+    Finish(String),
+    /// Token usage, sent in the final chunk when `include_usage` is requested
+    Usage(TokenUsage),
+    /// An `error` event or an error object in a data payload
+    Error(String),
+    /// The `[DONE]` sentinel
+    Done,
+}
+
+#[derive(Debug, Default)]
+struct PartialToolCall {
+    name: String,
+    arguments: String,
+}
+
+/// Decodes an OpenAI-style chat completion stream into `ChatDelta`s
+#[derive(Debug, Default)]
+pub struct ChatDeltaDecoder {
+    sse: SseDecoder,
+    /// Tool-call fragments keyed by their `index`
+    tool_calls: BTreeMap<u64, PartialToolCall>,
+    done: bool,
+}
+
+impl ChatDeltaDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether `[DONE]` has been seen; later bytes are ignored
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<ChatDelta> {
+        let events = self.sse.push(bytes);
+        let mut deltas = Vec::new();
+        for event in events {
+            self.interpret(event, &mut deltas);
+        }
+        deltas
+    }
+
+    /// Flush at end of stream, emitting any tool calls that never saw a finish reason
+    pub fn finish(&mut self) -> Vec<ChatDelta> {
+        let mut deltas = Vec::new();
+        if let Some(event) = self.sse.finish() {
+            self.interpret(event, &mut deltas);
+        }
+        self.flush_tool_calls(&mut deltas);
+        deltas
+    }
+
+    fn interpret(&mut self, event: SseEvent, deltas: &mut Vec<ChatDelta>) {
+        if self.done {
+            return;
+        }
+        let data = event.data.trim();
+        if event.event.as_deref() == Some("error") {
+            deltas.push(ChatDelta::Error(error_message(data)));
+            return;
+        }
+        if data == "[DONE]" {
+            self.flush_tool_calls(deltas);
+            self.done = true;
+            deltas.push(ChatDelta::Done);
+            return;
+        }
+
+        let json: serde_json::Value = match serde_json::from_str(data) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!("Skipping malformed SSE payload ({}): {}", e, data);
+                return;
+            }
+        };
+        if !json["error"].is_null() {
+            deltas.push(ChatDelta::Error(error_message(data)));
+            return;
+        }
+
+        let choice = &json["choices"][0];
+        if let Some(content) = choice["delta"]["content"].as_str() {
+            if !content.is_empty() {
+                deltas.push(ChatDelta::Content(content.to_string()));
+            }
+        }
+        if let Some(fragments) = choice["delta"]["tool_calls"].as_array() {
+            for (position, fragment) in fragments.iter().enumerate() {
+                let index = fragment["index"].as_u64().unwrap_or(position as u64);
+                let call = self.tool_calls.entry(index).or_default();
+                if let Some(name) = fragment["function"]["name"].as_str() {
+                    call.name.push_str(name);
+                }
+                if let Some(arguments) = fragment["function"]["arguments"].as_str() {
+                    call.arguments.push_str(arguments);
+                }
+            }
+        }
+        if let Some(reason) = choice["finish_reason"].as_str() {
+            self.flush_tool_calls(deltas);
+            deltas.push(ChatDelta::Finish(reason.to_string()));
+        }
+        if let Some(usage) = TokenUsage::from_openai(&json["usage"]) {
+            deltas.push(ChatDelta::Usage(usage));
+        }
+    }
+
+    fn flush_tool_calls(&mut self, deltas: &mut Vec<ChatDelta>) {
+        if self.tool_calls.is_empty() {
+            return;
+        }
+        let message = json!({
+            "tool_calls": std::mem::take(&mut self.tool_calls).into_values().map(|call| json!({
+                "function": { "name": call.name, "arguments": call.arguments }
+            })).collect::<Vec<_>>()
+        });
+        let calls = parse_tool_calls(&message);
+        if !calls.is_empty() {
+            deltas.push(ChatDelta::ToolCalls(calls));
+        }
+    }
+}
+
+/// Best-effort human-readable message from an error payload
+fn error_message(data: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(data).ok()
+        .and_then(|json| {
+            let error = if json["error"].is_null() { &json } else { &json["error"] };
+            error["message"].as_str().or_else(|| error.as_str()).map(|s| s.to_string())
+        })
+        .unwrap_or_else(|| data.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn chunk(delta: serde_json::Value, finish: Option<&str>) -> String {
+        format!("data: {}\n\n", json!({ "choices": [{ "delta": delta, "finish_reason": finish }] }))
+    }
+
+    /// A realistic stream: multi-byte text, split tool-call arguments, usage and `[DONE]`
+    fn sample_stream() -> String {
+        let mut body = String::from(": keep-alive\r\n\r\n");
+        body.push_str(&chunk(json!({ "content": "Grüße " }), None));
+        body.push_str(&chunk(json!({ "content": "🦀 aus Köln" }), None));
+        body.push_str(&chunk(json!({ "tool_calls": [{ "index": 0, "function": { "name": "get_weather", "arguments": "{\"loc" } }] }), None));
+        body.push_str(&chunk(json!({ "tool_calls": [{ "index": 0, "function": { "arguments": "ation\": \"Köln\"}" } }] }), None));
+        body.push_str(&chunk(json!({}), Some("tool_calls")));
+        body.push_str(&format!("data: {}\r\n\r\n", json!({ "choices": [], "usage": { "prompt_tokens": 9, "completion_tokens": 4 } })));
+        body.push_str("data: [DONE]\n\n");
+        body
+    }
+
+    fn expected_deltas() -> Vec<ChatDelta> {
+        vec![
+            ChatDelta::Content("Grüße ".to_string()),
+            ChatDelta::Content("🦀 aus Köln".to_string()),
+            ChatDelta::ToolCalls(vec![ToolCall { name: "get_weather".to_string(), parameters: json!({ "location": "Köln" }) }]),
+            ChatDelta::Finish("tool_calls".to_string()),
+            ChatDelta::Usage(TokenUsage::new(9, 4)),
+            ChatDelta::Done,
+        ]
+    }
+
+    fn decode_in_chunks(bytes: &[u8], cuts: &[usize]) -> Vec<ChatDelta> {
+        let mut decoder = ChatDeltaDecoder::new();
+        let mut deltas = Vec::new();
+        let mut start = 0;
+        for &cut in cuts {
+            deltas.extend(decoder.push(&bytes[start..cut]));
+            start = cut;
+        }
+        deltas.extend(decoder.push(&bytes[start..]));
+        deltas.extend(decoder.finish());
+        deltas
+    }
+
+    #[test]
+    fn test_whole_stream_decodes() {
+        assert_eq!(decode_in_chunks(sample_stream().as_bytes(), &[]), expected_deltas());
+    }
+
+    #[test]
+    fn test_byte_at_a_time_decodes() {
+        let bytes = sample_stream().into_bytes();
+        let cuts: Vec<usize> = (1..bytes.len()).collect();
+        assert_eq!(decode_in_chunks(&bytes, &cuts), expected_deltas());
+    }
+
+    #[test]
+    fn test_multiline_data_and_trailing_event() {
+        let mut sse = SseDecoder::new();
+        let events = sse.push(b"event: note\ndata: a\ndata:b\r\r\ndata: tail");
+        assert_eq!(events, vec![SseEvent { event: Some("note".to_string()), data: "a\nb".to_string(), id: None }]);
+        assert_eq!(sse.finish().map(|e| e.data), Some("tail".to_string()));
+    }
+
+    #[test]
+    fn test_error_events_surface() {
+        let mut decoder = ChatDeltaDecoder::new();
+        let deltas = decoder.push(b"event: error\ndata: {\"error\": {\"message\": \"rate limited\"}}\n\ndata: {\"error\": \"overloaded\"}\n\n");
+        assert_eq!(deltas, vec![ChatDelta::Error("rate limited".to_string()), ChatDelta::Error("overloaded".to_string())]);
+    }
+
+    #[test]
+    fn test_nothing_after_done() {
+        let mut decoder = ChatDeltaDecoder::new();
+        let deltas = decoder.push(format!("data: [DONE]\n\n{}", chunk(json!({ "content": "late" }), None)).as_bytes());
+        assert_eq!(deltas, vec![ChatDelta::Done]);
+        assert!(decoder.is_done());
+    }
+
+    proptest! {
+        #[test]
+        fn prop_chunk_boundaries_do_not_change_output(mut cuts in proptest::collection::vec(0usize..400, 0..24)) {
+            let bytes = sample_stream().into_bytes();
+            for cut in cuts.iter_mut() {
+                *cut %= bytes.len() + 1;
+            }
+            cuts.sort_unstable();
+            prop_assert_eq!(decode_in_chunks(&bytes, &cuts), expected_deltas());
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use crate::orchestrator::profile::AgencyProfile;
+use super::provider::GenerationOptions;
 
 /// Types of specialized agents
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -86,6 +87,15 @@ pub struct AgentConfig {
     pub system_prompt: String,
     pub temperature: f32,
     pub max_tokens: Option<u32>,
+    /// Stop sequences passed to the provider
+    #[serde(default)]
+    pub stop: Vec<String>,
+    /// Sampling seed for reproducible runs (defaults to `AGENCY_SEED` when set)
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Context window requested from local backends
+    #[serde(default)]
+    pub num_ctx: Option<u32>,
     /// Which tools this agent can use
     pub allowed_tools: Vec<String>,
     /// Which tools are in the 'laboratory' (not yet promoted)
@@ -96,6 +106,20 @@ pub struct AgentConfig {
     pub provider_url: Option<String>,
     /// Whether to enforce strict reasoning/planning tags
     pub reasoning_enabled: bool,
+    /// Wall-clock limit for a whole turn, across all iterations
+    #[serde(default)]
+    pub turn_timeout_secs: Option<u64>,
+    /// Limit for a single tool call; an overrunning call fails but the turn goes on
+    #[serde(default)]
+    pub tool_timeout_secs: Option<u64>,
+    /// Tokens the ReAct trace may take before early steps are summarized
+    /// (defaults to half the context window)
+    #[serde(default)]
+    pub trace_token_budget: Option<usize>,
+    /// Model that writes context and trace summaries (defaults to
+    /// `AGENCY_SUMMARY_MODEL`, then to this agent's own model)
+    #[serde(default)]
+    pub summary_model: Option<String>,
 }
 
 impl AgentConfig {
@@ -110,6 +134,7 @@ impl AgentConfig {
                 "knowledge_graph_viewer".to_string(),
                 "system_monitor".to_string(),
                 "web_search".to_string(),
+                "web_fetch".to_string(),
                 "code_exec".to_string(),
                 "sandbox".to_string(),
                 "model_manager".to_string(),
@@ -126,6 +151,7 @@ impl AgentConfig {
                 "knowledge_graph_viewer".to_string(),
                 "system_monitor".to_string(),
                 "web_search".to_string(),
+                "web_fetch".to_string(),
                 "code_exec".to_string(),
                 "sandbox".to_string(),
                 "model_manager".to_string(),
@@ -143,11 +169,13 @@ impl AgentConfig {
                 "agency_control".to_string(),
                 "speaker_rust".to_string(),
                 "web_search".to_string(),
+                "web_fetch".to_string(),
                 "model_manager".to_string(),
                 "visualization_tool".to_string()
             ],
             AgentType::Researcher => vec![
                 "web_search".to_string(), 
+                "web_fetch".to_string(),
                 "memory_query".to_string(), 
                 "speaker_rust".to_string(),
                 "codebase_explorer".to_string(),
@@ -164,11 +192,45 @@ impl AgentConfig {
             system_prompt: agent_type.generate_system_prompt(profile),
             temperature: 0.7,
             max_tokens: None,
+            stop: Vec::new(),
+            seed: GenerationOptions::from_env().seed,
+            num_ctx: None,
             allowed_tools,
             laboratory_tools: Vec::new(),
             max_iterations: 5,
             provider_url: None,
             reasoning_enabled: true,
+            turn_timeout_secs: Some(600),
+            tool_timeout_secs: Some(120),
+            trace_token_budget: None,
+            summary_model: std::env::var("AGENCY_SUMMARY_MODEL").ok().filter(|m| !m.is_empty()),
+        }
+    }
+
+    pub fn summary_model(&self) -> &str {
+        self.summary_model.as_deref().unwrap_or(&self.model)
+    }
+
+    pub fn trace_budget(&self) -> usize {
+        self.trace_token_budget.unwrap_or(self.num_ctx.unwrap_or(4096) as usize / 2)
+    }
+
+    pub fn turn_timeout(&self) -> Option<std::time::Duration> {
+        self.turn_timeout_secs.map(std::time::Duration::from_secs)
+    }
+
+    pub fn tool_timeout(&self) -> Option<std::time::Duration> {
+        self.tool_timeout_secs.map(std::time::Duration::from_secs)
+    }
+
+    /// Per-call generation options for this agent
+    pub fn generation_options(&self) -> GenerationOptions {
+        GenerationOptions {
+            temperature: Some(self.temperature),
+            max_tokens: self.max_tokens,
+            stop: self.stop.clone(),
+            seed: self.seed,
+            num_ctx: self.num_ctx,
         }
     }
 }
@@ -179,3 +241,23 @@ impl Default for AgentConfig {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generation_options_from_config() {
+        let mut config = AgentConfig::new(AgentType::Coder, &AgencyProfile::default());
+        config.max_tokens = Some(256);
+        config.stop = vec!["STOP".to_string()];
+        config.seed = Some(99);
+
+        let options = config.generation_options();
+        assert_eq!(options.temperature, Some(0.7));
+        assert_eq!(options.max_tokens, Some(256));
+        assert_eq!(options.stop, vec!["STOP".to_string()]);
+        assert_eq!(options.seed, Some(99));
+        assert_eq!(options.num_ctx, None);
+    }
+}
@@ -0,0 +1,28 @@
+use anyhow::Result;
+
+/// Serve the agency's tools over MCP.
+///
+/// `mcp_server` speaks stdio (for clients that spawn it); `mcp_server --http 127.0.0.1:3010`
+/// serves streamable HTTP at `/mcp` to clients presenting `AGENCY_MCP_TOKEN` as a bearer
+/// token. Non-loopback addresses also need `--allow-remote`. `--tools a,b` limits the
+/// exposed tools.
+#[tokio::main]
+async fn main() -> Result<()> {
+    // stdout carries the protocol; logs go to stderr
+    tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+
+    let mut http_addr = std::env::var("AGENCY_MCP_HTTP").ok();
+    let mut exposed = None;
+    let mut allow_remote = std::env::var("AGENCY_MCP_ALLOW_REMOTE").is_ok_and(|v| v == "1");
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--http" => http_addr = args.next(),
+            "--tools" => exposed = args.next().map(|list| list.split(',').map(|s| s.trim().to_string()).collect()),
+            "--allow-remote" => allow_remote = true,
+            other => anyhow::bail!("Unknown argument: {} (expected --http ADDR, --allow-remote or --tools a,b)", other),
+        }
+    }
+
+    rust_agency::services::mcp::run_mcp_server(http_addr, exposed, allow_remote).await
+}
@@ -8,7 +8,7 @@
 //! - Safety guardrails
 //! - Full session persistence
 
-use rust_agency::tools::McpServer;
+use rust_agency::tools::{McpServer, McpServerConfig};
 use anyhow::Result;
 use std::sync::Arc;
 use std::io::Write;
@@ -22,7 +22,7 @@ use rust_agency::tools::{
     Tool, ToolRegistry, WebSearchTool, CodeExecTool, MemoryQueryTool, 
     KnowledgeGraphTool, ArtifactTool, SandboxTool, CodebaseTool, 
     SystemTool, ForgeTool, VisualizationTool, 
-    SpeakerRsTool, ScienceTool, ModelManager, VisionTool
+    SpeakerRsTool, ScienceTool, ModelManager, VisionTool, ArtifactStore, DEFAULT_ARTIFACT_DIR
 };
 use rust_agency::server::{run_server, AppState};
 
@@ -41,6 +41,8 @@ struct AgencyConfig {
     session_file: String,
     /// Path to agency profile file
     profile_file: String,
+    /// Directory WASI programs see as their filesystem root
+    wasm_workspace: String,
 }
 
 impl Default for AgencyConfig {
@@ -49,6 +51,7 @@ impl Default for AgencyConfig {
             memory_file: "memory.json".to_string(),
             session_file: "session.json".to_string(),
             profile_file: "config/agency_profile.json".to_string(),
+            wasm_workspace: "wasm_workspace".to_string(),
         }
     }
 }
@@ -227,14 +230,19 @@ async fn main() -> Result<()> {
 
     // Initialize tools
     let tools = Arc::new(ToolRegistry::default());
+    let artifacts = ArtifactStore::open(DEFAULT_ARTIFACT_DIR)?;
+    // WASI guests get this directory as `/`, never the project itself
+    std::fs::create_dir_all(&config.wasm_workspace)?;
+    let wasm_runtime = rust_agency::runtime::wasm::WasmRuntime::new().with_workspace(&config.wasm_workspace);
     
     // SOTA: Concurrent Tool Registration (FPF Principle: Rapid Capability Establishment)
     tokio::join!(
         tools.register_instance(WebSearchTool::new()),
+        tools.register_instance(rust_agency::tools::WebFetchTool::new()),
         tools.register_instance(CodeExecTool::new()),
-        tools.register_instance(MemoryQueryTool::new(memory.clone())),
+        tools.register_instance(MemoryQueryTool::new(memory.clone()).with_mcp_resources(tools.mcp_resources())),
         tools.register_instance(KnowledgeGraphTool::new(memory.clone())),
-        tools.register_instance(ArtifactTool::default()),
+        tools.register_instance(ArtifactTool::with_store(artifacts.clone())),
         tools.register_instance(SandboxTool::default()),
         tools.register_instance(CodebaseTool::default()),
         tools.register_instance(ModelManager),
@@ -246,7 +254,7 @@ async fn main() -> Result<()> {
         tools.register_instance(SystemTool::new(manager.clone())),
         tools.register_instance(rust_agency::tools::ProviderTool::new(provider.clone())),
         tools.register_instance(rust_agency::tools::WasmCompilerTool::new()),
-        tools.register_instance(rust_agency::tools::WasmExecutorTool::new())
+        tools.register_instance(rust_agency::tools::WasmExecutorTool::new().with_runtime(wasm_runtime))
     );
 
     // SOTA: Markdown-Based Skill Discovery (pi-mono-inspired)
@@ -265,6 +273,10 @@ async fn main() -> Result<()> {
             println!("🛠️  Loaded {} dynamic tools from laboratory ('custom_tools').", count);
         }
     }
+    // Hot reload: edited, re-forged and deleted tools take effect without a restart
+    let _tool_watcher = tools.watch_dynamic_tools()
+        .map_err(|e| eprintln!("⚠️  Dynamic tool hot reload disabled: {}", e))
+        .ok();
     let profile_manager = ProfileManager::new(&config.profile_file);
     let profile = profile_manager.load().await.unwrap_or_default();
     println!("👤 Agency Profile loaded: {}", profile.name);
@@ -290,6 +302,8 @@ async fn main() -> Result<()> {
     }
     
     // Wrap Supervisor in Shared Mutex for Hybrid Access
+    let session_usage = supervisor.session_usage.clone();
+    let turn_canceller = supervisor.turn_canceller();
     let shared_supervisor = Arc::new(Mutex::new(supervisor));
 
     // ──────────────────────────────────────────────────────────────────────────
@@ -302,6 +316,7 @@ async fn main() -> Result<()> {
     let server_episodic = episodic_memory.clone();
     let server_tx = tx.clone();
     let server_start_local = start_local.clone();
+    let server_artifacts = artifacts.clone();
 
     tokio::spawn(async move {
         let server_state = AppState {
@@ -311,7 +326,10 @@ async fn main() -> Result<()> {
             tx: server_tx,
             episodic_memory: server_episodic,
             supervisor: server_shared_supervisor,
+            usage: session_usage,
+            turns: turn_canceller,
             current_task: Arc::new(Mutex::new(None)),
+            artifacts: server_artifacts,
         };
         
         if let Err(e) = run_server(server_state).await {
@@ -330,29 +348,27 @@ async fn main() -> Result<()> {
             if let Ok(config) = serde_json::from_str::<serde_json::Value>(&content) {
                 if let Some(servers) = config["servers"].as_array() {
                     for server_cfg in servers {
-                        let name = server_cfg["name"].as_str().unwrap_or("unnamed");
-                        let command = server_cfg["command"].as_str().unwrap_or("");
-                        let args: Vec<String> = server_cfg["args"]
-                            .as_array()
-                            .unwrap_or(&vec![])
-                            .iter()
-                            .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                            .collect();
-
-                        if !command.is_empty() {
-                                                    match McpServer::spawn(name, command, &args).await {
-                                                        Ok(server) => {
-                                                            // SOTA: Automatic Root Registration (FPF Grounding)
-                                                            let cwd = std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."));
-                                                            let _ = server.add_root(&cwd.to_string_lossy()).await;
-                            
-                                                            match mcp_tools.register_mcp_server(server).await {
-                                                                Ok(count) => println!("🔌 Connected to MCP Server '{}' ({} tools loaded)", name, count),
-                                                                Err(e) => tracing::warn!("Failed to register tools from MCP server '{}': {}", name, e),
-                                                            }
-                                                        }
-                                                        Err(e) => tracing::warn!("Failed to spawn MCP server '{}': {}", name, e),
-                                                    }                        }
+                        let server_cfg: McpServerConfig = match serde_json::from_value(server_cfg.clone()) {
+                            Ok(cfg) => cfg,
+                            Err(e) => {
+                                tracing::warn!("Invalid MCP server entry {}: {}", server_cfg, e);
+                                continue;
+                            }
+                        };
+                        let name = server_cfg.name.clone();
+                        match McpServer::connect(&server_cfg).await {
+                            Ok(server) => {
+                                // SOTA: Automatic Root Registration (FPF Grounding)
+                                let cwd = std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."));
+                                let _ = server.add_root(&cwd.to_string_lossy()).await;
+
+                                match mcp_tools.register_mcp_server(server).await {
+                                    Ok(count) => println!("🔌 Connected to MCP Server '{}' ({} tools loaded)", name, count),
+                                    Err(e) => tracing::warn!("Failed to register tools from MCP server '{}': {}", name, e),
+                                }
+                            }
+                            Err(e) => tracing::warn!("Failed to connect to MCP server '{}': {}", name, e),
+                        }
                     }
                 }
             }
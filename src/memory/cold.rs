@@ -0,0 +1,541 @@
+//! Memory-Mapped COLD Tier
+//!
+//! The payload of a `.cold` snapshot (format v2) is used straight from the
+//! mapping. Opening it reads only the ids; embeddings are read from the
+//! matrix and records are decoded when an entry is actually returned.
+//!
+//! | section  | contents                                                        |
+//! |----------|-----------------------------------------------------------------|
+//! | preamble | entry count (u64), embedding dimension (u32), reserved (u32)    |
+//! | matrix   | `count × dim` little-endian f32, one row per entry               |
+//! | ids      | `count` end offsets (u32), then the UTF-8 ids, padded to 8 bytes |
+//! | records  | one bincode `MemoryEntry` per entry, stored without embedding    |
+//! | table    | `count` fixed-size rows, see `Row`                               |
+//!
+//! Access statistics, importance changes and removals are kept in an overlay
+//! until the tier is rewritten (`write_merged`).
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use memmap2::Mmap;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use tracing::{info, warn};
+
+use super::entry::MemoryMetadata;
+use super::hnsw::ExternalVectors;
+use super::persistence::{decode_entries, parse_snapshot, write_snapshot_with, Snapshot, Tier, SNAPSHOT_HEADER_LEN};
+use super::MemoryEntry;
+
+/// First snapshot format with the mapped COLD layout
+const MAPPED_VERSION: u16 = 2;
+const PREAMBLE_LEN: usize = 16;
+const ROW_LEN: usize = 40;
+const FLAG_EMBEDDED: u32 = 1;
+const NO_TIMESTAMP: i64 = i64::MIN;
+
+/// Table row: where the record is, plus what ranking and tiering need
+/// without decoding it
+#[derive(Debug, Clone, Copy)]
+struct Row {
+    record_offset: u64,
+    record_len: u32,
+    flags: u32,
+    timestamp_ms: i64,
+    last_accessed_ms: i64,
+    importance: f32,
+    access_count: u32,
+}
+
+impl Row {
+    fn for_entry(entry: &MemoryEntry, embedded: bool, record_offset: u64, record_len: u32) -> Self {
+        let meta = &entry.metadata;
+        Self {
+            record_offset,
+            record_len,
+            flags: if embedded { FLAG_EMBEDDED } else { 0 },
+            timestamp_ms: entry.timestamp.timestamp_millis(),
+            last_accessed_ms: meta.last_accessed.map_or(NO_TIMESTAMP, |t| t.timestamp_millis()),
+            importance: meta.importance,
+            access_count: meta.access_count,
+        }
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let u64_at = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+        Self {
+            record_offset: u64_at(0),
+            record_len: u32_at(8),
+            flags: u32_at(12),
+            timestamp_ms: u64_at(16) as i64,
+            last_accessed_ms: u64_at(24) as i64,
+            importance: f32::from_bits(u32_at(32)),
+            access_count: u32_at(36),
+        }
+    }
+
+    fn encode(&self) -> [u8; ROW_LEN] {
+        let mut bytes = [0u8; ROW_LEN];
+        bytes[0..8].copy_from_slice(&self.record_offset.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.record_len.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.flags.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.timestamp_ms.to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.last_accessed_ms.to_le_bytes());
+        bytes[32..36].copy_from_slice(&self.importance.to_le_bytes());
+        bytes[36..40].copy_from_slice(&self.access_count.to_le_bytes());
+        bytes
+    }
+}
+
+/// Ranking and tiering inputs of a COLD entry, read without decoding it
+#[derive(Debug, Clone, Copy)]
+pub struct ColdStats {
+    pub timestamp: DateTime<Utc>,
+    pub last_accessed: Option<DateTime<Utc>>,
+    pub importance: f32,
+    pub access_count: u32,
+}
+
+pub struct ColdTier {
+    map: Option<Mmap>,
+    count: usize,
+    dim: usize,
+    /// Section offsets within the payload
+    ids_offset: usize,
+    table_offset: usize,
+    /// Live entries by id
+    slots: HashMap<String, u32>,
+    /// Metadata changed since the file was written
+    patches: HashMap<u32, MemoryMetadata>,
+    removed: HashSet<u32>,
+}
+
+impl ColdTier {
+    pub fn empty() -> Self {
+        Self {
+            map: None,
+            count: 0,
+            dim: 0,
+            ids_offset: PREAMBLE_LEN,
+            table_offset: PREAMBLE_LEN,
+            slots: HashMap::new(),
+            patches: HashMap::new(),
+            removed: HashSet::new(),
+        }
+    }
+
+    /// Map the COLD snapshot at `path`. Files in an older format are rewritten
+    /// in the mapped layout first.
+    pub fn open(path: &Path) -> Result<Self> {
+        if !path.exists() || std::fs::metadata(path)?.len() == 0 {
+            return Ok(Self::empty());
+        }
+        let file = File::open(path)?;
+        let map = unsafe { Mmap::map(&file)? };
+        let legacy = match parse_snapshot(&map, Tier::Cold)? {
+            Snapshot::Current { version, .. } if version >= MAPPED_VERSION => None,
+            older => Some(decode_entries(older, Tier::Cold)?),
+        };
+        match legacy {
+            None => Self::from_map(map),
+            Some(entries) => {
+                drop(map);
+                info!("Migrating {} COLD memories to the memory-mapped layout", entries.len());
+                Self::empty().write_merged(path, &entries)?;
+                Self::open(path)
+            }
+        }
+    }
+
+    fn from_map(map: Mmap) -> Result<Self> {
+        let payload = &map[SNAPSHOT_HEADER_LEN..];
+        if payload.len() < PREAMBLE_LEN {
+            bail!("COLD snapshot preamble truncated");
+        }
+        let count = u64::from_le_bytes(payload[0..8].try_into()?) as usize;
+        let dim = u32::from_le_bytes(payload[8..12].try_into()?) as usize;
+
+        let ids_offset = count
+            .checked_mul(dim)
+            .and_then(|n| n.checked_mul(4))
+            .and_then(|n| n.checked_add(PREAMBLE_LEN))
+            .context("COLD snapshot matrix size overflows")?;
+        let table_offset = count
+            .checked_mul(ROW_LEN)
+            .and_then(|n| payload.len().checked_sub(n))
+            .context("COLD snapshot table truncated")?;
+        let id_bytes_offset = ids_offset + count * 4;
+        if id_bytes_offset > table_offset {
+            bail!("COLD snapshot sections overlap");
+        }
+
+        let mut slots = HashMap::with_capacity(count);
+        let mut start = 0usize;
+        for slot in 0..count {
+            let at = ids_offset + slot * 4;
+            let end = u32::from_le_bytes(payload[at..at + 4].try_into()?) as usize;
+            let id = payload
+                .get(id_bytes_offset + start..id_bytes_offset + end)
+                .context("COLD snapshot id out of bounds")?;
+            slots.insert(std::str::from_utf8(id)?.to_string(), slot as u32);
+            start = end;
+        }
+        let records_offset = align8(id_bytes_offset + start);
+        if records_offset > table_offset {
+            bail!("COLD snapshot sections overlap");
+        }
+
+        let tier = Self {
+            map: Some(map),
+            count,
+            dim,
+            ids_offset,
+            table_offset,
+            slots,
+            patches: HashMap::new(),
+            removed: HashSet::new(),
+        };
+        for slot in 0..count as u32 {
+            let row = tier.row(slot);
+            let end = row.record_offset.checked_add(row.record_len as u64);
+            if row.record_offset < records_offset as u64 || end.is_none_or(|end| end > table_offset as u64) {
+                bail!("COLD snapshot record {} out of bounds", slot);
+            }
+        }
+        Ok(tier)
+    }
+
+    fn payload(&self) -> &[u8] {
+        self.map.as_ref().map_or(&[], |map| &map[SNAPSHOT_HEADER_LEN..])
+    }
+
+    fn row(&self, slot: u32) -> Row {
+        let at = self.table_offset + slot as usize * ROW_LEN;
+        Row::read(&self.payload()[at..at + ROW_LEN])
+    }
+
+    fn id(&self, slot: u32) -> &str {
+        let payload = self.payload();
+        let end_at = |s: usize| u32::from_le_bytes(payload[self.ids_offset + s * 4..self.ids_offset + s * 4 + 4].try_into().unwrap()) as usize;
+        let base = self.ids_offset + self.count * 4;
+        let start = if slot == 0 { 0 } else { end_at(slot as usize - 1) };
+        // Validated in `from_map`
+        std::str::from_utf8(&payload[base + start..base + end_at(slot as usize)]).unwrap_or_default()
+    }
+
+    fn record(&self, slot: u32) -> &[u8] {
+        let row = self.row(slot);
+        &self.payload()[row.record_offset as usize..(row.record_offset + row.record_len as u64) as usize]
+    }
+
+    /// Raw little-endian matrix row of an entry with an embedding
+    fn embedding_bytes(&self, slot: u32) -> Option<&[u8]> {
+        if slot as usize >= self.count || self.row(slot).flags & FLAG_EMBEDDED == 0 {
+            return None;
+        }
+        let at = PREAMBLE_LEN + slot as usize * self.dim * 4;
+        Some(&self.payload()[at..at + self.dim * 4])
+    }
+
+    pub fn embedding(&self, slot: u32) -> Option<Vec<f32>> {
+        self.embedding_bytes(slot)
+            .map(|bytes| bytes.chunks_exact(4).map(|b| f32::from_le_bytes(b.try_into().unwrap())).collect())
+    }
+
+    /// Dot product of an entry's embedding with `query`, read from the mapping
+    pub fn similarity(&self, slot: u32, query: &[f32]) -> Option<f32> {
+        let bytes = self.embedding_bytes(slot)?;
+        if query.len() != self.dim {
+            return None;
+        }
+        Some(bytes.chunks_exact(4).zip(query).map(|(b, q)| f32::from_le_bytes(b.try_into().unwrap()) * q).sum())
+    }
+
+    /// Live entries
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Whether the overlay holds changes the file does not
+    pub fn is_dirty(&self) -> bool {
+        !self.patches.is_empty() || !self.removed.is_empty()
+    }
+
+    pub fn slot(&self, id: &str) -> Option<u32> {
+        self.slots.get(id).copied()
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.slots.contains_key(id)
+    }
+
+    /// Live slots in file order
+    pub fn live_slots(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.count as u32).filter(|slot| !self.removed.contains(slot))
+    }
+
+    /// Ids of live entries
+    pub fn ids(&self) -> impl Iterator<Item = &str> + '_ {
+        self.live_slots().map(|slot| self.id(slot))
+    }
+
+    /// Ids of live entries that have an embedding
+    pub fn embedded_ids(&self) -> impl Iterator<Item = &str> + '_ {
+        self.live_slots().filter(|&slot| self.row(slot).flags & FLAG_EMBEDDED != 0).map(|slot| self.id(slot))
+    }
+
+    pub fn stats(&self, slot: u32) -> ColdStats {
+        let row = self.row(slot);
+        let timestamp = DateTime::from_timestamp_millis(row.timestamp_ms).unwrap_or_default();
+        match self.patches.get(&slot) {
+            Some(meta) => ColdStats {
+                timestamp,
+                last_accessed: meta.last_accessed,
+                importance: meta.importance,
+                access_count: meta.access_count,
+            },
+            None => ColdStats {
+                timestamp,
+                last_accessed: (row.last_accessed_ms != NO_TIMESTAMP)
+                    .then(|| DateTime::from_timestamp_millis(row.last_accessed_ms))
+                    .flatten(),
+                importance: row.importance,
+                access_count: row.access_count,
+            },
+        }
+    }
+
+    /// Decode the entry in `slot`, with its embedding and any pending changes
+    pub fn get(&self, slot: u32) -> Result<MemoryEntry> {
+        let mut entry: MemoryEntry = bincode::deserialize(self.record(slot))
+            .with_context(|| format!("Failed to decode COLD memory {}", self.id(slot)))?;
+        entry.embedding = self.embedding(slot);
+        if let Some(meta) = self.patches.get(&slot) {
+            entry.metadata = meta.clone();
+        }
+        Ok(entry)
+    }
+
+    pub fn get_by_id(&self, id: &str) -> Option<MemoryEntry> {
+        let slot = self.slot(id)?;
+        self.get(slot).map_err(|e| warn!("{}", e)).ok()
+    }
+
+    /// Decoded live entries, one at a time
+    pub fn entries(&self) -> impl Iterator<Item = MemoryEntry> + '_ {
+        self.live_slots().filter_map(|slot| self.get(slot).map_err(|e| warn!("{}", e)).ok())
+    }
+
+    /// The `limit` newest entries, newest first; only those are decoded
+    pub fn recent(&self, limit: usize) -> Vec<MemoryEntry> {
+        let mut slots: Vec<(i64, u32)> = self.live_slots().map(|slot| (self.row(slot).timestamp_ms, slot)).collect();
+        slots.sort_unstable_by(|a, b| b.cmp(a));
+        slots.into_iter().take(limit).filter_map(|(_, slot)| self.get(slot).map_err(|e| warn!("{}", e)).ok()).collect()
+    }
+
+    /// Change an entry's metadata in place; returns whether it was found
+    pub fn update(&mut self, id: &str, update: impl FnOnce(&mut MemoryEntry)) -> bool {
+        let Some(slot) = self.slot(id) else { return false };
+        match self.get(slot) {
+            Ok(mut entry) => {
+                update(&mut entry);
+                self.patches.insert(slot, entry.metadata);
+                true
+            }
+            Err(e) => {
+                warn!("{}", e);
+                false
+            }
+        }
+    }
+
+    /// Drop an entry; returns whether it was live
+    pub fn remove(&mut self, id: &str) -> bool {
+        let Some(slot) = self.slots.remove(id) else { return false };
+        self.patches.remove(&slot);
+        self.removed.insert(slot);
+        true
+    }
+
+    /// Write the live entries (with pending changes) followed by `extra` to
+    /// `path` as a new snapshot. Unchanged records and embeddings are copied
+    /// from the mapping without decoding.
+    pub fn write_merged(&self, path: &Path, extra: &[MemoryEntry]) -> Result<()> {
+        let live: Vec<u32> = self.live_slots().collect();
+        let count = live.len() + extra.len();
+        let dim = match self.dim {
+            0 => extra.iter().find_map(|e| e.embedding.as_ref().map(Vec::len)).unwrap_or(0),
+            dim => dim,
+        };
+        let fits = |e: &MemoryEntry| e.embedding.as_ref().is_some_and(|v| v.len() == dim && dim > 0);
+        for entry in extra.iter().filter(|e| e.embedding.is_some() && !fits(e)) {
+            warn!("COLD memory {} has a {}-d embedding instead of {}-d; it will only be found lexically", entry.id, entry.embedding.as_ref().map_or(0, Vec::len), dim);
+        }
+        let ids: Vec<&str> = live.iter().map(|&slot| self.id(slot)).chain(extra.iter().map(|e| e.id.as_str())).collect();
+
+        write_snapshot_with(path, Tier::Cold, |w| {
+            let mut out = Positioned { inner: w, pos: 0 };
+            out.put(&(count as u64).to_le_bytes())?;
+            out.put(&(dim as u32).to_le_bytes())?;
+            out.put(&0u32.to_le_bytes())?;
+
+            let zeros = vec![0u8; dim * 4];
+            for &slot in &live {
+                out.put(self.embedding_bytes(slot).unwrap_or(&zeros))?;
+            }
+            for entry in extra {
+                match &entry.embedding {
+                    Some(vector) if fits(entry) => {
+                        let row: Vec<u8> = vector.iter().flat_map(|x| x.to_le_bytes()).collect();
+                        out.put(&row)?;
+                    }
+                    _ => out.put(&zeros)?,
+                }
+            }
+
+            let mut end = 0u32;
+            for id in &ids {
+                end += id.len() as u32;
+                out.put(&end.to_le_bytes())?;
+            }
+            for id in &ids {
+                out.put(id.as_bytes())?;
+            }
+            out.put(&vec![0u8; align8(out.pos) - out.pos])?;
+
+            let mut rows = Vec::with_capacity(count);
+            for &slot in &live {
+                let offset = out.pos as u64;
+                if self.patches.contains_key(&slot) {
+                    let mut entry = self.get(slot)?;
+                    let embedded = entry.embedding.take().is_some();
+                    let record = bincode::serialize(&entry)?;
+                    rows.push(Row::for_entry(&entry, embedded, offset, record.len() as u32));
+                    out.put(&record)?;
+                } else {
+                    let record = self.record(slot);
+                    rows.push(Row { record_offset: offset, ..self.row(slot) });
+                    out.put(record)?;
+                }
+            }
+            for entry in extra {
+                let offset = out.pos as u64;
+                let mut stored = entry.clone();
+                stored.embedding = None;
+                stored.similarity = None;
+                let record = bincode::serialize(&stored)?;
+                rows.push(Row::for_entry(entry, fits(entry), offset, record.len() as u32));
+                out.put(&record)?;
+            }
+
+            for row in &rows {
+                out.put(&row.encode())?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// The ANN index scores COLD nodes straight from the mapped matrix
+impl ExternalVectors for ColdTier {
+    fn similarity(&self, row: u32, query: &[f32]) -> Option<f32> {
+        ColdTier::similarity(self, row, query)
+    }
+
+    fn embedding(&self, row: u32) -> Option<Vec<f32>> {
+        ColdTier::embedding(self, row)
+    }
+}
+
+/// Tracks the payload offset while writing
+struct Positioned<'a> {
+    inner: &'a mut dyn Write,
+    pos: usize,
+}
+
+impl Positioned<'_> {
+    fn put(&mut self, bytes: &[u8]) -> Result<()> {
+        self.inner.write_all(bytes)?;
+        self.pos += bytes.len();
+        Ok(())
+    }
+}
+
+fn align8(n: usize) -> usize {
+    (n + 7) & !7
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::entry::MemorySource;
+    use crate::memory::persistence::{encode_entries, write_snapshot};
+    use tempfile::tempdir;
+
+    fn entry(content: &str, embedding: Option<Vec<f32>>) -> MemoryEntry {
+        let mut entry = MemoryEntry::new(content, "coder", MemorySource::Tool);
+        entry.embedding = embedding;
+        entry
+    }
+
+    #[test]
+    fn test_mapped_roundtrip_with_overlay() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("memory.cold");
+        let mut old = entry("oldest", Some(vec![1.0, 0.0]));
+        old.timestamp -= chrono::Duration::days(3);
+        let entries = vec![old, entry("no embedding", None), entry("newest", Some(vec![0.0, 1.0]))];
+        ColdTier::empty().write_merged(&path, &entries)?;
+
+        let mut tier = ColdTier::open(&path)?;
+        assert_eq!(tier.len(), 3);
+        let slot = tier.slot(&entries[2].id).unwrap();
+        assert_eq!(tier.similarity(slot, &[0.0, 1.0]), Some(1.0));
+        assert_eq!(tier.get(slot)?.content, "newest");
+        assert_eq!(tier.get(tier.slot(&entries[1].id).unwrap())?.embedding, None);
+        assert_eq!(tier.embedded_ids().count(), 2);
+        assert_eq!(tier.recent(1)[0].id, entries[2].id);
+
+        assert!(tier.update(&entries[0].id, |e| e.metadata.access_count = 9));
+        assert!(tier.remove(&entries[1].id));
+        assert!(tier.is_dirty());
+        assert_eq!(tier.stats(tier.slot(&entries[0].id).unwrap()).access_count, 9);
+
+        // Rewriting applies the overlay and appends new entries
+        tier.write_merged(&path, &[entry("appended", Some(vec![0.6, 0.8]))])?;
+        drop(tier);
+        let tier = ColdTier::open(&path)?;
+        assert!(!tier.is_dirty());
+        let contents: Vec<String> = tier.entries().map(|e| e.content).collect();
+        assert_eq!(contents, vec!["oldest", "newest", "appended"]);
+        assert_eq!(tier.get_by_id(&entries[0].id).unwrap().metadata.access_count, 9);
+        assert_eq!(tier.get_by_id(&entries[0].id).unwrap().embedding, Some(vec![1.0, 0.0]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialized_cold_files_are_migrated() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("memory.cold");
+        let entries = vec![entry("kept", Some(vec![0.6, 0.8]))];
+        // A v1 snapshot: the serialized entry list
+        write_snapshot(&path, Tier::Cold, &encode_entries(&entries, Tier::Cold)?)?;
+        let mut bytes = std::fs::read(&path)?;
+        bytes[8..10].copy_from_slice(&1u16.to_le_bytes());
+        std::fs::write(&path, bytes)?;
+
+        let tier = ColdTier::open(&path)?;
+        assert_eq!(tier.get_by_id(&entries[0].id).unwrap().content, "kept");
+        assert_eq!(tier.embedding(0), Some(vec![0.6, 0.8]));
+        let bytes = std::fs::read(&path)?;
+        assert!(matches!(parse_snapshot(&bytes, Tier::Cold)?, Snapshot::Current { version: MAPPED_VERSION, .. }), "rewritten in place");
+        Ok(())
+    }
+}
@@ -1,18 +1,40 @@
 //! High-Fidelity Context Compaction
 //! 
 //! Provides logic to summarize and compress long conversation histories
-//! while preserving the core objective and recent context.
+//! while preserving the core objective and recent context. The same approach
+//! keeps ReAct traces inside a token budget (`compact_trace`).
 
 use anyhow::Result;
+use std::collections::HashSet;
 use std::sync::Arc;
 use tracing::{info, warn};
 
-use crate::agent::{LLMProvider, SimpleAgent, AgentConfig, AgentType};
-use crate::memory::episodic::{EpisodicMemory, Role};
+use crate::agent::{LLMProvider, SimpleAgent, AgentConfig, AgentType, ReActStep, TokenUsage};
+use crate::memory::episodic::EpisodicMemory;
 use crate::orchestrator::profile::AgencyProfile;
 
+/// Marks a trace step produced by `compact_trace`
+const TRACE_SUMMARY_TAG: &str = "[TRACE SUMMARY";
+
+/// Steps at the end of a trace that are never compacted
+const RECENT_STEPS: usize = 3;
+
+/// Key outputs carried in a trace summary, newest kept
+const MAX_KEY_OUTPUTS: usize = 12;
+
 pub struct ContextCompactor;
 
+/// What `compact_trace` did
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceCompaction {
+    /// Steps folded into the summary
+    pub evicted: usize,
+    /// Middle steps kept because later steps refer to their observations
+    pub pinned: usize,
+    /// Tokens spent on the summarizer call
+    pub usage: TokenUsage,
+}
+
 impl ContextCompactor {
     /// Compacts the episodic memory if it exceeds the specified token limit.
     pub async fn compact_if_needed(
@@ -41,24 +63,20 @@ impl ContextCompactor {
         let recent_turns = turns.iter().rev().take(last_n_turns).rev().cloned().collect::<Vec<_>>();
         
         // 2. Identify turns to summarize (everything in between)
-        let middle_turns = turns[1..turns.len() - last_n_turns].to_vec();
-        let middle_text = middle_turns.iter()
-            .map(|t| format!("{}: {}", match t.role { Role::User => "User", _ => "Assistant" }, t.content))
-            .collect::<Vec<_>>()
-            .join("\n\n");
+        // They are handed to the summarizer as real conversation turns.
+        let middle_messages = turns[1..turns.len() - last_n_turns].iter()
+            .map(|t| t.to_chat_message())
+            .collect::<Vec<_>>();
 
         // 3. Perform summarization
         let mut config = AgentConfig::new(AgentType::GeneralChat, profile);
-        config.model = "qwen2.5:3b-q4".to_string(); // Use a fast model for summary
-        let summarizer = SimpleAgent::new_with_provider(provider, config);
+        config.model = config.summary_model().to_string();
+        let summarizer = SimpleAgent::new_with_provider(provider, config)
+            .with_history(middle_messages);
 
-        let prompt = format!(
-            "Please provide a concise technical summary of the following conversation history. \nFocus on key decisions made, tools used, and the current progress toward the goal. \nKEEP IT UNDER 500 CHARACTERS.\n\n### History to Summarize:\n{}"
-            , 
-            middle_text
-        );
+        let prompt = "Please provide a concise technical summary of the conversation above. \nFocus on key decisions made, tools used, and the current progress toward the goal. \nKEEP IT UNDER 500 CHARACTERS.";
 
-        let summary_response = summarizer.execute_simple(&prompt, None).await?;
+        let summary_response = summarizer.execute_simple(prompt, None).await?;
         let summary_content = summary_response.answer;
 
         // 4. Construct new memory state
@@ -79,4 +97,338 @@ impl ContextCompactor {
 
         Ok(true)
     }
-}
\ No newline at end of file
+
+    /// Rough token count of a ReAct trace (~4 characters per token)
+    pub fn estimate_trace_tokens(steps: &[ReActStep]) -> usize {
+        steps.iter().map(Self::estimate_step_tokens).sum()
+    }
+
+    fn estimate_step_tokens(step: &ReActStep) -> usize {
+        let actions = serde_json::to_string(&step.actions).map(|s| s.len()).unwrap_or(0);
+        let observations: usize = step.observations.iter().map(|o| o.len()).sum();
+        (step.thought.len() + actions + observations) / 4
+    }
+
+    /// Compacts a ReAct trace once it exceeds `max_tokens`.
+    ///
+    /// The first step (the plan) and the last few steps are kept verbatim, as are
+    /// middle steps whose observations later steps refer to (a path, number or
+    /// identifier from the observation reappears in a later thought or tool call).
+    /// The remaining middle steps are folded into one summary step written by
+    /// `model`, which takes the place of the earliest step it replaces. The
+    /// summary always lists the tools used and a short excerpt of each output,
+    /// so nothing is lost outright when the summarizer is unavailable.
+    pub async fn compact_trace(
+        steps: &mut Vec<ReActStep>,
+        provider: Arc<dyn LLMProvider>,
+        model: &str,
+        max_tokens: usize,
+    ) -> Result<Option<TraceCompaction>> {
+        let current_tokens = Self::estimate_trace_tokens(steps);
+        if current_tokens <= max_tokens || steps.len() <= RECENT_STEPS + 1 {
+            return Ok(None);
+        }
+
+        info!("Compacting ReAct trace ({} steps, ~{} tokens, budget {})", steps.len(), current_tokens, max_tokens);
+
+        let middle_end = steps.len() - RECENT_STEPS;
+        let mut pinned = Vec::new();
+        let mut evicted = Vec::new();
+        // Pinned steps that came before the first evicted one keep their place ahead of the summary
+        let mut pinned_before = 0;
+        for i in 1..middle_end {
+            if Self::is_referenced_later(&steps[i], &steps[i + 1..]) {
+                pinned.push(steps[i].clone());
+                if evicted.is_empty() {
+                    pinned_before += 1;
+                }
+            } else {
+                evicted.push(steps[i].clone());
+            }
+        }
+        // A lone earlier summary has nothing new to fold in
+        if evicted.iter().all(|s| s.thought.starts_with(TRACE_SUMMARY_TAG)) {
+            warn!("No unpinned middle steps to summarize; trace left as is");
+            return Ok(None);
+        }
+
+        let (tools_used, key_outputs) = Self::digest(&evicted);
+
+        // Prior summaries are folded in; the summarizer sees their text too
+        let transcript = evicted.iter().map(|s| {
+            let mut entry = format!("Thought: {}", s.thought);
+            for (i, action) in s.actions.iter().enumerate() {
+                entry.push_str(&format!("\nAction: {} {}", action.name, action.parameters));
+                if let Some(obs) = s.observations.get(i) {
+                    entry.push_str(&format!("\nObservation: {}", crate::agent::truncate(obs, 600)));
+                }
+            }
+            entry
+        }).collect::<Vec<_>>().join("\n\n");
+
+        let mut config = AgentConfig::new(AgentType::GeneralChat, &AgencyProfile::default());
+        config.model = model.to_string();
+        let summarizer = SimpleAgent::new_with_provider(provider, config);
+        let prompt = format!(
+            "Summarize these earlier steps of an agent's work in under 400 characters. \
+            Keep facts, values and file names the agent found; drop reasoning chatter.\n\n{}",
+            transcript
+        );
+        let (summary, usage) = match summarizer.execute_simple(&prompt, None).await {
+            Ok(response) if response.success && !response.answer.trim().is_empty() => (response.answer.trim().to_string(), response.usage),
+            Ok(response) => {
+                warn!("Trace summarizer returned nothing useful; keeping the digest only");
+                (String::new(), response.usage)
+            }
+            Err(e) => {
+                warn!("Trace summarizer failed ({}); keeping the digest only", e);
+                (String::new(), TokenUsage::default())
+            }
+        };
+
+        let mut thought = format!("{}: {} earlier steps]", TRACE_SUMMARY_TAG, Self::count_summarized(&evicted));
+        if !summary.is_empty() {
+            thought.push('\n');
+            thought.push_str(&summary);
+        }
+        if !tools_used.is_empty() {
+            thought.push_str(&format!("\nTools used: {}", tools_used.join(", ")));
+        }
+        if !key_outputs.is_empty() {
+            thought.push_str("\nKey outputs:");
+            for line in &key_outputs {
+                thought.push_str(&format!("\n- {}", line));
+            }
+        }
+
+        let recent = steps.split_off(middle_end);
+        let first = steps[0].clone();
+        let (evicted_count, pinned_count) = (evicted.len(), pinned.len());
+        steps.clear();
+        steps.push(first);
+        let mut pinned = pinned.into_iter();
+        steps.extend(pinned.by_ref().take(pinned_before));
+        steps.push(ReActStep::thought(thought).with_usage(usage));
+        steps.extend(pinned);
+        steps.extend(recent);
+
+        info!("Trace compacted: {} steps summarized, {} pinned, ~{} tokens now", evicted_count, pinned_count, Self::estimate_trace_tokens(steps));
+        Ok(Some(TraceCompaction { evicted: evicted_count, pinned: pinned_count, usage }))
+    }
+
+    /// Whether a later step mentions something specific from this step's observations
+    fn is_referenced_later(step: &ReActStep, later: &[ReActStep]) -> bool {
+        if step.observations.is_empty() || step.thought.starts_with(TRACE_SUMMARY_TAG) {
+            return false;
+        }
+        let terms: HashSet<String> = step.observations.iter().flat_map(|o| salient_terms(o)).collect();
+        if terms.is_empty() {
+            return false;
+        }
+        later.iter().any(|s| {
+            let mut text = s.thought.clone();
+            for action in &s.actions {
+                text.push(' ');
+                text.push_str(&action.parameters.to_string());
+            }
+            if let Some(ref answer) = s.answer {
+                text.push(' ');
+                text.push_str(answer);
+            }
+            salient_terms(&text).iter().any(|t| terms.contains(t))
+        })
+    }
+
+    /// Tool names and one-line output excerpts, merged with those of earlier summaries
+    fn digest(evicted: &[ReActStep]) -> (Vec<String>, Vec<String>) {
+        let mut tools: Vec<String> = Vec::new();
+        let mut outputs = Vec::new();
+        for step in evicted {
+            if step.thought.starts_with(TRACE_SUMMARY_TAG) {
+                let mut in_outputs = false;
+                for line in step.thought.lines() {
+                    if let Some(names) = line.strip_prefix("Tools used: ") {
+                        tools.extend(names.split(", ").map(|n| n.to_string()));
+                    } else if line == "Key outputs:" {
+                        in_outputs = true;
+                    } else if let Some(output) = line.strip_prefix("- ").filter(|_| in_outputs) {
+                        outputs.push(output.to_string());
+                    }
+                }
+                continue;
+            }
+            for (i, action) in step.actions.iter().enumerate() {
+                tools.push(action.name.clone());
+                let observation = step.observations.get(i).map(|o| o.as_str()).unwrap_or("no output");
+                outputs.push(format!("{}: {}", action.name, crate::agent::truncate(observation, 160)));
+            }
+        }
+        let mut seen = HashSet::new();
+        tools.retain(|t| seen.insert(t.clone()));
+        if outputs.len() > MAX_KEY_OUTPUTS {
+            outputs.drain(..outputs.len() - MAX_KEY_OUTPUTS);
+        }
+        (tools, outputs)
+    }
+
+    /// Original steps represented, counting through earlier summaries
+    fn count_summarized(evicted: &[ReActStep]) -> usize {
+        evicted.iter().map(|s| {
+            s.thought.strip_prefix(TRACE_SUMMARY_TAG)
+                .and_then(|rest| rest.trim_start_matches(':').split_whitespace().next())
+                .and_then(|n| n.parse::<usize>().ok())
+                .unwrap_or(1)
+        }).sum()
+    }
+}
+
+/// Tokens specific enough that their reappearance means a reference: paths,
+/// file names, URLs, identifiers and numbers, rather than ordinary words
+fn salient_terms(text: &str) -> HashSet<String> {
+    text.split(|c: char| c.is_whitespace() || "\"'`,;()[]{}<>=".contains(c))
+        .map(|t| t.trim_matches(|c: char| c == '.' || c == ':' || c == '!' || c == '?'))
+        .filter(|t| t.len() >= 4)
+        .filter(|t| t.chars().any(|c| c.is_ascii_digit() || c == '/' || c == '_' || c == '.'))
+        .map(|t| t.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::ToolCall;
+    use async_trait::async_trait;
+    use futures_util::stream::BoxStream;
+    use serde_json::json;
+
+    /// Summarizer stand-in; `None` simulates an unreachable model
+    struct Summarizer(Option<&'static str>);
+
+    #[async_trait]
+    impl LLMProvider for Summarizer {
+        async fn generate(&self, _model: &str, _prompt: String, _system: Option<String>) -> Result<String> {
+            self.0.map(|s| s.to_string()).ok_or_else(|| anyhow::anyhow!("model offline"))
+        }
+
+        async fn generate_stream(&self, _model: &str, _prompt: String, _system: Option<String>) -> Result<BoxStream<'static, Result<String>>> {
+            Err(anyhow::anyhow!("not used"))
+        }
+
+        fn get_lock(&self) -> Arc<tokio::sync::Mutex<()>> {
+            Arc::new(tokio::sync::Mutex::new(()))
+        }
+    }
+
+    /// Fails the test if the summarizer is consulted at all
+    struct Unreachable;
+
+    #[async_trait]
+    impl LLMProvider for Unreachable {
+        async fn generate(&self, _model: &str, _prompt: String, _system: Option<String>) -> Result<String> {
+            panic!("summarizer should not be called");
+        }
+
+        async fn generate_stream(&self, _model: &str, _prompt: String, _system: Option<String>) -> Result<BoxStream<'static, Result<String>>> {
+            panic!("summarizer should not be called");
+        }
+
+        fn get_lock(&self) -> Arc<tokio::sync::Mutex<()>> {
+            Arc::new(tokio::sync::Mutex::new(()))
+        }
+    }
+
+    fn tool_step(thought: &str, tool: &str, params: serde_json::Value, observation: &str) -> ReActStep {
+        let mut step = ReActStep::thought(thought).with_actions(vec![ToolCall { name: tool.to_string(), parameters: params }]);
+        step.observations.push(observation.to_string());
+        step
+    }
+
+    /// Eight steps; step 2 found a path that the last step reads
+    fn long_trace() -> Vec<ReActStep> {
+        let filler = "lorem ipsum ".repeat(40);
+        let mut steps = vec![ReActStep::thought("Plan: find the config and report the port")];
+        steps.push(tool_step("List the repo", "codebase_explorer", json!({ "action": "list" }), &format!("README.md src/ {}", filler)));
+        steps.push(tool_step("Search for config", "codebase_explorer", json!({ "action": "search", "query": "port" }), &format!("match in config/server_v2.toml line 14 {}", filler)));
+        for i in 0..4 {
+            steps.push(tool_step(&format!("Check status {}", i), "system_monitor", json!({}), &format!("cpu ok {}", filler)));
+        }
+        steps.push(tool_step("Read config/server_v2.toml", "codebase_explorer", json!({ "action": "read", "path": "config/server_v2.toml" }), "port = 8443"));
+        steps
+    }
+
+    #[tokio::test]
+    async fn test_under_budget_is_untouched() {
+        let mut steps = long_trace();
+        let before = steps.clone();
+        let done = ContextCompactor::compact_trace(&mut steps, Arc::new(Summarizer(Some("x"))), "summarizer", 100_000).await.unwrap();
+        assert!(done.is_none());
+        assert_eq!(steps, before);
+    }
+
+    #[tokio::test]
+    async fn test_referenced_steps_are_pinned_and_rest_summarized() {
+        let mut steps = long_trace();
+        let done = ContextCompactor::compact_trace(&mut steps, Arc::new(Summarizer(Some("🎯 Repo listed; CPU healthy."))), "summarizer", 200).await.unwrap().unwrap();
+
+        assert_eq!(done.pinned, 1);
+        assert_eq!(done.evicted, 3);
+        // first, summary, pinned search step, last three
+        assert_eq!(steps.len(), 6);
+        assert!(steps[0].thought.starts_with("Plan"));
+        let summary = &steps[1].thought;
+        assert!(summary.starts_with("[TRACE SUMMARY: 3 earlier steps]"));
+        assert!(summary.contains("Repo listed; CPU healthy."));
+        assert!(summary.contains("Tools used: codebase_explorer, system_monitor"));
+        assert!(summary.contains("- codebase_explorer: README.md src/"));
+        assert_eq!(steps[2].thought, "Search for config");
+        assert_eq!(steps[5].observations, vec!["port = 8443".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_summary_survives_offline_summarizer_and_recompaction() {
+        let mut steps = long_trace();
+        ContextCompactor::compact_trace(&mut steps, Arc::new(Summarizer(None)), "summarizer", 200).await.unwrap().unwrap();
+        assert!(steps[1].thought.contains("Key outputs:"));
+
+        // Grow the trace again and compact a second time
+        for i in 0..3 {
+            steps.push(tool_step(&format!("Ping {}", i), "system_monitor", json!({}), &"pong ".repeat(80)));
+        }
+        ContextCompactor::compact_trace(&mut steps, Arc::new(Summarizer(None)), "summarizer", 200).await.unwrap().unwrap();
+        let summaries: Vec<&ReActStep> = steps.iter().filter(|s| s.thought.starts_with(TRACE_SUMMARY_TAG)).collect();
+        assert_eq!(summaries.len(), 1, "summaries are merged, not stacked");
+        assert!(summaries[0].thought.contains("- codebase_explorer: README.md src/"), "earlier key outputs carry over");
+    }
+
+    #[tokio::test]
+    async fn test_summary_keeps_chronological_position() {
+        // Put the referenced search step ahead of the listing that gets evicted
+        let mut steps = long_trace();
+        steps.swap(1, 2);
+        let done = ContextCompactor::compact_trace(&mut steps, Arc::new(Summarizer(None)), "summarizer", 200).await.unwrap().unwrap();
+
+        assert_eq!(done.pinned, 1);
+        assert_eq!(steps[1].thought, "Search for config");
+        assert!(steps[2].thought.starts_with(TRACE_SUMMARY_TAG));
+    }
+
+    #[tokio::test]
+    async fn test_lone_prior_summary_is_not_resummarized() {
+        let mut steps = long_trace();
+        ContextCompactor::compact_trace(&mut steps, Arc::new(Summarizer(None)), "summarizer", 200).await.unwrap().unwrap();
+        let before = steps.clone();
+
+        // Only the earlier summary is evictable now; the search step stays pinned
+        let done = ContextCompactor::compact_trace(&mut steps, Arc::new(Unreachable), "summarizer", 50).await.unwrap();
+        assert!(done.is_none());
+        assert_eq!(steps, before);
+    }
+
+    #[test]
+    fn test_salient_terms_skip_plain_words() {
+        let terms = salient_terms("The server reads config/server_v2.toml on port 8443.");
+        assert!(terms.contains("config/server_v2.toml"));
+        assert!(terms.contains("8443"));
+        assert!(!terms.contains("server"));
+    }
+}
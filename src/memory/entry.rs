@@ -28,6 +28,9 @@ pub struct MemoryMetadata {
     pub access_count: u32,
     /// Tags for categorization
     pub tags: Vec<String>,
+    /// When a search last returned this memory
+    #[serde(default)]
+    pub last_accessed: Option<DateTime<Utc>>,
 }
 
 /// Source of a memory entry
@@ -55,11 +58,10 @@ pub struct MemoryEntry {
     pub metadata: MemoryMetadata,
     /// When this memory was created
     pub timestamp: DateTime<Utc>,
-    /// Optional embedding (populated on retrieval)
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Optional embedding (populated on retrieval).
+    /// Always serialized: the tier files are bincode, which cannot skip fields.
     pub embedding: Option<Vec<f32>>,
     /// Similarity score (only set during search results)
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub similarity: Option<f32>,
 }
 
@@ -84,6 +86,7 @@ impl MemoryEntry {
                 importance: 0.5,
                 access_count: 0,
                 tags: Vec::new(),
+                last_accessed: None,
             },
             timestamp: Utc::now(),
             embedding: None,
@@ -118,6 +121,7 @@ impl MemoryEntry {
                 importance: 0.5,
                 access_count: 0,
                 tags: Vec::new(),
+                last_accessed: None,
             },
             timestamp: Utc::now(),
             embedding: None,
@@ -184,6 +188,8 @@ impl Default for MemoryMetadata {
 
             tags: Vec::new(),
 
+            last_accessed: None,
+
         }
 
     }
@@ -6,6 +6,8 @@ use std::collections::VecDeque;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::agent::{ChatMessage, ChatRole};
+
 /// A single conversation turn
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ConversationTurn {
@@ -15,6 +17,23 @@ pub struct ConversationTurn {
     pub agent: Option<String>,
 }
 
+impl ConversationTurn {
+    /// Convert to a chat message; agent attribution is kept inline as in ChatML
+    pub fn to_chat_message(&self) -> ChatMessage {
+        let role = match self.role {
+            Role::User => ChatRole::User,
+            Role::Assistant => ChatRole::Assistant,
+            Role::System => ChatRole::System,
+            Role::Tool => ChatRole::Tool,
+        };
+        let content = match (&self.role, &self.agent) {
+            (Role::Assistant, Some(agent)) => format!("[{}]: {}", agent, self.content),
+            _ => self.content.clone(),
+        };
+        ChatMessage::new(role, content)
+    }
+}
+
 /// Role in a conversation
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -160,6 +179,11 @@ impl EpisodicMemory {
             .join("\n")
     }
 
+    /// Convert history into chat messages for multi-turn providers
+    pub fn to_chat_messages(&self) -> Vec<ChatMessage> {
+        self.turns.iter().map(ConversationTurn::to_chat_message).collect()
+    }
+
     /// Get the last N turns
     pub fn last_n(&self, n: usize) -> Vec<&ConversationTurn> {
         self.turns.iter().rev().take(n).rev().collect()
@@ -263,4 +287,18 @@ mod tests {
         assert!(formatted.contains("Message 4"));
         assert!(!formatted.contains("Message 0"));
     }
+
+    #[test]
+    fn test_to_chat_messages() {
+        let mut memory = EpisodicMemory::new(5, 10000);
+        memory.add_user("Hello");
+        memory.add_assistant("Hi there!", Some("GeneralChat".to_string()));
+        memory.add_assistant("Plain reply", None);
+
+        let messages = memory.to_chat_messages();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0], ChatMessage::user("Hello"));
+        assert_eq!(messages[1], ChatMessage::assistant("[GeneralChat]: Hi there!"));
+        assert_eq!(messages[2], ChatMessage::assistant("Plain reply"));
+    }
 }
@@ -0,0 +1,658 @@
+//! HNSW Approximate Nearest-Neighbor Index
+//!
+//! A Hierarchical Navigable Small World graph over the (normalized) memory
+//! embeddings of both tiers. Similarity is the dot product. Each node keeps
+//! the filterable attributes of its entry (`EntryAttrs`) so filters are
+//! applied during the walk: non-matching nodes are still traversed, but never
+//! returned.
+//!
+//! HOT nodes own their vector. COLD nodes only record their row in the mapped
+//! COLD matrix and are scored through `ExternalVectors`, so neither RAM nor
+//! the saved index holds a second copy of those embeddings. Rows change when
+//! the COLD file is rewritten; `relocate` re-points the nodes.
+//!
+//! Removal leaves a tombstone; the graph is rebuilt from live nodes once
+//! tombstones outnumber them.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+use super::MemoryEntry;
+use crate::orchestrator::Kind;
+
+/// Bumped whenever the on-disk layout changes; older files are rebuilt
+const FORMAT_VERSION: u32 = 3;
+/// Links per node on upper layers (twice that on layer 0)
+const DEFAULT_M: usize = 16;
+const DEFAULT_EF_CONSTRUCTION: usize = 128;
+/// Candidate list size for queries (raised to `k` when larger)
+pub const DEFAULT_EF_SEARCH: usize = 64;
+const MAX_LEVEL: usize = 16;
+/// Tombstones tolerated before a rebuild
+const MIN_TOMBSTONES_FOR_REBUILD: usize = 64;
+
+/// Restricts search results to matching entries
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IndexFilter<'a> {
+    pub context: Option<&'a str>,
+    pub kind: Option<&'a Kind>,
+    /// Entries must carry every one of these tags
+    pub tags: &'a [String],
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl IndexFilter<'_> {
+    pub fn accepts(&self, context: &str, kind: &Kind, tags: &[String], timestamp: DateTime<Utc>) -> bool {
+        self.context.is_none_or(|c| context == c)
+            && self.kind.is_none_or(|k| kind == k)
+            && self.tags.iter().all(|t| tags.contains(t))
+            && self.since.is_none_or(|s| timestamp >= s)
+            && self.until.is_none_or(|u| timestamp <= u)
+    }
+
+    pub fn matches(&self, attrs: &EntryAttrs) -> bool {
+        self.accepts(&attrs.context, &attrs.kind, &attrs.tags, attrs.timestamp)
+    }
+
+    pub fn matches_entry(&self, entry: &MemoryEntry) -> bool {
+        self.accepts(&entry.metadata.context, &entry.metadata.kind, &entry.metadata.tags, entry.timestamp)
+    }
+}
+
+/// The parts of an entry that `IndexFilter` looks at, kept inside indexes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntryAttrs {
+    pub context: String,
+    pub kind: Kind,
+    pub tags: Vec<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl From<&MemoryEntry> for EntryAttrs {
+    fn from(entry: &MemoryEntry) -> Self {
+        Self {
+            context: entry.metadata.context.clone(),
+            kind: entry.metadata.kind.clone(),
+            tags: entry.metadata.tags.clone(),
+            timestamp: entry.timestamp,
+        }
+    }
+}
+
+/// Embeddings stored outside the index, by row: the mapped COLD matrix
+pub trait ExternalVectors {
+    /// Dot product of the embedding in `row` with `query`
+    fn similarity(&self, row: u32, query: &[f32]) -> Option<f32>;
+
+    fn embedding(&self, row: u32) -> Option<Vec<f32>>;
+}
+
+/// No external embeddings: every node owns its vector
+impl ExternalVectors for () {
+    fn similarity(&self, _row: u32, _query: &[f32]) -> Option<f32> {
+        None
+    }
+
+    fn embedding(&self, _row: u32) -> Option<Vec<f32>> {
+        None
+    }
+}
+
+/// Where a node's embedding lives
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum Vector {
+    /// Kept in the node (HOT entries; empty for tombstones)
+    Owned(Vec<f32>),
+    /// Row of the external matrix
+    External(u32),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Node {
+    id: String,
+    vector: Vector,
+    attrs: EntryAttrs,
+    /// Neighbor slots per layer, layer 0 first
+    links: Vec<Vec<u32>>,
+    deleted: bool,
+}
+
+impl Node {
+    fn level(&self) -> usize {
+        self.links.len() - 1
+    }
+}
+
+/// A similarity paired with a node slot, ordered by similarity
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Scored(f32, u32);
+
+impl Eq for Scored {}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then_with(|| other.1.cmp(&self.1))
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HnswIndex {
+    format: u32,
+    m: usize,
+    ef_construction: usize,
+    nodes: Vec<Node>,
+    by_id: HashMap<String, u32>,
+    entry_point: Option<u32>,
+}
+
+impl Default for HnswIndex {
+    fn default() -> Self {
+        Self::new(DEFAULT_M, DEFAULT_EF_CONSTRUCTION)
+    }
+}
+
+impl HnswIndex {
+    pub fn new(m: usize, ef_construction: usize) -> Self {
+        Self {
+            format: FORMAT_VERSION,
+            m: m.max(2),
+            ef_construction: ef_construction.max(m),
+            nodes: Vec::new(),
+            by_id: HashMap::new(),
+            entry_point: None,
+        }
+    }
+
+    /// Index every entry that has an embedding
+    pub fn build<'a>(entries: impl IntoIterator<Item = &'a MemoryEntry>) -> Self {
+        let mut index = Self::default();
+        for entry in entries {
+            index.insert_entry(entry, &());
+        }
+        index
+    }
+
+    /// Number of live (non-removed) entries
+    pub fn len(&self) -> usize {
+        self.by_id.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_id.is_empty()
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.by_id.contains_key(id)
+    }
+
+    /// Whether the index holds exactly the embedded entries given
+    pub fn covers<'a>(&self, entries: impl IntoIterator<Item = &'a MemoryEntry>) -> bool {
+        self.covers_ids(entries.into_iter().filter(|e| e.embedding.is_some()).map(|e| e.id.as_str()))
+    }
+
+    /// Whether the index holds exactly the given ids (of embedded entries)
+    pub fn covers_ids<'a>(&self, ids: impl IntoIterator<Item = &'a str>) -> bool {
+        let mut embedded = 0;
+        for id in ids {
+            if !self.contains(id) {
+                return false;
+            }
+            embedded += 1;
+        }
+        embedded == self.len()
+    }
+
+    /// Whether every live node reads its embedding from where `row_of` says
+    /// the entry is stored: that external row, or the node itself when `None`
+    pub fn matches_external(&self, row_of: impl Fn(&str) -> Option<u32>) -> bool {
+        self.by_id.values().all(|&slot| {
+            let node = &self.nodes[slot as usize];
+            match node.vector {
+                Vector::External(row) => row_of(&node.id) == Some(row),
+                Vector::Owned(_) => row_of(&node.id).is_none(),
+            }
+        })
+    }
+
+    /// Live nodes whose embedding lives outside the index
+    pub fn external_len(&self) -> usize {
+        self.by_id.values().filter(|&&slot| matches!(self.nodes[slot as usize].vector, Vector::External(_))).count()
+    }
+
+    /// Index `entry` (replacing any previous version); skipped without an embedding
+    pub fn insert_entry(&mut self, entry: &MemoryEntry, external: &dyn ExternalVectors) {
+        if let Some(vector) = &entry.embedding {
+            self.insert(&entry.id, vector.clone(), EntryAttrs::from(entry), external);
+        }
+    }
+
+    pub fn insert(&mut self, id: &str, vector: Vec<f32>, attrs: EntryAttrs, external: &dyn ExternalVectors) {
+        let query = vector.clone();
+        self.insert_node(id, &query, Vector::Owned(vector), attrs, external);
+    }
+
+    /// Index an entry whose embedding is `row` of `external`; returns false
+    /// (indexing nothing) when that row has no embedding
+    pub fn insert_external(&mut self, id: &str, row: u32, attrs: EntryAttrs, external: &dyn ExternalVectors) -> bool {
+        let Some(query) = external.embedding(row) else { return false };
+        self.insert_node(id, &query, Vector::External(row), attrs, external);
+        true
+    }
+
+    fn insert_node(&mut self, id: &str, query: &[f32], vector: Vector, attrs: EntryAttrs, external: &dyn ExternalVectors) {
+        self.remove(id, external);
+        let level = self.random_level(id);
+        let slot = self.nodes.len() as u32;
+        self.nodes.push(Node {
+            id: id.to_string(),
+            vector,
+            attrs,
+            links: vec![Vec::new(); level + 1],
+            deleted: false,
+        });
+        self.by_id.insert(id.to_string(), slot);
+
+        let Some(entry_point) = self.entry_point else {
+            self.entry_point = Some(slot);
+            return;
+        };
+        let top = self.nodes[entry_point as usize].level();
+
+        let mut current = entry_point;
+        for layer in (level + 1..=top).rev() {
+            current = self.greedy(query, current, layer, external);
+        }
+        for layer in (0..=level.min(top)).rev() {
+            let candidates = self.search_layer(query, current, self.ef_construction, layer, external, |_| true);
+            let neighbors = self.select_neighbors(&candidates, self.m, external);
+            let max_links = self.max_links(layer);
+            for &neighbor in &neighbors {
+                let links = &mut self.nodes[neighbor as usize].links[layer];
+                links.push(slot);
+                if links.len() > max_links {
+                    self.shrink_links(neighbor, layer, max_links, external);
+                }
+            }
+            self.nodes[slot as usize].links[layer] = neighbors;
+            if let Some(best) = candidates.first() {
+                current = best.1;
+            }
+        }
+        if level > top {
+            self.entry_point = Some(slot);
+        }
+    }
+
+    /// Remove `id`; returns whether it was indexed
+    pub fn remove(&mut self, id: &str, external: &dyn ExternalVectors) -> bool {
+        let Some(slot) = self.by_id.remove(id) else { return false };
+        self.nodes[slot as usize].deleted = true;
+        if self.by_id.is_empty() {
+            *self = Self::new(self.m, self.ef_construction);
+        } else {
+            let tombstones = self.nodes.len() - self.by_id.len();
+            if tombstones >= MIN_TOMBSTONES_FOR_REBUILD && tombstones > self.by_id.len() {
+                self.rebuild(external);
+            }
+        }
+        true
+    }
+
+    /// Re-insert live nodes into a fresh graph, dropping tombstones
+    pub fn rebuild(&mut self, external: &dyn ExternalVectors) {
+        let mut fresh = Self::new(self.m, self.ef_construction);
+        for node in std::mem::take(&mut self.nodes).into_iter().filter(|n| !n.deleted) {
+            match node.vector {
+                Vector::Owned(vector) => fresh.insert(&node.id, vector, node.attrs, external),
+                Vector::External(row) => {
+                    fresh.insert_external(&node.id, row, node.attrs, external);
+                }
+            }
+        }
+        *self = fresh;
+    }
+
+    /// Re-point nodes after the external matrix was rewritten. Entries found
+    /// by `row_of` read from their new row (dropping any owned copy); external
+    /// nodes that left it take `owned(id)`, or are removed without one.
+    pub fn relocate(&mut self, row_of: impl Fn(&str) -> Option<u32>, owned: impl Fn(&str) -> Option<Vec<f32>>) {
+        for node in &mut self.nodes {
+            if node.deleted {
+                // Tombstones are still walked; their old row may now be another entry
+                node.vector = Vector::Owned(Vec::new());
+                continue;
+            }
+            match (row_of(&node.id), &node.vector) {
+                (Some(row), _) => node.vector = Vector::External(row),
+                (None, Vector::Owned(_)) => {}
+                (None, Vector::External(_)) => match owned(&node.id) {
+                    Some(vector) => node.vector = Vector::Owned(vector),
+                    None => {
+                        node.deleted = true;
+                        node.vector = Vector::Owned(Vec::new());
+                        self.by_id.remove(&node.id);
+                    }
+                },
+            }
+        }
+        if self.by_id.is_empty() {
+            *self = Self::new(self.m, self.ef_construction);
+        }
+    }
+
+    /// The `k` most similar live entries passing `filter`, best first
+    pub fn search(&self, query: &[f32], k: usize, ef: usize, filter: &IndexFilter<'_>, external: &dyn ExternalVectors) -> Vec<(String, f32)> {
+        let Some(entry_point) = self.entry_point else { return Vec::new() };
+        if k == 0 {
+            return Vec::new();
+        }
+        let mut current = entry_point;
+        for layer in (1..=self.nodes[entry_point as usize].level()).rev() {
+            current = self.greedy(query, current, layer, external);
+        }
+        self.search_layer(query, current, ef.max(k), 0, external, |node| !node.deleted && filter.matches(&node.attrs))
+            .into_iter()
+            .take(k)
+            .map(|Scored(score, slot)| (self.nodes[slot as usize].id.clone(), score))
+            .collect()
+    }
+
+    /// Load a saved index; `None` when missing or written by another format
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let bytes = std::fs::read(path).with_context(|| format!("Failed to read ANN index {:?}", path))?;
+        match bincode::deserialize::<Self>(&bytes) {
+            Ok(index) if index.format == FORMAT_VERSION => Ok(Some(index)),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    /// Write encoded index bytes atomically (temp file + fsync + rename)
+    pub fn write(path: &Path, bytes: &[u8]) -> Result<()> {
+        super::persistence::write_atomic(path, &[bytes])
+    }
+
+    fn max_links(&self, layer: usize) -> usize {
+        if layer == 0 { self.m * 2 } else { self.m }
+    }
+
+    /// Geometric level distribution, seeded by the id so rebuilds are stable
+    fn random_level(&self, id: &str) -> usize {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        id.hash(&mut hasher);
+        let unit = ((hasher.finish() >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+        let scale = 1.0 / (self.m as f64).ln();
+        ((-unit.ln() * scale) as usize).min(MAX_LEVEL)
+    }
+
+    /// Similarity of a node to `query`; a node without an embedding scores
+    /// as the opposite of every query
+    fn similarity(&self, query: &[f32], slot: u32, external: &dyn ExternalVectors) -> f32 {
+        match &self.nodes[slot as usize].vector {
+            Vector::Owned(vector) => dot(query, vector),
+            Vector::External(row) => external.similarity(*row, query).unwrap_or(-1.0),
+        }
+    }
+
+    fn vector(&self, slot: u32, external: &dyn ExternalVectors) -> Cow<'_, [f32]> {
+        match &self.nodes[slot as usize].vector {
+            Vector::Owned(vector) => Cow::Borrowed(vector),
+            Vector::External(row) => Cow::Owned(external.embedding(*row).unwrap_or_default()),
+        }
+    }
+
+    /// Walk to the locally most similar node on `layer`
+    fn greedy(&self, query: &[f32], start: u32, layer: usize, external: &dyn ExternalVectors) -> u32 {
+        let mut best = Scored(self.similarity(query, start, external), start);
+        loop {
+            let mut improved = false;
+            for &neighbor in self.links(best.1, layer) {
+                let score = self.similarity(query, neighbor, external);
+                if score > best.0 {
+                    best = Scored(score, neighbor);
+                    improved = true;
+                }
+            }
+            if !improved {
+                return best.1;
+            }
+        }
+    }
+
+    fn links(&self, slot: u32, layer: usize) -> &[u32] {
+        self.nodes[slot as usize].links.get(layer).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Best-first search on one layer. Every reachable node guides the walk,
+    /// but only those `admit` accepts are kept (up to `ef`), best first.
+    fn search_layer(
+        &self,
+        query: &[f32],
+        start: u32,
+        ef: usize,
+        layer: usize,
+        external: &dyn ExternalVectors,
+        admit: impl Fn(&Node) -> bool,
+    ) -> Vec<Scored> {
+        // Sized by the walk, not the index, so each layer visit stays cheap
+        let mut visited = HashSet::with_capacity(ef * 8);
+        visited.insert(start);
+        let first = Scored(self.similarity(query, start, external), start);
+        let mut candidates = BinaryHeap::from([first]);
+        let mut results: BinaryHeap<Reverse<Scored>> = BinaryHeap::new();
+        if admit(&self.nodes[start as usize]) {
+            results.push(Reverse(first));
+        }
+
+        while let Some(candidate) = candidates.pop() {
+            if results.len() >= ef && results.peek().is_some_and(|Reverse(worst)| candidate.0 < worst.0) {
+                break;
+            }
+            for &neighbor in self.links(candidate.1, layer) {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let scored = Scored(self.similarity(query, neighbor, external), neighbor);
+                let full = results.len() >= ef;
+                if full && results.peek().is_some_and(|Reverse(worst)| scored.0 <= worst.0) {
+                    continue;
+                }
+                candidates.push(scored);
+                if admit(&self.nodes[neighbor as usize]) {
+                    results.push(Reverse(scored));
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+
+        let mut found: Vec<Scored> = results.into_iter().map(|Reverse(s)| s).collect();
+        found.sort_by(|a, b| b.cmp(a));
+        found
+    }
+
+    /// Neighbor selection heuristic: prefer candidates closer to the new node
+    /// than to any already selected one, then fill up with the rest
+    fn select_neighbors(&self, candidates: &[Scored], m: usize, external: &dyn ExternalVectors) -> Vec<u32> {
+        let mut selected: Vec<(u32, Cow<'_, [f32]>)> = Vec::with_capacity(m);
+        let mut skipped = Vec::new();
+        for &Scored(score, slot) in candidates {
+            if selected.len() >= m {
+                break;
+            }
+            let vector = self.vector(slot, external);
+            if selected.iter().all(|(_, chosen)| dot(&vector, chosen) < score) {
+                selected.push((slot, vector));
+            } else {
+                skipped.push(slot);
+            }
+        }
+        let mut selected: Vec<u32> = selected.into_iter().map(|(slot, _)| slot).collect();
+        selected.extend(skipped.into_iter().take(m.saturating_sub(selected.len())));
+        selected
+    }
+
+    fn shrink_links(&mut self, slot: u32, layer: usize, max_links: usize, external: &dyn ExternalVectors) {
+        let kept = {
+            let vector = self.vector(slot, external);
+            let mut scored: Vec<Scored> = self.nodes[slot as usize].links[layer]
+                .iter()
+                .map(|&n| Scored(self.similarity(&vector, n, external), n))
+                .collect();
+            scored.sort_by(|a, b| b.cmp(a));
+            self.select_neighbors(&scored, max_links, external)
+        };
+        self.nodes[slot as usize].links[layer] = kept;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn attrs(context: &str) -> EntryAttrs {
+        EntryAttrs { context: context.to_string(), kind: Kind::Technical, tags: Vec::new(), timestamp: Utc::now() }
+    }
+
+    fn random_unit(rng: &mut StdRng, dims: usize) -> Vec<f32> {
+        let mut v: Vec<f32> = (0..dims).map(|_| rng.gen_range(-1.0..1.0)).collect();
+        let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+        v.iter_mut().for_each(|x| *x /= norm);
+        v
+    }
+
+    fn brute_force(vectors: &[(String, Vec<f32>)], query: &[f32], k: usize) -> Vec<String> {
+        let mut scored: Vec<(f32, &String)> = vectors.iter().map(|(id, v)| (dot(query, v), id)).collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored.into_iter().take(k).map(|(_, id)| id.clone()).collect()
+    }
+
+    #[test]
+    fn test_recall_against_linear_scan() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let vectors: Vec<(String, Vec<f32>)> = (0..2000).map(|i| (format!("m{}", i), random_unit(&mut rng, 32))).collect();
+        let mut index = HnswIndex::default();
+        for (id, v) in &vectors {
+            index.insert(id, v.clone(), attrs("General"), &());
+        }
+
+        let mut hits = 0;
+        for _ in 0..50 {
+            let query = random_unit(&mut rng, 32);
+            let expected = brute_force(&vectors, &query, 10);
+            let found = index.search(&query, 10, DEFAULT_EF_SEARCH, &IndexFilter::default(), &());
+            hits += found.iter().filter(|(id, _)| expected.contains(id)).count();
+        }
+        let recall = hits as f32 / 500.0;
+        assert!(recall > 0.9, "recall {}", recall);
+    }
+
+    #[test]
+    fn test_filters_removal_and_persistence() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut index = HnswIndex::default();
+        for i in 0..300 {
+            let context = if i % 10 == 0 { "Rare" } else { "General" };
+            index.insert(&format!("m{}", i), random_unit(&mut rng, 16), attrs(context), &());
+        }
+
+        let query = random_unit(&mut rng, 16);
+        let rare = index.search(&query, 50, DEFAULT_EF_SEARCH, &IndexFilter { context: Some("Rare"), ..Default::default() }, &());
+        assert_eq!(rare.len(), 30, "selective filters still find every match");
+        assert!(rare.windows(2).all(|w| w[0].1 >= w[1].1));
+
+        for i in 0..250 {
+            assert!(index.remove(&format!("m{}", i), &()));
+        }
+        assert_eq!(index.len(), 50);
+        assert!(index.nodes.len() < 300, "tombstones trigger a rebuild");
+        let found = index.search(&query, 100, DEFAULT_EF_SEARCH, &IndexFilter::default(), &());
+        assert_eq!(found.len(), 50);
+        assert!(found.iter().all(|(id, _)| id[1..].parse::<usize>().unwrap() >= 250));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("memory.hnsw");
+        HnswIndex::write(&path, &index.encode().unwrap()).unwrap();
+        let loaded = HnswIndex::load(&path).unwrap().unwrap();
+        let search = |index: &HnswIndex| index.search(&query, 5, DEFAULT_EF_SEARCH, &IndexFilter::default(), &());
+        assert_eq!(search(&loaded), search(&index));
+    }
+
+    /// Rows of an external matrix, like the mapped COLD tier
+    struct Matrix(Vec<Vec<f32>>);
+
+    impl ExternalVectors for Matrix {
+        fn similarity(&self, row: u32, query: &[f32]) -> Option<f32> {
+            self.0.get(row as usize).map(|v| dot(v, query))
+        }
+
+        fn embedding(&self, row: u32) -> Option<Vec<f32>> {
+            self.0.get(row as usize).cloned()
+        }
+    }
+
+    #[test]
+    fn test_external_nodes_are_scored_in_place() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let vectors: Vec<Vec<f32>> = (0..400).map(|_| random_unit(&mut rng, 16)).collect();
+        // The first half lives in the external matrix, in reverse order
+        let matrix = Matrix(vectors[..200].iter().rev().cloned().collect());
+
+        let mut owned = HnswIndex::default();
+        let mut mixed = HnswIndex::default();
+        for (i, v) in vectors.iter().enumerate() {
+            let id = format!("m{}", i);
+            owned.insert(&id, v.clone(), attrs("General"), &());
+            if i < 200 {
+                assert!(mixed.insert_external(&id, 199 - i as u32, attrs("General"), &matrix));
+            } else {
+                mixed.insert(&id, v.clone(), attrs("General"), &matrix);
+            }
+        }
+        assert_eq!(mixed.external_len(), 200);
+        let saved = owned.encode().unwrap().len() - mixed.encode().unwrap().len();
+        assert!(saved > 200 * 16 * 3, "external vectors are not saved ({} bytes smaller)", saved);
+        assert!(mixed.matches_external(|id| id[1..].parse::<u32>().ok().filter(|&i| i < 200).map(|i| 199 - i)));
+
+        let query = random_unit(&mut rng, 16);
+        let search = |index: &HnswIndex, external: &dyn ExternalVectors| index.search(&query, 10, DEFAULT_EF_SEARCH, &IndexFilter::default(), external);
+        assert_eq!(search(&mixed, &matrix), search(&owned, &()));
+
+        // After a rewrite that appends the second half, every node is external
+        let rewritten = Matrix(vectors.clone());
+        mixed.relocate(|id| id[1..].parse().ok(), |_| None);
+        assert_eq!(mixed.external_len(), 400);
+        assert_eq!(search(&mixed, &rewritten), search(&owned, &()));
+
+        // Entries that leave the matrix take an owned vector or are dropped
+        mixed.relocate(|id| id[1..].parse::<u32>().ok().filter(|&i| i >= 100), |id| (id == "m0").then(|| vectors[0].clone()));
+        assert_eq!(mixed.len(), 301);
+        assert!(mixed.contains("m0") && !mixed.contains("m1"));
+        assert_eq!(mixed.search(&vectors[0], 1, DEFAULT_EF_SEARCH, &IndexFilter::default(), &rewritten)[0].0, "m0");
+    }
+}
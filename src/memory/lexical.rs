@@ -0,0 +1,295 @@
+//! BM25 Lexical Index
+//!
+//! An inverted index over memory text, kept next to the ANN index so exact
+//! identifiers, error codes and file names can be found even when their
+//! embeddings say little. Tokens are identifier-aware: `src/main.rs`,
+//! `E0382` and `parse_config` are indexed whole *and* split into their parts.
+//!
+//! Lexical and semantic rankings are merged with reciprocal rank fusion.
+//!
+//! The postings are saved next to the ANN index (`<path>.bm25`), so opening
+//! the memory does not decode every COLD record to re-tokenize it.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use super::hnsw::{EntryAttrs, IndexFilter};
+use super::MemoryEntry;
+
+/// BM25 term-frequency saturation
+const K1: f32 = 1.2;
+/// BM25 length normalization
+const B: f32 = 0.75;
+/// Reciprocal rank fusion damping constant
+pub const RRF_K: f32 = 60.0;
+/// Bumped whenever the on-disk layout changes; older files are rebuilt
+const FORMAT_VERSION: u32 = 1;
+
+/// Lowercased tokens of `text`. Compound tokens (`main.rs`, `std::io`,
+/// `parse_config`, `parseConfig`) are kept and also split into their parts.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let is_joiner = |c: char| matches!(c, '_' | '.' | ':' | '/' | '-');
+    for raw in text.split(|c: char| !(c.is_alphanumeric() || is_joiner(c))) {
+        let compound = raw.trim_matches(|c: char| is_joiner(c) && c != '_');
+        if compound.is_empty() {
+            continue;
+        }
+        let parts = split_parts(compound);
+        tokens.push(compound.to_lowercase());
+        if parts.len() > 1 {
+            tokens.extend(parts);
+        }
+    }
+    tokens
+}
+
+/// Alphanumeric parts of a compound token, also split at camelCase humps
+fn split_parts(compound: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    for piece in compound.split(|c: char| !c.is_alphanumeric()).filter(|p| !p.is_empty()) {
+        let mut current = String::new();
+        let mut previous: Option<char> = None;
+        for c in piece.chars() {
+            if c.is_uppercase() && previous.is_some_and(|p| p.is_lowercase()) && !current.is_empty() {
+                parts.push(std::mem::take(&mut current).to_lowercase());
+            }
+            current.push(c);
+            previous = Some(c);
+        }
+        parts.push(current.to_lowercase());
+    }
+    parts
+}
+
+/// Text of an entry that is searchable lexically
+fn document_text(entry: &MemoryEntry) -> String {
+    let meta = &entry.metadata;
+    let mut text = entry.content.clone();
+    for extra in meta.tags.iter().chain(meta.described_entity.iter()).chain(meta.grounding_holon.iter()) {
+        text.push('\n');
+        text.push_str(extra);
+    }
+    text
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Doc {
+    id: String,
+    len: u32,
+    terms: Vec<String>,
+    attrs: EntryAttrs,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Bm25Index {
+    docs: HashMap<u32, Doc>,
+    by_id: HashMap<String, u32>,
+    /// term -> (doc slot -> term frequency)
+    postings: HashMap<String, HashMap<u32, u32>>,
+    total_len: u64,
+    next_slot: u32,
+}
+
+impl Bm25Index {
+    pub fn build<'a>(entries: impl IntoIterator<Item = &'a MemoryEntry>) -> Self {
+        let mut index = Self::default();
+        for entry in entries {
+            index.insert_entry(entry);
+        }
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+
+    /// Whether the index holds exactly the given ids
+    pub fn covers_ids<'a>(&self, ids: impl IntoIterator<Item = &'a str>) -> bool {
+        let mut count = 0;
+        for id in ids {
+            if !self.by_id.contains_key(id) {
+                return false;
+            }
+            count += 1;
+        }
+        count == self.len()
+    }
+
+    /// Load a saved index; `None` when missing or written by another format
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let bytes = std::fs::read(path).with_context(|| format!("Failed to read lexical index {:?}", path))?;
+        match bincode::deserialize::<(u32, Self)>(&bytes) {
+            Ok((format, index)) if format == FORMAT_VERSION => Ok(Some(index)),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(&(FORMAT_VERSION, self))?)
+    }
+
+    /// Write encoded index bytes atomically (temp file + fsync + rename)
+    pub fn write(path: &Path, bytes: &[u8]) -> Result<()> {
+        super::persistence::write_atomic(path, &[bytes])
+    }
+
+    /// Index `entry`, replacing any previous version
+    pub fn insert_entry(&mut self, entry: &MemoryEntry) {
+        self.insert(&entry.id, &document_text(entry), EntryAttrs::from(entry));
+    }
+
+    pub fn insert(&mut self, id: &str, text: &str, attrs: EntryAttrs) {
+        self.remove(id);
+        let tokens = tokenize(text);
+        let mut frequencies: HashMap<String, u32> = HashMap::new();
+        for token in &tokens {
+            *frequencies.entry(token.clone()).or_default() += 1;
+        }
+
+        let slot = self.next_slot;
+        self.next_slot += 1;
+        for (term, tf) in &frequencies {
+            self.postings.entry(term.clone()).or_default().insert(slot, *tf);
+        }
+        self.total_len += tokens.len() as u64;
+        self.by_id.insert(id.to_string(), slot);
+        self.docs.insert(slot, Doc { id: id.to_string(), len: tokens.len() as u32, terms: frequencies.into_keys().collect(), attrs });
+    }
+
+    /// Remove `id`; returns whether it was indexed
+    pub fn remove(&mut self, id: &str) -> bool {
+        let Some(slot) = self.by_id.remove(id) else { return false };
+        if let Some(doc) = self.docs.remove(&slot) {
+            self.total_len -= doc.len as u64;
+            for term in doc.terms {
+                if let Some(posting) = self.postings.get_mut(&term) {
+                    posting.remove(&slot);
+                    if posting.is_empty() {
+                        self.postings.remove(&term);
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// The `k` best BM25 matches passing `filter`, best first
+    pub fn search(&self, query: &str, k: usize, filter: &IndexFilter<'_>) -> Vec<(String, f32)> {
+        if self.docs.is_empty() || k == 0 {
+            return Vec::new();
+        }
+        let n = self.docs.len() as f32;
+        let avg_len = (self.total_len as f32 / n).max(1.0);
+        let terms: HashSet<String> = tokenize(query).into_iter().collect();
+
+        let mut scores: HashMap<u32, f32> = HashMap::new();
+        for term in &terms {
+            let Some(posting) = self.postings.get(term) else { continue };
+            let df = posting.len() as f32;
+            let idf = (1.0 + (n - df + 0.5) / (df + 0.5)).ln();
+            for (&slot, &tf) in posting {
+                let doc = &self.docs[&slot];
+                if !filter.matches(&doc.attrs) {
+                    continue;
+                }
+                let tf = tf as f32;
+                let norm = K1 * (1.0 - B + B * doc.len as f32 / avg_len);
+                *scores.entry(slot).or_default() += idf * tf * (K1 + 1.0) / (tf + norm);
+            }
+        }
+
+        let mut ranked: Vec<(u32, f32)> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        ranked.truncate(k);
+        ranked.into_iter().map(|(slot, score)| (self.docs[&slot].id.clone(), score)).collect()
+    }
+}
+
+/// Merge rankings by reciprocal rank fusion: each list adds `1 / (RRF_K + rank)`.
+/// Scores are scaled so an item ranked first everywhere scores 1.0.
+pub fn reciprocal_rank_fusion(rankings: &[Vec<(String, f32)>], k: usize) -> Vec<(String, f32)> {
+    let mut fused: HashMap<&str, f32> = HashMap::new();
+    for ranking in rankings {
+        for (rank, (id, _)) in ranking.iter().enumerate() {
+            *fused.entry(id.as_str()).or_default() += 1.0 / (RRF_K + rank as f32 + 1.0);
+        }
+    }
+    let best_possible = rankings.len().max(1) as f32 / (RRF_K + 1.0);
+    let mut ranked: Vec<(String, f32)> = fused.into_iter().map(|(id, s)| (id.to_string(), s / best_possible)).collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked.truncate(k);
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::entry::MemorySource;
+
+    fn entry(id: &str, content: &str) -> MemoryEntry {
+        let mut entry = MemoryEntry::new(content, "coder", MemorySource::Tool);
+        entry.id = id.to_string();
+        entry
+    }
+
+    #[test]
+    fn test_tokenize_keeps_identifiers() {
+        let tokens = tokenize("error[E0382]: see src/memory/vector.rs, call parseConfig() or load_index.");
+        for expected in ["e0382", "src/memory/vector.rs", "vector", "rs", "parseconfig", "parse", "config", "load_index", "load", "index"] {
+            assert!(tokens.contains(&expected.to_string()), "missing {}: {:?}", expected, tokens);
+        }
+        assert!(!tokens.iter().any(|t| t.ends_with('.')));
+    }
+
+    #[test]
+    fn test_bm25_ranks_exact_identifiers_and_filters() {
+        let mut tagged = entry("c", "Fixed E0382 by cloning before the move in HnswIndex::insert");
+        tagged.metadata.tags = vec!["borrowck".to_string()];
+        let entries = vec![
+            entry("a", "The borrow checker complains when a value is used after a move"),
+            entry("b", "Build failed in src/memory/vector.rs with error E0382"),
+            tagged,
+        ];
+        let mut index = Bm25Index::build(&entries);
+
+        let hits = index.search("E0382 vector.rs", 10, &IndexFilter::default());
+        assert_eq!(hits[0].0, "b");
+        assert_eq!(hits.len(), 2, "{:?}", hits);
+
+        let tags = vec!["borrowck".to_string()];
+        let tagged_only = index.search("E0382", 10, &IndexFilter { tags: &tags, ..Default::default() });
+        assert_eq!(tagged_only.iter().map(|h| h.0.as_str()).collect::<Vec<_>>(), vec!["c"]);
+
+        assert!(index.remove("b"));
+        assert_eq!(index.search("vector.rs", 10, &IndexFilter::default()).len(), 0);
+        assert_eq!(index.len(), 2);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("memory.bm25");
+        Bm25Index::write(&path, &index.encode().unwrap()).unwrap();
+        let loaded = Bm25Index::load(&path).unwrap().unwrap();
+        assert!(loaded.covers_ids(["a", "c"]));
+        assert!(!loaded.covers_ids(["a", "b", "c"]));
+        assert_eq!(loaded.search("E0382", 10, &IndexFilter::default()), index.search("E0382", 10, &IndexFilter::default()));
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion() {
+        let semantic = vec![("a".to_string(), 0.9), ("b".to_string(), 0.8)];
+        let lexical = vec![("b".to_string(), 7.0), ("c".to_string(), 3.0)];
+        let fused = reciprocal_rank_fusion(&[semantic, lexical], 3);
+        assert_eq!(fused[0].0, "b", "found by both rankings");
+        assert_eq!(fused.len(), 3);
+        assert!(fused[0].1 <= 1.0 && fused[0].1 > fused[1].1);
+    }
+}
@@ -10,6 +10,11 @@ pub mod manager;
 pub mod indexer;
 pub mod history;
 pub mod compactor;
+pub mod hnsw;
+pub mod lexical;
+pub mod persistence;
+pub mod cold;
+pub mod scoring;
 
 pub use vector::{VectorMemory, LocalVectorMemory, RemoteVectorMemory};
 pub use episodic::EpisodicMemory;
@@ -17,10 +22,76 @@ pub use entry::MemoryEntry;
 pub use manager::MemoryManager;
 pub use indexer::CodebaseIndexer;
 pub use history::{HistoryManager, HistoryEntry};
-pub use compactor::ContextCompactor;
+pub use compactor::{ContextCompactor, TraceCompaction};
+pub use hnsw::{EntryAttrs, ExternalVectors, HnswIndex, IndexFilter};
+pub use lexical::{reciprocal_rank_fusion, Bm25Index};
+pub use scoring::{RankingWeights, Reinforcement};
+pub use cold::ColdTier;
 
 use anyhow::Result;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// How `Memory::search_with` ranks entries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// Embedding similarity only
+    #[default]
+    Semantic,
+    /// BM25 over the entry text (exact identifiers, error codes, file names)
+    Lexical,
+    /// Both, merged by reciprocal rank fusion
+    Hybrid,
+}
+
+impl SearchMode {
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode.to_lowercase().as_str() {
+            "semantic" => Some(Self::Semantic),
+            "lexical" | "keyword" | "bm25" => Some(Self::Lexical),
+            "hybrid" => Some(Self::Hybrid),
+            _ => None,
+        }
+    }
+}
+
+/// Retrieval mode and filters for `Memory::search_with`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchOptions {
+    #[serde(default)]
+    pub mode: SearchMode,
+    #[serde(default)]
+    pub context: Option<String>,
+    #[serde(default)]
+    pub kind: Option<crate::orchestrator::Kind>,
+    /// Entries must carry every one of these tags
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Only entries created at or after this time
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    /// Only entries created at or before this time
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl SearchOptions {
+    pub fn new(mode: SearchMode) -> Self {
+        Self { mode, ..Default::default() }
+    }
+
+    pub fn filter(&self) -> IndexFilter<'_> {
+        IndexFilter {
+            context: self.context.as_deref(),
+            kind: self.kind.as_ref(),
+            tags: &self.tags,
+            since: self.since,
+            until: self.until,
+        }
+    }
+}
 
 /// Trait for memory systems that can store and retrieve entries
 #[async_trait]
@@ -30,6 +101,18 @@ pub trait Memory: Send + Sync {
     
     /// Search for relevant memories based on a query
     async fn search(&self, query: &str, top_k: usize, context: Option<&str>, kind: Option<crate::orchestrator::Kind>) -> Result<Vec<MemoryEntry>>;
+
+    /// Search with an explicit retrieval mode and tag/time filters.
+    /// Backends without a lexical index fall back to filtered semantic search.
+    async fn search_with(&self, query: &str, top_k: usize, options: &SearchOptions) -> Result<Vec<MemoryEntry>> {
+        let filter = options.filter();
+        let filtered = !options.tags.is_empty() || options.since.is_some() || options.until.is_some();
+        let fetch = if filtered { top_k.saturating_mul(4) } else { top_k };
+        let mut entries = self.search(query, fetch, options.context.as_deref(), options.kind.clone()).await?;
+        entries.retain(|e| filter.matches_entry(e));
+        entries.truncate(top_k);
+        Ok(entries)
+    }
     
     /// Get the N most recent memories
     async fn get_recent(&self, limit: usize) -> Result<Vec<MemoryEntry>>;
@@ -50,6 +133,12 @@ pub trait Memory: Send + Sync {
     /// Remove specific memories by ID
     async fn prune(&self, ids: Vec<String>) -> Result<()>;
 
+    /// Re-estimate the importance of the given memories from feedback.
+    /// Returns how many were found; backends without scoring ignore it.
+    async fn reinforce(&self, _ids: &[String], _feedback: Reinforcement) -> Result<usize> {
+        Ok(0)
+    }
+
     /// Clear transient caches to free up RAM
     #[allow(dead_code)]
     async fn clear_cache(&self) -> Result<()>;
@@ -0,0 +1,462 @@
+//! Crash-Safe Memory Persistence
+//!
+//! Tier files are snapshots: a fixed header (magic, format version, tier,
+//! payload length and CRC-32) followed by the payload. Snapshots are written
+//! to a temp file, fsynced and renamed over the previous one, so a crash
+//! leaves either the old or the new snapshot intact, never a torn one.
+//!
+//! Between snapshots every `store` and `prune` is appended to a write-ahead
+//! log (`<memory>.wal`) and replayed on load. Each record is length-prefixed
+//! and checksummed; a tail torn by a crash is dropped on replay.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use super::entry::{MemoryMetadata, MemorySource};
+use super::MemoryEntry;
+use crate::orchestrator::Kind;
+
+/// Bumped whenever the snapshot, log or entry layout changes.
+/// v2: the COLD payload is the memory-mappable layout of `cold`.
+pub const FORMAT_VERSION: u16 = 2;
+const HOT_COMPRESSION_LEVEL: i32 = 3;
+const SNAPSHOT_MAGIC: &[u8; 8] = b"AGMEMSNP";
+const WAL_MAGIC: &[u8; 8] = b"AGMEMWAL";
+/// magic, version, tier, payload length, CRC-32
+pub const SNAPSHOT_HEADER_LEN: usize = 8 + 2 + 2 + 8 + 4;
+const WAL_HEADER_LEN: u64 = 8 + 2;
+/// length, CRC-32
+const RECORD_HEADER_LEN: usize = 4 + 4;
+
+/// Which tier a snapshot holds, so the files cannot be swapped unnoticed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tier {
+    /// zstd-compressed bincode
+    Hot = 1,
+    /// Plain bincode, read through a memory map
+    Cold = 2,
+}
+
+/// Payload of a snapshot file
+#[derive(Debug, PartialEq, Eq)]
+pub enum Snapshot<'a> {
+    /// Verified payload following a header
+    Current { version: u16, payload: &'a [u8] },
+    /// A file written before snapshots had headers; the whole file is payload
+    Legacy(&'a [u8]),
+}
+
+/// Check the header of snapshot `bytes` and return its payload
+pub fn parse_snapshot(bytes: &[u8], tier: Tier) -> Result<Snapshot<'_>> {
+    if !bytes.starts_with(SNAPSHOT_MAGIC) {
+        return Ok(Snapshot::Legacy(bytes));
+    }
+    if bytes.len() < SNAPSHOT_HEADER_LEN {
+        bail!("Snapshot header truncated ({} bytes)", bytes.len());
+    }
+    let version = u16::from_le_bytes(bytes[8..10].try_into()?);
+    if version > FORMAT_VERSION {
+        bail!("Snapshot format v{} is newer than supported v{}", version, FORMAT_VERSION);
+    }
+    let found_tier = u16::from_le_bytes(bytes[10..12].try_into()?);
+    if found_tier != tier as u16 {
+        bail!("Snapshot holds tier {} instead of {:?}", found_tier, tier);
+    }
+    let len = u64::from_le_bytes(bytes[12..20].try_into()?) as usize;
+    let checksum = u32::from_le_bytes(bytes[20..24].try_into()?);
+    let payload = &bytes[SNAPSHOT_HEADER_LEN..];
+    if payload.len() != len {
+        bail!("Snapshot payload is {} bytes, header says {}", payload.len(), len);
+    }
+    if crc32fast::hash(payload) != checksum {
+        bail!("Snapshot checksum mismatch");
+    }
+    Ok(Snapshot::Current { version, payload })
+}
+
+/// Serialize a tier's entries into a snapshot payload
+pub fn encode_entries(entries: &[MemoryEntry], tier: Tier) -> Result<Vec<u8>> {
+    let raw = bincode::serialize(entries)?;
+    Ok(match tier {
+        Tier::Hot => zstd::encode_all(&raw[..], HOT_COMPRESSION_LEVEL)?,
+        Tier::Cold => raw,
+    })
+}
+
+/// Entries of a HOT snapshot, or of a COLD one from before v2. Files from
+/// before snapshot headers are read with the entry layout of that time.
+pub fn decode_entries(snapshot: Snapshot<'_>, tier: Tier) -> Result<Vec<MemoryEntry>> {
+    let (payload, legacy) = match snapshot {
+        Snapshot::Current { version, .. } if tier == Tier::Cold && version >= 2 => {
+            bail!("COLD snapshot v{} is memory-mapped, not a serialized list", version)
+        }
+        Snapshot::Current { payload, .. } => (payload, false),
+        Snapshot::Legacy(payload) => (payload, true),
+    };
+    let raw: Cow<'_, [u8]> = match tier {
+        Tier::Hot => Cow::Owned(zstd::decode_all(payload).context("Failed to decompress HOT snapshot")?),
+        Tier::Cold => Cow::Borrowed(payload),
+    };
+    if legacy {
+        if let Ok(entries) = bincode::deserialize::<Vec<LegacyEntry>>(&raw) {
+            return Ok(entries.into_iter().map(MemoryEntry::from).collect());
+        }
+    }
+    Ok(bincode::deserialize(&raw)?)
+}
+
+/// Entry layout of tier files written before snapshot headers: no
+/// `last_accessed`, and `similarity` (never set on stored entries) skipped
+#[derive(Serialize, Deserialize)]
+struct LegacyEntry {
+    id: String,
+    query: Option<String>,
+    content: String,
+    metadata: LegacyMetadata,
+    timestamp: DateTime<Utc>,
+    embedding: Option<Vec<f32>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LegacyMetadata {
+    agent: String,
+    context: String,
+    kind: Kind,
+    described_entity: Option<String>,
+    grounding_holon: Option<String>,
+    viewpoint: Option<String>,
+    source: MemorySource,
+    importance: f32,
+    access_count: u32,
+    tags: Vec<String>,
+}
+
+impl From<LegacyEntry> for MemoryEntry {
+    fn from(old: LegacyEntry) -> Self {
+        let meta = old.metadata;
+        Self {
+            id: old.id,
+            query: old.query,
+            content: old.content,
+            metadata: MemoryMetadata {
+                agent: meta.agent,
+                context: meta.context,
+                kind: meta.kind,
+                described_entity: meta.described_entity,
+                grounding_holon: meta.grounding_holon,
+                viewpoint: meta.viewpoint,
+                source: meta.source,
+                importance: meta.importance,
+                access_count: meta.access_count,
+                tags: meta.tags,
+                last_accessed: None,
+            },
+            timestamp: old.timestamp,
+            embedding: old.embedding,
+            similarity: None,
+        }
+    }
+}
+
+fn snapshot_header(tier: Tier, len: u64, checksum: u32) -> [u8; SNAPSHOT_HEADER_LEN] {
+    let mut header = [0u8; SNAPSHOT_HEADER_LEN];
+    header[..8].copy_from_slice(SNAPSHOT_MAGIC);
+    header[8..10].copy_from_slice(&FORMAT_VERSION.to_le_bytes());
+    header[10..12].copy_from_slice(&(tier as u16).to_le_bytes());
+    header[12..20].copy_from_slice(&len.to_le_bytes());
+    header[20..24].copy_from_slice(&checksum.to_le_bytes());
+    header
+}
+
+/// Write `payload` with a snapshot header, atomically
+pub fn write_snapshot(path: &Path, tier: Tier, payload: &[u8]) -> Result<()> {
+    write_atomic(path, &[&snapshot_header(tier, payload.len() as u64, crc32fast::hash(payload)), payload])
+}
+
+/// Like `write_snapshot`, with the payload streamed by `write_payload` so it
+/// never has to be held in memory
+pub fn write_snapshot_with(path: &Path, tier: Tier, write_payload: impl FnOnce(&mut dyn Write) -> Result<()>) -> Result<()> {
+    let tmp = sibling(path, "tmp");
+    {
+        let file = File::create(&tmp).with_context(|| format!("Failed to create {:?}", tmp))?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(&[0u8; SNAPSHOT_HEADER_LEN])?;
+        let mut payload = ChecksumWriter { inner: &mut writer, hasher: crc32fast::Hasher::new(), len: 0 };
+        write_payload(&mut payload)?;
+        let (len, checksum) = (payload.len, payload.hasher.finalize());
+
+        let mut file = writer.into_inner().map_err(|e| e.into_error())?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&snapshot_header(tier, len, checksum))?;
+        file.sync_all()?;
+    }
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to replace {:?}", path))?;
+    sync_dir(path);
+    Ok(())
+}
+
+/// Counts and checksums what passes through
+struct ChecksumWriter<'a, W: Write> {
+    inner: &'a mut W,
+    hasher: crc32fast::Hasher,
+    len: u64,
+}
+
+impl<W: Write> Write for ChecksumWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        self.len += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Replace `path` with `parts`: temp file, fsync, rename, fsync the directory
+pub fn write_atomic(path: &Path, parts: &[&[u8]]) -> Result<()> {
+    let tmp = sibling(path, "tmp");
+    {
+        let file = File::create(&tmp).with_context(|| format!("Failed to create {:?}", tmp))?;
+        let mut writer = BufWriter::new(file);
+        for part in parts {
+            writer.write_all(part)?;
+        }
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    }
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to replace {:?}", path))?;
+    sync_dir(path);
+    Ok(())
+}
+
+/// `<path>.<suffix>`, keeping the original extension
+pub fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Make a rename durable. Directories cannot be opened for syncing on every
+/// platform, so this is best effort.
+fn sync_dir(path: &Path) {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+}
+
+/// A change to the HOT tier not yet covered by a snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WalRecord {
+    Store(Box<MemoryEntry>),
+    Prune(Vec<String>),
+}
+
+/// Append-only log of changes since the last snapshot
+pub struct WriteAheadLog {
+    path: PathBuf,
+    file: File,
+    len: u64,
+}
+
+impl WriteAheadLog {
+    /// Open (or create) the log at `path` and return the records it holds.
+    /// A torn or corrupt tail is cut off.
+    pub fn open(path: impl Into<PathBuf>) -> Result<(Self, Vec<WalRecord>)> {
+        let path = path.into();
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path)
+            .with_context(|| format!("Failed to open write-ahead log {:?}", path))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+
+        let mut log = Self { path, file, len: bytes.len() as u64 };
+        if bytes.is_empty() {
+            log.reset()?;
+            return Ok((log, Vec::new()));
+        }
+        if bytes.len() < WAL_HEADER_LEN as usize || !bytes.starts_with(WAL_MAGIC) {
+            bail!("{:?} is not a memory write-ahead log", log.path);
+        }
+        let version = u16::from_le_bytes(bytes[8..10].try_into()?);
+        if version > FORMAT_VERSION {
+            bail!("Write-ahead log format v{} is newer than supported v{}", version, FORMAT_VERSION);
+        }
+
+        let mut records = Vec::new();
+        let mut offset = WAL_HEADER_LEN as usize;
+        while let Some((record, next)) = Self::read_record(&bytes, offset) {
+            records.push(record);
+            offset = next;
+        }
+        if offset < bytes.len() {
+            warn!("Dropping {} bytes of torn write-ahead log tail in {:?}", bytes.len() - offset, log.path);
+            log.file.set_len(offset as u64)?;
+            log.file.sync_data()?;
+            log.len = offset as u64;
+        }
+        log.file.seek(SeekFrom::End(0))?;
+        Ok((log, records))
+    }
+
+    fn read_record(bytes: &[u8], offset: usize) -> Option<(WalRecord, usize)> {
+        let header = bytes.get(offset..offset + RECORD_HEADER_LEN)?;
+        let len = u32::from_le_bytes(header[..4].try_into().ok()?) as usize;
+        let checksum = u32::from_le_bytes(header[4..].try_into().ok()?);
+        let start = offset + RECORD_HEADER_LEN;
+        let payload = bytes.get(start..start + len)?;
+        if crc32fast::hash(payload) != checksum {
+            return None;
+        }
+        let record = bincode::deserialize(payload).ok()?;
+        Some((record, start + len))
+    }
+
+    /// Frame a record for `append_encoded`; can be done before taking the log
+    pub fn encode(record: &WalRecord) -> Result<Vec<u8>> {
+        let payload = bincode::serialize(record)?;
+        let mut frame = Vec::with_capacity(RECORD_HEADER_LEN + payload.len());
+        frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        frame.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+        frame.extend_from_slice(&payload);
+        Ok(frame)
+    }
+
+    /// Append an encoded record and wait until it is on disk
+    pub fn append_encoded(&mut self, frame: &[u8]) -> Result<()> {
+        self.file.write_all(frame)?;
+        self.file.sync_data()?;
+        self.len += frame.len() as u64;
+        Ok(())
+    }
+
+    pub fn append(&mut self, record: &WalRecord) -> Result<()> {
+        self.append_encoded(&Self::encode(record)?)
+    }
+
+    /// Bytes in the log, header included
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Whether the log holds no records
+    pub fn is_empty(&self) -> bool {
+        self.len <= WAL_HEADER_LEN
+    }
+
+    /// Empty the log once a snapshot covers everything in it
+    pub fn reset(&mut self) -> Result<()> {
+        self.file.set_len(0)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(WAL_MAGIC)?;
+        self.file.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        self.file.sync_data()?;
+        self.len = WAL_HEADER_LEN;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::entry::MemorySource;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_snapshot_roundtrip_and_corruption() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("memory.cold");
+        write_snapshot(&path, Tier::Cold, b"payload")?;
+        assert!(!sibling(&path, "tmp").exists());
+
+        let mut bytes = std::fs::read(&path)?;
+        assert_eq!(parse_snapshot(&bytes, Tier::Cold)?, Snapshot::Current { version: FORMAT_VERSION, payload: b"payload" });
+        assert!(parse_snapshot(&bytes, Tier::Hot).is_err(), "tier is checked");
+        assert_eq!(parse_snapshot(b"old bincode", Tier::Cold)?, Snapshot::Legacy(b"old bincode"));
+
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        assert!(parse_snapshot(&bytes, Tier::Cold).is_err(), "flipped byte is caught");
+        assert!(parse_snapshot(&bytes[..last], Tier::Cold).is_err(), "truncation is caught");
+
+        write_snapshot_with(&path, Tier::Hot, |w| Ok(w.write_all(b"streamed")?))?;
+        let bytes = std::fs::read(&path)?;
+        assert_eq!(parse_snapshot(&bytes, Tier::Hot)?, Snapshot::Current { version: FORMAT_VERSION, payload: b"streamed" });
+        Ok(())
+    }
+
+    #[test]
+    fn test_entries_roundtrip_and_legacy_layout() -> Result<()> {
+        let mut entry = MemoryEntry::new("remember this", "coder", MemorySource::User);
+        entry.embedding = Some(vec![0.6, 0.8]);
+        let payload = encode_entries(&[entry.clone()], Tier::Hot)?;
+        let decoded = decode_entries(Snapshot::Current { version: FORMAT_VERSION, payload: &payload }, Tier::Hot)?;
+        assert_eq!(decoded[0].id, entry.id);
+
+        // An old COLD file: plain bincode of the previous layout
+        let meta = entry.metadata.clone();
+        let old = LegacyEntry {
+            id: entry.id.clone(),
+            query: None,
+            content: entry.content.clone(),
+            metadata: LegacyMetadata {
+                agent: meta.agent, context: meta.context, kind: meta.kind, described_entity: None,
+                grounding_holon: None, viewpoint: None, source: meta.source, importance: 0.9,
+                access_count: 7, tags: vec!["legacy".to_string()],
+            },
+            timestamp: entry.timestamp,
+            embedding: entry.embedding.clone(),
+        };
+        let bytes = bincode::serialize(&vec![old])?;
+        let decoded = decode_entries(parse_snapshot(&bytes, Tier::Cold)?, Tier::Cold)?;
+        assert_eq!(decoded[0].metadata.access_count, 7);
+        assert_eq!(decoded[0].metadata.tags, vec!["legacy".to_string()]);
+        assert_eq!(decoded[0].embedding, Some(vec![0.6, 0.8]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_wal_replays_and_drops_torn_tail() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("memory.wal");
+        let entry = MemoryEntry::new("remember this", "coder", MemorySource::User);
+        {
+            let (mut wal, records) = WriteAheadLog::open(&path)?;
+            assert!(records.is_empty() && wal.is_empty());
+            wal.append(&WalRecord::Store(Box::new(entry.clone())))?;
+            wal.append(&WalRecord::Prune(vec!["gone".to_string()]))?;
+        }
+
+        // A crash in the middle of the next append
+        let frame = WriteAheadLog::encode(&WalRecord::Store(Box::new(entry.clone())))?;
+        let mut file = OpenOptions::new().append(true).open(&path)?;
+        file.write_all(&frame[..frame.len() / 2])?;
+        drop(file);
+
+        let (mut wal, records) = WriteAheadLog::open(&path)?;
+        assert_eq!(records.len(), 2);
+        assert!(matches!(&records[0], WalRecord::Store(e) if e.id == entry.id));
+        assert!(matches!(&records[1], WalRecord::Prune(ids) if ids == &["gone".to_string()]));
+
+        // Appends continue after the last good record
+        wal.append(&WalRecord::Prune(Vec::new()))?;
+        drop(wal);
+        assert_eq!(WriteAheadLog::open(&path)?.1.len(), 3);
+
+        let (mut wal, _) = WriteAheadLog::open(&path)?;
+        wal.reset()?;
+        assert!(WriteAheadLog::open(&path)?.1.is_empty());
+        Ok(())
+    }
+}
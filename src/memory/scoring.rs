@@ -0,0 +1,162 @@
+//! Memory Ranking & Reinforcement
+//!
+//! Search results are ranked by a blend of relevance, recency (exponential
+//! decay since the last access) and importance. Importance starts at the
+//! value an entry was stored with and is re-estimated from feedback: user
+//! ratings and PAI learning signals, plus the outcome of turns that used the
+//! memory. Access statistics decide which tier an entry lives in.
+
+use chrono::{DateTime, Utc};
+use pai_core::learning::{Signal, SignalType};
+use serde::{Deserialize, Serialize};
+
+use super::MemoryEntry;
+
+/// Weights of the ranking blend; they should sum to 1.0
+#[derive(Debug, Clone, Copy)]
+pub struct RankingWeights {
+    pub similarity: f32,
+    pub recency: f32,
+    pub importance: f32,
+    /// Days after which recency has decayed to one half
+    pub half_life_days: f32,
+}
+
+impl Default for RankingWeights {
+    fn default() -> Self {
+        Self { similarity: 0.7, recency: 0.15, importance: 0.15, half_life_days: 30.0 }
+    }
+}
+
+impl RankingWeights {
+    /// 1.0 for an entry used just now, halving every `half_life_days`
+    pub fn recency(&self, entry: &MemoryEntry, now: DateTime<Utc>) -> f32 {
+        let last_used = entry.metadata.last_accessed.unwrap_or(entry.timestamp).max(entry.timestamp);
+        let age_days = (now - last_used).num_seconds().max(0) as f32 / 86_400.0;
+        0.5f32.powf(age_days / self.half_life_days.max(f32::EPSILON))
+    }
+
+    /// Blended rank of an entry whose relevance to the query is `similarity`
+    pub fn score(&self, similarity: f32, entry: &MemoryEntry, now: DateTime<Utc>) -> f32 {
+        self.similarity * similarity
+            + self.recency * self.recency(entry, now)
+            + self.importance * entry.metadata.importance.clamp(0.0, 1.0)
+    }
+}
+
+/// Minimum accesses for an entry to stay in (or return to) the HOT tier
+pub const HOT_ACCESS_COUNT: u32 = 5;
+/// Importance that keeps an entry HOT regardless of use
+pub const HOT_IMPORTANCE: f32 = 0.8;
+
+/// Whether an entry belongs in the HOT tier: important, or used often and recently
+pub fn stays_hot(entry: &MemoryEntry, now: DateTime<Utc>) -> bool {
+    entry.metadata.importance > HOT_IMPORTANCE
+        || (entry.metadata.access_count > HOT_ACCESS_COUNT && RankingWeights::default().recency(entry, now) >= 0.5)
+}
+
+/// Count a retrieval against the stored entry
+pub fn record_access(entry: &mut MemoryEntry, now: DateTime<Utc>) {
+    entry.metadata.access_count = entry.metadata.access_count.saturating_add(1);
+    entry.metadata.last_accessed = Some(now);
+}
+
+/// Feedback about memories that were used
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reinforcement {
+    /// Explicit rating on a 0-10 scale
+    Rating(u8),
+    /// The work that used the memory failed
+    Failure,
+    /// The work had to loop back to an earlier phase
+    Loopback,
+    /// Something about the work looked unusual
+    Anomaly,
+    /// A turn (or its tool calls) that used the memory succeeded or not
+    Outcome(bool),
+}
+
+impl From<&SignalType> for Reinforcement {
+    fn from(signal: &SignalType) -> Self {
+        match signal {
+            SignalType::Failure => Self::Failure,
+            SignalType::Loopback => Self::Loopback,
+            SignalType::Rating(r) => Self::Rating(*r),
+            SignalType::Anomaly => Self::Anomaly,
+        }
+    }
+}
+
+impl From<&Signal> for Reinforcement {
+    fn from(signal: &Signal) -> Self {
+        Self::from(&signal.signal_type)
+    }
+}
+
+impl Reinforcement {
+    /// The importance this feedback points to, and how far to move towards it
+    fn target_and_rate(&self) -> (f32, f32) {
+        match self {
+            Self::Rating(r) => ((*r).min(10) as f32 / 10.0, 0.5),
+            Self::Failure => (0.2, 0.3),
+            Self::Loopback => (0.35, 0.15),
+            Self::Anomaly => (0.3, 0.1),
+            Self::Outcome(true) => (0.8, 0.1),
+            Self::Outcome(false) => (0.3, 0.1),
+        }
+    }
+
+    /// Re-estimate `entry`'s importance as a moving average towards the feedback
+    pub fn apply(&self, entry: &mut MemoryEntry) {
+        let (target, rate) = self.target_and_rate();
+        let importance = entry.metadata.importance;
+        entry.metadata.importance = (importance + rate * (target - importance)).clamp(0.0, 1.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::entry::MemorySource;
+    use chrono::Duration;
+
+    #[test]
+    fn test_ranking_prefers_recent_and_important() {
+        let now = Utc::now();
+        let weights = RankingWeights::default();
+        let fresh = MemoryEntry::new("fresh", "a", MemorySource::User);
+        let mut stale = MemoryEntry::new("stale", "a", MemorySource::User);
+        stale.timestamp = now - Duration::days(60);
+        assert!((weights.recency(&stale, now) - 0.25).abs() < 0.01);
+        assert!(weights.score(0.8, &fresh, now) > weights.score(0.8, &stale, now));
+
+        // Using a memory again refreshes it
+        record_access(&mut stale, now);
+        assert_eq!(stale.metadata.access_count, 1);
+        assert!(weights.recency(&stale, now) > 0.99);
+
+        let important = MemoryEntry::new("important", "a", MemorySource::User).with_importance(1.0);
+        assert!(weights.score(0.7, &important, now) > weights.score(0.75, &fresh, now));
+    }
+
+    #[test]
+    fn test_reinforcement_moves_importance() {
+        let mut entry = MemoryEntry::new("x", "a", MemorySource::User);
+        Reinforcement::Rating(10).apply(&mut entry);
+        assert!((entry.metadata.importance - 0.75).abs() < 1e-6);
+        Reinforcement::from(&SignalType::Failure).apply(&mut entry);
+        assert!(entry.metadata.importance < 0.75);
+        for _ in 0..50 {
+            Reinforcement::Outcome(true).apply(&mut entry);
+        }
+        assert!(entry.metadata.importance <= 0.8 + 1e-6);
+        assert!(!stays_hot(&entry, Utc::now()));
+
+        entry.metadata.access_count = HOT_ACCESS_COUNT + 1;
+        assert!(stays_hot(&entry, Utc::now()));
+        entry.metadata.last_accessed = Some(Utc::now() - Duration::days(90));
+        entry.timestamp = Utc::now() - Duration::days(90);
+        assert!(!stays_hot(&entry, Utc::now()), "frequent but long-unused memories cool down");
+    }
+}
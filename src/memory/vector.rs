@@ -3,23 +3,52 @@
 //! Tiers:
 //! 1. HOT: Active in RAM (RwLock<Vec>) - High speed, frequent access.
 //! 2. COLD: Memory-Mapped (mmap) - Infinite lifespan, zero-RAM overhead until touched.
+//!    Embeddings are read from the mapped matrix; records are decoded only
+//!    when returned (see `cold`).
 //! 3. COMPRESSED: Persisted Zstd on disk.
+//!
+//! Both tier files are checksummed snapshots replaced atomically; `store` and
+//! `prune` go to a write-ahead log (`<path>.wal`) in between, which is replayed
+//! on load and compacted into a new snapshot once it grows (see `persistence`).
+//!
+//! Search goes through an HNSW index over both tiers (`<path>.hnsw`, next to
+//! the `.cold` file) instead of scanning every entry, plus a BM25 index
+//! (`<path>.bm25`) for lexical and hybrid retrieval. COLD nodes of the HNSW
+//! index are scored from the mapped matrix rather than a copy of their
+//! embedding, and both indexes load without decoding COLD records.
+//!
+//! Hits are reranked by relevance, recency and importance (see `scoring`).
+//! Retrievals and feedback are written back to the stored entries, so the
+//! HOT/COLD partition in `consolidate` follows actual use.
 
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use fastembed::{TextEmbedding, InitOptions, EmbeddingModel};
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, OwnedMutexGuard, RwLock};
 use tracing::{info, debug, error};
 use reqwest::Client;
 use serde_json::json;
-use std::fs::{File, OpenOptions};
-use std::io::{BufReader, BufWriter, Read, Write};
-use rayon::prelude::*;
-use memmap2::Mmap;
 
-use super::{Memory, MemoryEntry};
+use super::cold::ColdTier;
+use super::hnsw::{EntryAttrs, ExternalVectors, HnswIndex, DEFAULT_EF_SEARCH};
+use super::lexical::{reciprocal_rank_fusion, Bm25Index};
+use super::persistence::{decode_entries, encode_entries, parse_snapshot, write_snapshot, Tier, WalRecord, WriteAheadLog};
+use super::scoring::{record_access, stays_hot, RankingWeights, Reinforcement, HOT_ACCESS_COUNT, HOT_IMPORTANCE};
+use super::{Memory, MemoryEntry, SearchMode, SearchOptions};
+
+/// Candidates taken from each ranking before hybrid fusion, per requested result
+const HYBRID_POOL_FACTOR: usize = 4;
+const MIN_HYBRID_POOL: usize = 20;
+/// Candidates reranked by recency and importance, per requested result
+const RERANK_POOL_FACTOR: usize = 3;
+const MIN_RERANK_POOL: usize = 10;
+/// HOT entries before consolidation starts moving unused ones to COLD
+const CONSOLIDATE_THRESHOLD: usize = 50;
+/// Write-ahead log size that triggers a new HOT snapshot
+const WAL_CHECKPOINT_BYTES: u64 = 8 * 1024 * 1024;
 
 pub enum VectorMemory {
     Local(LocalVectorMemory),
@@ -60,6 +89,13 @@ impl Memory for VectorMemory {
         }
     }
 
+    async fn search_with(&self, query: &str, top_k: usize, options: &SearchOptions) -> Result<Vec<MemoryEntry>> {
+        match self {
+            Self::Local(m) => m.search_with(query, top_k, options).await,
+            Self::Remote(m) => m.search_with(query, top_k, options).await,
+        }
+    }
+
     async fn get_recent(&self, limit: usize) -> Result<Vec<MemoryEntry>> {
         match self {
             Self::Local(m) => m.get_recent(limit).await,
@@ -102,6 +138,13 @@ impl Memory for VectorMemory {
         }
     }
 
+    async fn reinforce(&self, ids: &[String], feedback: Reinforcement) -> Result<usize> {
+        match self {
+            Self::Local(m) => m.reinforce(ids, feedback).await,
+            Self::Remote(m) => m.reinforce(ids, feedback).await,
+        }
+    }
+
     async fn clear_cache(&self) -> Result<()> {
         match self {
             Self::Local(m) => m.clear_cache().await,
@@ -127,80 +170,324 @@ impl Memory for VectorMemory {
 pub struct LocalVectorMemory {
     path: PathBuf,
     cold_path: PathBuf,
+    index_path: PathBuf,
+    lexical_path: PathBuf,
     embedder: Arc<RwLock<Option<TextEmbedding>>>,
     /// HOT Memory: All entries currently in RAM
     hot_entries: Arc<RwLock<Vec<MemoryEntry>>>,
     /// COLD Memory: Memory-mapped pool
-    cold_cache: Arc<RwLock<Option<Vec<MemoryEntry>>>>,
+    cold_cache: Arc<RwLock<Option<ColdTier>>>,
+    /// ANN and BM25 indexes over both tiers, loaded (or rebuilt) on first use.
+    /// Lock order: hot, then cold, then index.
+    index: Arc<RwLock<Option<SearchIndexes>>>,
+    /// Pruned ids replayed from the log that still have to be dropped from
+    /// the COLD file once it is opened
+    replayed_prunes: Mutex<HashSet<String>>,
+    /// Stores and prunes since the last HOT snapshot.
+    /// Lock order: log, then hot.
+    wal: Arc<Mutex<WriteAheadLog>>,
+    ranking: RankingWeights,
+}
+
+struct SearchIndexes {
+    ann: HnswIndex,
+    lexical: Bm25Index,
+}
+
+impl SearchIndexes {
+    fn insert(&mut self, entry: &MemoryEntry, cold: &dyn ExternalVectors) {
+        self.ann.insert_entry(entry, cold);
+        self.lexical.insert_entry(entry);
+    }
+
+    fn remove(&mut self, id: &str, cold: &dyn ExternalVectors) {
+        self.ann.remove(id, cold);
+        self.lexical.remove(id);
+    }
+}
+
+/// Embeddings of the mapped COLD tier, or none while it is released
+fn cold_vectors(cold: &Option<ColdTier>) -> &dyn ExternalVectors {
+    match cold {
+        Some(tier) => tier,
+        None => &(),
+    }
 }
 
 impl LocalVectorMemory {
     pub fn new(path: PathBuf) -> Result<Self> {
         let cold_path = path.with_extension("cold");
+        let index_path = path.with_extension("hnsw");
+        let lexical_path = path.with_extension("bm25");
         let embedder = TextEmbedding::try_new(
             InitOptions::new(EmbeddingModel::AllMiniLML6V2)
         ).context("Failed to initialize embedding model")?;
 
-        let mut instance = Self {
+        let wal_path = path.with_extension("wal");
+        let (wal, replay) = match WriteAheadLog::open(&wal_path) {
+            Ok(opened) => opened,
+            Err(e) => {
+                error!("Memory Corruption Detected (WAL): {}", e);
+                Self::recover_corrupt(&wal_path);
+                WriteAheadLog::open(&wal_path)?
+            }
+        };
+        let (hot, replayed_prunes) = Self::load(&path, replay);
+
+        Ok(Self {
             path,
             cold_path,
+            index_path,
+            lexical_path,
             embedder: Arc::new(RwLock::new(Some(embedder))),
-            hot_entries: Arc::new(RwLock::new(Vec::new())),
+            hot_entries: Arc::new(RwLock::new(hot)),
             cold_cache: Arc::new(RwLock::new(None)),
-        };
+            index: Arc::new(RwLock::new(None)),
+            replayed_prunes: Mutex::new(replayed_prunes),
+            wal: Arc::new(Mutex::new(wal)),
+            ranking: RankingWeights::default(),
+        })
+    }
 
-        instance.load()?;
-        Ok(instance)
+    pub fn with_ranking(mut self, ranking: RankingWeights) -> Self {
+        self.ranking = ranking;
+        self
     }
 
-    fn load(&mut self) -> Result<()> {
-        if self.path.exists() {
-            let file = File::open(&self.path)?;
-            let decoder = match zstd::stream::read::Decoder::new(file) {
-                Ok(d) => d,
-                Err(e) => {
-                    error!("Memory Corruption Detected (Zstd init): {}", e);
-                    return self.recover_corrupt();
-                }
-            };
-            
-            match bincode::deserialize_from::<_, Vec<MemoryEntry>>(decoder) {
-                Ok(entries) => {
-                    info!("Loaded {} memories into HOT cache", entries.len());
-                    *self.hot_entries.blocking_write() = entries;
+    /// HOT entries: the last snapshot with the write-ahead log replayed on top.
+    /// Also returns the pruned ids, which may refer to COLD entries.
+    fn load(path: &Path, replay: Vec<WalRecord>) -> (Vec<MemoryEntry>, HashSet<String>) {
+        let mut entries = Vec::new();
+        if path.exists() {
+            let loaded = std::fs::read(path)
+                .map_err(anyhow::Error::from)
+                .and_then(|bytes| decode_entries(parse_snapshot(&bytes, Tier::Hot)?, Tier::Hot));
+            match loaded {
+                Ok(loaded) => {
+                    info!("Loaded {} memories into HOT cache", loaded.len());
+                    entries = loaded;
                 },
                 Err(e) => {
-                    error!("Memory Corruption Detected (Deserialize): {}", e);
-                    return self.recover_corrupt();
+                    error!("Memory Corruption Detected (HOT snapshot): {}", e);
+                    Self::recover_corrupt(path);
                 }
             }
         }
-        Ok(())
+
+        if !replay.is_empty() {
+            info!("Replaying {} write-ahead log records", replay.len());
+        }
+        let mut pruned = HashSet::new();
+        for record in replay {
+            match record {
+                WalRecord::Store(entry) => {
+                    entries.retain(|e| e.id != entry.id);
+                    entries.push(*entry);
+                }
+                WalRecord::Prune(ids) => {
+                    entries.retain(|e| !ids.contains(&e.id));
+                    pruned.extend(ids);
+                }
+            }
+        }
+        (entries, pruned)
     }
 
-    fn recover_corrupt(&self) -> Result<()> {
+    fn recover_corrupt(path: &Path) {
         let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
-        let corrupt_path = self.path.with_extension(format!("corrupt.{}", timestamp));
+        let corrupt_path = path.with_extension(format!("corrupt.{}", timestamp));
         
-        if let Err(e) = std::fs::rename(&self.path, &corrupt_path) {
+        if let Err(e) = std::fs::rename(path, &corrupt_path) {
             error!("Failed to rename corrupt memory file: {}", e);
         } else {
             error!("⚠️ Corrupt memory file moved to {:?}. Starting fresh.", corrupt_path);
         }
-        Ok(())
     }
 
     async fn ensure_cold_cache(&self) -> Result<()> {
+        if self.cold_cache.read().await.is_some() {
+            return Ok(());
+        }
+        // An interrupted consolidation can leave an entry in both tiers; HOT wins
+        let hot_ids: HashSet<String> = self.hot_entries.read().await.iter().map(|e| e.id.clone()).collect();
+
         let mut cache = self.cold_cache.write().await;
-        if cache.is_none() && self.cold_path.exists() {
-            debug!("Mmap: Mapping COLD memory into address space...");
-            let file = File::open(&self.cold_path)?;
-            let mmap = unsafe { Mmap::map(&file)? };
-            let entries: Vec<MemoryEntry> = bincode::deserialize(&mmap[..])?;
-            *cache = Some(entries);
-        } else if cache.is_none() {
-            *cache = Some(Vec::new());
+        if cache.is_some() {
+            return Ok(());
         }
+        debug!("Mmap: Mapping COLD memory into address space...");
+        let cold_path = self.cold_path.clone();
+        let mut tier = match tokio::task::spawn_blocking(move || ColdTier::open(&cold_path)).await? {
+            Ok(tier) => tier,
+            Err(e) => {
+                error!("Memory Corruption Detected (COLD snapshot): {}", e);
+                Self::recover_corrupt(&self.cold_path);
+                ColdTier::empty()
+            }
+        };
+        let superseded = hot_ids.iter().filter(|id| tier.remove(id)).count();
+        if superseded > 0 {
+            debug!("Dropped {} COLD entries superseded by HOT ones", superseded);
+        }
+        for id in self.replayed_prunes.lock().await.drain() {
+            tier.remove(&id);
+        }
+        *cache = Some(tier);
+        Ok(())
+    }
+
+    /// Durably log `record`. The returned guard keeps snapshots out until the
+    /// caller has applied the change.
+    async fn log(&self, record: &WalRecord) -> Result<OwnedMutexGuard<WriteAheadLog>> {
+        let frame = WriteAheadLog::encode(record)?;
+        let mut wal = self.wal.clone().lock_owned().await;
+        tokio::task::spawn_blocking(move || {
+            wal.append_encoded(&frame)?;
+            Ok(wal)
+        }).await?
+    }
+
+    /// Load the ANN and BM25 indexes, rebuilding whichever is missing or out
+    /// of sync with the tiers. The COLD tier is mapped too, since COLD nodes
+    /// are scored from it.
+    async fn ensure_index(&self) -> Result<()> {
+        self.ensure_cold_cache().await?;
+        if self.index.read().await.is_some() {
+            return Ok(());
+        }
+
+        let hot = self.hot_entries.read().await;
+        let cold_guard = self.cold_cache.read().await;
+        let cold = cold_guard.as_ref().context("COLD tier not loaded")?;
+        let mut index = self.index.write().await;
+        if index.is_some() {
+            return Ok(());
+        }
+
+        let embedded_ids = hot.iter().filter(|e| e.embedding.is_some()).map(|e| e.id.as_str()).chain(cold.embedded_ids());
+        let ann = match HnswIndex::load(&self.index_path) {
+            Ok(loaded) => loaded.filter(|i| i.covers_ids(embedded_ids) && i.matches_external(|id| cold.slot(id))),
+            Err(e) => {
+                error!("Failed to load ANN index: {}", e);
+                None
+            }
+        };
+        let all_ids = hot.iter().map(|e| e.id.as_str()).chain(cold.ids());
+        let lexical = match Bm25Index::load(&self.lexical_path) {
+            Ok(loaded) => loaded.filter(|i| i.covers_ids(all_ids)),
+            Err(e) => {
+                error!("Failed to load lexical index: {}", e);
+                None
+            }
+        };
+
+        let indexes = match (ann, lexical) {
+            (Some(ann), Some(lexical)) => SearchIndexes { ann, lexical },
+            (ann, lexical) => {
+                info!("Building search indexes over {} memories", hot.len() + cold.len());
+                let (build_ann, build_lexical) = (ann.is_none(), lexical.is_none());
+                let mut ann = ann.unwrap_or_else(|| HnswIndex::build(hot.iter()));
+                let mut lexical = lexical.unwrap_or_else(|| Bm25Index::build(hot.iter()));
+                // COLD records are decoded one at a time, in a single pass for both indexes
+                for slot in cold.live_slots() {
+                    let entry = match cold.get(slot) {
+                        Ok(entry) => entry,
+                        Err(e) => {
+                            error!("{}", e);
+                            continue;
+                        }
+                    };
+                    if build_lexical {
+                        lexical.insert_entry(&entry);
+                    }
+                    if build_ann {
+                        ann.insert_external(&entry.id, slot, EntryAttrs::from(&entry), cold);
+                    }
+                }
+                SearchIndexes { ann, lexical }
+            }
+        };
+        *index = Some(indexes);
+        Ok(())
+    }
+
+    /// Rewrite the COLD file from `tier` plus `extra` and map the result. On
+    /// failure the old tier is handed back unchanged.
+    async fn write_cold(&self, tier: ColdTier, extra: Vec<MemoryEntry>) -> (ColdTier, Result<()>) {
+        let cold_path = self.cold_path.clone();
+        let written = tokio::task::spawn_blocking(move || {
+            match tier.write_merged(&cold_path, &extra).and_then(|_| ColdTier::open(&cold_path)) {
+                Ok(reopened) => (reopened, Ok(())),
+                Err(e) => (tier, Err(e)),
+            }
+        }).await;
+        match written {
+            Ok(written) => written,
+            // The tier moved into the panicked task; fall back to what is on disk
+            Err(e) => (ColdTier::open(&self.cold_path).unwrap_or_else(|_| ColdTier::empty()), Err(e.into())),
+        }
+    }
+
+    /// Point the ANN index at the rows of a rewritten COLD tier. Entries that
+    /// left it read their embedding from HOT again.
+    async fn relocate_index(&self, hot: &[MemoryEntry], cold: &ColdTier) {
+        if let Some(index) = self.index.write().await.as_mut() {
+            index.ann.relocate(
+                |id| cold.slot(id),
+                |id| hot.iter().find(|e| e.id == id).and_then(|e| e.embedding.clone()),
+            );
+        }
+    }
+
+    /// Write back COLD entries updated or removed in place
+    async fn flush_cold(&self) -> Result<()> {
+        let hot = self.hot_entries.read().await;
+        let mut cache = self.cold_cache.write().await;
+        if !cache.as_ref().is_some_and(ColdTier::is_dirty) {
+            return Ok(());
+        }
+        let tier = cache.take().context("COLD tier not loaded")?;
+        let (tier, written) = self.write_cold(tier, Vec::new()).await;
+        self.relocate_index(&hot, &tier).await;
+        *cache = Some(tier);
+        written
+    }
+
+    /// Drop the COLD cache (unmapping the file), writing it back first if it
+    /// was changed in place
+    async fn release_cold_cache(&self) -> Result<()> {
+        self.flush_cold().await?;
+        *self.cold_cache.write().await = None;
+        Ok(())
+    }
+
+    /// Apply `update` to the stored entries with the given ids in both tiers;
+    /// returns how many were found
+    async fn update_stored(&self, ids: &[String], mut update: impl FnMut(&mut MemoryEntry)) -> usize {
+        let mut updated = 0;
+        let mut hot = self.hot_entries.write().await;
+        for entry in hot.iter_mut().filter(|e| ids.contains(&e.id)) {
+            update(entry);
+            updated += 1;
+        }
+        let mut cold_guard = self.cold_cache.write().await;
+        if let Some(cold) = cold_guard.as_mut() {
+            updated += ids.iter().filter(|id| cold.update(id, &mut update)).count();
+        }
+        updated
+    }
+
+    /// Write the ANN and BM25 indexes next to the `.cold` file
+    async fn persist_index(&self) -> Result<()> {
+        let (ann, lexical) = match self.index.read().await.as_ref() {
+            Some(index) => (index.ann.encode()?, index.lexical.encode()?),
+            None => return Ok(()),
+        };
+        let (index_path, lexical_path) = (self.index_path.clone(), self.lexical_path.clone());
+        tokio::task::spawn_blocking(move || {
+            HnswIndex::write(&index_path, &ann)?;
+            Bm25Index::write(&lexical_path, &lexical)
+        }).await??;
         Ok(())
     }
 
@@ -219,10 +506,6 @@ impl LocalVectorMemory {
         let norm: f32 = vec.iter().map(|x| x * x).sum::<f32>().sqrt();
         if norm > 0.0 { for x in vec { *x /= norm; } }
     }
-
-    fn dot_product(a: &[f32], b: &[f32]) -> f32 {
-        a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
-    }
 }
 
 #[async_trait]
@@ -232,101 +515,210 @@ impl Memory for LocalVectorMemory {
             let embeddings = self.embed(&[entry.content.clone()]).await?;
             entry.embedding = Some(embeddings[0].clone());
         }
+        self.ensure_index().await?;
+        let wal = self.log(&WalRecord::Store(Box::new(entry.clone()))).await?;
+        let checkpoint = wal.len() > WAL_CHECKPOINT_BYTES;
         
         let mut hot = self.hot_entries.write().await;
         hot.retain(|e| e.id != entry.id);
+        let cold = self.cold_cache.read().await;
+        if let Some(index) = self.index.write().await.as_mut() {
+            index.insert(&entry, cold_vectors(&cold));
+        }
+        drop(cold);
         
         let id = entry.id.clone();
         hot.push(entry);
+        drop(hot);
+        drop(wal);
+
+        if checkpoint {
+            debug!("Write-ahead log reached {} bytes, taking a snapshot", WAL_CHECKPOINT_BYTES);
+            self.persist().await?;
+        }
         Ok(id)
     }
 
     async fn search(&self, query: &str, top_k: usize, context: Option<&str>, kind: Option<crate::orchestrator::Kind>) -> Result<Vec<MemoryEntry>> {
-        let query_embedding = self.embed(&[query.to_string()]).await?.into_iter().next().context("No embedding")?;
+        let options = SearchOptions { context: context.map(str::to_string), kind, ..SearchOptions::new(SearchMode::Semantic) };
+        self.search_with(query, top_k, &options).await
+    }
+
+    async fn search_with(&self, query: &str, top_k: usize, options: &SearchOptions) -> Result<Vec<MemoryEntry>> {
+        let query_embedding = match options.mode {
+            SearchMode::Lexical => None,
+            _ => Some(self.embed(&[query.to_string()]).await?.into_iter().next().context("No embedding")?),
+        };
+        self.ensure_index().await?;
         self.ensure_cold_cache().await?;
 
         let hot = self.hot_entries.read().await;
         let cold_guard = self.cold_cache.read().await;
-        let cold = cold_guard.as_ref().unwrap();
-
-        // Parallel Search over BOTH Tiers simultaneously via Rayon
-        let mut all_results: Vec<(f32, MemoryEntry)> = hot.par_iter()
-            .chain(cold.par_iter())
-            .filter(|e| {
-                let ctx_m = context.map_or(true, |c| e.metadata.context == c);
-                let kind_m = kind.as_ref().map_or(true, |k| &e.metadata.kind == k);
-                ctx_m && kind_m
-            })
-            .filter_map(|e| {
-                e.embedding.as_ref().map(|emb| (Self::dot_product(&query_embedding, emb), e.clone()))
+        let cold = cold_guard.as_ref().context("COLD tier not loaded")?;
+        let index_guard = self.index.read().await;
+        let index = index_guard.as_ref().context("Search indexes not loaded")?;
+
+        // Filters are applied inside the index walks; only the top-k hits are cloned
+        let filter = options.filter();
+        let candidates = (top_k * RERANK_POOL_FACTOR).max(MIN_RERANK_POOL);
+        let pool = match options.mode {
+            SearchMode::Hybrid => (top_k * HYBRID_POOL_FACTOR).max(MIN_HYBRID_POOL).max(candidates),
+            _ => candidates,
+        };
+        let semantic = match &query_embedding {
+            Some(embedding) => index.ann.search(embedding, pool, DEFAULT_EF_SEARCH, &filter, cold),
+            None => Vec::new(),
+        };
+        let hits = match options.mode {
+            SearchMode::Semantic => semantic,
+            SearchMode::Lexical => {
+                // BM25 is unbounded; report relevance relative to the best match
+                let lexical = index.lexical.search(query, candidates, &filter);
+                let best = lexical.first().map(|(_, s)| *s).unwrap_or(1.0).max(f32::EPSILON);
+                lexical.into_iter().map(|(id, s)| (id, s / best)).collect()
+            }
+            SearchMode::Hybrid => {
+                let lexical = index.lexical.search(query, pool, &filter);
+                reciprocal_rank_fusion(&[semantic, lexical], candidates)
+            }
+        };
+        let mut scores: HashMap<&str, f32> = hits.iter().map(|(id, score)| (id.as_str(), *score)).collect();
+
+        // Rerank the candidates; `similarity` keeps the relevance alone.
+        // COLD candidates are the only COLD records decoded.
+        let now = chrono::Utc::now();
+        let mut candidates: Vec<(f32, MemoryEntry)> = hot.iter()
+            .filter_map(|e| scores.remove(e.id.as_str()).map(|s| (s, e.clone())))
+            .collect();
+        candidates.extend(scores.into_iter().filter_map(|(id, s)| cold.get_by_id(id).map(|e| (s, e))));
+        let mut ranked: Vec<(f32, MemoryEntry)> = candidates.into_iter()
+            .map(|(s, mut e)| {
+                e.similarity = Some(s);
+                (self.ranking.score(s, &e, now), e)
             })
             .collect();
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+        ranked.truncate(top_k);
+        drop(index_guard);
+        drop(cold_guard);
+        drop(hot);
 
-        all_results.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
-        
-        let mut final_entries: Vec<MemoryEntry> = all_results.into_iter().take(top_k).map(|(s, mut e)| {
-            e.similarity = Some(s);
-            e.metadata.access_count += 1;
-            e
-        }).collect();
+        let mut final_entries: Vec<MemoryEntry> = ranked.into_iter().map(|(_, e)| e).collect();
+        let ids: Vec<String> = final_entries.iter().map(|e| e.id.clone()).collect();
+        self.update_stored(&ids, |e| record_access(e, now)).await;
+        for entry in &mut final_entries {
+            record_access(entry, now);
+        }
 
         Ok(final_entries)
     }
 
     async fn count(&self) -> Result<usize> { 
-        let hot = self.hot_entries.read().await.len();
         self.ensure_cold_cache().await?;
-        let cold = self.cold_cache.read().await.as_ref().unwrap().len();
+        let hot = self.hot_entries.read().await.len();
+        let cold = self.cold_cache.read().await.as_ref().map_or(0, ColdTier::len);
         Ok(hot + cold)
     }
     
     async fn persist(&self) -> Result<()> {
-        let hot = self.hot_entries.read().await;
+        // Holding the log keeps stores and prunes out until the snapshot covers them
+        let mut wal = self.wal.clone().lock_owned().await;
+        // Resetting the log forgets replayed prunes; apply them to the COLD file first
+        if !self.replayed_prunes.lock().await.is_empty() {
+            self.ensure_cold_cache().await?;
+        }
+        self.flush_cold().await?;
         let path = self.path.clone();
-        let hot_clone = hot.clone(); 
+        let hot_clone = self.hot_entries.read().await.clone();
 
         tokio::task::spawn_blocking(move || {
-            let file = File::create(path)?;
-            let writer = BufWriter::new(file);
-            let mut encoder = zstd::stream::write::Encoder::new(writer, 3)?;
-            bincode::serialize_into(&mut encoder, &hot_clone)?;
-            encoder.finish()?;
-            Ok::<(), anyhow::Error>(())
+            write_snapshot(&path, Tier::Hot, &encode_entries(&hot_clone, Tier::Hot)?)?;
+            wal.reset()
         }).await??;
         
-        Ok(())
+        self.persist_index().await
     }
 
     async fn consolidate(&self) -> Result<usize> {
+        self.ensure_cold_cache().await?;
+        let mut wal = self.wal.clone().lock_owned().await;
         let mut hot = self.hot_entries.write().await;
-        if hot.len() < 50 { return Ok(0); }
-
-        info!("🧠 Memory Metabolism: Moving cold experiences to mmap storage...");
+        let mut cold_guard = self.cold_cache.write().await;
+        let cold = cold_guard.as_mut().context("COLD tier not loaded")?;
+        let now = chrono::Utc::now();
 
-        let (stay_hot, to_cold): (Vec<_>, Vec<_>) = hot.drain(..).partition(|e| {
-            e.metadata.access_count > 5 || e.metadata.importance > 0.8
-        });
+        // COLD entries that became important or are in regular use again come
+        // back. The table stats rule out most entries without decoding them.
+        let maybe_hot: Vec<u32> = cold.live_slots()
+            .filter(|&slot| {
+                let stats = cold.stats(slot);
+                stats.importance > HOT_IMPORTANCE || stats.access_count > HOT_ACCESS_COUNT
+            })
+            .collect();
+        let promoted: Vec<MemoryEntry> = maybe_hot.into_iter()
+            .filter_map(|slot| cold.get(slot).ok())
+            .filter(|e| stays_hot(e, now))
+            .collect();
+        for entry in &promoted {
+            cold.remove(&entry.id);
+        }
+        let promoted_count = promoted.len();
+        // Until the new HOT snapshot lands, promoted entries only exist in the log
+        let promotions = promoted.iter()
+            .map(|e| WriteAheadLog::encode(&WalRecord::Store(Box::new(e.clone()))))
+            .collect::<Result<Vec<_>>>()?;
+        hot.extend(promoted);
 
+        let mut to_cold = Vec::new();
+        if hot.len() >= CONSOLIDATE_THRESHOLD {
+            info!("🧠 Memory Metabolism: Moving cold experiences to mmap storage...");
+            let (stay_hot, demoted): (Vec<_>, Vec<_>) = hot.drain(..).partition(|e| stays_hot(e, now));
+            *hot = stay_hot;
+            to_cold = demoted;
+        }
         let moved_count = to_cold.len();
-        *hot = stay_hot;
-
-        // Append to COLD binary file
-        self.ensure_cold_cache().await?;
-        let mut cold_guard = self.cold_cache.write().await;
-        let cold = cold_guard.as_mut().unwrap();
-        cold.extend(to_cold);
+        if promoted_count + moved_count == 0 {
+            return Ok(0);
+        }
 
-        // Persist COLD tier
-        let cold_clone = cold.clone();
-        let cold_path = self.cold_path.clone();
+        // Persist both tiers. A crash in between leaves demoted entries in both
+        // files (HOT wins on load) and promoted ones in the log.
+        let hot_clone = hot.clone();
+        let path = self.path.clone();
+        let logged = tokio::task::spawn_blocking(move || {
+            for frame in &promotions {
+                wal.append_encoded(frame)?;
+            }
+            Ok::<_, anyhow::Error>(wal)
+        }).await?;
+        let mut wal = match logged {
+            Ok(wal) => wal,
+            Err(e) => {
+                hot.extend(to_cold);
+                return Err(e);
+            }
+        };
+        let tier = cold_guard.take().context("COLD tier not loaded")?;
+        let (tier, written) = self.write_cold(tier, to_cold.clone()).await;
+        // Demoted nodes drop their vector for the new COLD rows; promoted ones take HOT's
+        self.relocate_index(&hot, &tier).await;
+        *cold_guard = Some(tier);
+        if let Err(e) = written {
+            hot.extend(to_cold);
+            return Err(e);
+        }
+        drop(cold_guard);
+        drop(hot);
         tokio::task::spawn_blocking(move || {
-            let file = OpenOptions::new().create(true).write(true).truncate(true).open(cold_path)?;
-            bincode::serialize_into(file, &cold_clone)?;
-            Ok::<(), anyhow::Error>(())
+            write_snapshot(&path, Tier::Hot, &encode_entries(&hot_clone, Tier::Hot)?)?;
+            wal.reset()
         }).await??;
 
-        info!("🧠 Consolidation complete: Moved {} memories to COLD tier.", moved_count);
-        Ok(moved_count)
+        // Entries keep their index nodes across tiers; save them alongside the new cold file
+        self.persist_index().await?;
+
+        info!("🧠 Consolidation complete: Moved {} memories to COLD tier, promoted {} back to HOT.", moved_count, promoted_count);
+        Ok(moved_count + promoted_count)
     }
 
     async fn get_cold_memories(&self, limit: usize) -> Result<Vec<MemoryEntry>> {
@@ -340,34 +732,62 @@ impl Memory for LocalVectorMemory {
     }
 
     async fn get_recent(&self, limit: usize) -> Result<Vec<MemoryEntry>> {
+        self.ensure_cold_cache().await?;
         let hot = self.hot_entries.read().await;
-        let mut recent: Vec<_> = hot.iter().cloned().collect();
         // HOT entries are appended, so last is newest.
-        recent.reverse();
+        let mut recent: Vec<_> = hot.iter().rev().take(limit).cloned().collect();
+        if let Some(cold) = self.cold_cache.read().await.as_ref() {
+            recent.extend(cold.recent(limit));
+        }
+        recent.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
         recent.truncate(limit);
         Ok(recent)
     }
 
     async fn prune(&self, ids: Vec<String>) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        self.ensure_cold_cache().await?;
+        let _wal = self.log(&WalRecord::Prune(ids.clone())).await?;
         let mut hot = self.hot_entries.write().await;
         hot.retain(|e| !ids.contains(&e.id));
+        // COLD removals stay in the overlay (and the log) until the next snapshot
+        let mut cold = self.cold_cache.write().await;
+        if let Some(tier) = cold.as_mut() {
+            for id in &ids {
+                tier.remove(id);
+            }
+        }
+        if let Some(index) = self.index.write().await.as_mut() {
+            for id in &ids {
+                index.remove(id, cold_vectors(&cold));
+            }
+        }
         Ok(())
     }
+
+    async fn reinforce(&self, ids: &[String], feedback: Reinforcement) -> Result<usize> {
+        self.ensure_cold_cache().await?;
+        let updated = self.update_stored(ids, |e| feedback.apply(e)).await;
+        debug!("Reinforced {} memories with {:?}", updated, feedback);
+        Ok(updated)
+    }
     
     async fn clear_cache(&self) -> Result<()> { 
-        *self.cold_cache.write().await = None;
-        Ok(()) 
+        self.release_cold_cache().await
     }
     
     async fn hibernate(&self) -> Result<()> {
         *self.embedder.write().await = None;
-        *self.cold_cache.write().await = None;
+        self.release_cold_cache().await?;
+        self.persist_index().await?;
+        *self.index.write().await = None;
         Ok(())
     }
     
     async fn wake(&self) -> Result<()> {
-        self.ensure_cold_cache().await?;
-        Ok(())
+        self.ensure_index().await
     }
 }
 
@@ -421,6 +841,159 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_ann_index_spans_tiers_and_persists() -> Result<()> {
+        std::env::set_var("AGENCY_USE_REMOTE_MEMORY", "0");
+        if std::env::var("ORT_DYLIB_PATH").is_err() && !std::path::Path::new("libonnxruntime.dylib").exists() {
+            return Ok(());
+        }
+
+        let dir = tempdir()?;
+        let path = dir.path().join("test.mem");
+        let memory = LocalVectorMemory::new(path.clone())?;
+        for i in 0..60 {
+            let mut entry = MemoryEntry::new(format!("Memory {}", i), "test", MemorySource::User);
+            entry.embedding = Some(vec![(i as f32).cos(), (i as f32).sin()]);
+            entry.metadata.importance = if i < 10 { 0.9 } else { 0.1 };
+            memory.store(entry).await?;
+        }
+        memory.consolidate().await?;
+        memory.persist().await?;
+        assert!(memory.index_path.exists(), "Index should be saved next to the cold file");
+        assert!(memory.lexical_path.exists(), "BM25 postings should be saved too");
+        let cold_count = memory.count().await? - memory.hot_entries.read().await.len();
+        assert!(cold_count > 0);
+        // Demoted nodes are scored from the COLD mapping, not a copy
+        assert_eq!(memory.index.read().await.as_ref().map(|i| i.ann.external_len()), Some(cold_count));
+
+        let reopened = LocalVectorMemory::new(path)?;
+        reopened.wake().await?;
+        let index = reopened.index.read().await;
+        assert_eq!(index.as_ref().map(|i| (i.ann.len(), i.lexical.len())), Some((60, 60)));
+        assert_eq!(index.as_ref().map(|i| i.ann.external_len()), Some(cold_count));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unpersisted_changes_survive_restart() -> Result<()> {
+        std::env::set_var("AGENCY_USE_REMOTE_MEMORY", "0");
+        if std::env::var("ORT_DYLIB_PATH").is_err() && !std::path::Path::new("libonnxruntime.dylib").exists() {
+            return Ok(());
+        }
+
+        let dir = tempdir()?;
+        let path = dir.path().join("test.mem");
+        let memory = LocalVectorMemory::new(path.clone())?;
+        let mut ids = Vec::new();
+        for i in 0..3 {
+            let mut entry = MemoryEntry::new(format!("Memory {}", i), "test", MemorySource::User);
+            entry.embedding = Some(vec![1.0, i as f32]);
+            ids.push(memory.store(entry).await?);
+        }
+        memory.persist().await?;
+        let mut late = MemoryEntry::new("Stored after the snapshot", "test", MemorySource::User);
+        late.embedding = Some(vec![0.0, 1.0]);
+        let late_id = memory.store(late).await?;
+        memory.prune(vec![ids[0].clone()]).await?;
+        // Simulated crash: no persist
+        drop(memory);
+
+        let reopened = LocalVectorMemory::new(path.clone())?;
+        let hot: Vec<String> = reopened.hot_entries.read().await.iter().map(|e| e.id.clone()).collect();
+        assert_eq!(hot, vec![ids[1].clone(), ids[2].clone(), late_id]);
+
+        // A damaged snapshot is moved aside instead of being read
+        reopened.persist().await?;
+        let mut bytes = std::fs::read(&path)?;
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        std::fs::write(&path, bytes)?;
+        assert!(LocalVectorMemory::new(path)?.hot_entries.read().await.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_usage_is_written_back_and_promotes() -> Result<()> {
+        std::env::set_var("AGENCY_USE_REMOTE_MEMORY", "0");
+        if std::env::var("ORT_DYLIB_PATH").is_err() && !std::path::Path::new("libonnxruntime.dylib").exists() {
+            return Ok(());
+        }
+
+        let dir = tempdir()?;
+        let path = dir.path().join("test.mem");
+        let memory = LocalVectorMemory::new(path.clone())?;
+        for i in 0..60 {
+            memory.store(MemoryEntry::new(format!("Note {} about deployment window {}", i, i), "test", MemorySource::User).with_importance(0.1)).await?;
+        }
+        memory.consolidate().await?;
+        assert!(memory.hot_entries.read().await.is_empty());
+
+        // Retrievals are counted on the stored COLD entry and survive a restart
+        let mut used = String::new();
+        for _ in 0..6 {
+            let hits = memory.search_with("deployment window 7", 1, &SearchOptions::new(SearchMode::Lexical)).await?;
+            used = hits[0].id.clone();
+        }
+        memory.persist().await?;
+        let reopened = LocalVectorMemory::new(path)?;
+        reopened.ensure_cold_cache().await?;
+        let stored = reopened.cold_cache.read().await.as_ref().unwrap().get_by_id(&used).unwrap();
+        assert_eq!(stored.metadata.access_count, 6);
+        assert!(stored.metadata.last_accessed.is_some());
+
+        // Frequently used entries return to HOT; positive feedback raises importance
+        assert_eq!(reopened.consolidate().await?, 1);
+        assert_eq!(reopened.hot_entries.read().await[0].id, used);
+        assert_eq!(reopened.reinforce(&[used.clone()], Reinforcement::Rating(9)).await?, 1);
+        assert!(reopened.hot_entries.read().await[0].metadata.importance > 0.4);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prune_count_and_recent_span_tiers() -> Result<()> {
+        std::env::set_var("AGENCY_USE_REMOTE_MEMORY", "0");
+        if std::env::var("ORT_DYLIB_PATH").is_err() && !std::path::Path::new("libonnxruntime.dylib").exists() {
+            return Ok(());
+        }
+
+        let dir = tempdir()?;
+        let path = dir.path().join("test.mem");
+        let memory = LocalVectorMemory::new(path.clone())?;
+        let mut ids = Vec::new();
+        for i in 0..60 {
+            let mut entry = MemoryEntry::new(format!("Cold note {}", i), "test", MemorySource::User).with_importance(0.1);
+            entry.timestamp -= chrono::Duration::minutes(60 - i);
+            entry.embedding = Some(vec![(i as f32).cos(), (i as f32).sin()]);
+            ids.push(memory.store(entry).await?);
+        }
+        memory.consolidate().await?;
+        assert!(memory.hot_entries.read().await.is_empty());
+        let mut fresh = MemoryEntry::new("Hot note", "test", MemorySource::User);
+        fresh.embedding = Some(vec![1.0, 0.0]);
+        let fresh_id = memory.store(fresh).await?;
+
+        assert_eq!(memory.count().await?, 61);
+        let recent: Vec<String> = memory.get_recent(3).await?.into_iter().map(|e| e.id).collect();
+        assert_eq!(recent, vec![fresh_id, ids[59].clone(), ids[58].clone()]);
+
+        // Pruning a COLD entry is logged and replayed like any other change
+        memory.prune(vec![ids[59].clone()]).await?;
+        assert_eq!(memory.count().await?, 60);
+        drop(memory);
+
+        let reopened = LocalVectorMemory::new(path)?;
+        assert_eq!(reopened.count().await?, 60);
+        let hits = reopened.search_with("Cold note 59", 60, &SearchOptions::new(SearchMode::Lexical)).await?;
+        assert!(!hits.iter().any(|e| e.id == ids[59]));
+        reopened.persist().await?;
+        assert!(!ColdTier::open(&reopened.cold_path)?.contains(&ids[59]), "the prune reached the COLD file");
+
+        Ok(())
+    }
 }
 
 pub struct RemoteVectorMemory {
@@ -458,6 +1031,18 @@ impl Memory for RemoteVectorMemory {
         Ok(entries)
     }
 
+    async fn search_with(&self, query: &str, top_k: usize, options: &SearchOptions) -> Result<Vec<MemoryEntry>> {
+        let mut body = serde_json::to_value(options)?;
+        body["query"] = json!(query);
+        body["top_k"] = json!(top_k);
+        let resp = self.client.post(format!("{}/search", self.url))
+            .json(&body)
+            .send().await?;
+        let data: serde_json::Value = resp.json().await?;
+        let entries = serde_json::from_value(data["entries"].clone())?;
+        Ok(entries)
+    }
+
     async fn count(&self) -> Result<usize> {
         let resp = self.client.get(format!("{}/count", self.url)).send().await?;
         let data: serde_json::Value = resp.json().await?;
@@ -473,6 +1058,15 @@ impl Memory for RemoteVectorMemory {
     async fn get_cold_memories(&self, _limit: usize) -> Result<Vec<MemoryEntry>> { Ok(Vec::new()) }
     async fn get_recent(&self, _limit: usize) -> Result<Vec<MemoryEntry>> { Ok(Vec::new()) }
     async fn prune(&self, _ids: Vec<String>) -> Result<()> { Ok(()) }
+
+    async fn reinforce(&self, ids: &[String], feedback: Reinforcement) -> Result<usize> {
+        let resp = self.client.post(format!("{}/reinforce", self.url))
+            .json(&json!({ "ids": ids, "feedback": feedback }))
+            .send().await?;
+        let data: serde_json::Value = resp.json().await?;
+        Ok(data["updated"].as_u64().unwrap_or(0) as usize)
+    }
+
     async fn clear_cache(&self) -> Result<()> { Ok(()) }
     async fn hibernate(&self) -> Result<()> { Ok(()) }
     async fn wake(&self) -> Result<()> { Ok(()) }
@@ -56,7 +56,7 @@ impl A2ABridge {
         
                 let mut a2a_context = format!(
         
-                    "\n<|im_start|>system\nDIRECT PEER CALL [ID: {}]\nSOURCE: {:?}\nTARGET: {:?}\n",
+                    "## Direct Peer Call [ID: {}]\nSOURCE: {:?}\nTARGET: {:?}\n",
         
                     interaction.interaction_id, interaction.source_agent, interaction.target_agent
         
@@ -80,8 +80,6 @@ impl A2ABridge {
         
                 }
         
-                a2a_context.push_str("<|im_end|>\n");
-        
         
         
                 // 2. Delegate to Supervisor's peer handling
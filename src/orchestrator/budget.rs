@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::atomic::{AtomicU32, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+
+use crate::agent::TokenUsage;
 
 /// FPF-aligned Autonomy Ledger (E.16)
 /// 
@@ -71,3 +74,103 @@ INSTRUCTION: If resources are low, prioritize immediate completion or 'forge_too
         )
     }
 }
+
+/// Token usage accumulated over a session, broken down by model and agent
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageReport {
+    pub total: TokenUsage,
+    pub total_tokens: u32,
+    pub calls: u32,
+    pub by_model: BTreeMap<String, TokenUsage>,
+    pub by_agent: BTreeMap<String, TokenUsage>,
+}
+
+impl std::fmt::Display for UsageReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Session usage: {} tokens ({} prompt / {} completion) over {} calls",
+            self.total_tokens, self.total.prompt_tokens, self.total.completion_tokens, self.calls)?;
+        for (model, usage) in &self.by_model {
+            writeln!(f, "  model {}: {}", model, usage.total_tokens())?;
+        }
+        for (agent, usage) in &self.by_agent {
+            writeln!(f, "  agent {}: {}", agent, usage.total_tokens())?;
+        }
+        Ok(())
+    }
+}
+
+/// Shared per-session usage accountant; clones record into the same report
+#[derive(Debug, Clone, Default)]
+pub struct SessionUsage {
+    report: Arc<Mutex<UsageReport>>,
+    /// Charged with the total of every recorded call, when attached
+    ledger: Option<AutonomyLedger>,
+}
+
+impl SessionUsage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_ledger(mut self, ledger: AutonomyLedger) -> Self {
+        self.ledger = Some(ledger);
+        self
+    }
+
+    pub fn record(&self, model: &str, agent: &str, usage: TokenUsage) {
+        if let Some(ledger) = &self.ledger {
+            ledger.record_tokens(usage.total_tokens());
+        }
+        if let Ok(mut report) = self.report.lock() {
+            report.total += usage;
+            report.total_tokens = report.total.total_tokens();
+            report.calls += 1;
+            *report.by_model.entry(model.to_string()).or_default() += usage;
+            *report.by_agent.entry(agent.to_string()).or_default() += usage;
+        }
+    }
+
+    pub fn report(&self) -> UsageReport {
+        self.report.lock().map(|r| r.clone()).unwrap_or_default()
+    }
+
+    pub fn reset(&self) {
+        if let Ok(mut report) = self.report.lock() {
+            *report = UsageReport::default();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_usage_breakdown() {
+        let usage = SessionUsage::new();
+        let shared = usage.clone();
+        usage.record("glm-4", "Coder", TokenUsage::new(100, 20));
+        shared.record("glm-4", "Reasoner", TokenUsage::new(50, 10));
+        shared.record("glm-4-flash", "Coder", TokenUsage::new(5, 5));
+
+        let report = usage.report();
+        assert_eq!(report.calls, 3);
+        assert_eq!(report.total, TokenUsage::new(155, 35));
+        assert_eq!(report.total_tokens, 190);
+        assert_eq!(report.by_model["glm-4"], TokenUsage::new(150, 30));
+        assert_eq!(report.by_agent["Coder"], TokenUsage::new(105, 25));
+
+        usage.reset();
+        assert_eq!(shared.report(), UsageReport::default());
+    }
+
+    #[test]
+    fn test_session_usage_charges_ledger() {
+        let ledger = AutonomyLedger::new();
+        let usage = SessionUsage::new().with_ledger(ledger.clone());
+        usage.clone().record("glm-4", "Router", TokenUsage::new(30, 5));
+        usage.record("glm-4", "Coder", TokenUsage::new(100, 20));
+
+        assert_eq!(ledger.token_usage.load(Ordering::SeqCst), 155);
+    }
+}
@@ -17,7 +17,7 @@ use ratatui::{
     Frame, Terminal,
 };
 
-use crate::orchestrator::{Supervisor, AgencyEvent, AGENCY_EVENT_BUS};
+use crate::orchestrator::{Supervisor, AgencyEvent, TurnCanceller, AGENCY_EVENT_BUS};
 use crate::orchestrator::mvpk::Publication;
 
 /// Events sent from the background worker or event bus to the TUI
@@ -35,6 +35,8 @@ struct App {
     status: String,
     is_orchestrating: bool,
     supervisor: Arc<Mutex<Supervisor>>,
+    /// Cancels the running turn without waiting for the supervisor lock
+    turns: TurnCanceller,
     last_publication: Option<Publication>,
     speaker: Arc<Mutex<crate::orchestrator::Speaker>>,
     event_rx: mpsc::Receiver<AppEvent>,
@@ -42,7 +44,7 @@ struct App {
 }
 
 impl App {
-    fn new(supervisor: Arc<Mutex<Supervisor>>, speaker: Arc<Mutex<crate::orchestrator::Speaker>>) -> Self {
+    fn new(supervisor: Arc<Mutex<Supervisor>>, turns: TurnCanceller, speaker: Arc<Mutex<crate::orchestrator::Speaker>>) -> Self {
         let (tx, rx) = mpsc::channel(100);
         
         // Subscribe to global agency events
@@ -61,6 +63,7 @@ impl App {
             status: "Idle".to_string(),
             is_orchestrating: false,
             supervisor,
+            turns,
             last_publication: None,
             speaker,
             event_rx: rx,
@@ -105,6 +108,14 @@ impl App {
             return;
         }
 
+        if query.trim() == "/usage" {
+            tokio::spawn(async move {
+                let report = supervisor.lock().await.usage_report();
+                let _ = tx.send(AppEvent::Response(report.to_string(), None)).await;
+            });
+            return;
+        }
+
         if query.starts_with("/queue ") {
             let task_description = query.strip_prefix("/queue ").unwrap().trim().to_string();
             tokio::spawn(async move {
@@ -144,6 +155,15 @@ impl App {
         let guard = self.supervisor.lock().await;
         let _ = guard.steer(msg).await;
     }
+
+    fn cancel_turn(&mut self) {
+        if self.turns.cancel() {
+            self.status = "Cancelling...".to_string();
+            self.push_log("🛑 Cancelling current turn".to_string());
+        } else {
+            self.push_log("No turn is running".to_string());
+        }
+    }
 }
 
 pub struct AgencyCLI {
@@ -162,7 +182,8 @@ impl AgencyCLI {
         let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
 
         // Consuming self allows moving supervisor safely
-        let mut app = App::new(self.supervisor, self.speaker.clone());
+        let turns = self.supervisor.lock().await.turn_canceller();
+        let mut app = App::new(self.supervisor, turns, self.speaker.clone());
 
         let tick_rate = Duration::from_millis(50);
         let mut last_tick = Instant::now();
@@ -199,6 +220,7 @@ impl AgencyCLI {
                                 app.push_log(format!("{} Tool End: {}", icon, tool));
                             }
                             AgencyEvent::TurnStarted { agent, model } => app.push_log(format!("🤖 Turn Start: {} ({})", agent, model)),
+                            AgencyEvent::ProviderFailover { from, to, .. } => app.push_log(format!("🔀 Provider failover: {} → {}", from, to)),
                             _ => app.push_log(format!("📝 Event: {:?}", e)),
                         }
                     }
@@ -215,7 +237,9 @@ impl AgencyCLI {
                                 if query == "quit" || query == "exit" {
                                     break;
                                 }
-                                if app.is_orchestrating {
+                                if query.trim() == "/cancel" {
+                                    app.cancel_turn();
+                                } else if app.is_orchestrating {
                                     app.steer(query).await;
                                 } else {
                                     app.execute_query(query).await;
@@ -45,8 +45,9 @@ impl SkillCrystallizer {
         let prompt = format!(
             "Analyze these recent successful tasks:\n{}\n\n\
             Identify ONE purely algorithmic or repetitive task that was solved by reasoning but COULD be solved by a simple Rust function. \
-            If found, write a Rust function signature and implementation. \
-            The function MUST be valid Rust, `no_mangle`, `extern \"C\"`, and perform a useful, atomic operation. \
+            If found, write it as a complete Rust program targeting WASI: `fn main` reads one JSON value from stdin, \
+            performs a useful, atomic operation, and prints one JSON value to stdout. \
+            Use only the standard library (no crates); parse and format the JSON by hand. \
             \
             Return JSON ONLY: {{ \"found\": true, \"name\": \"tool_name\", \"description\": \"desc\", \"code\": \"...rust code...\" }} \
             If no pattern is rigid enough for code, return {{ \"found\": false }}",
@@ -73,7 +74,8 @@ impl SkillCrystallizer {
                 if let Some(compiler) = self.tools.get_tool("wasm_compiler").await {
                     let compile_res = compiler.execute(json!({
                         "filename": name,
-                        "source_code": code
+                        "source_code": code,
+                        "target": "wasi"
                     })).await?;
 
                     if compile_res.success {
@@ -81,7 +83,10 @@ impl SkillCrystallizer {
                         
                         // Save a memory about this new capability
                         self.memory.store(crate::memory::MemoryEntry::new(
-                            format!("New Skill Crystallized: {}. Description: {}. Path: {}", name, description, compile_res.data["wasm_path"]),
+                            format!(
+                                "New Skill Crystallized: {}. Description: {}. Path: {}. Run with wasm_executor mode 'wasi', passing JSON as input.",
+                                name, description, compile_res.data["wasm_path"]
+                            ),
                             "Crystallizer",
                             crate::memory::entry::MemorySource::System
                         )).await?;
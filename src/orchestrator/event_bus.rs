@@ -28,6 +28,10 @@ pub enum AgencyEvent {
     SkillDiscovered { name: String, version: String },
     /// A skill was promoted to the standard set
     SkillPromoted { name: String },
+    /// A forged tool passed its tests and was promoted to the standard set
+    ToolPromoted { name: String, version: String },
+    /// A promoted tool kept failing and was rolled back to an earlier version
+    ToolRolledBack { name: String, from: String, to: String },
     /// Context compaction was triggered
     ContextCompacted { before_tokens: usize, after_tokens: usize },
     /// An agent turn started
@@ -40,6 +44,10 @@ pub enum AgencyEvent {
     ToolCallFinished { tool: String, success: bool },
     /// HITL Approval was requested
     ApprovalRequested { id: String, tool: String },
+    /// An LLM backend failed and the call moved to the next one
+    ProviderFailover { from: String, to: String, reason: String },
+    /// An LLM backend's circuit breaker opened or closed
+    ProviderCircuitChanged { provider: String, open: bool },
     /// Generic system status update
     StatusUpdate(String),
 }
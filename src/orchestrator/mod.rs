@@ -49,7 +49,7 @@ pub mod sovereignty;
 pub mod vault;
 
 pub use crate::agent::speaker_rs::Speaker;
-pub use supervisor::{Supervisor, SupervisorResult};
+pub use supervisor::{Supervisor, SupervisorResult, TurnCanceller};
 pub use planner::{Planner, Plan, PlanStep};
 pub use optimal_info::OptimalInfoSelector;
 pub use router::{Router, RoutingDecision};
@@ -61,7 +61,7 @@ pub use role_algebra::RoleAlgebra;
 pub use mht::{MHTEngine, MHTEvent};
 pub use governance::{NormSquare, AdmissibilityGate, GateStatus, DeonticRule, DeonticModality, AdjudicationResult, AdjudicationVerdict};
 pub use scale::{ScaleClass, ScaleProfile};
-pub use budget::{AutonomyLedger, BudgetStatus};
+pub use budget::{AutonomyLedger, BudgetStatus, SessionUsage, UsageReport};
 pub use mvpk::Publication;
 pub use bridge::Bridge;
 pub use service::{ServiceClause, ServiceStatus};
@@ -9,7 +9,7 @@ use serde::{Deserialize, Serialize};
 use tracing::{debug, info};
 use std::sync::Arc;
 
-use crate::agent::{AgentType, LLMProvider, OllamaProvider};
+use crate::agent::{AgentType, ChatMessage, GenerationOptions, LLMProvider, OllamaProvider};
 
 /// A step in a plan
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -178,8 +178,12 @@ q = "{}"
             query
         );
 
-        let system = Some(super::sns::get_sns_system_prompt());
-        let content = self.provider.generate(&self.model, prompt, system).await?;
+        let messages = vec![
+            ChatMessage::system(super::sns::get_sns_system_prompt()),
+            ChatMessage::user(prompt),
+        ];
+        let options = GenerationOptions::from_env();
+        let content = self.provider.chat(&self.model, messages, options).await?.content;
 
         let mut plan = self.parse_plan(query, &content)?;
 
@@ -399,7 +403,8 @@ EXPECTED: [expected output]
             feedback
         );
 
-        let content = self.provider.generate(&self.model, prompt, None).await?;
+        let options = GenerationOptions::from_env();
+        let content = self.provider.chat(&self.model, vec![ChatMessage::user(prompt)], options).await?.content;
 
         self.parse_plan(&plan.goal, &content)
     }
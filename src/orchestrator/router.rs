@@ -9,8 +9,8 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tracing::info;
 
-use crate::agent::{AgentType, LLMProvider, OllamaProvider, OpenAICompatibleProvider};
-use crate::orchestrator::ScaleProfile;
+use crate::agent::{AgentType, ChatMessage, GenerationOptions, LLMProvider, OllamaProvider, OpenAICompatibleProvider, TokenUsage};
+use crate::orchestrator::{ScaleProfile, SessionUsage};
 
 /// Routing decision for a query
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +27,9 @@ pub struct RoutingDecision {
     pub reason: String,
     /// FPF Integration: Scaling-Law Lens (C.18.1)
     pub scale: ScaleProfile,
+    /// Tokens spent by the LLM routing call, zero for heuristic routes
+    #[serde(default)]
+    pub usage: TokenUsage,
 }
 
 /// Router for directing queries to appropriate agents
@@ -34,6 +37,8 @@ pub struct RoutingDecision {
 pub struct Router {
     provider: Arc<dyn LLMProvider>,
     model: String,
+    /// Charged with the usage of LLM routing calls
+    usage_meter: Option<SessionUsage>,
 }
 
 impl Router {
@@ -41,6 +46,7 @@ impl Router {
         Self {
             provider: Arc::new(OllamaProvider::new(ollama)),
             model: "llama3.2:3b".to_string(),
+            usage_meter: None,
         }
     }
 
@@ -48,6 +54,7 @@ impl Router {
         Self {
             provider,
             model: "llama3.2:3b".to_string(),
+            usage_meter: None,
         }
     }
 
@@ -62,6 +69,11 @@ impl Router {
         self
     }
 
+    pub fn with_usage_meter(mut self, meter: SessionUsage) -> Self {
+        self.usage_meter = Some(meter);
+        self
+    }
+
     #[allow(dead_code)]
     pub fn with_provider_url(mut self, url: Option<String>) -> Self {
         if let Some(url_str) = url {
@@ -109,6 +121,7 @@ impl Router {
                 confidence: 0.95,
                 reason: "Query explicitly mentions tool usage (FPF Tool Detection)".to_string(),
                 scale,
+                usage: TokenUsage::default(),
             });
         }
         
@@ -128,6 +141,7 @@ impl Router {
                 confidence: 0.9,
                 reason: "Simple greeting or short message".to_string(),
                 scale,
+                usage: TokenUsage::default(),
             });
         }
 
@@ -140,6 +154,7 @@ impl Router {
                 confidence: 0.95,
                 reason: "Direct filesystem query (heuristics fast-path)".to_string(),
                 scale,
+                usage: TokenUsage::default(),
             });
         }
 
@@ -152,6 +167,7 @@ impl Router {
                 confidence: 0.9,
                 reason: "Knowledge graph or relationship query".to_string(),
                 scale,
+                usage: TokenUsage::default(),
             });
         }
 
@@ -164,6 +180,7 @@ impl Router {
                 confidence: 0.85,
                 reason: "Query contains code-related keywords".to_string(),
                 scale,
+                usage: TokenUsage::default(),
             });
         }
 
@@ -176,6 +193,7 @@ impl Router {
                 confidence: 0.8,
                 reason: "Query involves planning or task decomposition".to_string(),
                 scale,
+                usage: TokenUsage::default(),
             });
         }
 
@@ -188,6 +206,7 @@ impl Router {
                 confidence: 0.8,
                 reason: "Query requires information gathering".to_string(),
                 scale,
+                usage: TokenUsage::default(),
             });
         }
 
@@ -286,10 +305,19 @@ q = "{}"
             query
         );
 
-        let system = Some(super::sns::get_sns_system_prompt());
-        let content = self.provider.generate(&self.model, prompt, system).await?;
+        let messages = vec![
+            ChatMessage::system(super::sns::get_sns_system_prompt()),
+            ChatMessage::user(prompt),
+        ];
+        let options = GenerationOptions::from_env();
+        let response = self.provider.chat(&self.model, messages, options).await?;
+        if let Some(meter) = &self.usage_meter {
+            meter.record(&self.model, "Router", response.usage);
+        }
 
-        self.parse_routing_response(&content)
+        let mut decision = self.parse_routing_response(&response.content)?;
+        decision.usage = response.usage;
+        Ok(decision)
     }
 
     fn parse_routing_response(&self, response: &str) -> Result<RoutingDecision> {
@@ -330,6 +358,7 @@ q = "{}"
                         confidence: 0.7,
                         reason,
                         scale: ScaleProfile::new(0.5, 8.0), // Placeholder, will be updated by caller
+                        usage: TokenUsage::default(),
                     });
                 }
             }
@@ -373,6 +402,7 @@ q = "{}"
             confidence: 0.7, // LLM routing is less certain
             reason,
             scale: ScaleProfile::new(0.5, 8.0), // Placeholder
+            usage: TokenUsage::default(),
         })
     }
 }
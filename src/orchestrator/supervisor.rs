@@ -9,14 +9,15 @@ use tokio::sync::{Semaphore, Mutex, mpsc};
 use std::collections::VecDeque;
 use tracing::{info, warn, error};
 use futures_util::future::join_all;
+use tokio_util::sync::CancellationToken;
 
 use crate::agent::{
     ReActAgent, AgentType, AgentConfig, LLMCache, LLMProvider, Agent,
     AutonomousMachine, AgentResponse, OllamaProvider, AgentResult, AgentError,
-    PubCharacteristic
+    PubCharacteristic, ChatRole, TokenUsage
 };
 use crate::agent::rl::ExperienceBuffer;
-use crate::memory::{Memory, EpisodicMemory};
+use crate::memory::{Memory, EpisodicMemory, Reinforcement};
 use crate::emit_event;
 use crate::orchestrator::{
     Plan, Router, SessionManager, 
@@ -25,10 +26,13 @@ use crate::orchestrator::{
     aggregation::{Candidate, Gamma, RewardModel},
     ResultPortfolio, ScaleProfile, AgencyEvent,
     queue::{TaskQueue, SqliteTaskQueue},
-    governance::NormSquare
+    governance::NormSquare, AutonomyLedger, SessionUsage, UsageReport
 };
 use pai_core::{HookManager, HookEvent, HookEventType};
 
+/// How long context gathering waits on MCP servers before going without their resources
+const MCP_RESOURCE_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(1500);
+
 pub struct SupervisorResult {
     pub answer: String,
     pub success: bool,
@@ -37,6 +41,49 @@ pub struct SupervisorResult {
     pub publication: Option<Publication>,
     pub pending_approval: Option<crate::safety::ApprovalRequest>,
     pub has_followup: bool,
+    /// Tokens spent on this turn across all candidates and escalations
+    pub usage: TokenUsage,
+}
+
+/// Cancels whatever turn the supervisor is running. Cheap to clone, and usable
+/// while `handle` holds the supervisor lock.
+#[derive(Clone, Default)]
+pub struct TurnCanceller(Arc<std::sync::Mutex<Option<CancellationToken>>>);
+
+impl TurnCanceller {
+    /// Cancel the running turn; `false` when no turn is running
+    pub fn cancel(&self) -> bool {
+        let current = self.0.lock().ok().and_then(|slot| slot.clone());
+        match current {
+            Some(token) if !token.is_cancelled() => {
+                token.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn begin(&self) -> ActiveTurn {
+        let token = CancellationToken::new();
+        if let Ok(mut slot) = self.0.lock() {
+            *slot = Some(token.clone());
+        }
+        ActiveTurn { canceller: self.clone(), token }
+    }
+}
+
+/// The token of the running turn; clears the canceller when the turn ends
+struct ActiveTurn {
+    canceller: TurnCanceller,
+    token: CancellationToken,
+}
+
+impl Drop for ActiveTurn {
+    fn drop(&mut self) {
+        if let Ok(mut slot) = self.canceller.0.lock() {
+            *slot = None;
+        }
+    }
 }
 
 pub struct Supervisor {
@@ -75,6 +122,14 @@ pub struct Supervisor {
     pub metabolism: Arc<crate::orchestrator::metabolism::EconomicMetabolism>,
     /// Cryptographic Identity (Sovereignty)
     pub identity: Arc<crate::orchestrator::sovereignty::SovereignIdentity>,
+    /// Token usage accumulated over this session
+    pub session_usage: SessionUsage,
+    /// Session resource ledger, charged with every call recorded in `session_usage`
+    pub autonomy_ledger: AutonomyLedger,
+    /// Cancels the turn in progress
+    pub turns: TurnCanceller,
+    /// Memories injected as context into the last turn, the target of user feedback
+    pub last_turn_memories: Vec<String>,
 }
 
 impl Supervisor {
@@ -90,6 +145,7 @@ impl Supervisor {
         let vocal_cords = Arc::new(crate::orchestrator::vocal_cords::VocalCords::new());
         let metabolism = Arc::new(crate::orchestrator::metabolism::EconomicMetabolism::new()); // Default initial balance handled inside
         let identity = Arc::new(crate::orchestrator::sovereignty::SovereignIdentity::new().expect("Failed to initialize Sovereign Identity"));
+        let autonomy_ledger = AutonomyLedger::new();
 
         // Register the TaskSpawnerTool to enable Cellular Division
         tools.register_instance(crate::tools::TaskSpawnerTool::new(task_queue.clone())).await;
@@ -120,7 +176,7 @@ impl Supervisor {
             session: None,
             history_manager: Arc::new(crate::memory::HistoryManager::new(crate::memory::HistoryManager::default_path(), Some(10 * 1024 * 1024))),
             max_retries: 2,
-            cache: Arc::new(LLMCache::new()),
+            cache: Arc::new(LLMCache::from_env()),
             safety: Arc::new(Mutex::new(crate::safety::SafetyGuard::new())),
             role_algebra: crate::orchestrator::RoleAlgebra::new(),
             concurrency_limit: Arc::new(Semaphore::new(4)),
@@ -155,6 +211,26 @@ impl Supervisor {
             vocal_cords,
             metabolism,
             identity,
+            session_usage: SessionUsage::new().with_ledger(autonomy_ledger.clone()),
+            autonomy_ledger,
+            turns: TurnCanceller::default(),
+            last_turn_memories: Vec::new(),
+        }
+    }
+
+    /// Session token usage broken down by model and agent
+    pub fn usage_report(&self) -> UsageReport {
+        self.session_usage.report()
+    }
+
+    /// Re-estimate the importance of the memories used in the last turn from a
+    /// PAI learning signal (e.g. a user rating); returns how many were updated
+    pub async fn apply_signal(&self, signal: &pai_core::learning::Signal) -> Result<usize> {
+        match self.memory {
+            Some(ref memory) if !self.last_turn_memories.is_empty() => {
+                memory.reinforce(&self.last_turn_memories, Reinforcement::from(signal)).await
+            }
+            _ => Ok(0),
         }
     }
 
@@ -326,6 +402,11 @@ impl Supervisor {
         Ok(())
     }
 
+    /// A handle that cancels the running turn without taking the supervisor lock
+    pub fn turn_canceller(&self) -> TurnCanceller {
+        self.turns.clone()
+    }
+
     /// Queue a message to be processed after the current turn
     pub async fn enqueue_followup(&self, message: impl Into<String>) {
         self.followup_queue.lock().await.push_back(message.into());
@@ -405,6 +486,8 @@ impl Supervisor {
         let _work_start_time = std::time::Instant::now();
         
         let session_id = uuid::Uuid::new_v4().to_string();
+        let active_turn = self.turns.begin();
+        let turn = active_turn.token.clone();
 
         // PAI: Trigger and LOG SessionStart Event
         let mut start_event = HookEvent {
@@ -434,10 +517,13 @@ impl Supervisor {
             ).await;
         }
 
+        // Prior turns travel as real chat messages; the current query is sent by the agent itself
+        let mut history = self.episodic_memory.lock().await.to_chat_messages();
+        if history.last().is_some_and(|m| m.role == ChatRole::User && m.content == query) {
+            history.pop();
+        }
+
         let mut full_context = String::new();
-        let chatml = self.episodic_memory.lock().await.format_as_chatml();
-        full_context.push_str(&chatml);
-        full_context.push_str("\n\n");
 
         // SOTA: Concurrent Pre-processing (FPF Principle: Minimize Latency)
         // Perform memory search, agent routing, and project context discovery in parallel.
@@ -445,12 +531,12 @@ impl Supervisor {
             if let Some(ref memory) = self.memory {
                 match memory.search(query, 3, None, None).await {
                     Ok(relevant) if !relevant.is_empty() => {
-                        let mut ctx = String::from("<|im_start|>system\nRelevant Memory:\n");
-                        for entry in relevant {
+                        let mut ctx = String::from("## Relevant Memory\n");
+                        for entry in &relevant {
                             ctx.push_str(&format!("- {}\n", entry.content));
                         }
-                        ctx.push_str("<|im_end|>\n");
-                        Some(ctx)
+                        ctx.push('\n');
+                        Some((ctx, relevant.into_iter().map(|e| e.id).collect::<Vec<_>>()))
                     },
                     _ => None
                 }
@@ -460,30 +546,58 @@ impl Supervisor {
         };
 
         let router_task = async {
-            let router = Router::new_with_provider(self.provider.clone());
+            let router = Router::new_with_provider(self.provider.clone()).with_usage_meter(self.session_usage.clone());
             router.route(query, Some(8.0)).await
         };
 
         let project_context_task = async {
             match crate::orchestrator::context::ContextLoader::load_project_context().await {
                 Ok(context) if !context.is_empty() => {
-                    let mut ctx = String::from("<|im_start|>system\nProject Context (discovered recursively):\n");
+                    let mut ctx = String::from("## Project Context (discovered recursively)\n");
                     ctx.push_str(&context);
-                    ctx.push_str("<|im_end|>\n");
+                    ctx.push_str("\n\n");
                     Some(ctx)
                 },
                 _ => None
             }
         };
 
-        let (memory_ctx, routing_result, project_ctx) = tokio::join!(memory_search_task, router_task, project_context_task);
+        let mcp_resource_task = async {
+            // A slow or hung server must not hold up the turn
+            let hits = match tokio::time::timeout(MCP_RESOURCE_TIMEOUT, self.tools.mcp_resources().search(query, 2)).await {
+                Ok(hits) => hits,
+                Err(_) => {
+                    warn!("MCP resource search timed out after {:?}; continuing without resources", MCP_RESOURCE_TIMEOUT);
+                    Vec::new()
+                }
+            };
+            if hits.is_empty() {
+                return None;
+            }
+            let mut ctx = String::from("## Relevant MCP Resources\n");
+            for hit in hits {
+                ctx.push_str(&format!("### {} ({})\n{}\n", hit.name, hit.uri, hit.text));
+            }
+            ctx.push('\n');
+            Some(ctx)
+        };
+
+        let (memory_ctx, routing_result, project_ctx, mcp_ctx) =
+            tokio::join!(memory_search_task, router_task, project_context_task, mcp_resource_task);
         let routing_decision = routing_result.map_err(|e| AgentError::Execution(e.to_string()))?;
 
         if let Some(ctx) = project_ctx {
             full_context.push_str(&ctx);
         }
 
-        if let Some(ctx) = memory_ctx {
+        self.last_turn_memories.clear();
+        let mut injected_memories = Vec::new();
+        if let Some((ctx, ids)) = memory_ctx {
+            full_context.push_str(&ctx);
+            injected_memories = ids;
+        }
+
+        if let Some(ctx) = mcp_ctx {
             full_context.push_str(&ctx);
         }
         
@@ -503,7 +617,7 @@ impl Supervisor {
                     let _ = self.provider.notify(&format!("🔍 Resolving Uncertainty: {}", q.description)).await;
                     // Execute query as a lightweight system prompt injection
                     // In a real implementation, we would run a one-off tool call here.
-                    full_context.push_str(&format!("\n<|im_start|>system\nVerified Assumption ({}): {}\n<|im_end|>\n", q.description, q.tool_call));
+                    full_context.push_str(&format!("## Verified Assumption ({})\n{}\n\n", q.description, q.tool_call));
                 }
             }
         }
@@ -511,12 +625,18 @@ impl Supervisor {
         let mut current_scale = routing_decision.scale.clone();
         let mut final_res: Option<AgentResponse> = None;
         let mut final_performer = String::new();
+        // The router's call is part of the turn too
+        let mut turn_usage = routing_decision.usage;
         let final_routing = routing_decision.clone();
         let mut final_winner_idx = 0;
 
         // SOTA: Escalation Loop (FPF Principle C.18.2)
         // If execution fails, escalate to a stronger model and retry.
         for attempt in 0..3 {
+            if turn.is_cancelled() {
+                info!("Turn cancelled; not escalating further");
+                break;
+            }
             if attempt > 0 {
                 let _ = self.provider.notify(&format!("\n⚠️ Task failed with {}. Escalating to next intelligence tier...\n", current_scale.target_model)).await;
                 let next_class = current_scale.class.escalate();
@@ -548,6 +668,7 @@ impl Supervisor {
                 let provider = self.create_cached_provider();
                 let query_owned = query.to_string();
                 let context_owned = full_context.clone();
+                let history_owned = history.clone();
                 let semaphore = self.concurrency_limit.clone();
                 let tools = self.tools.clone();
                 let memory = self.memory.clone();
@@ -555,6 +676,8 @@ impl Supervisor {
                 let hooks = self.pai_hooks.clone();
                 let pai_mem = self.pai_memory.clone();
                 let recovery = self.recovery.clone();
+                let cancel = turn.child_token();
+                let meter = self.session_usage.clone();
                 
                 let (steer_tx, steer_rx) = mpsc::channel(10);
                 self.active_steer_txs.lock().await.push(steer_tx);
@@ -564,7 +687,10 @@ impl Supervisor {
                     let mut agent = ReActAgent::new_with_provider(provider, config, tools)
                         .with_hooks(hooks)
                         .with_memory_manager(pai_mem)
-                        .with_recovery(recovery);
+                        .with_recovery(recovery)
+                        .with_history(history_owned)
+                        .with_cancellation(cancel)
+                        .with_usage_meter(meter);
                     if let Some(ref memory) = memory { agent = agent.with_memory(memory.clone()); }
                     agent = agent.with_safety(safety);
                     agent.execute_with_steering(&query_owned, Some(&context_owned), Some(steer_rx)).await
@@ -579,6 +705,7 @@ impl Supervisor {
                 let agent_type = final_routing.candidate_agents[i];
                 match tr {
                     Ok(Ok(res)) => {
+                        turn_usage += res.usage;
                         portfolio.candidates.push(Candidate {
                             agent_id: format!("{:?}", agent_type),
                             answer: res.answer.clone(),
@@ -631,9 +758,23 @@ impl Supervisor {
             }
         }
 
-        let final_res = final_res.ok_or_else(|| AgentError::Execution("All execution attempts and escalations failed".to_string()))?;
+        let final_res = final_res.ok_or_else(|| if turn.is_cancelled() {
+            AgentError::Cancelled("turn cancelled before any agent finished".to_string())
+        } else {
+            AgentError::Execution("All execution attempts and escalations failed".to_string())
+        })?;
         let latency_ms = _work_start_time.elapsed().as_millis();
 
+        // Memories the turn was grounded in share its outcome; a paused turn has none yet
+        if let Some(ref memory) = self.memory {
+            if !injected_memories.is_empty() && final_res.pending_approval.is_none() {
+                if let Err(e) = memory.reinforce(&injected_memories, Reinforcement::Outcome(final_res.success)).await {
+                    warn!("Failed to reinforce context memories: {}", e);
+                }
+            }
+        }
+        self.last_turn_memories = injected_memories;
+
         // Emit FPF-Aligned Publication Characteristics (E.17.5.5)
         emit_event!(AgencyEvent::PublicationUpdate {
             pc: PubCharacteristic {
@@ -698,6 +839,7 @@ impl Supervisor {
             publication: Some(publication),
             pending_approval: final_res.pending_approval,
             has_followup: !self.followup_queue.lock().await.is_empty(),
+            usage: turn_usage,
         })
     }
 
@@ -721,7 +863,8 @@ impl Supervisor {
         let provider = self.create_cached_provider();
         let tools = self.tools.clone();
         
-        let agent = ReActAgent::new_with_provider(provider, config, tools);
+        let agent = ReActAgent::new_with_provider(provider, config, tools)
+            .with_usage_meter(self.session_usage.clone());
 
         agent.execute(query, Some(&full_context)).await
     }
@@ -730,13 +873,15 @@ impl Supervisor {
         let provider = self.create_cached_provider();
         let objective = Objective::new(goal);
         let mut machine = AutonomousMachine::new_with_provider(provider.clone(), self.tools.clone(), &self.profile, objective);
-        machine = machine.with_provider(provider);
+        machine = machine.with_provider(provider).with_usage_meter(self.session_usage.clone());
         
         let mut last_res = AgentResponse::failure("Autonomous loop failed to start", Vec::new(), AgentType::Coder);
+        let mut usage = TokenUsage::default();
         for i in 0..5 {
             info!("Autonomous iteration {}/5", i + 1);
             match machine.run_iteration().await {
                 Ok(res) => {
+                    usage += res.usage;
                     let success = res.success;
                     last_res = res;
                     if success { break; }
@@ -777,6 +922,7 @@ impl Supervisor {
             publication: Some(publication),
             pending_approval: None,
             has_followup: false,
+            usage,
         })
     }
 }
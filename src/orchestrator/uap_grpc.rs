@@ -7,6 +7,7 @@ use std::sync::Arc;
 use tonic::{Request, Response, Status};
 use crate::agent::SovereignAgent; 
 use pai_core::sap::AuditStatus;
+use pai_core::uap::UapArtifact;
 
 // Import the generated gRPC code
 pub mod proto {
@@ -27,6 +28,18 @@ impl UapGrpcWrapper {
     }
 }
 
+/// Page size used when a `ListArtifacts` request leaves it unset
+const DEFAULT_ARTIFACT_PAGE_SIZE: usize = 50;
+
+fn artifact_to_proto(artifact: UapArtifact) -> proto::Artifact {
+    proto::Artifact {
+        artifact_id: artifact.artifact_id,
+        file_name: artifact.file_name,
+        relative_path: artifact.relative_path,
+        hash: artifact.hash,
+    }
+}
+
 #[tonic::async_trait]
 impl AgentService for UapGrpcWrapper {
     async fn create_task(
@@ -42,7 +55,7 @@ impl AgentService for UapGrpcWrapper {
         Ok(Response::new(proto::Task {
             task_id: task.task_id,
             input: task.input,
-            artifacts: vec![],
+            artifacts: task.artifacts.into_iter().map(artifact_to_proto).collect(),
             created_at: None,
             updated_at: None,
             status: 1, // Created
@@ -118,7 +131,7 @@ impl AgentService for UapGrpcWrapper {
             input: step.input,
             output: step.output,
             status: proto::StepStatus::Completed as i32,
-            artifacts: vec![],
+            artifacts: step.artifacts.into_iter().map(artifact_to_proto).collect(),
             is_last: step.is_last,
             phase_metadata: step.phase_metadata,
         }))
@@ -139,11 +152,14 @@ impl AgentService for UapGrpcWrapper {
         let task = self.agent.get_task(&req.task_id)
             .await
             .map_err(|e| Status::internal(e.to_string()))?;
+        let artifacts = self.agent.list_artifacts(&req.task_id)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
 
         Ok(Response::new(proto::Task {
             task_id: task.task_id,
             input: task.input,
-            artifacts: vec![],
+            artifacts: artifacts.into_iter().map(artifact_to_proto).collect(),
             created_at: None,
             updated_at: None,
             status: 1, // Created
@@ -170,11 +186,32 @@ impl AgentService for UapGrpcWrapper {
 
     async fn list_artifacts(
         &self,
-        _request: Request<proto::ListArtifactsRequest>,
+        request: Request<proto::ListArtifactsRequest>,
     ) -> Result<Response<proto::ListArtifactsResponse>, Status> {
+        let req = request.into_inner();
+        let artifacts = self.agent.list_artifacts(&req.task_id)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        // Page tokens are plain offsets into the (newest first) listing
+        let offset: usize = if req.page_token.is_empty() {
+            0
+        } else {
+            req.page_token.parse()
+                .map_err(|_| Status::invalid_argument(format!("Invalid page_token: {}", req.page_token)))?
+        };
+        let page_size = if req.page_size > 0 { req.page_size as usize } else { DEFAULT_ARTIFACT_PAGE_SIZE };
+        let total = artifacts.len();
+        let page: Vec<proto::Artifact> = artifacts.into_iter()
+            .skip(offset)
+            .take(page_size)
+            .map(artifact_to_proto)
+            .collect();
+        let next = offset + page.len();
+
         Ok(Response::new(proto::ListArtifactsResponse {
-            artifacts: vec![],
-            next_page_token: "".to_string(),
+            artifacts: page,
+            next_page_token: if next < total { next.to_string() } else { String::new() },
         }))
     }
 }
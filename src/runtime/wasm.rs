@@ -1,36 +1,438 @@
-use wasmer::{Store, Module, Instance, Value, Imports};
-use anyhow::{Result, Context};
-use std::path::Path;
+//! WASM Runtime
+//!
+//! Runs agent-compiled modules under wasmtime with hard resource limits:
+//! fuel metering (an instruction budget), a linear-memory cap, and a
+//! wall-clock deadline enforced through epoch interruption. Compiled modules
+//! are cached by the SHA-256 of their bytes, so repeated calls skip
+//! compilation.
+//!
+//! Three calling conventions are supported:
+//! - **WASI command** (`_start`): input on stdin, result on stdout. The
+//!   workspace, if any, is preopened as `/`.
+//! - **JSON ABI**: the module exports `memory`, `alloc(len: i32) -> i32` and
+//!   `f(ptr: i32, len: i32) -> i64`, which receives UTF-8 JSON and returns
+//!   `(out_ptr << 32) | out_len` of its UTF-8 JSON result.
+//! - **Numeric**: plain exports taking and returning i32/i64/f32/f64.
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value as JsonValue};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use wasmtime::{Config, Engine, Instance, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, Trap, Val, ValType};
+use wasmtime_wasi::pipe::{MemoryInputPipe, MemoryOutputPipe};
+use wasmtime_wasi::preview1::{self, WasiP1Ctx};
+use wasmtime_wasi::{DirPerms, FilePerms, I32Exit, WasiCtxBuilder};
+
+/// Granularity of the wall-clock deadline
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+/// Compiled modules kept in memory
+const MODULE_CACHE_CAPACITY: usize = 32;
+
+/// Resource limits applied to every call
+#[derive(Debug, Clone)]
+pub struct WasmLimits {
+    /// Fuel units (roughly one per executed instruction)
+    pub fuel: u64,
+    /// Cap on linear memory, in bytes
+    pub memory_bytes: usize,
+    /// Wall-clock limit for instantiation plus the call
+    pub timeout: Duration,
+    /// Cap on captured stdout/stderr and JSON results, in bytes
+    pub max_output_bytes: usize,
+}
+
+impl Default for WasmLimits {
+    fn default() -> Self {
+        Self {
+            fuel: 1_000_000_000,
+            memory_bytes: 64 * 1024 * 1024,
+            timeout: Duration::from_secs(10),
+            max_output_bytes: 1024 * 1024,
+        }
+    }
+}
+
+/// Result of running a WASI command module
+#[derive(Debug, Clone)]
+pub struct WasmOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: i32,
+    pub fuel_used: u64,
+}
+
+struct StoreState {
+    wasi: WasiP1Ctx,
+    limits: StoreLimits,
+}
+
+/// Compiled modules by content hash, evicting the oldest beyond capacity
+#[derive(Default)]
+struct ModuleCache {
+    modules: HashMap<String, Module>,
+    order: VecDeque<String>,
+}
 
 pub struct WasmRuntime {
-    store: Store,
+    engine: Engine,
+    linker: Linker<StoreState>,
+    limits: WasmLimits,
+    workspace: Option<PathBuf>,
+    modules: Mutex<ModuleCache>,
+    ticker_stop: Arc<AtomicBool>,
 }
 
 impl WasmRuntime {
     pub fn new() -> Self {
-        Self {
-            store: Store::default(),
+        Self::with_limits(WasmLimits::default())
+    }
+
+    pub fn with_limits(limits: WasmLimits) -> Self {
+        let mut config = Config::new();
+        config.consume_fuel(true).epoch_interruption(true);
+        let engine = Engine::new(&config).expect("wasmtime engine configuration is valid");
+
+        let mut linker = Linker::new(&engine);
+        preview1::add_to_linker_sync(&mut linker, |s: &mut StoreState| &mut s.wasi)
+            .expect("WASI preview1 imports register once");
+
+        // Advance the engine epoch so store deadlines measure wall-clock time
+        let ticker_stop = Arc::new(AtomicBool::new(false));
+        {
+            let engine = engine.clone();
+            let stop = ticker_stop.clone();
+            std::thread::Builder::new()
+                .name("wasm-epoch".to_string())
+                .spawn(move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        std::thread::sleep(EPOCH_TICK);
+                        engine.increment_epoch();
+                    }
+                })
+                .expect("spawn wasm epoch thread");
         }
+
+        Self { engine, linker, limits, workspace: None, modules: Mutex::new(ModuleCache::default()), ticker_stop }
+    }
+
+    /// Preopen `dir` as the guest's `/` for WASI filesystem access
+    pub fn with_workspace(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.workspace = Some(dir.into());
+        self
+    }
+
+    pub fn limits(&self) -> &WasmLimits {
+        &self.limits
+    }
+
+    /// Number of compiled modules currently cached
+    pub fn cached_modules(&self) -> usize {
+        self.modules.lock().unwrap().modules.len()
+    }
+
+    /// Compile `bytes` (binary or WAT), reusing a cached module with the same hash
+    fn module(&self, bytes: &[u8]) -> Result<Module> {
+        let key = hex::encode(Sha256::digest(bytes));
+        if let Some(module) = self.modules.lock().unwrap().modules.get(&key) {
+            return Ok(module.clone());
+        }
+        let module = Module::new(&self.engine, bytes).context("Failed to compile WASM module")?;
+        let mut cache = self.modules.lock().unwrap();
+        if !cache.modules.contains_key(&key) {
+            if cache.order.len() >= MODULE_CACHE_CAPACITY {
+                if let Some(oldest) = cache.order.pop_front() {
+                    cache.modules.remove(&oldest);
+                }
+            }
+            cache.order.push_back(key.clone());
+            cache.modules.insert(key, module.clone());
+        }
+        Ok(module)
+    }
+
+    /// Fresh store with limits, fuel and deadline armed, and an instance of the module
+    fn instantiate(&self, wasm_path: &Path, stdin: &[u8], args: &[String]) -> Result<Prepared> {
+        let bytes = std::fs::read(wasm_path).context("Failed to read WASM file")?;
+        let module = self.module(&bytes)?;
+
+        let stdout = MemoryOutputPipe::new(self.limits.max_output_bytes);
+        let stderr = MemoryOutputPipe::new(self.limits.max_output_bytes);
+        let mut wasi = WasiCtxBuilder::new();
+        wasi.stdin(MemoryInputPipe::new(stdin.to_vec()))
+            .stdout(stdout.clone())
+            .stderr(stderr.clone())
+            .arg(wasm_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default())
+            .args(args);
+        if let Some(dir) = &self.workspace {
+            wasi.preopened_dir(dir, "/", DirPerms::all(), FilePerms::all())
+                .with_context(|| format!("Failed to preopen workspace {}", dir.display()))?;
+        }
+
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.limits.memory_bytes)
+            .trap_on_grow_failure(true)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&self.engine, StoreState { wasi: wasi.build_p1(), limits });
+        store.limiter(|s| &mut s.limits);
+        store.set_fuel(self.limits.fuel)?;
+        store.set_epoch_deadline(self.limits.timeout.as_millis().div_ceil(EPOCH_TICK.as_millis()) as u64 + 1);
+
+        let instance = self.linker.instantiate(&mut store, &module).map_err(|e| self.describe(e))?;
+        // Reactor modules expect their initializer before any export is called
+        if let Ok(init) = instance.get_typed_func::<(), ()>(&mut store, "_initialize") {
+            init.call(&mut store, ()).map_err(|e| self.describe(e))?;
+        }
+        Ok(Prepared { store, instance, stdout, stderr })
+    }
+
+    /// Turn fuel, deadline and memory traps into actionable messages
+    fn describe(&self, err: anyhow::Error) -> anyhow::Error {
+        match err.downcast_ref::<Trap>() {
+            Some(Trap::OutOfFuel) => anyhow::anyhow!("WASM module ran out of fuel (budget {})", self.limits.fuel),
+            Some(Trap::Interrupt) => anyhow::anyhow!("WASM module exceeded its {:?} wall-clock limit", self.limits.timeout),
+            _ if err.chain().any(|e| e.to_string().contains("growing memory")) => {
+                anyhow::anyhow!("WASM module exceeded its {} byte memory limit", self.limits.memory_bytes)
+            }
+            _ => err,
+        }
+    }
+
+    fn fuel_used(&self, store: &Store<StoreState>) -> u64 {
+        self.limits.fuel.saturating_sub(store.get_fuel().unwrap_or(0))
+    }
+
+    /// Run a WASI command module with `stdin` as input
+    pub fn run_wasi(&self, wasm_path: &Path, stdin: &[u8], args: &[String]) -> Result<WasmOutput> {
+        let Prepared { mut store, instance, stdout, stderr } = self.instantiate(wasm_path, stdin, args)?;
+        let start = instance
+            .get_typed_func::<(), ()>(&mut store, "_start")
+            .context("Module has no `_start`; compile it as a WASI command (wasm32-wasip1 binary)")?;
+        let exit_code = match start.call(&mut store, ()) {
+            Ok(()) => 0,
+            Err(e) => match e.downcast_ref::<I32Exit>() {
+                Some(exit) => exit.0,
+                None => return Err(self.describe(e)),
+            },
+        };
+        Ok(WasmOutput {
+            stdout: String::from_utf8_lossy(&stdout.contents()).to_string(),
+            stderr: String::from_utf8_lossy(&stderr.contents()).to_string(),
+            exit_code,
+            fuel_used: self.fuel_used(&store),
+        })
     }
 
-    /// Run a specific function from a WASM file with numeric arguments
-    /// Currently supports (i32, i32) -> i32 for simplicity.
-    pub fn execute(&mut self, wasm_path: &Path, func_name: &str, args: &[i32]) -> Result<i32> {
-        let wasm_bytes = std::fs::read(wasm_path).context("Failed to read WASM file")?;
-        let module = Module::new(&self.store, wasm_bytes).context("Failed to compile WASM module")?;
-        
-        let import_object = Imports::new();
-        let instance = Instance::new(&mut self.store, &module, &import_object).context("Failed to instantiate WASM module")?;
+    /// Call `func_name` through the JSON ABI. Non-JSON output is returned as a string.
+    pub fn call_json(&self, wasm_path: &Path, func_name: &str, input: &JsonValue) -> Result<(JsonValue, u64)> {
+        let Prepared { mut store, instance, .. } = self.instantiate(wasm_path, &[], &[])?;
+        let memory = instance.get_memory(&mut store, "memory").context("JSON ABI requires an exported `memory`")?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&mut store, "alloc")
+            .context("JSON ABI requires an exported `alloc(len: i32) -> i32`")?;
+        let func = instance
+            .get_typed_func::<(i32, i32), i64>(&mut store, func_name)
+            .with_context(|| format!("JSON ABI requires `{}(ptr: i32, len: i32) -> i64`", func_name))?;
 
-        let func = instance.exports.get_function(func_name).context("Function not found")?;
-        
-        let wasm_args: Vec<Value> = args.iter().map(|&x| Value::I32(x)).collect();
-        let result = func.call(&mut self.store, &wasm_args).context("Failed to call function")?;
+        let bytes = serde_json::to_vec(input)?;
+        let ptr = alloc.call(&mut store, bytes.len() as i32).map_err(|e| self.describe(e))?;
+        memory.write(&mut store, ptr as u32 as usize, &bytes).context("alloc returned an out-of-bounds pointer")?;
+        let packed = func.call(&mut store, (ptr, bytes.len() as i32)).map_err(|e| self.describe(e))? as u64;
 
-        if let Some(Value::I32(res)) = result.get(0) {
-            Ok(*res)
-        } else {
-            Err(anyhow::anyhow!("Function returned unexpected type or no value"))
+        let (out_ptr, out_len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        if out_len > self.limits.max_output_bytes {
+            anyhow::bail!("WASM result of {} bytes exceeds the {} byte output limit", out_len, self.limits.max_output_bytes);
         }
+        let mut out = vec![0; out_len];
+        memory.read(&store, out_ptr, &mut out).context("Result pointer is out of bounds")?;
+        let text = String::from_utf8(out).context("Result is not UTF-8")?;
+        let value = serde_json::from_str(&text).unwrap_or(JsonValue::String(text));
+        Ok((value, self.fuel_used(&store)))
+    }
+
+    /// Call a function with numeric parameters, converting `args` to its signature
+    pub fn call_numeric(&self, wasm_path: &Path, func_name: &str, args: &[JsonValue]) -> Result<(Vec<JsonValue>, u64)> {
+        let Prepared { mut store, instance, .. } = self.instantiate(wasm_path, &[], &[])?;
+        let func = instance.get_func(&mut store, func_name).context("Function not found")?;
+        let ty = func.ty(&store);
+
+        let params: Vec<ValType> = ty.params().collect();
+        if params.len() != args.len() {
+            anyhow::bail!("`{}` takes {} arguments ({}), got {}", func_name, params.len(), signature(&params), args.len());
+        }
+        let wasm_args = params
+            .iter()
+            .zip(args)
+            .map(|(ty, arg)| to_val(ty, arg).with_context(|| format!("Argument {} does not fit {}", arg, ty)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut results = vec![Val::I32(0); ty.results().len()];
+        func.call(&mut store, &wasm_args, &mut results).map_err(|e| self.describe(e))?;
+
+        let results = results.iter().map(from_val).collect::<Result<Vec<_>>>()?;
+        Ok((results, self.fuel_used(&store)))
+    }
+
+    /// Run a specific function from a WASM file with i32 arguments and an i32 result
+    pub fn execute(&self, wasm_path: &Path, func_name: &str, args: &[i32]) -> Result<i32> {
+        let args: Vec<JsonValue> = args.iter().map(|&a| json!(a)).collect();
+        let (results, _) = self.call_numeric(wasm_path, func_name, &args)?;
+        results
+            .first()
+            .and_then(|v| v.as_i64())
+            .map(|v| v as i32)
+            .ok_or_else(|| anyhow::anyhow!("Function returned unexpected type or no value"))
+    }
+}
+
+impl Default for WasmRuntime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for WasmRuntime {
+    fn drop(&mut self) {
+        self.ticker_stop.store(true, Ordering::Relaxed);
+    }
+}
+
+struct Prepared {
+    store: Store<StoreState>,
+    instance: Instance,
+    stdout: MemoryOutputPipe,
+    stderr: MemoryOutputPipe,
+}
+
+fn signature(params: &[ValType]) -> String {
+    params.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+fn to_val(ty: &ValType, arg: &JsonValue) -> Option<Val> {
+    match ty {
+        ValType::I32 => arg.as_i64().and_then(|v| i32::try_from(v).ok()).map(Val::I32),
+        ValType::I64 => arg.as_i64().map(Val::I64),
+        ValType::F32 => arg.as_f64().map(|v| Val::F32((v as f32).to_bits())),
+        ValType::F64 => arg.as_f64().map(|v| Val::F64(v.to_bits())),
+        _ => None,
+    }
+}
+
+fn from_val(val: &Val) -> Result<JsonValue> {
+    Ok(match val {
+        Val::I32(v) => json!(v),
+        Val::I64(v) => json!(v),
+        Val::F32(bits) => json!(f32::from_bits(*bits)),
+        Val::F64(bits) => json!(f64::from_bits(*bits)),
+        other => anyhow::bail!("Unsupported result type {:?}", other),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(dir: &tempfile::TempDir, name: &str, wat: &str) -> PathBuf {
+        let path = dir.path().join(name);
+        std::fs::write(&path, wat).unwrap();
+        path
+    }
+
+    const ADD: &str = r#"(module
+        (func (export "add") (param i32 i32) (result i32) local.get 0 local.get 1 i32.add)
+        (func (export "scale") (param f64 i64) (result f64) local.get 0 local.get 1 f64.convert_i64_s f64.mul)
+        (func (export "spin") (loop br 0)))"#;
+
+    #[test]
+    fn test_numeric_calls_and_module_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = module(&dir, "add.wat", ADD);
+        let runtime = WasmRuntime::new();
+
+        assert_eq!(runtime.execute(&path, "add", &[2, 40]).unwrap(), 42);
+        let (results, fuel) = runtime.call_numeric(&path, "scale", &[json!(1.5), json!(4)]).unwrap();
+        assert_eq!(results, vec![json!(6.0)]);
+        assert!(fuel > 0);
+        assert_eq!(runtime.cached_modules(), 1, "same bytes compile once");
+
+        let err = runtime.call_numeric(&path, "add", &[json!(1)]).unwrap_err();
+        assert!(err.to_string().contains("takes 2 arguments (i32, i32)"), "{}", err);
+    }
+
+    #[test]
+    fn test_fuel_and_deadline_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = module(&dir, "add.wat", ADD);
+
+        let metered = WasmRuntime::with_limits(WasmLimits { fuel: 10_000, ..WasmLimits::default() });
+        let err = metered.call_numeric(&path, "spin", &[]).unwrap_err();
+        assert!(err.to_string().contains("ran out of fuel"), "{}", err);
+
+        let timed = WasmRuntime::with_limits(WasmLimits {
+            fuel: 1 << 60,
+            timeout: Duration::from_millis(50),
+            ..WasmLimits::default()
+        });
+        let started = std::time::Instant::now();
+        let err = timed.call_numeric(&path, "spin", &[]).unwrap_err();
+        assert!(err.to_string().contains("wall-clock limit"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_memory_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = module(&dir, "grow.wat", r#"(module
+            (memory 1)
+            (func (export "grow") (param i32) (result i32) local.get 0 memory.grow))"#);
+        let runtime = WasmRuntime::with_limits(WasmLimits { memory_bytes: 4 * 65536, ..WasmLimits::default() });
+
+        assert_eq!(runtime.execute(&path, "grow", &[2]).unwrap(), 1);
+        let err = runtime.execute(&path, "grow", &[16]).unwrap_err();
+        assert!(err.to_string().contains("memory limit"), "{}", err);
+    }
+
+    #[test]
+    fn test_json_abi_echo() {
+        let dir = tempfile::tempdir().unwrap();
+        // Echoes its input: result pointer/length are the input's
+        let path = module(&dir, "echo.wat", r#"(module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) i32.const 1024)
+            (func (export "echo") (param i32 i32) (result i64)
+                local.get 0 i64.extend_i32_u i64.const 32 i64.shl
+                local.get 1 i64.extend_i32_u i64.or))"#);
+        let runtime = WasmRuntime::new();
+
+        let input = json!({ "skill": "sum", "values": [1, 2, 3] });
+        let (output, _) = runtime.call_json(&path, "echo", &input).unwrap();
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn test_wasi_stdin_stdout_and_exit_code() {
+        let dir = tempfile::tempdir().unwrap();
+        // Copies up to 1KiB of stdin to stdout, then exits with status 3
+        let path = module(&dir, "cat.wat", r#"(module
+            (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+            (memory (export "memory") 1)
+            (func (export "_start")
+                (i32.store (i32.const 0) (i32.const 64))
+                (i32.store (i32.const 4) (i32.const 1024))
+                (drop (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8)))
+                (i32.store (i32.const 4) (i32.load (i32.const 8)))
+                (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 12)))
+                (call $proc_exit (i32.const 3))))"#);
+        let runtime = WasmRuntime::new().with_workspace(dir.path());
+
+        let out = runtime.run_wasi(&path, br#"{"n": 7}"#, &[]).unwrap();
+        assert_eq!(out.stdout, r#"{"n": 7}"#);
+        assert_eq!(out.exit_code, 3);
+        assert!(out.fuel_used > 0);
     }
 }
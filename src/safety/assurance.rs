@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use crate::tools::Tool;
+use crate::tools::{schema, Tool};
 use std::sync::Arc;
 
 /// FPF-aligned Trust & Assurance Calculus (B.3)
@@ -20,12 +20,8 @@ pub struct AssuranceScore {
 impl AssuranceScore {
     pub fn calculate(tool: Arc<dyn Tool>, params: &Value) -> Self {
         // 1. Calculate Formality (F)
-        // Check if parameters match the expected schema (simplified)
-        let f = if params.is_object() && !params.as_object().unwrap().is_empty() {
-            1.0
-        } else {
-            0.5
-        };
+        // Fraction of the tool's declared schema the parameters conform to
+        let f = schema::validate(&tool.parameters(), params).conformance();
 
         // 2. Calculate Scope Alignment (G)
         // Check params against the tool's WorkScope
@@ -58,3 +54,42 @@ impl AssuranceScore {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::ToolOutput;
+    use crate::agent::AgentResult;
+    use async_trait::async_trait;
+    use serde_json::json;
+
+    struct ReadTool;
+
+    #[async_trait]
+    impl Tool for ReadTool {
+        fn name(&self) -> String { "read".to_string() }
+        fn description(&self) -> String { "Read a file".to_string() }
+        fn parameters(&self) -> Value {
+            json!({
+                "type": "object",
+                "properties": { "path": { "type": "string" }, "lines": { "type": "integer" } },
+                "required": ["path"]
+            })
+        }
+        async fn execute(&self, _params: Value) -> AgentResult<ToolOutput> {
+            Ok(ToolOutput::success_str("ok"))
+        }
+    }
+
+    #[test]
+    fn test_formality_tracks_schema_conformance() {
+        let good = AssuranceScore::calculate(Arc::new(ReadTool), &json!({ "path": "a.rs", "lines": 5 }));
+        assert_eq!(good.f, 1.0);
+        assert!(good.is_trustworthy());
+
+        // Non-empty but wrong: previously scored full formality
+        let bad = AssuranceScore::calculate(Arc::new(ReadTool), &json!({ "file": "a.rs", "lines": "five" }));
+        assert!(bad.f < 0.7, "f = {}", bad.f);
+        assert!(!bad.is_trustworthy());
+    }
+}
@@ -60,6 +60,7 @@ impl RateLimiter {
         
         // Configure limits for different operations
         buckets.insert("web_search".to_string(), TokenBucket::new(10, 60)); // 10 per minute
+        buckets.insert("web_fetch".to_string(), TokenBucket::new(20, 60));  // 20 per minute
         buckets.insert("code_exec".to_string(), TokenBucket::new(5, 60));   // 5 per minute
         buckets.insert("llm_call".to_string(), TokenBucket::new(30, 60));   // 30 per minute
         
@@ -1,5 +1,5 @@
 use axum::{
-    extract::{Json, State, ws::{WebSocketUpgrade, Message as WsMessage}},
+    extract::{Json, Path, Query, State, ws::{WebSocketUpgrade, Message as WsMessage}},
     response::{IntoResponse, Html, Response, sse::{Event, Sse}},
     routing::{get, post},
     Router,
@@ -13,9 +13,10 @@ use futures_util::{StreamExt, SinkExt};
 use axum::http::StatusCode;
 use tower_http::trace::TraceLayer;
 
-use crate::agent::{Speaker, LLMProvider};
+use crate::agent::{Speaker, LLMProvider, ChatMessage, GenerationOptions};
 use crate::memory::EpisodicMemory;
-use crate::orchestrator::Supervisor;
+use crate::orchestrator::{SessionUsage, Supervisor, TurnCanceller};
+use crate::tools::{ArtifactFilter, ArtifactOwner, ArtifactStore};
 
 // --- SOTA: Robust Error Handling ---
 pub struct ServerError(anyhow::Error);
@@ -42,7 +43,30 @@ pub struct AppState {
     pub tx: broadcast::Sender<String>,
     pub episodic_memory: Arc<Mutex<EpisodicMemory>>,
     pub supervisor: Arc<Mutex<Supervisor>>,
-    pub current_task: Arc<Mutex<Option<tokio::task::AbortHandle>>>
+    /// Session token accounting, shared with the supervisor
+    pub usage: SessionUsage,
+    /// Cancels the supervisor's running turn
+    pub turns: TurnCanceller,
+    pub current_task: Arc<Mutex<Option<tokio::task::AbortHandle>>>,
+    /// Versioned artifacts produced by tools, listed on the dashboard
+    pub artifacts: Arc<ArtifactStore>,
+}
+
+/// Artifacts are private to the caller's UAP task and session, given as
+/// `task_id` and `session_id` (the dashboard's own turns have neither)
+#[derive(Deserialize)]
+struct ArtifactContentQuery {
+    version: Option<u32>,
+    task_id: Option<String>,
+    session_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FeedbackRequest {
+    /// 0-10
+    rating: u8,
+    #[serde(default)]
+    reason: String,
 }
 
 #[derive(Deserialize)]
@@ -159,6 +183,11 @@ pub async fn run_server(state: AppState) -> Result<()> {
         .route("/v1/responses", post(crate::services::responses::responses_handler))
         .route("/v1/a2a/interact", post(a2a_interact_handler))
         .route("/v1/memory/clear", post(clear_memory))
+        .route("/v1/usage", get(usage_report))
+        .route("/v1/turns/cancel", post(cancel_turn))
+        .route("/v1/feedback", post(turn_feedback))
+        .route("/v1/artifacts", get(list_artifacts))
+        .route("/v1/artifacts/{id}", get(artifact_content))
         .layer(TraceLayer::new_for_http())
         .with_state(state);
 
@@ -177,6 +206,81 @@ async fn clear_memory(State(state): State<AppState>) -> impl IntoResponse {
     (StatusCode::OK, Json(serde_json::json!({ "status": "cleared" })))
 }
 
+async fn usage_report(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.usage.report())
+}
+
+async fn cancel_turn(State(state): State<AppState>) -> impl IntoResponse {
+    let cancelled = state.turns.cancel();
+    let status = if cancelled { StatusCode::OK } else { StatusCode::CONFLICT };
+    (status, Json(serde_json::json!({ "cancelled": cancelled })))
+}
+
+/// Rate the last turn; the memories it used are re-weighted accordingly
+async fn turn_feedback(State(state): State<AppState>, Json(feedback): Json<FeedbackRequest>) -> Result<Response, ServerError> {
+    if feedback.rating > 10 {
+        return Ok((StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": "rating must be 0-10" }))).into_response());
+    }
+    // A running turn would take the rating meant for the previous one
+    let Ok(supervisor) = state.supervisor.try_lock() else {
+        return Ok((StatusCode::CONFLICT, Json(serde_json::json!({ "error": "a turn is in progress" }))).into_response());
+    };
+    let signal = pai_core::learning::Signal {
+        timestamp: chrono::Utc::now(),
+        session_id: "dashboard".to_string(),
+        signal_type: pai_core::learning::SignalType::Rating(feedback.rating),
+        phase: "feedback".to_string(),
+        reason: feedback.reason,
+    };
+    let updated = supervisor.apply_signal(&signal).await?;
+    Ok(Json(serde_json::json!({ "updated": updated })).into_response())
+}
+
+async fn list_artifacts(State(state): State<AppState>, Query(owner): Query<ArtifactOwner>) -> impl IntoResponse {
+    let filter = ArtifactFilter::owner(owner);
+    Json(serde_json::json!({ "artifacts": state.artifacts.list(&filter) }))
+}
+
+/// Raw content of an artifact version, served with its MIME type
+async fn artifact_content(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<ArtifactContentQuery>,
+) -> Result<Response, ServerError> {
+    let owner = ArtifactOwner { task_id: query.task_id, session_id: query.session_id };
+    let Some(record) = state.artifacts.get_by_id(&id).filter(|r| r.owner == owner) else {
+        return Ok((StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "artifact not found" }))).into_response());
+    };
+    let (version, content) = match state.artifacts.read(&record, query.version) {
+        Ok(found) => found,
+        Err(e) => return Ok((StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": e.to_string() }))).into_response()),
+    };
+    // Agent-written content must not run as this origin: no sniffing, a
+    // sandboxed document, and anything but plain text, JSON or images is
+    // downloaded rather than rendered
+    let mime = record.mime_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    let inline = mime == "text/plain" || mime == "application/json" || (mime.starts_with("image/") && mime != "image/svg+xml");
+    let disposition = if inline { "inline" } else { "attachment" };
+    let file_name: String = record
+        .name
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_graphic() && *c != '"' && *c != '\\')
+        .collect();
+    Ok((
+        [
+            (axum::http::header::CONTENT_TYPE, record.mime_type.clone()),
+            (axum::http::header::ETAG, format!("\"{}\"", version.hash)),
+            (axum::http::header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+            (axum::http::header::CONTENT_SECURITY_POLICY, "sandbox".to_string()),
+            (axum::http::header::CONTENT_DISPOSITION, format!("{}; filename=\"{}\"", disposition, file_name)),
+        ],
+        content,
+    ).into_response())
+}
+
 async fn a2a_interact_handler(
     State(state): State<AppState>,
     Json(interaction): Json<crate::orchestrator::a2a::AgentInteraction>,
@@ -232,6 +336,9 @@ async fn dashboard(State(state): State<AppState>) -> impl IntoResponse {
         .message-user {{ color: #888; font-style: italic; border-left: 2px solid #444; padding-left: 10px; margin-bottom: 15px; }}
         .r-value-display {{ font-size: 42px; font-weight: 200; font-family: var(--font-mono); color: var(--accent-assurance); text-align: center; }}
         .assurance-log {{ flex: 1; font-family: var(--font-mono); font-size: 10px; color: #555; overflow-y: auto; padding: 10px; }}
+        .artifact-list {{ max-height: 30%; overflow-y: auto; font-family: var(--font-mono); font-size: 10px; border-top: 1px solid var(--border-color); padding: 10px; }}
+        .artifact-list a {{ color: var(--accent-assurance); text-decoration: none; }}
+        .artifact-meta {{ color: #444; }}
         .input-area {{ background: #0a0a0a; border-top: 1px solid var(--border-color); display: flex; align-items: center; padding: 0 20px; gap: 15px; }}
         #chat-input {{ flex: 1; background: transparent; border: none; color: #fff; outline: none; font-size: 14px; font-family: var(--font-ui); }}
         .btn {{ background: #222; border: 1px solid #333; color: #ccc; padding: 6px 12px; font-size: 11px; border-radius: 4px; cursor: pointer; }}
@@ -262,6 +369,8 @@ async fn dashboard(State(state): State<AppState>) -> impl IntoResponse {
                 <div class="r-value-display" id="r-value">1.00</div>
                 <div style="font-size:9px; color:#444; text-align:center; margin-bottom:20px;">CONFIDENCE SCORE</div>
                 <div class="assurance-log" id="assurance-log"></div>
+                <div style="font-size:9px; color:#444; letter-spacing:1.5px; margin-top:10px;">ARTIFACTS</div>
+                <div class="artifact-list" id="artifact-list"></div>
             </div>
         </div>
     </div>
@@ -338,7 +447,7 @@ async fn dashboard(State(state): State<AppState>) -> impl IntoResponse {
             else if (data.startsWith('STATE:')) {{
                 if (data.startsWith('STATE:ANSWER_START')) {{ isAnswerMode = true; currentPlainBlock = null; currentPlainRaw = ''; if (currentTechBlock) {{ const full = currentTechBlock.textContent; const match = full.match(/[[A-Z]ANSWER]*|ANSWER:?$/i); if (match) currentTechBlock.textContent = full.substring(0, match.index).trim(); }} }} 
                 else if (data.startsWith('STATE:THOUGHT_START')) {{ isAnswerMode = false; currentTechBlock = null; }} 
-                else if (data.startsWith('STATE:TURN_COMPLETE') || data.startsWith('STATE:STOPPED')) {{ isAnswerMode = false; currentTechBlock = null; currentPlainBlock = null; currentPlainRaw = ''; sendBtn.style.display = 'inline-block'; stopBtn.style.display = 'none'; }} 
+                else if (data.startsWith('STATE:TURN_COMPLETE') || data.startsWith('STATE:STOPPED')) {{ isAnswerMode = false; currentTechBlock = null; currentPlainBlock = null; currentPlainRaw = ''; sendBtn.style.display = 'inline-block'; stopBtn.style.display = 'none'; refreshArtifacts(); }} 
                 else if (data.startsWith('STATE:ABORTED')) {{ isAnswerMode = false; currentTechBlock = null; currentPlainBlock = null; currentPlainRaw = ''; }}
                 logAssurance('System', data);
             }} else if (data.startsWith('🚀 Request')) {{
@@ -349,6 +458,30 @@ async fn dashboard(State(state): State<AppState>) -> impl IntoResponse {
             }}
         }};
 
+        async function refreshArtifacts() {{
+            try {{
+                const res = await fetch('/v1/artifacts');
+                const {{ artifacts }} = await res.json();
+                const list = document.getElementById('artifact-list');
+                list.replaceChildren();
+                if (!artifacts.length) {{ list.textContent = 'None yet.'; return; }}
+                for (const a of artifacts) {{
+                    const latest = a.versions[a.versions.length - 1];
+                    const row = document.createElement('div');
+                    const link = document.createElement('a');
+                    link.href = '/v1/artifacts/' + a.artifact_id;
+                    link.target = '_blank';
+                    link.textContent = a.name;
+                    const meta = document.createElement('span');
+                    meta.className = 'artifact-meta';
+                    meta.textContent = ` v${{latest.version}} · ${{a.mime_type}} · ${{latest.size}}B` + (a.owner.task_id ? ` · task ${{a.owner.task_id.slice(0, 8)}}` : '');
+                    row.append(link, meta);
+                    list.appendChild(row);
+                }}
+            }} catch (err) {{}}
+        }}
+        refreshArtifacts();
+
         function logAssurance(source, msg, color) {{ 
             const div = document.createElement('div');
             div.style.marginBottom = '5px';
@@ -492,19 +625,17 @@ async fn chat_completions(
     Json(req): Json<ChatRequest>,
 ) -> Result<impl IntoResponse, ServerError> {
     let last_msg = req.messages.last().map(|m| m.content.clone()).unwrap_or_default();
-    let history = { let mut memory = state.episodic_memory.lock().await; if !last_msg.is_empty() { memory.add_user(&last_msg); } memory.format_as_chatml() };
+    let history = { let mut memory = state.episodic_memory.lock().await; if !last_msg.is_empty() { memory.add_user(&last_msg); } memory.to_chat_messages() };
 
-    let prompt = format!(
-        "<|im_start|>system\nYou are a high-fidelity intelligence layer. 
+    let mut messages = vec![ChatMessage::system("You are a high-fidelity intelligence layer. 
 Follow the First Principles Framework (FPF): ALWAYS separate internal thought from external communication. 
-Use [THOUGHT] for your internal reasoning and [ANSWER] for the final user surface.<|im_end|>\n{}\n<|im_start|>assistant\n[THOUGHT]\n",
-        history
-    );
+Use [THOUGHT] for your internal reasoning and [ANSWER] for the final user surface.")];
+    messages.extend(history);
 
     let tx = state.tx.clone();
     let _ = tx.send(format!("🚀 Request (Streaming Inference)"));
 
-    let mut stream = state.provider.generate_stream("standard", prompt, None).await
+    let mut stream = state.provider.chat_stream("standard", messages, GenerationOptions::from_env()).await
         .map_err(|e| ServerError(e))?;
 
     if req.stream {
@@ -540,6 +671,7 @@ Use [THOUGHT] for your internal reasoning and [ANSWER] for the final user surfac
                 }
             }
             tts.flush().await;
+            state_c.usage.record("standard", "Nexus", stream.usage());
             let mut memory = state_c.episodic_memory.lock().await;
             memory.add_assistant(full_response, Some("Nexus".to_string()));
             let _ = sse_tx.send(Ok(Event::default().data("[DONE]")));
@@ -553,6 +685,7 @@ Use [THOUGHT] for your internal reasoning and [ANSWER] for the final user surfac
                 full_response.push_str(&chunk);
             }
         }
+        state.usage.record("standard", "Nexus", stream.usage());
         let mut memory = state.episodic_memory.lock().await;
         memory.add_assistant(full_response.clone(), Some("Nexus".to_string()));
         Ok(Json(ChatResponse { choices: vec![Choice { message: Message { role: "assistant".to_string(), content: full_response } } ] }).into_response())
@@ -0,0 +1,561 @@
+//! MCP Tool Server
+//!
+//! Serves a `ToolRegistry` to other MCP clients over stdio or streamable
+//! HTTP. Each `Tool` becomes an MCP tool definition (name, description and
+//! `parameters()` as `inputSchema`). Calls pass the PAI `PreToolUse` hooks
+//! and `SafetyGuard::check_tool_safety` before reaching the registry, so
+//! external clients get the same guardrails as the agency's own agents.
+//! Calls that would need human approval are refused, since an MCP client
+//! has no way to grant it; that includes every forged (dynamic) tool.
+//!
+//! The HTTP endpoint requires a bearer token, rejects browser origins that
+//! are not explicitly allowed, and only answers loopback `Host` names unless
+//! remote access was enabled. Non-loopback binds must be opted into.
+
+use anyhow::{Context, Result};
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use pai_core::{HookAction, HookEvent, HookEventType, HookManager};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, Mutex};
+use tracing::{info, warn};
+
+use crate::memory::{Memory, VectorMemory};
+use crate::safety::SafetyGuard;
+use crate::tools::{
+    ArtifactTool, CodebaseTool, KnowledgeGraphTool, MemoryQueryTool, SkillLoader, Tool, ToolCall, ToolRegistry,
+    DEFAULT_ARTIFACT_DIR,
+};
+
+const PROTOCOL_VERSIONS: [&str; 2] = ["2025-03-26", "2024-11-05"];
+/// How often stdio mode checks the registry for added or removed tools
+const LIST_CHANGE_POLL: Duration = Duration::from_secs(5);
+
+/// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+pub struct McpToolServer {
+    tools: Arc<ToolRegistry>,
+    safety: Arc<Mutex<SafetyGuard>>,
+    hooks: Option<Arc<HookManager>>,
+    exposed: Option<HashSet<String>>,
+    session_id: String,
+    /// Bearer token HTTP clients must present; without one HTTP is refused
+    http_token: Option<String>,
+    /// Browser origins allowed to call the HTTP endpoint
+    allowed_origins: HashSet<String>,
+    /// Whether non-loopback `Host` names are answered
+    remote_access: bool,
+}
+
+impl McpToolServer {
+    pub fn new(tools: Arc<ToolRegistry>) -> Self {
+        Self {
+            tools,
+            safety: Arc::new(Mutex::new(SafetyGuard::new())),
+            hooks: None,
+            exposed: None,
+            session_id: format!("mcp-{}", uuid::Uuid::new_v4()),
+            http_token: None,
+            allowed_origins: HashSet::new(),
+            remote_access: false,
+        }
+    }
+
+    pub fn with_http_token(mut self, token: impl Into<String>) -> Self {
+        self.http_token = Some(token.into());
+        self
+    }
+
+    /// Browser origins (e.g. `http://localhost:5173`) allowed over HTTP;
+    /// requests carrying any other `Origin` are rejected
+    pub fn with_allowed_origins(mut self, origins: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.allowed_origins = origins.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Answer HTTP requests addressed to non-loopback host names
+    pub fn with_remote_access(mut self, allow: bool) -> Self {
+        self.remote_access = allow;
+        self
+    }
+
+    /// Share a guard (and its rate limits) with other entry points
+    pub fn with_safety(mut self, safety: Arc<Mutex<SafetyGuard>>) -> Self {
+        self.safety = safety;
+        self
+    }
+
+    pub fn with_hooks(mut self, hooks: Arc<HookManager>) -> Self {
+        self.hooks = Some(hooks);
+        self
+    }
+
+    /// Only list and accept these tools (default: the whole registry)
+    pub fn with_exposed_tools(mut self, names: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.exposed = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    fn exposes(&self, name: &str) -> bool {
+        self.exposed.as_ref().is_none_or(|set| set.contains(name))
+    }
+
+    async fn exposed_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.tools.tool_names().await.into_iter().filter(|n| self.exposes(n)).collect();
+        names.sort();
+        names
+    }
+
+    /// Handle one JSON-RPC message (or batch); `None` when nothing is owed in reply
+    pub async fn handle(&self, message: Value) -> Option<Value> {
+        if let Value::Array(batch) = message {
+            let mut replies = Vec::new();
+            for item in batch {
+                if let Some(reply) = Box::pin(self.handle(item)).await {
+                    replies.push(reply);
+                }
+            }
+            return (!replies.is_empty()).then_some(Value::Array(replies));
+        }
+
+        let id = message.get("id").filter(|id| !id.is_null()).cloned();
+        let method = message["method"].as_str().unwrap_or_default();
+        // Notifications (initialized, cancelled) and replies need no answer
+        let id = id.filter(|_| !method.is_empty())?;
+
+        let result = match method {
+            "initialize" => Ok(self.initialize(&message["params"])),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(self.list_tools().await),
+            "tools/call" => self.call_tool(&message["params"]).await,
+            _ => Err((METHOD_NOT_FOUND, format!("Method not found: {}", method))),
+        };
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_reply(id, code, message),
+        })
+    }
+
+    fn initialize(&self, params: &Value) -> Value {
+        let requested = params["protocolVersion"].as_str().unwrap_or_default();
+        let version = PROTOCOL_VERSIONS.iter().find(|v| **v == requested).unwrap_or(&PROTOCOL_VERSIONS[0]);
+        json!({
+            "protocolVersion": version,
+            "capabilities": { "tools": { "listChanged": true } },
+            "serverInfo": { "name": "rust_agency", "version": env!("CARGO_PKG_VERSION") },
+            "instructions": "Tools of the rust_agency registry. Calls are subject to the agency's safety guard and PAI hooks."
+        })
+    }
+
+    async fn list_tools(&self) -> Value {
+        let names = self.exposed_names().await;
+        let definitions = self.tools.tool_definitions(&names).await;
+        let tools: Vec<Value> = definitions
+            .into_iter()
+            .map(|d| json!({ "name": d.name, "description": d.description, "inputSchema": d.parameters }))
+            .collect();
+        json!({ "tools": tools })
+    }
+
+    async fn call_tool(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let name = params["name"].as_str().ok_or((INVALID_PARAMS, "Missing tool name".to_string()))?;
+        let arguments = match &params["arguments"] {
+            Value::Null => json!({}),
+            args => args.clone(),
+        };
+        let Some(tool) = self.tools.get_tool(name).await.filter(|_| self.exposes(name)) else {
+            return Err((INVALID_PARAMS, format!("Unknown tool: {}", name)));
+        };
+
+        if let Err(reason) = self.admit(tool.as_ref(), name, &arguments).await {
+            warn!("MCP call to {} refused: {}", name, reason);
+            return Ok(tool_result(false, reason, Value::Null));
+        }
+
+        info!("MCP client calling {}", name);
+        let call = ToolCall { name: name.to_string(), parameters: arguments };
+        Ok(match self.tools.execute(&call).await {
+            Ok(output) => tool_result(output.success, output.summary, output.data),
+            Err(e) => tool_result(false, format!("Tool execution failed: {}", e), Value::Null),
+        })
+    }
+
+    /// PAI hooks, then the safety guard; `Err` carries the refusal shown to the client
+    async fn admit(&self, tool: &dyn Tool, name: &str, arguments: &Value) -> Result<(), String> {
+        if let Some(hooks) = &self.hooks {
+            let mut event = HookEvent {
+                event_type: HookEventType::PreToolUse,
+                session_id: self.session_id.clone(),
+                payload: json!({
+                    "tool_name": name,
+                    "tool_input": arguments,
+                    "description": name
+                }),
+                timestamp: chrono::Utc::now(),
+            };
+            pai_core::enrichment::EnrichmentEngine::enrich(&mut event);
+            match hooks.trigger(&event).await {
+                Ok(HookAction::Block(reason)) => return Err(format!("Security Block: {}", reason)),
+                Ok(_) => {}
+                Err(e) => warn!("PAI Hook error: {}", e),
+            }
+        }
+
+        let mut guard = self.safety.lock().await;
+        guard
+            .check_tool_safety(name, arguments, self.tools.clone())
+            .await
+            .map_err(|e| format!("Blocked by safety guard: {}", e))?;
+        // Forged tools run agent-written scripts; nobody reviews them over MCP
+        if tool.requires_confirmation() || tool.is_forged() {
+            return Err(format!("'{}' requires human confirmation, which MCP clients cannot give", name));
+        }
+        if let Some(request) = guard.needs_human_approval(name, arguments, self.tools.clone()).await {
+            return Err(format!("Human approval required ({}); not available over MCP", request.rationale));
+        }
+        Ok(())
+    }
+
+    /// Serve newline-delimited JSON-RPC on stdin/stdout until stdin closes
+    pub async fn serve_stdio(self: Arc<Self>) -> Result<()> {
+        let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+        let writer = tokio::spawn(async move {
+            let mut stdout = tokio::io::stdout();
+            while let Some(message) = rx.recv().await {
+                let line = serde_json::to_string(&message).unwrap_or_default() + "\n";
+                if stdout.write_all(line.as_bytes()).await.is_err() || stdout.flush().await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        // Forged tools and re-synced MCP proxies change the registry at runtime
+        let watcher = {
+            let (server, tx) = (self.clone(), tx.clone());
+            tokio::spawn(async move {
+                let mut known = server.exposed_names().await;
+                loop {
+                    tokio::time::sleep(LIST_CHANGE_POLL).await;
+                    let current = server.exposed_names().await;
+                    if current != known {
+                        known = current;
+                        if tx.send(json!({ "jsonrpc": "2.0", "method": "notifications/tools/list_changed" })).is_err() {
+                            break;
+                        }
+                    }
+                }
+            })
+        };
+
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let message: Value = match serde_json::from_str(&line) {
+                Ok(message) => message,
+                Err(e) => {
+                    let _ = tx.send(error_reply(Value::Null, PARSE_ERROR, format!("Parse error: {}", e)));
+                    continue;
+                }
+            };
+            // Tools can be slow; answer each request as it finishes
+            let (server, tx) = (self.clone(), tx.clone());
+            tokio::spawn(async move {
+                if let Some(reply) = server.handle(message).await {
+                    let _ = tx.send(reply);
+                }
+            });
+        }
+
+        watcher.abort();
+        drop(tx);
+        let _ = writer.await;
+        Ok(())
+    }
+
+    /// Streamable HTTP endpoint at `/mcp` (JSON responses, no server-initiated stream)
+    pub fn router(self: Arc<Self>) -> Router {
+        Router::new()
+            .route("/mcp", post(http_handler).get(|| async { StatusCode::METHOD_NOT_ALLOWED }))
+            .route("/health", axum::routing::get(|| async { "OK" }))
+            .with_state(self)
+    }
+}
+
+impl McpToolServer {
+    /// Check an HTTP request's token, `Origin` and `Host`
+    fn authorize_http(&self, headers: &HeaderMap) -> Result<(), (StatusCode, &'static str)> {
+        let header = |name: header::HeaderName| headers.get(name).and_then(|v| v.to_str().ok());
+        if let Some(origin) = header(header::ORIGIN) {
+            if !self.allowed_origins.contains(origin) {
+                return Err((StatusCode::FORBIDDEN, "Origin not allowed"));
+            }
+        }
+        if !self.remote_access && !header(header::HOST).is_some_and(is_loopback_host) {
+            return Err((StatusCode::FORBIDDEN, "Host not allowed"));
+        }
+        let Some(expected) = &self.http_token else {
+            return Err((StatusCode::UNAUTHORIZED, "No access token configured for this server"));
+        };
+        let presented = header(header::AUTHORIZATION).and_then(|v| v.strip_prefix("Bearer ")).unwrap_or_default();
+        if !constant_time_eq(presented.as_bytes(), expected.as_bytes()) {
+            return Err((StatusCode::UNAUTHORIZED, "Missing or invalid bearer token"));
+        }
+        Ok(())
+    }
+}
+
+/// `localhost`, `127.0.0.1`, `[::1]` (with or without a port)
+fn is_loopback_host(host: &str) -> bool {
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) && (name.ends_with(']') || !name.contains(':')) => name,
+        _ => host,
+    };
+    let name = name.trim_start_matches('[').trim_end_matches(']');
+    name.eq_ignore_ascii_case("localhost") || name.parse::<std::net::IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+async fn http_handler(State(server): State<Arc<McpToolServer>>, headers: HeaderMap, Json(message): Json<Value>) -> Response {
+    if let Err((status, reason)) = server.authorize_http(&headers) {
+        warn!("MCP HTTP request refused: {}", reason);
+        return (status, reason).into_response();
+    }
+    match server.handle(message).await {
+        Some(reply) => Json(reply).into_response(),
+        None => StatusCode::ACCEPTED.into_response(),
+    }
+}
+
+fn error_reply(id: Value, code: i64, message: String) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn tool_result(success: bool, summary: String, data: Value) -> Value {
+    let mut result = json!({
+        "content": [{ "type": "text", "text": summary }],
+        "isError": !success
+    });
+    if data.is_object() {
+        result["structuredContent"] = data;
+    }
+    result
+}
+
+/// Build the agency's shareable tools (plus skills and forged tools) and serve
+/// them over stdio, or over HTTP when `http_addr` is given. `exposed` narrows
+/// the served tools to the given names.
+///
+/// HTTP clients authenticate with `Authorization: Bearer $AGENCY_MCP_TOKEN`;
+/// `AGENCY_MCP_ALLOWED_ORIGINS` lists browser origins that may call it.
+/// Binding a non-loopback address requires `allow_remote`.
+pub async fn run_mcp_server(http_addr: Option<String>, exposed: Option<Vec<String>>, allow_remote: bool) -> Result<()> {
+    let memory_path = std::env::var("AGENCY_MEMORY_PATH").unwrap_or_else(|_| "memory.json".to_string());
+    let memory: Arc<dyn Memory> = Arc::new(VectorMemory::new(&memory_path)?);
+
+    let artifacts = ArtifactTool::new(DEFAULT_ARTIFACT_DIR)?;
+
+    let tools = Arc::new(ToolRegistry::default());
+    tokio::join!(
+        tools.register_instance(MemoryQueryTool::new(memory.clone())),
+        tools.register_instance(CodebaseTool::default()),
+        tools.register_instance(KnowledgeGraphTool::new(memory.clone())),
+        tools.register_instance(artifacts),
+    );
+    if let Ok(skills) = SkillLoader::discover_skills("skills").await {
+        for skill in skills {
+            tools.register_instance(skill).await;
+        }
+    }
+    let _ = tools.load_dynamic_tools("standard_tools").await;
+    let _ = tools.load_dynamic_tools("custom_tools").await;
+
+    // Same hook chain as the Supervisor
+    let mut hooks = HookManager::new();
+    hooks.register(Arc::new(pai_core::safety::SecurityValidator::new()));
+    hooks.register(Arc::new(pai_core::hooks::LoggerHook));
+
+    let mut server = McpToolServer::new(tools.clone()).with_hooks(Arc::new(hooks));
+    if let Some(names) = exposed {
+        server = server.with_exposed_tools(names);
+    }
+
+    match http_addr {
+        Some(addr) => {
+            let token = std::env::var("AGENCY_MCP_TOKEN")
+                .ok()
+                .filter(|t| !t.trim().is_empty())
+                .context("Serving MCP over HTTP requires AGENCY_MCP_TOKEN (clients send it as a bearer token)")?;
+            let origins = std::env::var("AGENCY_MCP_ALLOWED_ORIGINS").unwrap_or_default();
+            let loopback = tokio::net::lookup_host(&addr).await?.all(|a| a.ip().is_loopback());
+            if !loopback && !allow_remote {
+                anyhow::bail!("Refusing to serve MCP on non-loopback address {}; pass --allow-remote to override", addr);
+            }
+            let server = Arc::new(
+                server
+                    .with_http_token(token)
+                    .with_allowed_origins(origins.split(',').map(str::trim).filter(|o| !o.is_empty()))
+                    .with_remote_access(!loopback),
+            );
+            info!("🔌 Serving {} tools over MCP", server.exposed_names().await.len());
+            let listener = tokio::net::TcpListener::bind(&addr).await?;
+            info!("🚀 MCP server listening at http://{}/mcp", addr);
+            axum::serve(listener, server.router()).await?;
+            Ok(())
+        }
+        None => {
+            let server = Arc::new(server);
+            info!("🔌 Serving {} tools over MCP", server.exposed_names().await.len());
+            server.serve_stdio().await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentResult;
+    use crate::tools::ToolOutput;
+    use async_trait::async_trait;
+
+    struct LookupTool;
+
+    #[async_trait]
+    impl Tool for LookupTool {
+        fn name(&self) -> String { "lookup".to_string() }
+        fn description(&self) -> String { "Look up a key".to_string() }
+        fn parameters(&self) -> Value {
+            json!({ "type": "object", "properties": { "key": { "type": "string" } }, "required": ["key"] })
+        }
+        async fn execute(&self, params: Value) -> AgentResult<ToolOutput> {
+            Ok(ToolOutput::success(json!({ "value": 42 }), format!("{} = 42", params["key"].as_str().unwrap_or_default())))
+        }
+    }
+
+    struct ConfirmTool;
+
+    #[async_trait]
+    impl Tool for ConfirmTool {
+        fn name(&self) -> String { "wipe".to_string() }
+        fn description(&self) -> String { "Needs a human".to_string() }
+        fn parameters(&self) -> Value { json!({ "type": "object" }) }
+        async fn execute(&self, _params: Value) -> AgentResult<ToolOutput> {
+            Ok(ToolOutput::success_str("wiped"))
+        }
+        fn requires_confirmation(&self) -> bool { true }
+    }
+
+    struct ForgedTool;
+
+    #[async_trait]
+    impl Tool for ForgedTool {
+        fn name(&self) -> String { "forged".to_string() }
+        fn description(&self) -> String { "Runs an agent-written script".to_string() }
+        fn parameters(&self) -> Value { json!({ "type": "object" }) }
+        async fn execute(&self, _params: Value) -> AgentResult<ToolOutput> {
+            Ok(ToolOutput::success_str("ran"))
+        }
+        fn is_forged(&self) -> bool { true }
+    }
+
+    async fn server() -> McpToolServer {
+        let tools = Arc::new(ToolRegistry::default());
+        tools.register_instance(LookupTool).await;
+        tools.register_instance(ConfirmTool).await;
+        McpToolServer::new(tools)
+    }
+
+    fn request(method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params })
+    }
+
+    #[tokio::test]
+    async fn test_lists_registry_tools() {
+        let server = server().await;
+        let init = server.handle(request("initialize", json!({ "protocolVersion": "2024-11-05" }))).await.unwrap();
+        assert_eq!(init["result"]["protocolVersion"], "2024-11-05");
+        assert!(server.handle(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })).await.is_none());
+
+        let listed = server.handle(request("tools/list", json!({}))).await.unwrap();
+        let tools = listed["result"]["tools"].as_array().unwrap();
+        assert_eq!(tools.len(), 2);
+        assert_eq!(tools[0]["name"], "lookup");
+        assert_eq!(tools[0]["inputSchema"]["required"][0], "key");
+
+        let narrowed = server.with_exposed_tools(["wipe"]);
+        let listed = narrowed.handle(request("tools/list", json!({}))).await.unwrap();
+        assert_eq!(listed["result"]["tools"].as_array().unwrap().len(), 1);
+        let hidden = narrowed.handle(request("tools/call", json!({ "name": "lookup", "arguments": { "key": "a" } }))).await.unwrap();
+        assert_eq!(hidden["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn test_calls_pass_through_guardrails() {
+        let server = server().await;
+        let ok = server.handle(request("tools/call", json!({ "name": "lookup", "arguments": { "key": "answer" } }))).await.unwrap();
+        assert_eq!(ok["result"]["isError"], false);
+        assert_eq!(ok["result"]["content"][0]["text"], "answer = 42");
+        assert_eq!(ok["result"]["structuredContent"]["value"], 42);
+
+        // Schema violations come back as tool errors the client can fix
+        let invalid = server.handle(request("tools/call", json!({ "name": "lookup", "arguments": {} }))).await.unwrap();
+        assert_eq!(invalid["result"]["isError"], true);
+
+        let refused = server.handle(request("tools/call", json!({ "name": "wipe" }))).await.unwrap();
+        assert_eq!(refused["result"]["isError"], true);
+        assert!(refused["result"]["content"][0]["text"].as_str().unwrap().contains("human confirmation"));
+
+        let unknown = server.handle(request("resources/list", json!({}))).await.unwrap();
+        assert_eq!(unknown["error"]["code"], METHOD_NOT_FOUND);
+
+        server.tools.register_instance(ForgedTool).await;
+        let forged = server.handle(request("tools/call", json!({ "name": "forged" }))).await.unwrap();
+        assert_eq!(forged["result"]["isError"], true, "forged tools are never run for MCP clients");
+    }
+
+    #[tokio::test]
+    async fn test_http_requests_are_authorized() {
+        let headers = |pairs: &[(header::HeaderName, &str)]| {
+            let mut map = HeaderMap::new();
+            for (name, value) in pairs {
+                map.insert(name.clone(), value.parse().unwrap());
+            }
+            map
+        };
+        let host = (header::HOST, "127.0.0.1:3010");
+        let bearer = (header::AUTHORIZATION, "Bearer s3cret");
+
+        let unconfigured = server().await;
+        assert_eq!(unconfigured.authorize_http(&headers(&[host.clone(), bearer.clone()])).unwrap_err().0, StatusCode::UNAUTHORIZED);
+
+        let server = server().await.with_http_token("s3cret").with_allowed_origins(["http://localhost:5173"]);
+        assert!(server.authorize_http(&headers(&[host.clone(), bearer.clone()])).is_ok());
+        assert!(server.authorize_http(&headers(&[(header::HOST, "[::1]:3010"), bearer.clone()])).is_ok());
+        let wrong = (header::AUTHORIZATION, "Bearer guess");
+        assert_eq!(server.authorize_http(&headers(&[host.clone(), wrong])).unwrap_err().0, StatusCode::UNAUTHORIZED);
+        assert_eq!(server.authorize_http(&headers(std::slice::from_ref(&host))).unwrap_err().0, StatusCode::UNAUTHORIZED);
+
+        // A web page (or a DNS-rebound name) cannot reach the endpoint
+        let page = (header::ORIGIN, "https://evil.example");
+        assert_eq!(server.authorize_http(&headers(&[host.clone(), bearer.clone(), page])).unwrap_err().0, StatusCode::FORBIDDEN);
+        let allowed = (header::ORIGIN, "http://localhost:5173");
+        assert!(server.authorize_http(&headers(&[host, bearer.clone(), allowed])).is_ok());
+        let rebound = (header::HOST, "evil.example:3010");
+        assert_eq!(server.authorize_http(&headers(&[rebound.clone(), bearer.clone()])).unwrap_err().0, StatusCode::FORBIDDEN);
+        assert!(server.with_remote_access(true).authorize_http(&headers(&[rebound, bearer])).is_ok());
+    }
+}
@@ -4,7 +4,7 @@ use axum::{
     Router,
 };
 use anyhow::Result;
-use crate::memory::{Memory, MemoryEntry, vector::LocalVectorMemory};
+use crate::memory::{Memory, MemoryEntry, Reinforcement, SearchOptions, vector::LocalVectorMemory};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tracing::info;
@@ -25,8 +25,15 @@ struct StoreRequest {
 struct SearchRequest {
     query: String,
     top_k: usize,
-    context: Option<String>,
-    kind: Option<crate::orchestrator::Kind>,
+    /// Mode, context, kind, tags and time range (all optional)
+    #[serde(flatten)]
+    options: SearchOptions,
+}
+
+#[derive(Deserialize)]
+struct ReinforceRequest {
+    ids: Vec<String>,
+    feedback: Reinforcement,
 }
 
 #[derive(Serialize)]
@@ -68,6 +75,7 @@ pub async fn run_memory_server() -> Result<()> {
         .route("/health", get(|| async { "OK" }))
         .route("/store", post(store_handler))
         .route("/search", post(search_handler))
+        .route("/reinforce", post(reinforce_handler))
         .route("/persist", post(persist_handler))
         .route("/hibernate", post(hibernate_handler))
         .route("/wake", post(wake_handler))
@@ -96,15 +104,18 @@ async fn search_handler(
     State(state): State<Arc<MemoryServerState>>,
     Json(payload): Json<SearchRequest>,
 ) -> Result<Json<SearchResponse>, ServerError> {
-    let entries = state.memory.search(
-        &payload.query, 
-        payload.top_k, 
-        payload.context.as_deref(), 
-        payload.kind
-    ).await?;
+    let entries = state.memory.search_with(&payload.query, payload.top_k, &payload.options).await?;
     Ok(Json(SearchResponse { entries }))
 }
 
+async fn reinforce_handler(
+    State(state): State<Arc<MemoryServerState>>,
+    Json(payload): Json<ReinforceRequest>,
+) -> Result<Json<serde_json::Value>, ServerError> {
+    let updated = state.memory.reinforce(&payload.ids, payload.feedback).await?;
+    Ok(Json(serde_json::json!({ "updated": updated })))
+}
+
 async fn persist_handler(State(state): State<Arc<MemoryServerState>>) -> Result<Json<serde_json::Value>, ServerError> {
     state.memory.persist().await?;
     Ok(Json(serde_json::json!({ "status": "ok" })))
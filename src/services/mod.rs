@@ -2,3 +2,4 @@ pub mod memory;
 pub mod speaker;
 pub mod listener;
 pub mod responses;
+pub mod mcp;
@@ -89,9 +89,9 @@ pub async fn responses_handler(
             }
         ],
         usage: Usage {
-            prompt_tokens: 0, // TODO: Track actual tokens
-            completion_tokens: 0,
-            total_tokens: 0,
+            prompt_tokens: result.usage.prompt_tokens,
+            completion_tokens: result.usage.completion_tokens,
+            total_tokens: result.usage.total_tokens(),
         },
     };
 
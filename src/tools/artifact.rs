@@ -2,54 +2,62 @@
 //! 
 //! Allows agents to manage persistent files (artifacts) in a dedicated workspace.
 //! This is useful for saving code, documentation, or search results.
+//!
+//! Content goes through the versioned `ArtifactStore`: every save that changes
+//! an artifact adds a version attributed to the current task, session and step.
 
+use anyhow::Context;
 use async_trait::async_trait;
 use serde_json::{json, Value};
 use std::path::PathBuf;
-use tokio::fs;
+use std::sync::Arc;
 use tracing::info;
 
 use crate::agent::{AgentResult, AgentError};
-use super::{Tool, ToolOutput};
+use super::artifact_store::{ArtifactContext, ArtifactFilter, ArtifactRecord, ArtifactStore};
+use super::{CachePolicy, Tool, ToolOutput};
 
 /// Tool for managing persistent artifacts
 pub struct ArtifactTool {
-    /// Base directory for artifacts
-    base_dir: PathBuf,
+    store: Arc<ArtifactStore>,
 }
 
 impl ArtifactTool {
-    /// Create a new ArtifactTool with the specified base directory
-    pub fn new(base_dir: impl Into<PathBuf>) -> Self {
+    /// Create a new ArtifactTool backed by the store under `base_dir`
+    pub fn new(base_dir: impl Into<PathBuf>) -> anyhow::Result<Self> {
         let base_dir = base_dir.into();
-        Self { base_dir }
+        let store = ArtifactStore::open(&base_dir)
+            .with_context(|| format!("Failed to open artifact store at {:?}", base_dir))?;
+        Ok(Self { store })
     }
 
-    /// Ensure the base directory exists
-    async fn ensure_dir(&self) -> AgentResult<()> {
-        if !self.base_dir.exists() {
-            fs::create_dir_all(&self.base_dir).await
-                .map_err(|e| AgentError::Io(e))?;
-        }
-        Ok(())
+    /// Create an ArtifactTool sharing an already opened store
+    pub fn with_store(store: Arc<ArtifactStore>) -> Self {
+        Self { store }
     }
 
-    /// Resolve a path relative to the base directory and ensure it stays within bounds
-    fn resolve_path(&self, filename: &str) -> AgentResult<PathBuf> {
-        let path = self.base_dir.join(filename);
-        
-        // Security check: ensure path is within base_dir
-        if !path.starts_with(&self.base_dir) {
-            return Err(AgentError::Validation("Access denied: Path is outside the artifacts directory".to_string()));
-        }
-        
-        Ok(path)
+    pub fn store(&self) -> Arc<ArtifactStore> {
+        self.store.clone()
+    }
+
+    fn required<'a>(params: &'a Value, key: &str) -> AgentResult<&'a str> {
+        params[key]
+            .as_str()
+            .ok_or_else(|| AgentError::Validation(format!("Missing required parameter: {}", key)))
     }
-}
 
-impl Default for ArtifactTool {
-    fn default() -> Self {
-        Self::new("artifacts")
+    fn version_param(params: &Value, key: &str) -> Option<u32> {
+        params[key].as_u64().map(|v| v as u32)
+    }
+
+    /// The caller's own artifact, or with `shared` the latest of any owner
+    fn lookup(&self, name: &str, context: &ArtifactContext, params: &Value) -> AgentResult<ArtifactRecord> {
+        let record = if params["shared"].as_bool().unwrap_or(false) {
+            self.store.get_shared(name)
+        } else {
+            self.store.get(name, &context.owner)
+        };
+        record.ok_or_else(|| AgentError::Tool(format!("Artifact not found: {}", name)))
     }
 }
 
@@ -60,7 +68,7 @@ impl Tool for ArtifactTool {
     }
 
     fn description(&self) -> String {
-        "Manage artifacts (files, images, documents) generated or used by agents. \n        Supports 'save', 'load', 'list', and 'delete' operations.".to_string()
+        "Manage artifacts (files, images, documents) generated or used by agents. \n        Supports 'save', 'load', 'list', 'history', 'diff', and 'delete' operations. \n        Every changed save creates a new version; 'load' and 'diff' accept version numbers.".to_string()
     }
 
     fn parameters(&self) -> Value {
@@ -69,16 +77,32 @@ impl Tool for ArtifactTool {
             "properties": {
                 "action": {
                     "type": "string",
-                    "enum": ["save", "load", "list", "delete"],
+                    "enum": ["save", "load", "list", "history", "diff", "delete"],
                     "description": "The action to perform"
                 },
                 "name": {
                     "type": "string",
-                    "description": "The name/id of the artifact"
+                    "description": "The name/id of the artifact (relative, e.g. 'reports/summary.md')"
                 },
                 "content": {
                     "type": "string",
                     "description": "Content to save (if action is 'save')"
+                },
+                "mime_type": {
+                    "type": "string",
+                    "description": "MIME type for 'save' (guessed from the name if omitted)"
+                },
+                "version": {
+                    "type": "integer",
+                    "description": "Version to load, or the newer side of a diff (default: latest)"
+                },
+                "from_version": {
+                    "type": "integer",
+                    "description": "Older side of a diff (default: the version before 'version')"
+                },
+                "shared": {
+                    "type": "boolean",
+                    "description": "For 'load', 'history' and 'diff': read the latest artifact of that name from any task or session, not only your own"
                 }
             },
             "required": ["action"]
@@ -88,85 +112,173 @@ impl Tool for ArtifactTool {
     fn work_scope(&self) -> Value {
         json!({
             "status": "constrained",
-            "environment": "local filesystem (artifacts/ directory)",
-            "persistence": "permanent",
+            "environment": "local filesystem (artifacts/.store, content-addressed)",
+            "persistence": "permanent, versioned",
             "data_types": ["text", "code", "json", "logs"]
         })
     }
 
-    async fn execute(&self, params: Value) -> AgentResult<ToolOutput> {
-        self.ensure_dir().await?;
+    fn cache_policy(&self, params: &Value) -> CachePolicy {
+        match params["action"].as_str() {
+            Some("load") | Some("list") | Some("history") | Some("diff") => CachePolicy::Ttl(std::time::Duration::from_secs(300)),
+            _ => CachePolicy::Never,
+        }
+    }
 
-        let action = params["action"]
-            .as_str()
-            .ok_or_else(|| AgentError::Validation("Missing required parameter: action".to_string()))?;
+    fn owner_scoped(&self) -> bool {
+        true
+    }
+
+    fn reads(&self, params: &Value) -> Vec<String> {
+        match (params["action"].as_str(), params["name"].as_str()) {
+            (Some("load") | Some("history") | Some("diff"), Some(name)) => vec![format!("artifact:{}", name)],
+            (Some("list"), _) => vec!["artifact:".to_string()],
+            _ => Vec::new(),
+        }
+    }
+
+    fn writes(&self, params: &Value) -> Vec<String> {
+        match (params["action"].as_str(), params["name"].as_str()) {
+            (Some("save") | Some("delete"), Some(name)) => vec![format!("artifact:{}", name)],
+            _ => Vec::new(),
+        }
+    }
+
+    async fn execute(&self, params: Value) -> AgentResult<ToolOutput> {
+        let action = Self::required(&params, "action")?;
+        let context = ArtifactContext::current();
 
         match action {
             "save" => {
-                let filename = params["name"]
-                    .as_str()
-                    .ok_or_else(|| AgentError::Validation("Missing required parameter: name".to_string()))?;
-                let content = params["content"]
-                    .as_str()
-                    .ok_or_else(|| AgentError::Validation("Missing required parameter: content".to_string()))?;
-                
-                let path = self.resolve_path(filename)?;
-                fs::write(&path, content).await
-                    .map_err(|e| AgentError::Io(e))?;
-                
-                info!("Artifact written: {}", filename);
+                let filename = Self::required(&params, "name")?;
+                let content = Self::required(&params, "content")?;
+
+                // Record the call without its (possibly large) content
+                let mut call = params.clone();
+                if let Some(obj) = call.as_object_mut() {
+                    obj.remove("content");
+                }
+                let provenance = context.provenance(&self.name(), Some(call));
+                let stored = self
+                    .store
+                    .put(filename, content.as_bytes(), params["mime_type"].as_str(), &context.owner, provenance)
+                    .map_err(|e| AgentError::Validation(e.to_string()))?;
+                let latest = stored.record.latest();
+
+                info!("Artifact written: {} (v{})", filename, latest.version);
+                let summary = if stored.new_version {
+                    format!("Successfully saved artifact: {} (version {})", filename, latest.version)
+                } else {
+                    format!("Artifact {} unchanged (version {})", filename, latest.version)
+                };
                 Ok(ToolOutput::success(
-                    json!({ "name": filename, "bytes": content.len() }),
-                    format!("Successfully saved artifact: {}", filename)
+                    json!({
+                        "name": filename,
+                        "artifact_id": stored.record.artifact_id,
+                        "version": latest.version,
+                        "hash": latest.hash,
+                        "mime_type": stored.record.mime_type,
+                        "bytes": content.len(),
+                        "new_version": stored.new_version
+                    }),
+                    summary
                 ))
             }
             "load" => {
-                let filename = params["name"]
-                    .as_str()
-                    .ok_or_else(|| AgentError::Validation("Missing required parameter: name".to_string()))?;
-                
-                let path = self.resolve_path(filename)?;
-                let content = fs::read_to_string(&path).await
-                    .map_err(|e| AgentError::Io(e))?;
-                
+                let filename = Self::required(&params, "name")?;
+                let record = self.lookup(filename, &context, &params)?;
+                let (version, bytes) = self
+                    .store
+                    .read(&record, Self::version_param(&params, "version"))
+                    .map_err(|e| AgentError::Tool(e.to_string()))?;
+                let content = String::from_utf8(bytes).map_err(|_| {
+                    AgentError::Tool(format!("Artifact {} is binary ({})", filename, record.mime_type))
+                })?;
+
                 Ok(ToolOutput::success(
-                    json!({ "name": filename, "content": content }),
-                    format!("Content of {}:\n\n{}", filename, content)
+                    json!({
+                        "name": filename,
+                        "content": content,
+                        "version": version.version,
+                        "mime_type": record.mime_type
+                    }),
+                    format!("Content of {} (version {}):\n\n{}", filename, version.version, content)
                 ))
             }
             "list" => {
-                let mut entries = fs::read_dir(&self.base_dir).await
-                    .map_err(|e| AgentError::Io(e))?;
-                let mut files = Vec::new();
-                
-                while let Some(entry) = entries.next_entry().await.map_err(|e| AgentError::Io(e))? {
-                    if let Ok(meta) = entry.metadata().await {
-                        if meta.is_file() {
-                            files.push(entry.file_name().to_string_lossy().to_string());
-                        }
-                    }
-                }
-                
-                let summary = if files.is_empty() {
+                let filter = ArtifactFilter {
+                    task_id: context.owner.task_id.clone(),
+                    session_id: context.owner.session_id.clone(),
+                    ..Default::default()
+                };
+                let records = self.store.list(&filter);
+                let files: Vec<&str> = records.iter().map(|r| r.name.as_str()).collect();
+                let artifacts: Vec<Value> = records
+                    .iter()
+                    .map(|r| json!({
+                        "name": r.name,
+                        "artifact_id": r.artifact_id,
+                        "mime_type": r.mime_type,
+                        "version": r.latest().version,
+                        "size": r.latest().size
+                    }))
+                    .collect();
+
+                let summary = if records.is_empty() {
                     "No artifacts found.".to_string()
                 } else {
-                    format!("Artifacts:\n- {}", files.join("\n- "))
+                    let lines: Vec<String> = records
+                        .iter()
+                        .map(|r| format!("{} (v{}, {})", r.name, r.latest().version, r.mime_type))
+                        .collect();
+                    format!("Artifacts:\n- {}", lines.join("\n- "))
                 };
-                
+
                 Ok(ToolOutput::success(
-                    json!({ "files": files }),
+                    json!({ "files": files, "artifacts": artifacts }),
                     summary
                 ))
             }
+            "history" => {
+                let filename = Self::required(&params, "name")?;
+                let record = self.lookup(filename, &context, &params)?;
+                let lines: Vec<String> = record
+                    .versions
+                    .iter()
+                    .map(|v| {
+                        let by = v.provenance.tool.as_deref().unwrap_or("unknown");
+                        let step = v.provenance.step.map(|s| format!(", step {}", s)).unwrap_or_default();
+                        format!("v{} {} {} bytes by {}{}", v.version, v.created_at.to_rfc3339(), v.size, by, step)
+                    })
+                    .collect();
+
+                Ok(ToolOutput::success(
+                    json!({ "name": filename, "artifact_id": record.artifact_id, "versions": record.versions }),
+                    format!("History of {}:\n- {}", filename, lines.join("\n- "))
+                ))
+            }
+            "diff" => {
+                let filename = Self::required(&params, "name")?;
+                let record = self.lookup(filename, &context, &params)?;
+                let diff = self
+                    .store
+                    .diff(&record, Self::version_param(&params, "from_version"), Self::version_param(&params, "version"))
+                    .map_err(|e| AgentError::Tool(e.to_string()))?;
+                let summary = if diff.is_empty() {
+                    format!("No differences in {}", filename)
+                } else {
+                    diff.clone()
+                };
+
+                Ok(ToolOutput::success(json!({ "name": filename, "diff": diff }), summary))
+            }
             "delete" => {
-                let filename = params["name"]
-                    .as_str()
-                    .ok_or_else(|| AgentError::Validation("Missing required parameter: name".to_string()))?;
-                
-                let path = self.resolve_path(filename)?;
-                fs::remove_file(&path).await
-                    .map_err(|e| AgentError::Io(e))?;
-                
+                let filename = Self::required(&params, "name")?;
+                self.store
+                    .delete(filename, &context.owner)
+                    .map_err(|e| AgentError::Tool(e.to_string()))?
+                    .ok_or_else(|| AgentError::Tool(format!("Artifact not found: {}", filename)))?;
+
                 info!("Artifact deleted: {}", filename);
                 Ok(ToolOutput::success(
                     json!({ "name": filename }),
@@ -186,7 +298,7 @@ mod tests {
     #[tokio::test]
     async fn test_artifact_write_read() {
         let temp_dir = tempdir().expect("Failed to create temp dir");
-        let tool = ArtifactTool::new(temp_dir.path());
+        let tool = ArtifactTool::new(temp_dir.path()).unwrap();
         
         let filename = "test.txt";
         let content = "hello artifact";
@@ -211,7 +323,7 @@ mod tests {
     #[tokio::test]
     async fn test_artifact_list_delete() {
         let temp_dir = tempdir().expect("Failed to create temp dir");
-        let tool = ArtifactTool::new(temp_dir.path());
+        let tool = ArtifactTool::new(temp_dir.path()).unwrap();
         
         tool.execute(json!({
             "action": "save",
@@ -232,4 +344,55 @@ mod tests {
         let res_list_after = tool.execute(json!({"action": "list"})).await.expect("Tool execution failed");
         assert_eq!(res_list_after.data["files"].as_array().expect("No files in data").len(), 0);
     }
+
+    #[tokio::test]
+    async fn test_artifact_versions_and_provenance() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let tool = ArtifactTool::new(temp_dir.path()).unwrap();
+        let context = ArtifactContext {
+            owner: crate::tools::ArtifactOwner { task_id: Some("task-1".to_string()), session_id: None },
+            step: Some(2),
+            step_id: Some("step-a".to_string()),
+            agent: None,
+        };
+
+        context.clone().scope(async {
+            tool.execute(json!({"action": "save", "name": "plan.md", "content": "a\nb\n"})).await.unwrap();
+            let saved = tool.execute(json!({"action": "save", "name": "plan.md", "content": "a\nc\n"})).await.unwrap();
+            assert_eq!(saved.data["version"], 2);
+
+            let old = tool.execute(json!({"action": "load", "name": "plan.md", "version": 1})).await.unwrap();
+            assert_eq!(old.data["content"], "a\nb\n");
+
+            let diff = tool.execute(json!({"action": "diff", "name": "plan.md"})).await.unwrap();
+            assert!(diff.data["diff"].as_str().unwrap().contains("-b\n+c"));
+        }).await;
+
+        let record = tool.store().list(&ArtifactFilter::task("task-1")).remove(0);
+        assert_eq!(record.mime_type, "text/markdown");
+        let provenance = &record.latest().provenance;
+        assert_eq!(provenance.tool.as_deref(), Some("artifact_manager"));
+        assert_eq!(provenance.step, Some(2));
+        assert_eq!(provenance.step_id.as_deref(), Some("step-a"));
+        assert!(provenance.tool_call.as_ref().unwrap().get("content").is_none());
+
+        // Outside the task the artifact is only reachable as a shared read
+        let own = tool.execute(json!({"action": "load", "name": "plan.md"})).await;
+        assert!(own.is_err());
+        let shared = tool.execute(json!({"action": "load", "name": "plan.md", "shared": true})).await.unwrap();
+        assert_eq!(shared.data["content"], "a\nc\n");
+        assert!(tool.execute(json!({"action": "delete", "name": "plan.md"})).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_artifact_rejects_traversal() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let tool = ArtifactTool::new(temp_dir.path().join("artifacts")).unwrap();
+
+        for name in ["../escape.txt", "nested/../../escape.txt", "/tmp/escape.txt"] {
+            let res = tool.execute(json!({"action": "save", "name": name, "content": "x"})).await;
+            assert!(res.is_err(), "{} was accepted", name);
+        }
+        assert!(!temp_dir.path().join("escape.txt").exists());
+    }
 }
@@ -0,0 +1,482 @@
+//! Artifact Store
+//!
+//! Content-addressed, versioned storage for agent outputs. Blobs live under
+//! `<base>/.store/objects/<hh>/<sha256>` (identical content is stored once)
+//! and `<base>/.store/index.json` catalogs every artifact with its owner
+//! (UAP task and/or session), MIME type and version history. Each version
+//! records its provenance: the tool call and ReAct step that produced it.
+//!
+//! Artifact names are catalog keys, never filesystem paths, so a name like
+//! `../../etc/passwd` cannot escape the store (and is rejected anyway).
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use pai_core::uap::UapArtifact;
+
+/// Directory used by agents and servers that are not given a store
+pub const DEFAULT_ARTIFACT_DIR: &str = "artifacts";
+
+/// Task and session an artifact belongs to
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ArtifactOwner {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+/// What produced a version
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ArtifactProvenance {
+    /// Tool that wrote the content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    /// Arguments of that call (bulky content omitted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call: Option<Value>,
+    /// ReAct iteration within the turn
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step: Option<usize>,
+    /// UAP step the turn ran as
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArtifactVersion {
+    pub version: u32,
+    /// SHA-256 of the content (hex)
+    pub hash: String,
+    pub size: u64,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub provenance: ArtifactProvenance,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArtifactRecord {
+    pub artifact_id: String,
+    pub name: String,
+    pub mime_type: String,
+    #[serde(default)]
+    pub owner: ArtifactOwner,
+    pub created_at: DateTime<Utc>,
+    /// Oldest first; never empty
+    pub versions: Vec<ArtifactVersion>,
+}
+
+impl ArtifactRecord {
+    pub fn latest(&self) -> &ArtifactVersion {
+        self.versions.last().expect("artifacts always have a version")
+    }
+
+    pub fn version(&self, version: u32) -> Option<&ArtifactVersion> {
+        self.versions.iter().find(|v| v.version == version)
+    }
+
+    pub fn updated_at(&self) -> DateTime<Utc> {
+        self.latest().created_at
+    }
+
+    /// UAP view of the latest version
+    pub fn to_uap(&self) -> UapArtifact {
+        let hash = self.latest().hash.clone();
+        UapArtifact {
+            artifact_id: self.artifact_id.clone(),
+            file_name: self.name.clone(),
+            relative_path: Some(object_relative_path(&hash)),
+            hash: Some(hash),
+        }
+    }
+}
+
+/// Who is producing artifacts right now. The agent loop sets it around tool
+/// calls so tools can attribute what they store.
+#[derive(Debug, Clone, Default)]
+pub struct ArtifactContext {
+    pub owner: ArtifactOwner,
+    pub step: Option<usize>,
+    pub step_id: Option<String>,
+    pub agent: Option<String>,
+}
+
+tokio::task_local! {
+    static ARTIFACT_CONTEXT: ArtifactContext;
+}
+
+impl ArtifactContext {
+    /// The context of the running task, or an empty one
+    pub fn current() -> Self {
+        ARTIFACT_CONTEXT.try_with(Clone::clone).unwrap_or_default()
+    }
+
+    /// Run `future` with this context
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        ARTIFACT_CONTEXT.scope(self, future).await
+    }
+
+    /// Provenance of a call to `tool` made in this context
+    pub fn provenance(&self, tool: &str, tool_call: Option<Value>) -> ArtifactProvenance {
+        ArtifactProvenance {
+            tool: Some(tool.to_string()),
+            tool_call,
+            step: self.step,
+            step_id: self.step_id.clone(),
+            agent: self.agent.clone(),
+        }
+    }
+}
+
+/// Which artifacts to list
+#[derive(Debug, Clone, Default)]
+pub struct ArtifactFilter {
+    pub task_id: Option<String>,
+    pub session_id: Option<String>,
+    /// Only artifacts with a version produced in this UAP step
+    pub step_id: Option<String>,
+    /// Only artifacts of exactly this owner (unset task or session included)
+    pub owner: Option<ArtifactOwner>,
+}
+
+impl ArtifactFilter {
+    pub fn task(task_id: impl Into<String>) -> Self {
+        Self { task_id: Some(task_id.into()), ..Default::default() }
+    }
+
+    pub fn owner(owner: ArtifactOwner) -> Self {
+        Self { owner: Some(owner), ..Default::default() }
+    }
+
+    fn matches(&self, record: &ArtifactRecord) -> bool {
+        let field = |want: &Option<String>, have: &Option<String>| want.is_none() || want == have;
+        self.owner.as_ref().is_none_or(|owner| *owner == record.owner)
+            && field(&self.task_id, &record.owner.task_id)
+            && field(&self.session_id, &record.owner.session_id)
+            && (self.step_id.is_none() || record.versions.iter().any(|v| v.provenance.step_id == self.step_id))
+    }
+}
+
+/// Result of `ArtifactStore::put`
+#[derive(Debug, Clone)]
+pub struct StoredArtifact {
+    pub record: ArtifactRecord,
+    /// False when the content matched the latest version
+    pub new_version: bool,
+}
+
+pub struct ArtifactStore {
+    base_dir: PathBuf,
+    index: Mutex<Vec<ArtifactRecord>>,
+}
+
+lazy_static::lazy_static! {
+    /// Open stores by directory, so every component shares one index
+    static ref OPEN_STORES: Mutex<HashMap<PathBuf, Arc<ArtifactStore>>> = Mutex::new(HashMap::new());
+}
+
+fn object_relative_path(hash: &str) -> String {
+    format!(".store/objects/{}/{}", &hash[..2], hash)
+}
+
+/// Reject names that are empty, absolute or climb out with `..`
+pub fn validate_name(name: &str) -> Result<()> {
+    let path = Path::new(name);
+    if name.trim().is_empty() || name.contains('\\') {
+        anyhow::bail!("Invalid artifact name '{}'", name);
+    }
+    if !path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+        anyhow::bail!("Access denied: artifact name '{}' must be a relative path without '..'", name);
+    }
+    Ok(())
+}
+
+/// MIME type from the name's extension, else sniffed from the content
+pub fn guess_mime(name: &str, content: &[u8]) -> String {
+    let known = match Path::new(name).extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+        Some("md") => Some("text/markdown"),
+        Some("rs") => Some("text/x-rust"),
+        Some("py") => Some("text/x-python"),
+        Some("toml") => Some("application/toml"),
+        Some("yaml" | "yml") => Some("application/yaml"),
+        _ => None,
+    };
+    if let Some(mime) = known {
+        return mime.to_string();
+    }
+    match mime_guess::from_path(name).first() {
+        Some(mime) => mime.essence_str().to_string(),
+        None if std::str::from_utf8(content).is_ok() => "text/plain".to_string(),
+        None => "application/octet-stream".to_string(),
+    }
+}
+
+impl ArtifactStore {
+    /// Open (or create) the store under `base_dir`. Opening the same
+    /// directory twice returns the same store.
+    pub fn open(base_dir: impl Into<PathBuf>) -> Result<Arc<Self>> {
+        let base_dir = base_dir.into();
+        std::fs::create_dir_all(base_dir.join(".store/objects"))?;
+        let key = base_dir.canonicalize().unwrap_or_else(|_| base_dir.clone());
+
+        let mut open = OPEN_STORES.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(store) = open.get(&key) {
+            return Ok(store.clone());
+        }
+        let index_path = base_dir.join(".store/index.json");
+        let index = match std::fs::read_to_string(&index_path) {
+            Ok(content) => serde_json::from_str(&content).with_context(|| format!("Corrupt artifact index at {:?}", index_path))?,
+            Err(_) => Vec::new(),
+        };
+        let store = Arc::new(Self { base_dir, index: Mutex::new(index) });
+        open.insert(key, store.clone());
+        Ok(store)
+    }
+
+    pub fn base_dir(&self) -> &Path {
+        &self.base_dir
+    }
+
+    fn object_path(&self, hash: &str) -> PathBuf {
+        self.base_dir.join(object_relative_path(hash))
+    }
+
+    fn index(&self) -> std::sync::MutexGuard<'_, Vec<ArtifactRecord>> {
+        self.index.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Write the catalog atomically (temp file + rename)
+    fn persist(&self, index: &[ArtifactRecord]) -> Result<()> {
+        let path = self.base_dir.join(".store/index.json");
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(index)?)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    /// Position of `owner`'s own artifact `name`
+    fn position(index: &[ArtifactRecord], name: &str, owner: &ArtifactOwner) -> Option<usize> {
+        index.iter().position(|r| r.name == name && r.owner == *owner)
+    }
+
+    /// Store `content` as the next version of `owner`'s artifact `name`
+    /// (creating it if needed). Unchanged content adds no version.
+    pub fn put(
+        &self,
+        name: &str,
+        content: &[u8],
+        mime_type: Option<&str>,
+        owner: &ArtifactOwner,
+        provenance: ArtifactProvenance,
+    ) -> Result<StoredArtifact> {
+        validate_name(name)?;
+        let hash = hex::encode(Sha256::digest(content));
+        // Held while writing the blob so a concurrent delete cannot collect it
+        let mut index = self.index();
+        let object = self.object_path(&hash);
+        if !object.exists() {
+            std::fs::create_dir_all(object.parent().unwrap_or(&self.base_dir))?;
+            let tmp = object.with_extension("tmp");
+            std::fs::write(&tmp, content)?;
+            std::fs::rename(&tmp, &object)?;
+        }
+
+        let now = Utc::now();
+        let existing = Self::position(&index, name, owner);
+        let record = match existing {
+            Some(i) if index[i].latest().hash == hash => {
+                return Ok(StoredArtifact { record: index[i].clone(), new_version: false });
+            }
+            Some(i) => {
+                let mut record = index[i].clone();
+                let version = record.latest().version + 1;
+                record.versions.push(ArtifactVersion { version, hash, size: content.len() as u64, created_at: now, provenance });
+                if let Some(mime) = mime_type {
+                    record.mime_type = mime.to_string();
+                }
+                record
+            }
+            None => ArtifactRecord {
+                artifact_id: uuid::Uuid::new_v4().to_string(),
+                name: name.to_string(),
+                mime_type: mime_type.map(str::to_string).unwrap_or_else(|| guess_mime(name, content)),
+                owner: owner.clone(),
+                created_at: now,
+                versions: vec![ArtifactVersion { version: 1, hash, size: content.len() as u64, created_at: now, provenance }],
+            },
+        };
+
+        // Undone if the catalog cannot be written, so the index matches disk
+        let previous = match existing {
+            Some(i) => Some(std::mem::replace(&mut index[i], record.clone())),
+            None => {
+                index.push(record.clone());
+                None
+            }
+        };
+        if let Err(e) = self.persist(&index) {
+            match (existing, previous) {
+                (Some(i), Some(previous)) => index[i] = previous,
+                _ => {
+                    index.pop();
+                }
+            }
+            return Err(e);
+        }
+        Ok(StoredArtifact { record, new_version: true })
+    }
+
+    /// `owner`'s own artifact `name`
+    pub fn get(&self, name: &str, owner: &ArtifactOwner) -> Option<ArtifactRecord> {
+        let index = self.index();
+        Self::position(&index, name, owner).map(|i| index[i].clone())
+    }
+
+    /// The most recently updated artifact `name` of any owner. Reading
+    /// another task's or session's output must be asked for explicitly.
+    pub fn get_shared(&self, name: &str) -> Option<ArtifactRecord> {
+        self.index().iter().filter(|r| r.name == name).max_by_key(|r| r.updated_at()).cloned()
+    }
+
+    pub fn get_by_id(&self, artifact_id: &str) -> Option<ArtifactRecord> {
+        self.index().iter().find(|r| r.artifact_id == artifact_id).cloned()
+    }
+
+    /// Content of a version (the latest when `version` is `None`)
+    pub fn read(&self, record: &ArtifactRecord, version: Option<u32>) -> Result<(ArtifactVersion, Vec<u8>)> {
+        let version = match version {
+            Some(v) => record.version(v).with_context(|| format!("'{}' has no version {}", record.name, v))?,
+            None => record.latest(),
+        };
+        let content = std::fs::read(self.object_path(&version.hash))
+            .with_context(|| format!("Missing content {} of '{}'", version.hash, record.name))?;
+        Ok((version.clone(), content))
+    }
+
+    /// Matching artifacts, most recently updated first
+    pub fn list(&self, filter: &ArtifactFilter) -> Vec<ArtifactRecord> {
+        let mut records: Vec<ArtifactRecord> = self.index().iter().filter(|r| filter.matches(r)).cloned().collect();
+        records.sort_by_key(|r| std::cmp::Reverse(r.updated_at()));
+        records
+    }
+
+    /// Unified diff between two versions (default: previous and latest)
+    pub fn diff(&self, record: &ArtifactRecord, from: Option<u32>, to: Option<u32>) -> Result<String> {
+        let to = to.unwrap_or(record.latest().version);
+        let from = from.unwrap_or_else(|| to.saturating_sub(1).max(1));
+        let (_, old) = self.read(record, Some(from))?;
+        let (_, new) = self.read(record, Some(to))?;
+        let (Ok(old), Ok(new)) = (std::str::from_utf8(&old), std::str::from_utf8(&new)) else {
+            anyhow::bail!("'{}' is binary ({}); no text diff available", record.name, record.mime_type);
+        };
+        Ok(similar::TextDiff::from_lines(old, new)
+            .unified_diff()
+            .context_radius(3)
+            .header(&format!("{}@v{}", record.name, from), &format!("{}@v{}", record.name, to))
+            .to_string())
+    }
+
+    /// Remove `owner`'s artifact and any content no other version references
+    pub fn delete(&self, name: &str, owner: &ArtifactOwner) -> Result<Option<ArtifactRecord>> {
+        let mut index = self.index();
+        let Some(position) = Self::position(&index, name, owner) else { return Ok(None) };
+        let removed = index.remove(position);
+        if let Err(e) = self.persist(&index) {
+            index.insert(position, removed);
+            return Err(e);
+        }
+        for version in &removed.versions {
+            let still_used = index.iter().any(|r| r.versions.iter().any(|v| v.hash == version.hash));
+            if !still_used {
+                let _ = std::fs::remove_file(self.object_path(&version.hash));
+            }
+        }
+        Ok(Some(removed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_versions_dedup_and_diff() {
+        let dir = tempdir().unwrap();
+        let store = ArtifactStore::open(dir.path()).unwrap();
+        let task = ArtifactOwner { task_id: Some("t1".to_string()), session_id: None };
+
+        let first = store.put("notes.md", b"one\ntwo\n", None, &task, ArtifactProvenance::default()).unwrap();
+        assert_eq!(first.record.mime_type, "text/markdown");
+        assert!(!store.put("notes.md", b"one\ntwo\n", None, &task, ArtifactProvenance::default()).unwrap().new_version);
+        let second = store.put("notes.md", b"one\nthree\n", None, &task, ArtifactProvenance::default()).unwrap();
+        assert_eq!(second.record.versions.len(), 2);
+        assert_eq!(second.record.artifact_id, first.record.artifact_id);
+
+        // Same content under another owner shares the blob
+        let other = ArtifactOwner { task_id: Some("t2".to_string()), session_id: None };
+        store.put("copy.md", b"one\nthree\n", None, &other, ArtifactProvenance::default()).unwrap();
+        let objects: usize = std::fs::read_dir(dir.path().join(".store/objects"))
+            .unwrap()
+            .map(|d| std::fs::read_dir(d.unwrap().path()).unwrap().count())
+            .sum();
+        assert_eq!(objects, 2);
+
+        let diff = store.diff(&second.record, None, None).unwrap();
+        assert!(diff.contains("-two\n+three"), "{}", diff);
+        assert_eq!(store.list(&ArtifactFilter::task("t1")).len(), 1);
+
+        // Reopening shares the in-memory index; a fresh process reads it back
+        assert!(Arc::ptr_eq(&store, &ArtifactStore::open(dir.path()).unwrap()));
+        let index: Vec<ArtifactRecord> = serde_json::from_slice(&std::fs::read(dir.path().join(".store/index.json")).unwrap()).unwrap();
+        assert_eq!(index.len(), 2);
+
+        // Other owners neither see nor delete the artifact unless they ask for a shared read
+        assert!(store.get("notes.md", &other).is_none());
+        assert!(store.delete("notes.md", &other).unwrap().is_none());
+        assert_eq!(store.get_shared("notes.md").unwrap().artifact_id, first.record.artifact_id);
+
+        // Deleting keeps content other artifacts still use
+        store.delete("notes.md", &task).unwrap().unwrap();
+        let (_, copy) = store.read(&store.get("copy.md", &other).unwrap(), None).unwrap();
+        assert_eq!(copy, b"one\nthree\n");
+    }
+
+    #[test]
+    fn test_failed_persist_leaves_index_unchanged() {
+        let dir = tempdir().unwrap();
+        let store = ArtifactStore::open(dir.path()).unwrap();
+        let task = ArtifactOwner { task_id: Some("t1".to_string()), session_id: None };
+        store.put("notes.md", b"one", None, &task, ArtifactProvenance::default()).unwrap();
+
+        // A directory in place of the catalog makes every write fail
+        let catalog = dir.path().join(".store/index.json");
+        std::fs::remove_file(&catalog).unwrap();
+        std::fs::create_dir(&catalog).unwrap();
+        assert!(store.put("notes.md", b"two", None, &task, ArtifactProvenance::default()).is_err());
+        assert!(store.put("other.md", b"two", None, &task, ArtifactProvenance::default()).is_err());
+        assert!(store.delete("notes.md", &task).is_err());
+
+        assert_eq!(store.get("notes.md", &task).unwrap().versions.len(), 1);
+        assert!(store.get("other.md", &task).is_none());
+        assert_eq!(store.list(&ArtifactFilter::owner(task.clone())).len(), 1);
+        assert!(store.list(&ArtifactFilter::owner(ArtifactOwner::default())).is_empty());
+    }
+
+    #[test]
+    fn test_names_cannot_escape() {
+        for name in ["../secret", "a/../../b", "/etc/passwd", "", "..\\win"] {
+            assert!(validate_name(name).is_err(), "{}", name);
+        }
+        assert!(validate_name("reports/2024/summary.md").is_ok());
+    }
+}
@@ -0,0 +1,228 @@
+//! Tool Result Cache
+//!
+//! Bounded store for successful tool outputs. What may be cached, and for how
+//! long, is declared by each tool through `Tool::cache_policy`; entries also
+//! remember the resources they read (`Tool::reads`) so that a later call
+//! writing an overlapping resource (`Tool::writes`) evicts them.
+//!
+//! A read that overlaps a write in flight could otherwise store what it saw
+//! before the write landed. Every invalidation therefore bumps a generation
+//! counter and is logged; a result is only stored if no write logged since the
+//! call started touched what it read.
+
+use super::ToolOutput;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Default number of results kept before the least recently used is evicted
+pub const DEFAULT_CACHE_CAPACITY: usize = 256;
+
+/// Writes remembered for racing reads; older reads are simply not cached
+const WRITE_LOG_CAPACITY: usize = 1024;
+
+/// How the registry may reuse a tool's successful result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// Always execute (live state or side effects)
+    Never,
+    /// Reuse for the given duration, unless a write invalidates it sooner
+    Ttl(Duration),
+    /// Output depends only on the parameters; reuse until evicted
+    Pure,
+}
+
+struct CacheEntry {
+    output: ToolOutput,
+    reads: Vec<String>,
+    stored: Instant,
+    expires: Option<Instant>,
+    last_used: Instant,
+}
+
+pub(crate) struct ToolCache {
+    entries: HashMap<String, CacheEntry>,
+    capacity: usize,
+    /// Bumped by every invalidation
+    generation: u64,
+    /// Recent writes and the generation they were logged at
+    writes: VecDeque<(u64, String)>,
+    /// Oldest generation the write log still fully covers
+    horizon: u64,
+}
+
+impl ToolCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self { entries: HashMap::new(), capacity, generation: 0, writes: VecDeque::new(), horizon: 0 }
+    }
+
+    /// Snapshot to take before executing a call whose result may be inserted
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Cached output and its age, dropping the entry if it has expired
+    pub(crate) fn get(&mut self, key: &str) -> Option<(ToolOutput, Duration)> {
+        let now = Instant::now();
+        let entry = self.entries.get_mut(key)?;
+        if entry.expires.is_some_and(|e| e <= now) {
+            self.entries.remove(key);
+            return None;
+        }
+        entry.last_used = now;
+        Some((entry.output.clone(), now.duration_since(entry.stored)))
+    }
+
+    /// Store a result computed by a call that started at generation `since`,
+    /// unless a write logged after that overlaps what it read
+    pub(crate) fn insert(&mut self, key: String, output: ToolOutput, policy: CachePolicy, reads: Vec<String>, since: u64) {
+        let now = Instant::now();
+        let expires = match policy {
+            CachePolicy::Never => return,
+            CachePolicy::Ttl(ttl) => Some(now + ttl),
+            CachePolicy::Pure => None,
+        };
+        if self.capacity == 0 {
+            return;
+        }
+        if since < self.horizon {
+            return;
+        }
+        let stale = self
+            .writes
+            .iter()
+            .rev()
+            .take_while(|(generation, _)| *generation > since)
+            .any(|(_, w)| reads.iter().any(|r| overlaps(r, w)));
+        if stale {
+            return;
+        }
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            self.entries.retain(|_, e| e.expires.is_none_or(|x| x > now));
+            if self.entries.len() >= self.capacity {
+                let lru = self.entries.iter().min_by_key(|(_, e)| e.last_used).map(|(k, _)| k.clone());
+                if let Some(lru) = lru {
+                    self.entries.remove(&lru);
+                }
+            }
+        }
+        self.entries.insert(key, CacheEntry { output, reads, stored: now, expires, last_used: now });
+    }
+
+    /// Evict every entry that read a resource overlapping one of `writes`
+    pub(crate) fn invalidate(&mut self, writes: &[String]) -> usize {
+        self.generation += 1;
+        for w in writes {
+            self.writes.push_back((self.generation, w.clone()));
+        }
+        while self.writes.len() > WRITE_LOG_CAPACITY {
+            if let Some((generation, _)) = self.writes.pop_front() {
+                self.horizon = generation;
+            }
+        }
+        let before = self.entries.len();
+        self.entries
+            .retain(|_, e| !e.reads.iter().any(|r| writes.iter().any(|w| overlaps(r, w))));
+        before - self.entries.len()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+/// Resource name for a filesystem path, canonicalized so that tools rooted at
+/// different relative directories agree on it
+pub fn file_resource(path: &Path) -> String {
+    let resolved = std::fs::canonicalize(path).ok().or_else(|| {
+        // Not created yet: resolve the parent instead
+        let parent = std::fs::canonicalize(path.parent()?).ok()?;
+        Some(parent.join(path.file_name()?))
+    });
+    format!("file:{}", resolved.as_deref().unwrap_or(path).display())
+}
+
+/// Resources overlap when equal or when one names a scope containing the
+/// other: `file:` covers every file, `file:src` covers `file:src/main.rs`
+fn overlaps(a: &str, b: &str) -> bool {
+    fn contains(scope: &str, inner: &str) -> bool {
+        inner.starts_with(scope)
+            && (scope.ends_with(['/', ':']) || inner.len() == scope.len() || inner[scope.len()..].starts_with('/'))
+    }
+    contains(a, b) || contains(b, a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn out(s: &str) -> ToolOutput {
+        ToolOutput::success_str(s)
+    }
+
+    #[test]
+    fn test_overlaps() {
+        assert!(overlaps("file:src/main.rs", "file:src/main.rs"));
+        assert!(overlaps("file:", "file:src/main.rs"));
+        assert!(overlaps("file:src/main.rs", "file:src"));
+        assert!(!overlaps("file:src/main.rs", "file:src/main.rs.bak"));
+        assert!(!overlaps("artifact:report", "file:report"));
+    }
+
+    #[test]
+    fn test_ttl_expiry_and_invalidation() {
+        let mut cache = ToolCache::new(8);
+        cache.insert("a".into(), out("a"), CachePolicy::Ttl(Duration::ZERO), vec![], 0);
+        assert!(cache.get("a").is_none());
+
+        cache.insert("read".into(), out("v1"), CachePolicy::Pure, vec!["file:src/lib.rs".into()], 0);
+        cache.insert("list".into(), out("ls"), CachePolicy::Pure, vec!["file:".into()], 0);
+        cache.insert("web".into(), out("w"), CachePolicy::Pure, vec![], 0);
+        assert_eq!(cache.invalidate(&["file:src/lib.rs".into()]), 2);
+        assert!(cache.get("web").is_some());
+    }
+
+    #[test]
+    fn test_bounded_lru() {
+        let mut cache = ToolCache::new(2);
+        cache.insert("a".into(), out("a"), CachePolicy::Pure, vec![], 0);
+        cache.insert("b".into(), out("b"), CachePolicy::Pure, vec![], 0);
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(cache.get("a").is_some());
+        cache.insert("c".into(), out("c"), CachePolicy::Pure, vec![], 0);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("b").is_none(), "least recently used entry should be evicted");
+        assert!(cache.get("a").is_some());
+
+        cache.insert("never".into(), out("n"), CachePolicy::Never, vec![], 0);
+        assert!(cache.get("never").is_none());
+    }
+
+    #[test]
+    fn test_write_during_read_is_not_cached() {
+        let mut cache = ToolCache::new(8);
+        let since = cache.generation();
+        // A write lands while both reads are still executing
+        cache.invalidate(&["file:src/lib.rs".into()]);
+        cache.insert("lib".into(), out("old"), CachePolicy::Pure, vec!["file:src/lib.rs".into()], since);
+        assert!(cache.get("lib").is_none(), "read racing an overlapping write must not be cached");
+        cache.insert("main".into(), out("m"), CachePolicy::Pure, vec!["file:src/main.rs".into()], since);
+        assert!(cache.get("main").is_some());
+
+        // Reads older than the write log are dropped conservatively
+        for _ in 0..=WRITE_LOG_CAPACITY {
+            cache.invalidate(&["artifact:x".into()]);
+        }
+        cache.clear();
+        cache.insert("main".into(), out("m"), CachePolicy::Pure, vec![], since);
+        assert!(cache.get("main").is_none());
+        let now = cache.generation();
+        cache.insert("main".into(), out("m"), CachePolicy::Pure, vec![], now);
+        assert!(cache.get("main").is_some());
+    }
+}
@@ -1,17 +1,21 @@
 //! Code Execution Tool
 //! 
 //! Safely executes code snippets in a sandboxed environment.
-//! Now with mandatory macOS Seatbelt (Immune System).
+//! Now with mandatory macOS Seatbelt (Immune System), and bubblewrap
+//! namespaces plus rlimits on Linux (see `utils::linux_sandbox`).
 
 use anyhow::Context;
 use async_trait::async_trait;
 use serde_json::{json, Value};
 use std::process::Stdio;
+#[cfg(target_os = "macos")]
 use tokio::process::Command;
 use tokio::time::{timeout, Duration};
 use tracing::{debug, warn, info};
 
 use crate::agent::{AgentResult, AgentError};
+use crate::utils::sandbox::SandboxPolicy;
+#[cfg(target_os = "macos")]
 use crate::utils::sandbox::TOOL_SANDBOX_POLICY;
 use super::{Tool, ToolOutput};
 
@@ -21,6 +25,8 @@ pub struct CodeExecTool {
     timeout_secs: u64,
     /// Maximum output length
     max_output_len: usize,
+    /// Confinement applied by non-Seatbelt backends
+    policy: SandboxPolicy,
 }
 
 impl CodeExecTool {
@@ -28,9 +34,16 @@ impl CodeExecTool {
         Self {
             timeout_secs: 30,
             max_output_len: 10000,
+            policy: SandboxPolicy::default().with_cpu_secs(30),
         }
     }
 
+    /// Replace the default confinement (read-only current dir, private /tmp, no network)
+    pub fn with_policy(mut self, policy: SandboxPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
     #[allow(dead_code)]
     pub fn with_timeout(mut self, secs: u64) -> Self {
         self.timeout_secs = secs;
@@ -42,9 +55,11 @@ impl CodeExecTool {
     }
 
     async fn execute_rust(&self, code: &str) -> anyhow::Result<(String, String, i32)> {
-        let temp_dir = std::env::temp_dir();
-        let file_path = temp_dir.join(format!("agent_code_{}.rs", uuid::Uuid::new_v4()));
-        let binary_path = temp_dir.join(format!("agent_code_{}", uuid::Uuid::new_v4()));
+        // The sandbox's own /tmp is private, so build in a directory bound into it
+        let build_dir = tempfile::tempdir().context("Failed to create build directory")?;
+        let policy = self.policy.clone().with_writable(build_dir.path());
+        let file_path = build_dir.path().join(format!("agent_code_{}.rs", uuid::Uuid::new_v4()));
+        let binary_path = build_dir.path().join(format!("agent_code_{}", uuid::Uuid::new_v4()));
 
         let file_path_str = file_path.to_str().ok_or_else(|| anyhow::anyhow!("Invalid temp file path"))?;
         let binary_path_str = binary_path.to_str().ok_or_else(|| anyhow::anyhow!("Invalid binary path"))?;
@@ -54,7 +69,7 @@ impl CodeExecTool {
 
         // Compile (Compile phase is ALSO sandboxed)
         let (stdout, stderr, code_result) = self
-            .run_command("rustc", &[
+            .run_confined(&policy, "rustc", &[
                 file_path_str,
                 "-o",
                 binary_path_str,
@@ -62,18 +77,11 @@ impl CodeExecTool {
             .await?;
 
         if code_result != 0 {
-            let _ = tokio::fs::remove_file(&file_path).await;
             return Ok((stdout, format!("Compilation failed:\n{}", stderr), code_result));
         }
 
-        // Run the compiled binary
-        let result = self.run_command(binary_path_str, &[]).await;
-
-        // Clean up
-        let _ = tokio::fs::remove_file(&file_path).await;
-        let _ = tokio::fs::remove_file(&binary_path).await;
-
-        result
+        // Run the compiled binary; the build directory is removed on drop
+        self.run_confined(&policy, binary_path_str, &[]).await
     }
 
     async fn execute_javascript(&self, code: &str) -> anyhow::Result<(String, String, i32)> {
@@ -85,11 +93,15 @@ impl CodeExecTool {
     }
 
     async fn run_command(&self, program: &str, args: &[&str]) -> anyhow::Result<(String, String, i32)> {
+        self.run_confined(&self.policy, program, args).await
+    }
+
+    async fn run_confined(&self, policy: &SandboxPolicy, program: &str, args: &[&str]) -> anyhow::Result<(String, String, i32)> {
         debug!("Running sandboxed command: {} {:?}", program, args);
 
         #[cfg(target_os = "macos")]
         {
-            let workspace_dir = std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."));
+            let workspace_dir = &policy.workspace;
             
             let mut sb_args = vec![
                 "-p".to_string(), TOOL_SANDBOX_POLICY.to_string(),
@@ -126,11 +138,12 @@ impl CodeExecTool {
 
         #[cfg(not(target_os = "macos"))]
         {
-            warn!("Mandatory Seatbelt sandboxing only available on macOS. Running unconfined.");
+            let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+            let mut command = crate::utils::sandbox::confined_command(program, &args, policy)?;
             let result = timeout(
                 Duration::from_secs(self.timeout_secs),
-                Command::new(program)
-                    .args(args)
+                command
+                    .kill_on_drop(true)
                     .stdout(Stdio::piped())
                     .stderr(Stdio::piped())
                     .stdin(Stdio::null())
@@ -173,7 +186,7 @@ impl Tool for CodeExecTool {
 
     fn description(&self) -> String {
         "Execute code in a MANDATORY sandboxed environment. Supports Python, JavaScript, Rust, and shell commands.\n 
-         Use this to run calculations, test code snippets, or perform automated tasks. Access restricted to project directory and /tmp.".to_string()
+         Use this to run calculations, test code snippets, or perform automated tasks. The project directory is read-only; write scratch files to /tmp.".to_string()
     }
 
     fn parameters(&self) -> Value {
@@ -195,13 +208,23 @@ impl Tool for CodeExecTool {
     }
 
     fn work_scope(&self) -> Value {
+        let environment = if cfg!(target_os = "macos") {
+            "MANDATORY macOS Seatbelt Sandbox"
+        } else {
+            "Linux namespaces plus seccomp via bubblewrap (read-only root and workspace, private /tmp)"
+        };
         json!({
             "status": "constrained",
-            "environment": "MANDATORY macOS Seatbelt Sandbox",
+            "environment": environment,
             "safety": "ULTRA-HIGH (Kernel-enforced isolation)",
+            // The Seatbelt profile allows outbound traffic for package managers
+            "network": if cfg!(target_os = "macos") || self.policy.allow_network { "allowed" } else { "denied" },
             "resource_limits": {
                 "timeout": format!("{}s", self.timeout_secs),
-                "max_output": format!("{} bytes", self.max_output_len)
+                "max_output": format!("{} bytes", self.max_output_len),
+                "cpu": format!("{}s", self.policy.cpu_secs),
+                "memory": format!("{} MB", self.policy.memory_bytes / (1024 * 1024)),
+                "processes": self.policy.max_processes
             }
         })
     }
@@ -210,6 +233,11 @@ impl Tool for CodeExecTool {
         true // Still require confirmation for auditing
     }
 
+    fn writes(&self, _params: &Value) -> Vec<String> {
+        // The sandbox profile allows writes to the workspace; assume any file changed
+        vec!["file:".to_string()]
+    }
+
     async fn execute(&self, params: Value) -> AgentResult<ToolOutput> {
         let code = params["code"]
             .as_str()
@@ -272,6 +300,7 @@ impl Tool for CodeExecTool {
                         }),
                         summary,
                         error: Some(format!("Exit code: {}", exit_code)),
+                        cache_age_secs: None,
                     })
                 }
             }
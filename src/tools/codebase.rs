@@ -4,12 +4,16 @@
 //! This helps agents understand their own capabilities and tool definitions.
 
 use async_trait::async_trait;
+use globset::{Glob, GlobMatcher};
+use lazy_static::lazy_static;
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
 use serde_json::{json, Value};
 use std::path::{Path, PathBuf};
 use tokio::fs;
 
 use crate::agent::{AgentResult, AgentError};
-use super::{Tool, ToolOutput};
+use super::{file_resource, CachePolicy, Tool, ToolOutput};
 
 /// Tool for exploring the agency's own codebase
 pub struct CodebaseTool {
@@ -39,6 +43,237 @@ impl CodebaseTool {
         path_str.ends_with("Cargo.lock") ||
         path_str.ends_with(".gitignore")
     }
+
+    /// `rel` under the source root. A missing path is judged by its nearest
+    /// existing ancestor, so it is "not found" inside the allowed areas and
+    /// "Access denied" outside them.
+    fn resolve(&self, rel: &str) -> Result<PathBuf, String> {
+        let path = self.src_dir.join(rel);
+        if !path.exists() {
+            let inside = path.ancestors().skip(1).find(|p| p.exists()).is_some_and(|p| self.is_safe_path(p));
+            return Err(if inside {
+                format!("File not found: {}", rel)
+            } else {
+                format!("Access denied: Path outside allowed areas ({})", rel)
+            });
+        }
+        if !self.is_safe_path(&path) {
+            return Err(format!("Access denied: Path outside allowed areas ({})", rel));
+        }
+        Ok(std::fs::canonicalize(&path).unwrap_or(path))
+    }
+
+    /// Directory an action is scoped to: `path` under the source root, or the root itself
+    fn scope(&self, params: &Value) -> Result<PathBuf, String> {
+        match params["path"].as_str().filter(|p| !p.is_empty()) {
+            Some(rel) => self.resolve(rel),
+            None => Ok(self.src_dir.clone()),
+        }
+    }
+
+    /// Path as shown to the model, relative to the source root when possible
+    fn display(&self, path: &Path) -> String {
+        relative(path, &self.src_dir)
+    }
+}
+
+/// Directories never descended into
+const SKIP_DIRS: &[&str] = &["target", ".git", "node_modules", "__pycache__", ".venv"];
+/// Files larger than this are skipped by `search` and `outline`
+const MAX_SCAN_BYTES: u64 = 1024 * 1024;
+/// Matched lines are clipped to this many characters
+const MAX_LINE_CHARS: usize = 240;
+
+fn relative(path: &Path, base: &Path) -> String {
+    path.strip_prefix(base).unwrap_or(path).to_string_lossy().to_string()
+}
+
+fn compile_glob(params: &Value) -> Result<Option<GlobMatcher>, String> {
+    match params["glob"].as_str().filter(|g| !g.is_empty()) {
+        Some(pattern) => Glob::new(pattern)
+            .map(|g| Some(g.compile_matcher()))
+            .map_err(|e| format!("Invalid glob '{}': {}", pattern, e)),
+        None => Ok(None),
+    }
+}
+
+/// Files under `root` (or `root` itself if it is a file), sorted, whose path
+/// relative to `base` matches `glob`
+fn walk(root: &Path, base: &Path, glob: Option<&GlobMatcher>) -> Vec<PathBuf> {
+    let matches = |path: &Path| match glob {
+        Some(g) => g.is_match(path.strip_prefix(base).unwrap_or(path)),
+        None => true,
+    };
+    if root.is_file() {
+        return if matches(root) { vec![root.to_path_buf()] } else { Vec::new() };
+    }
+
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            let Ok(kind) = entry.file_type() else { continue };
+            let path = entry.path();
+            if kind.is_dir() {
+                if !SKIP_DIRS.iter().any(|skip| entry.file_name() == *skip) {
+                    dirs.push(path);
+                }
+            } else if kind.is_file() && matches(&path) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Text of a file worth scanning, skipping large and non-UTF-8 (binary) files
+fn read_source(path: &Path) -> Option<String> {
+    let meta = std::fs::metadata(path).ok()?;
+    if meta.len() > MAX_SCAN_BYTES {
+        return None;
+    }
+    std::fs::read_to_string(path).ok()
+}
+
+fn clip(line: &str) -> String {
+    if line.chars().count() <= MAX_LINE_CHARS {
+        line.to_string()
+    } else {
+        format!("{}…", line.chars().take(MAX_LINE_CHARS).collect::<String>())
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct SearchMatch {
+    path: String,
+    line: usize,
+    text: String,
+    before: Vec<String>,
+    after: Vec<String>,
+}
+
+/// A symbol definition found by `outline`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct Symbol {
+    kind: &'static str,
+    name: String,
+    line: usize,
+    /// Nesting level derived from indentation (methods inside impls/classes)
+    depth: usize,
+}
+
+lazy_static! {
+    static ref RUST_ITEM: Regex = Regex::new(
+        r#"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:(?:const|async|unsafe|default|extern(?:\s+"[^"]*")?)\s+)*(fn|struct|enum|trait|mod|type)\s+([A-Za-z_][A-Za-z0-9_]*)"#
+    ).unwrap();
+    static ref RUST_IMPL: Regex = Regex::new(r"^\s*(?:unsafe\s+)?impl\b(.*)").unwrap();
+    static ref RUST_MACRO: Regex = Regex::new(r"^\s*macro_rules!\s*([A-Za-z_][A-Za-z0-9_]*)").unwrap();
+    static ref PY_ITEM: Regex = Regex::new(r"^\s*(?:async\s+)?(def|class)\s+([A-Za-z_][A-Za-z0-9_]*)").unwrap();
+    static ref JS_FUNCTION: Regex = Regex::new(
+        r"^\s*(?:export\s+)?(?:default\s+)?(?:async\s+)?function\s*\*?\s*([A-Za-z_$][A-Za-z0-9_$]*)"
+    ).unwrap();
+    static ref JS_DECL: Regex = Regex::new(
+        r"^\s*(?:export\s+)?(?:default\s+)?(?:abstract\s+)?(class|interface|enum|type)\s+([A-Za-z_$][A-Za-z0-9_$]*)"
+    ).unwrap();
+    static ref JS_ARROW: Regex = Regex::new(
+        r"^\s*(?:export\s+)?(?:const|let|var)\s+([A-Za-z_$][A-Za-z0-9_$]*)\s*=\s*(?:async\s+)?(?:function\b|\([^)]*\)\s*=>|[A-Za-z_$][A-Za-z0-9_$]*\s*=>)"
+    ).unwrap();
+    static ref JS_METHOD: Regex = Regex::new(
+        r"^\s+(?:(?:static|async|get|set|public|private|protected)\s+)*([A-Za-z_$][A-Za-z0-9_$]*)\s*\([^)]*\)\s*(?::[^{]*)?\{"
+    ).unwrap();
+}
+
+/// Keywords that look like method definitions to `JS_METHOD`
+const JS_NOT_METHODS: &[&str] = &["if", "for", "while", "switch", "catch", "function", "return", "with"];
+
+fn indent_depth(line: &str) -> usize {
+    let width: usize = line
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum();
+    width / 4
+}
+
+/// `impl<T: X> Trait for Type<T> where ... {` -> `Trait for Type<T>`
+fn impl_header(rest: &str) -> String {
+    let mut rest = rest.trim_start();
+    // Skip the impl's own generic parameters
+    if rest.starts_with('<') {
+        let mut depth = 0;
+        for (i, c) in rest.char_indices() {
+            match c {
+                '<' => depth += 1,
+                '>' => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                rest = &rest[i + 1..];
+                break;
+            }
+        }
+    }
+    let end = [rest.find('{'), rest.find(" where")].into_iter().flatten().min().unwrap_or(rest.len());
+    rest[..end].trim().to_string()
+}
+
+/// Symbols defined in `source`, or `None` if the language is not supported
+fn outline_source(extension: &str, source: &str) -> Option<Vec<Symbol>> {
+    let lang = match extension {
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" => "js",
+        _ => return None,
+    };
+    let mut symbols = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let symbol = |kind: &'static str, name: String| Symbol { kind, name, line: i + 1, depth: indent_depth(line) };
+        let found = match lang {
+            "rust" => {
+                if let Some(c) = RUST_ITEM.captures(line) {
+                    let kind = match &c[1] {
+                        "fn" => "fn",
+                        "struct" => "struct",
+                        "enum" => "enum",
+                        "trait" => "trait",
+                        "mod" => "mod",
+                        _ => "type",
+                    };
+                    Some(symbol(kind, c[2].to_string()))
+                } else if let Some(c) = RUST_IMPL.captures(line) {
+                    Some(symbol("impl", impl_header(&c[1])))
+                } else {
+                    RUST_MACRO.captures(line).map(|c| symbol("macro", c[1].to_string()))
+                }
+            }
+            "python" => PY_ITEM.captures(line).map(|c| {
+                let kind = if &c[1] == "class" { "class" } else { "def" };
+                symbol(kind, c[2].to_string())
+            }),
+            _ => {
+                if let Some(c) = JS_FUNCTION.captures(line).or_else(|| JS_ARROW.captures(line)) {
+                    Some(symbol("function", c[1].to_string()))
+                } else if let Some(c) = JS_DECL.captures(line) {
+                    let kind = match &c[1] {
+                        "class" => "class",
+                        "interface" => "interface",
+                        "enum" => "enum",
+                        _ => "type",
+                    };
+                    Some(symbol(kind, c[2].to_string()))
+                } else {
+                    JS_METHOD
+                        .captures(line)
+                        .filter(|c| !JS_NOT_METHODS.contains(&&c[1]))
+                        .map(|c| symbol("method", c[1].to_string()))
+                }
+            }
+        };
+        symbols.extend(found);
+    }
+    Some(symbols)
 }
 
 impl Default for CodebaseTool {
@@ -54,7 +289,7 @@ impl Tool for CodebaseTool {
     }
 
     fn description(&self) -> String {
-        "Explore and analyze the current project's codebase. \n        Supports 'list_files', 'read_file', and 'search' operations.".to_string()
+        "Explore and analyze the current project's codebase. \n        Supports 'list_files', 'read_file', 'search' (literal or regex content search with context lines) and 'outline' (functions, structs, impls and classes per file with line numbers).".to_string()
     }
 
     fn parameters(&self) -> Value {
@@ -63,16 +298,42 @@ impl Tool for CodebaseTool {
             "properties": {
                 "action": {
                     "type": "string",
-                    "enum": ["list_files", "read_file", "search"],
+                    "enum": ["list_files", "read_file", "search", "outline"],
                     "description": "The action to perform"
                 },
                 "path": {
                     "type": "string",
-                    "description": "File path (if action is 'read_file')"
+                    "description": "File path for 'read_file'; file or directory to restrict 'list_files', 'search' and 'outline' to"
                 },
                 "query": {
                     "type": "string",
-                    "description": "Search query (if action is 'search')"
+                    "description": "Text to find (if action is 'search')"
+                },
+                "regex": {
+                    "type": "boolean",
+                    "default": false,
+                    "description": "Treat 'query' as a regular expression instead of literal text"
+                },
+                "case_sensitive": {
+                    "type": "boolean",
+                    "default": true
+                },
+                "glob": {
+                    "type": "string",
+                    "description": "Only files whose path matches this glob, e.g. '*.rs' or 'agent/**/*.{rs,py}'"
+                },
+                "context": {
+                    "type": "integer",
+                    "default": 2,
+                    "minimum": 0,
+                    "maximum": 10,
+                    "description": "Lines of context around each search match"
+                },
+                "max_results": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "maximum": 1000,
+                    "description": "Cap on matches (search, default 50), symbols (outline, default 300) or files (list_files, default 500)"
                 }
             },
             "required": ["action"]
@@ -88,54 +349,55 @@ impl Tool for CodebaseTool {
         })
     }
 
+    fn cache_policy(&self, _params: &Value) -> CachePolicy {
+        // Read-only; edits made through other tools invalidate via `reads`
+        CachePolicy::Ttl(std::time::Duration::from_secs(60))
+    }
+
+    fn reads(&self, params: &Value) -> Vec<String> {
+        match params["path"].as_str().filter(|p| !p.is_empty()) {
+            Some(path) => vec![file_resource(&self.src_dir.join(path))],
+            None => vec![file_resource(&self.src_dir)],
+        }
+    }
+
     async fn execute(&self, params: Value) -> AgentResult<ToolOutput> {
         let action = params["action"].as_str().unwrap_or("list_files");
 
         match action {
             "list_files" => {
-                let mut files = Vec::new();
-                let mut dirs = vec![self.src_dir.clone()];
-                
-                while let Some(dir) = dirs.pop() {
-                    let mut entries = match fs::read_dir(dir).await {
-                        Ok(e) => e,
-                        Err(e) => return Ok(ToolOutput::failure(format!("Failed to read directory: {}", e))),
-                    };
-                    while let Some(entry) = entries.next_entry().await.map_err(|e| AgentError::Io(e))? {
-                        let path = entry.path();
-                        if path.is_dir() {
-                            let path_str = path.to_string_lossy();
-                            if !path_str.contains("target") && !path_str.contains(".git") {
-                                dirs.push(path);
-                            }
-                        } else {
-                            files.push(path.to_string_lossy().to_string());
-                        }
-                    }
+                let (root, glob) = match (self.scope(&params), compile_glob(&params)) {
+                    (Ok(root), Ok(glob)) => (root, glob),
+                    (Err(e), _) | (_, Err(e)) => return Ok(ToolOutput::failure(e)),
+                };
+                if !root.exists() {
+                    return Ok(ToolOutput::failure(format!("Failed to read directory: {} does not exist", root.display())));
                 }
-                
+                let limit = params["max_results"].as_u64().unwrap_or(500) as usize;
+                let base = self.src_dir.clone();
+                let found = tokio::task::spawn_blocking(move || walk(&root, &base, glob.as_ref()))
+                    .await
+                    .map_err(|e| AgentError::Tool(e.to_string()))?;
+                let truncated = found.len() > limit;
+                let files: Vec<String> = found.iter().take(limit).map(|f| self.display(f)).collect();
+
                 let mut tree_summary = String::from("Codebase Files:\n");
                 for f in &files {
                     tree_summary.push_str(&format!("- {}\n", f));
                 }
+                if truncated {
+                    tree_summary.push_str(&format!("... {} more (narrow with 'path' or 'glob')\n", found.len() - limit));
+                }
                 
-                Ok(ToolOutput::success(json!({ "files": files }), tree_summary))
+                Ok(ToolOutput::success(json!({ "files": files, "total": found.len(), "truncated": truncated }), tree_summary))
             },
             "read_file" => {
                 let rel_path = params["path"].as_str().ok_or_else(|| AgentError::Validation("Missing path".to_string()))?;
                 
-                // Construct the path carefully
-                let path = self.src_dir.join(rel_path);
-                
-                // SAFETY CHECK FIRST
-                if !self.is_safe_path(&path) {
-                    return Ok(ToolOutput::failure("Access denied: Path outside allowed areas"));
-                }
-
-                // Check existence after safety check
-                if !path.exists() {
-                    return Ok(ToolOutput::failure(format!("File not found: {}", rel_path)));
-                }
+                let path = match self.resolve(rel_path) {
+                    Ok(path) => path,
+                    Err(e) => return Ok(ToolOutput::failure(e)),
+                };
 
                 let content = match fs::read_to_string(&path).await {
                     Ok(c) => c,
@@ -147,6 +409,148 @@ impl Tool for CodebaseTool {
                     format!("Content of {}:\n\n{}", rel_path, content)
                 ))
             },
+            "search" => {
+                let query = params["query"].as_str().filter(|q| !q.is_empty())
+                    .ok_or_else(|| AgentError::Validation("Missing query".to_string()))?;
+                let pattern = if params["regex"].as_bool().unwrap_or(false) {
+                    query.to_string()
+                } else {
+                    regex::escape(query)
+                };
+                let matcher = match RegexBuilder::new(&pattern)
+                    .case_insensitive(!params["case_sensitive"].as_bool().unwrap_or(true))
+                    .build()
+                {
+                    Ok(m) => m,
+                    Err(e) => return Ok(ToolOutput::failure(format!("Invalid regex '{}': {}", query, e))),
+                };
+                let (root, glob) = match (self.scope(&params), compile_glob(&params)) {
+                    (Ok(root), Ok(glob)) => (root, glob),
+                    (Err(e), _) | (_, Err(e)) => return Ok(ToolOutput::failure(e)),
+                };
+                let context = params["context"].as_u64().unwrap_or(2) as usize;
+                let limit = params["max_results"].as_u64().unwrap_or(50) as usize;
+
+                let base = self.src_dir.clone();
+                let (matches, searched, truncated) = tokio::task::spawn_blocking(move || {
+                    let files = walk(&root, &base, glob.as_ref());
+                    let mut matches = Vec::new();
+                    let mut truncated = false;
+                    'files: for file in &files {
+                        let Some(source) = read_source(file) else { continue };
+                        let lines: Vec<&str> = source.lines().collect();
+                        for (i, line) in lines.iter().enumerate() {
+                            if !matcher.is_match(line) {
+                                continue;
+                            }
+                            if matches.len() == limit {
+                                truncated = true;
+                                break 'files;
+                            }
+                            let after_end = (i + 1 + context).min(lines.len());
+                            matches.push(SearchMatch {
+                                path: relative(file, &base),
+                                line: i + 1,
+                                text: clip(line),
+                                before: lines[i.saturating_sub(context)..i].iter().map(|l| clip(l)).collect(),
+                                after: lines[i + 1..after_end].iter().map(|l| clip(l)).collect(),
+                            });
+                        }
+                    }
+                    (matches, files.len(), truncated)
+                })
+                .await
+                .map_err(|e| AgentError::Tool(e.to_string()))?;
+
+                let mut files: Vec<&str> = matches.iter().map(|m| m.path.as_str()).collect();
+                files.dedup();
+
+                let mut summary = format!(
+                    "Found {}{} matches for '{}' in {} files ({} searched):\n",
+                    matches.len(),
+                    if truncated { "+" } else { "" },
+                    query,
+                    files.len(),
+                    searched
+                );
+                for m in &matches {
+                    let first = m.line - m.before.len();
+                    for (j, l) in m.before.iter().enumerate() {
+                        summary.push_str(&format!("{}-{}- {}\n", m.path, first + j, l));
+                    }
+                    summary.push_str(&format!("{}:{}: {}\n", m.path, m.line, m.text));
+                    for (j, l) in m.after.iter().enumerate() {
+                        summary.push_str(&format!("{}-{}- {}\n", m.path, m.line + 1 + j, l));
+                    }
+                    if context > 0 {
+                        summary.push_str("--\n");
+                    }
+                }
+                if truncated {
+                    summary.push_str(&format!("Stopped at {} matches; narrow with 'path', 'glob' or a more specific query.\n", limit));
+                }
+
+                Ok(ToolOutput::success(
+                    json!({ "query": query, "matches": matches, "files_searched": searched, "truncated": truncated }),
+                    summary,
+                ))
+            },
+            "outline" => {
+                let (root, glob) = match (self.scope(&params), compile_glob(&params)) {
+                    (Ok(root), Ok(glob)) => (root, glob),
+                    (Err(e), _) | (_, Err(e)) => return Ok(ToolOutput::failure(e)),
+                };
+                if !root.exists() {
+                    return Ok(ToolOutput::failure(format!("File not found: {}", self.display(&root))));
+                }
+                let limit = params["max_results"].as_u64().unwrap_or(300) as usize;
+
+                let base = self.src_dir.clone();
+                let outlines = tokio::task::spawn_blocking(move || {
+                    walk(&root, &base, glob.as_ref())
+                        .into_iter()
+                        .filter_map(|file| {
+                            let ext = file.extension()?.to_str()?.to_string();
+                            let symbols = outline_source(&ext, &read_source(&file)?)?;
+                            Some((file, symbols))
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .await
+                .map_err(|e| AgentError::Tool(e.to_string()))?;
+
+                let mut remaining = limit;
+                let mut truncated = false;
+                let mut files = Vec::new();
+                let mut summary = String::new();
+                for (file, mut symbols) in outlines {
+                    if symbols.is_empty() {
+                        continue;
+                    }
+                    if remaining == 0 {
+                        truncated = true;
+                        break;
+                    }
+                    if symbols.len() > remaining {
+                        symbols.truncate(remaining);
+                        truncated = true;
+                    }
+                    remaining -= symbols.len();
+                    let path = self.display(&file);
+                    summary.push_str(&format!("{}\n", path));
+                    for s in &symbols {
+                        summary.push_str(&format!("{}{:>5} {} {}\n", "  ".repeat(s.depth + 1), s.line, s.kind, s.name));
+                    }
+                    files.push(json!({ "path": path, "symbols": symbols }));
+                }
+                if files.is_empty() {
+                    summary = "No Rust, Python or JavaScript/TypeScript symbols found.".to_string();
+                } else if truncated {
+                    summary.push_str(&format!("Stopped at {} symbols; narrow with 'path' or 'glob'.\n", limit));
+                }
+
+                Ok(ToolOutput::success(json!({ "files": files, "truncated": truncated }), summary))
+            },
             _ => Ok(ToolOutput::failure("Unsupported codebase action"))
         }
     }
@@ -216,4 +620,100 @@ mod tests {
         assert!(!res.success);
         assert!(res.summary.contains("Access denied"));
     }
+
+    fn fixture_tree() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempdir().expect("Failed to create temp dir");
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(src.join("agent")).unwrap();
+        std::fs::create_dir_all(src.join("target")).unwrap();
+        std::fs::write(src.join("lib.rs"), "mod agent;\n\npub fn run() {\n    let retries = 3;\n    agent::step(retries);\n}\n").unwrap();
+        std::fs::write(src.join("agent/mod.rs"), "pub fn step(n: u32) {\n    // TODO: retry\n}\n").unwrap();
+        std::fs::write(src.join("agent/helper.py"), "def retry(n):\n    return n\n").unwrap();
+        std::fs::write(src.join("target/junk.rs"), "fn retries() {}\n").unwrap();
+        (dir, src)
+    }
+
+    #[tokio::test]
+    async fn test_codebase_search() {
+        let (_dir, src) = fixture_tree();
+        let tool = CodebaseTool::new(&src);
+
+        // Literal, case-insensitive, with context; build dirs are skipped
+        let res = tool.execute(json!({ "action": "search", "query": "RETRIES", "case_sensitive": false, "context": 1 })).await.unwrap();
+        assert!(res.success, "{}", res.summary);
+        let matches = res.data["matches"].as_array().unwrap();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0]["path"], "lib.rs");
+        assert_eq!(matches[0]["line"], 4);
+        assert_eq!(matches[0]["before"], json!(["pub fn run() {"]));
+        assert!(res.summary.contains("lib.rs:4:     let retries = 3;"));
+        assert!(res.summary.contains("lib.rs-3- pub fn run() {"));
+
+        // Regex with a glob and a result limit
+        let res = tool.execute(json!({ "action": "search", "query": r"retr(y|ies)", "regex": true, "glob": "agent/*", "max_results": 1 })).await.unwrap();
+        let matches = res.data["matches"].as_array().unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(res.data["truncated"], true);
+        assert!(matches[0]["path"].as_str().unwrap().starts_with("agent/"));
+
+        let res = tool.execute(json!({ "action": "search", "query": "(", "regex": true })).await.unwrap();
+        assert!(!res.success);
+        assert!(res.summary.contains("Invalid regex"));
+    }
+
+    #[tokio::test]
+    async fn test_codebase_outline() {
+        let (_dir, src) = fixture_tree();
+        let tool = CodebaseTool::new(&src);
+
+        let res = tool.execute(json!({ "action": "outline" })).await.unwrap();
+        assert!(res.success, "{}", res.summary);
+        let files = res.data["files"].as_array().unwrap();
+        let paths: Vec<&str> = files.iter().map(|f| f["path"].as_str().unwrap()).collect();
+        assert_eq!(paths, vec!["agent/helper.py", "agent/mod.rs", "lib.rs"]);
+        assert!(res.summary.contains("3 fn run"));
+
+        let res = tool.execute(json!({ "action": "outline", "path": "agent/mod.rs" })).await.unwrap();
+        assert_eq!(res.data["files"][0]["symbols"], json!([{ "kind": "fn", "name": "step", "line": 1, "depth": 0 }]));
+    }
+
+    #[tokio::test]
+    async fn test_missing_paths_are_not_found_inside_and_denied_outside() {
+        let (_dir, src) = fixture_tree();
+        let tool = CodebaseTool::new(&src);
+
+        for action in ["search", "outline"] {
+            let res = tool.execute(json!({ "action": action, "query": "retry", "path": "agent/missing" })).await.unwrap();
+            assert!(!res.success);
+            assert!(res.summary.contains("not found"), "{}: {}", action, res.summary);
+
+            let res = tool.execute(json!({ "action": action, "query": "retry", "path": "../missing" })).await.unwrap();
+            assert!(res.summary.contains("Access denied"), "{}: {}", action, res.summary);
+        }
+        let res = tool.execute(json!({ "action": "read_file", "path": "missing.rs" })).await.unwrap();
+        assert!(res.summary.contains("not found"), "{}", res.summary);
+    }
+
+    #[test]
+    fn test_outline_languages() {
+        let rust = "pub struct Agent<T> {\n    inner: T,\n}\n\nimpl<T: Clone> Tool for Agent<T> where T: Send {\n    pub(crate) async fn execute(&self) {}\n}\n\nmacro_rules! emit {\n    () => {};\n}\n";
+        let symbols = outline_source("rs", rust).unwrap();
+        let summary: Vec<(&str, &str, usize, usize)> = symbols.iter().map(|s| (s.kind, s.name.as_str(), s.line, s.depth)).collect();
+        assert_eq!(summary, vec![
+            ("struct", "Agent", 1, 0),
+            ("impl", "Tool for Agent<T>", 5, 0),
+            ("fn", "execute", 6, 1),
+            ("macro", "emit", 9, 0),
+        ]);
+
+        let python = "class Memory:\n    async def recall(self, q):\n        pass\n";
+        let names: Vec<String> = outline_source("py", python).unwrap().iter().map(|s| format!("{} {} @{}", s.kind, s.name, s.depth)).collect();
+        assert_eq!(names, vec!["class Memory @0", "def recall @1"]);
+
+        let js = "export class Store {\n  async load(id) {\n    if (id) {\n    }\n  }\n}\nexport const save = async (item) => item;\nfunction helper() {}\n";
+        let names: Vec<String> = outline_source("ts", js).unwrap().iter().map(|s| format!("{} {}", s.kind, s.name)).collect();
+        assert_eq!(names, vec!["class Store", "method load", "function save", "function helper"]);
+
+        assert!(outline_source("md", "# Title").is_none());
+    }
 }
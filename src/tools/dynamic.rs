@@ -1,6 +1,8 @@
 //! Dynamic Tool Implementation
 //! 
 //! Allows for loading and executing custom scripts as first-class tools.
+//! Each tool carries a semantic version and the test cases that gate its
+//! promotion from the laboratory (`custom_tools`) to `standard_tools`.
 
 use anyhow::Context;
 use async_trait::async_trait;
@@ -14,6 +16,8 @@ use tokio::time::{timeout, Duration};
 use tracing::{debug, warn};
 
 use crate::agent::{AgentResult, AgentError};
+use crate::utils::sandbox::SandboxPolicy;
+use super::history::ToolHistory;
 use super::{Tool, ToolOutput, ToolRegistry};
 
 /// Metadata for a dynamic tool
@@ -24,6 +28,35 @@ pub struct DynamicToolMetadata {
     pub parameters: Value,
     pub language: String, // "python", "shell", "node"
     pub script_path: String,
+    /// Confinement override (e.g. `{"allow_network": true}`); defaults apply otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<SandboxPolicy>,
+    /// Semantic version, bumped each time the tool is re-forged
+    #[serde(default = "default_version")]
+    pub version: String,
+    /// Cases that must pass in the sandbox before the tool is promoted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tests: Vec<DynamicToolTest>,
+}
+
+fn default_version() -> String {
+    "0.1.0".to_string()
+}
+
+impl DynamicToolMetadata {
+    /// Parsed `version`; unparseable versions sort first
+    pub fn semver(&self) -> semver::Version {
+        semver::Version::parse(&self.version).unwrap_or_else(|_| semver::Version::new(0, 0, 0))
+    }
+}
+
+/// A declared test case: the tool must succeed on `input`, and its output
+/// must contain `expect_contains` when given
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DynamicToolTest {
+    pub input: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expect_contains: Option<String>,
 }
 
 /// A tool that executes an external script
@@ -37,6 +70,36 @@ impl DynamicTool {
         Self { metadata, base_path }
     }
 
+    /// Confinement for this tool's script on non-Seatbelt hosts
+    pub fn sandbox_policy(&self) -> SandboxPolicy {
+        self.metadata.sandbox.clone().unwrap_or_default()
+    }
+
+    pub fn metadata(&self) -> &DynamicToolMetadata {
+        &self.metadata
+    }
+
+    /// Run the declared test cases through the (sandboxed) executor.
+    /// Returns the number of cases passed; a tool without tests fails.
+    pub async fn run_tests(&self) -> anyhow::Result<usize> {
+        if self.metadata.tests.is_empty() {
+            anyhow::bail!("Tool '{}' declares no test cases", self.metadata.name);
+        }
+        for (i, case) in self.metadata.tests.iter().enumerate() {
+            let output = self.execute(case.input.clone()).await
+                .map_err(|e| anyhow::anyhow!("Test {} of '{}' errored: {}", i + 1, self.metadata.name, e))?;
+            if !output.success {
+                anyhow::bail!("Test {} of '{}' failed: {}", i + 1, self.metadata.name, output.summary);
+            }
+            if let Some(expected) = &case.expect_contains {
+                if !output.summary.contains(expected.as_str()) {
+                    anyhow::bail!("Test {} of '{}' expected output containing {:?}, got {:?}", i + 1, self.metadata.name, expected, output.summary);
+                }
+            }
+        }
+        Ok(self.metadata.tests.len())
+    }
+
     pub fn from_file(path: &Path) -> anyhow::Result<Self> {
         let content = std::fs::read_to_string(path)
             .with_context(|| format!("Failed to read tool metadata at {:?}", path))?;
@@ -62,6 +125,10 @@ impl Tool for DynamicTool {
         self.metadata.parameters.clone()
     }
 
+    fn is_forged(&self) -> bool {
+        true
+    }
+
     fn work_scope(&self) -> Value {
         json!({
             "status": "custom",
@@ -69,6 +136,11 @@ impl Tool for DynamicTool {
         })
     }
 
+    fn writes(&self, _params: &Value) -> Vec<String> {
+        // Forged scripts run from the workspace and may touch any file
+        vec!["file:".to_string()]
+    }
+
     async fn execute(&self, params: Value) -> AgentResult<ToolOutput> {
         let script_abs_path = self.base_path.join(&self.metadata.script_path);
         
@@ -119,10 +191,20 @@ impl Tool for DynamicTool {
             ("/usr/bin/sandbox-exec".to_string(), sb_args)
         };
 
+        #[cfg(target_os = "macos")]
+        let mut command = {
+            let mut command = Command::new(&cmd);
+            command.args(&args);
+            command
+        };
+        #[cfg(not(target_os = "macos"))]
+        let mut command = crate::utils::sandbox::confined_command(&cmd, &args, &self.sandbox_policy())
+            .map_err(|e| AgentError::Tool(e.to_string()))?;
+
         let result = timeout(
             Duration::from_secs(60),
-            Command::new(&cmd)
-                .args(&args)
+            command
+                .kill_on_drop(true)
                 .stdout(Stdio::piped())
                 .stderr(Stdio::piped())
                 .stdin(Stdio::null())
@@ -146,6 +228,7 @@ impl Tool for DynamicTool {
                         data: json!({ "stdout": stdout, "stderr": stderr, "exit_code": exit_code }),
                         summary: format!("Tool failed with exit code {}.\nError: {}", exit_code, stderr),
                         error: Some(stderr),
+                        cache_age_secs: None,
                     })
                 }
             }
@@ -180,6 +263,7 @@ impl Tool for ForgeTool {
         "Forge a new specialized tool by providing metadata and a script.\n
          The new tool will be permanently available to the agency and CAN BE USED IMMEDIATELY in the next step.\n 
          BY DEFAULT, tools should be forged in 'rust' unless specifically requested otherwise by the human or necessitated by complex logic.\n 
+         Declare `tests` so the tool can be promoted; re-forging an existing tool releases a new version and keeps the old one in history.\n 
          Use this when you need a specialized functionality that doesn't exist yet (e.g. specialized file parsing, data transformation, or API interaction).".to_string()
     }
 
@@ -191,7 +275,13 @@ impl Tool for ForgeTool {
                 "description": { "type": "string", "description": "What the tool does" },
                 "parameters": { "type": "object", "description": "JSON schema for tool parameters" },
                 "language": { "type": "string", "enum": ["python", "shell", "node", "rust"], "default": "rust" },
-                "code": { "type": "string", "description": "The actual script code" }
+                "code": { "type": "string", "description": "The actual script code" },
+                "tests": {
+                    "type": "array",
+                    "description": "Test cases ({\"input\": {...}, \"expect_contains\": \"...\"}). The tool is only promoted to the standard set once they all pass.",
+                    "items": { "type": "object" }
+                },
+                "version": { "type": "string", "description": "Semantic version (default: next patch version)" }
             },
             "required": ["name", "description", "parameters", "language", "code"]
         })
@@ -230,11 +320,39 @@ impl Tool for ForgeTool {
         let script_path = self.custom_tools_dir.join(&script_filename);
         let metadata_path = self.custom_tools_dir.join(&metadata_filename);
 
+        let tests: Vec<DynamicToolTest> = match params.get("tests") {
+            Some(tests) if !tests.is_null() => serde_json::from_value(tests.clone())
+                .map_err(|e| AgentError::Validation(format!("Invalid tests: {}", e)))?,
+            _ => Vec::new(),
+        };
+
         // Ensure directory exists
         if !self.custom_tools_dir.exists() {
             std::fs::create_dir_all(&self.custom_tools_dir)?;
         }
 
+        // Versions continue from the laboratory or standard copy; the
+        // laboratory copy being replaced goes to history
+        let laboratory = DynamicTool::from_file(&metadata_path).ok().map(|tool| tool.metadata);
+        let standard = DynamicTool::from_file(&self.registry.standard_tools_dir.join(&metadata_filename)).ok().map(|tool| tool.metadata);
+        let latest = laboratory.iter().chain(standard.iter()).map(DynamicToolMetadata::semver).max();
+        let version = match params["version"].as_str() {
+            Some(v) => {
+                let v = semver::Version::parse(v).map_err(|e| AgentError::Validation(format!("Invalid version '{}': {}", v, e)))?;
+                if let Some(latest) = latest.as_ref().filter(|latest| v <= **latest) {
+                    return Ok(ToolOutput::failure(format!("Version {} is not newer than the existing {}", v, latest)));
+                }
+                v
+            }
+            None => match latest {
+                Some(latest) => semver::Version::new(latest.major, latest.minor, latest.patch + 1),
+                None => semver::Version::new(0, 1, 0),
+            },
+        };
+        if let Some(previous) = &laboratory {
+            ToolHistory::new(&self.custom_tools_dir).archive(previous).map_err(|e| AgentError::Tool(e.to_string()))?;
+        }
+
         // Write script
         std::fs::write(&script_path, code)?;
         
@@ -245,17 +363,23 @@ impl Tool for ForgeTool {
             parameters: params["parameters"].clone(),
             language: language.to_string(),
             script_path: script_filename,
+            // Forged tools get the default confinement; operators may loosen it in the metadata file
+            sandbox: None,
+            version: version.to_string(),
+            tests,
         };
         
         std::fs::write(&metadata_path, serde_json::to_string_pretty(&metadata)?)?;
+        let metadata_tests = metadata.tests.len();
 
         // IMMEDIATE HOT-RELOAD: Register the new tool in the active registry
         let new_tool = DynamicTool::new(metadata, self.custom_tools_dir.clone());
         self.registry.register_instance(new_tool).await;
 
+        let untested = if metadata_tests == 0 { " It declares no tests, so it cannot be promoted yet." } else { "" };
         Ok(ToolOutput::success(
-            json!({ "status": "success", "tool": name }),
-            format!("Successfully forged tool '{}'. It is now loaded and available for immediate use.", name)
+            json!({ "status": "success", "tool": name, "version": version.to_string(), "tests": metadata_tests }),
+            format!("Successfully forged tool '{}' v{}. It is now loaded and available for immediate use.{}", name, version, untested)
         ))
     }
 
@@ -294,4 +418,85 @@ mod tests {
         let tool_names = registry.tool_names().await;
         assert!(tool_names.contains(&"test_tool".to_string()));
     }
+
+    fn greet(code: &str, tests: Value) -> Value {
+        json!({
+            "name": "greet",
+            "description": "Greets",
+            "parameters": {"type": "object"},
+            "language": "shell",
+            "code": code,
+            "tests": tests
+        })
+    }
+
+    #[tokio::test]
+    async fn test_reforging_bumps_version_and_keeps_history() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let custom = temp_dir.path().join("custom");
+        let registry = Arc::new(ToolRegistry::new(&custom, temp_dir.path().join("standard")));
+        let forge = ForgeTool::new(&custom, registry.clone());
+
+        let first = forge.execute(greet("echo v1", json!([]))).await.unwrap();
+        assert_eq!(first.data["version"], "0.1.0");
+        let second = forge.execute(greet("echo v2", json!([]))).await.unwrap();
+        assert_eq!(second.data["version"], "0.1.1");
+
+        let history = ToolHistory::new(&custom);
+        assert_eq!(history.versions("greet").unwrap(), vec![semver::Version::new(0, 1, 0)]);
+        let archived = std::fs::read_to_string(custom.join(".history/greet/0.1.0/greet.sh")).unwrap();
+        assert_eq!(archived, "echo v1");
+
+        let mut stale = greet("echo v3", json!([]));
+        stale["version"] = json!("0.1.1");
+        assert!(!forge.execute(stale).await.unwrap().success, "versions only move forward");
+    }
+
+    #[tokio::test]
+    async fn test_promotion_is_test_gated_and_rolls_back() {
+        use crate::tools::{ToolCall, ROLLBACK_AFTER_FAILURES};
+
+        // Tool tests run in the sandbox, which fails closed where unavailable
+        #[cfg(target_os = "linux")]
+        if crate::utils::linux_sandbox::usable_bwrap().is_none() && !crate::utils::sandbox::unsandboxed_allowed() {
+            return;
+        }
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let (custom, standard) = (temp_dir.path().join("custom"), temp_dir.path().join("standard"));
+        let registry = Arc::new(ToolRegistry::new(&custom, &standard));
+        let forge = ForgeTool::new(&custom, registry.clone());
+
+        // Without tests, or with a failing one, the tool stays in the laboratory
+        forge.execute(greet("echo hello", json!([]))).await.unwrap();
+        assert!(registry.promote_tool("greet").await.is_err());
+        forge.execute(greet("echo hello", json!([{ "input": {}, "expect_contains": "goodbye" }]))).await.unwrap();
+        assert!(registry.promote_tool("greet").await.is_err());
+        assert!(custom.join("greet.json").exists());
+        // The same version is not tested again on every call
+        let again = registry.promote_tool("greet").await.unwrap_err();
+        assert!(again.to_string().contains("already failed"), "{}", again);
+
+        forge.execute(greet("echo hello", json!([{ "input": {}, "expect_contains": "hello" }]))).await.unwrap();
+        registry.promote_tool("greet").await.unwrap();
+        assert!(standard.join("greet.json").exists());
+        assert!(!custom.join("greet.json").exists());
+
+        // v0.1.3 passes its narrow test but fails on real input
+        let fragile = r#"if [ "$1" = '{}' ]; then echo hello; else exit 1; fi"#;
+        forge.execute(greet(fragile, json!([{ "input": {}, "expect_contains": "hello" }]))).await.unwrap();
+        registry.promote_tool("greet").await.unwrap();
+
+        let call = ToolCall { name: "greet".to_string(), parameters: json!({ "who": "world" }) };
+        for _ in 0..ROLLBACK_AFTER_FAILURES {
+            assert!(!registry.execute(&call).await.unwrap().success);
+        }
+        let restored = DynamicTool::from_file(&standard.join("greet.json")).unwrap();
+        assert_eq!(restored.metadata().version, "0.1.2");
+        let output = registry.execute(&call).await.unwrap();
+        assert_eq!(output.summary.trim(), "hello");
+
+        // Both promoted versions are kept
+        let versions = ToolHistory::new(&standard).versions("greet").unwrap();
+        assert_eq!(versions, vec![semver::Version::new(0, 1, 2), semver::Version::new(0, 1, 3)]);
+    }
 }
@@ -0,0 +1,70 @@
+//! Dynamic Tool History
+//!
+//! Keeps every released version of a dynamic tool under
+//! `<tools_dir>/.history/<name>/<version>/`, so a failing promotion can be
+//! rolled back to the last good version.
+
+use anyhow::{Context, Result};
+use semver::Version;
+use std::path::{Path, PathBuf};
+
+use super::dynamic::DynamicToolMetadata;
+
+pub struct ToolHistory {
+    tools_dir: PathBuf,
+}
+
+impl ToolHistory {
+    pub fn new(tools_dir: impl Into<PathBuf>) -> Self {
+        Self { tools_dir: tools_dir.into() }
+    }
+
+    fn version_dir(&self, name: &str, version: &Version) -> PathBuf {
+        self.tools_dir.join(".history").join(name).join(version.to_string())
+    }
+
+    /// Copy a tool's metadata and script from the tools directory into history
+    pub fn archive(&self, metadata: &DynamicToolMetadata) -> Result<PathBuf> {
+        let dir = self.version_dir(&metadata.name, &metadata.semver());
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join(format!("{}.json", metadata.name)), serde_json::to_string_pretty(metadata)?)?;
+        let script = self.tools_dir.join(&metadata.script_path);
+        if script.exists() {
+            std::fs::copy(&script, dir.join(&metadata.script_path))
+                .with_context(|| format!("Failed to archive {:?}", script))?;
+        }
+        Ok(dir)
+    }
+
+    /// Archived versions of `name`, oldest first
+    pub fn versions(&self, name: &str) -> Result<Vec<Version>> {
+        let dir = self.tools_dir.join(".history").join(name);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut versions: Vec<Version> = std::fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| Version::parse(&entry.file_name().to_string_lossy()).ok())
+            .collect();
+        versions.sort();
+        Ok(versions)
+    }
+
+    /// Copy an archived version back into the tools directory
+    pub fn restore(&self, name: &str, version: &Version) -> Result<DynamicToolMetadata> {
+        let dir = self.version_dir(name, version);
+        let content = std::fs::read_to_string(dir.join(format!("{}.json", name)))
+            .with_context(|| format!("Version {} of '{}' is not in history", version, name))?;
+        let metadata: DynamicToolMetadata = serde_json::from_str(&content)?;
+        copy_if_exists(&dir.join(&metadata.script_path), &self.tools_dir.join(&metadata.script_path))?;
+        std::fs::write(self.tools_dir.join(format!("{}.json", name)), content)?;
+        Ok(metadata)
+    }
+}
+
+fn copy_if_exists(from: &Path, to: &Path) -> Result<()> {
+    if from.exists() {
+        std::fs::copy(from, to).with_context(|| format!("Failed to restore {:?}", from))?;
+    }
+    Ok(())
+}
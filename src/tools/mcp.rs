@@ -1,22 +1,47 @@
 //! Model Context Protocol (MCP) Tool Integration
-//! 
+//!
 //! Allows rust_agency to act as an MCP client, connecting to external
-//! MCP servers over stdio and dynamically registering their tools.
+//! MCP servers and dynamically registering their tools.
+//!
+//! Servers are reached over a spawned child's stdio (respawned with backoff
+//! if it crashes) or over streamable HTTP, where each POST is answered with
+//! JSON or an SSE stream and an optional GET stream carries server-initiated
+//! messages. Besides tools, the client lists and reads resources and fetches
+//! prompts. `notifications/*/list_changed` are broadcast as [`McpEvent`]s so
+//! the registry can re-sync its proxies.
 
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail, Context};
 use async_trait::async_trait;
+use futures::StreamExt;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE};
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
 use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::process::{Child, Command, ChildStdin, ChildStdout};
-use tokio::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
-use tracing::{info, debug};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::{broadcast, oneshot, Mutex, RwLock};
+use tracing::{info, debug, warn};
 
 use crate::agent::{AgentResult, AgentError};
 use super::{Tool, ToolOutput};
 
+const PROTOCOL_VERSION: &str = "2025-03-26";
+/// How long a request may wait for its response
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+/// Restarts allowed for a stdio server that keeps crashing
+const MAX_RESTARTS: u32 = 5;
+/// A process that ran this long before crashing resets the restart budget
+const STABLE_AFTER: Duration = Duration::from_secs(60);
+/// Pages fetched from a paginated `*/list` before giving up
+const MAX_PAGES: usize = 100;
+/// Characters of a resource's text surfaced as context
+const MAX_RESOURCE_CHARS: usize = 2000;
+const SESSION_HEADER: &str = "mcp-session-id";
+
 /// JSON-RPC 2.0 Request
 #[derive(Debug, Serialize, Deserialize)]
 struct JsonRpcRequest {
@@ -26,12 +51,25 @@ struct JsonRpcRequest {
     id: Value,
 }
 
+impl JsonRpcRequest {
+    fn new(id: u64, method: &str, params: Option<Value>) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params,
+            id: json!(id),
+        }
+    }
+}
+
 /// JSON-RPC 2.0 Response
 #[derive(Debug, Serialize, Deserialize)]
 struct JsonRpcResponse {
+    #[serde(default)]
     jsonrpc: String,
     result: Option<Value>,
     error: Option<JsonRpcError>,
+    #[serde(default)]
     id: Value,
 }
 
@@ -43,171 +81,697 @@ struct JsonRpcError {
     data: Option<Value>,
 }
 
+/// Result of a response message, or its error as `MCP Error: ...`
+fn into_result(message: Value) -> anyhow::Result<Value> {
+    let response: JsonRpcResponse = serde_json::from_value(message).context("Malformed JSON-RPC response")?;
+    match response.error {
+        Some(err) => Err(anyhow!("MCP Error: {} (code {})", err.message, err.code)),
+        None => Ok(response.result.unwrap_or(Value::Null)),
+    }
+}
+
 /// MCP Tool definition from server
 #[derive(Debug, Clone, Deserialize)]
 pub struct McpToolDefinition {
     pub name: String,
     pub description: Option<String>,
-    #[serde(rename = "inputSchema")]
+    #[serde(rename = "inputSchema", default)]
     pub input_schema: Value,
 }
 
-/// MCP Server Manager
-pub struct McpServer {
+/// A resource advertised by `resources/list`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpResource {
+    pub uri: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(rename = "mimeType", default)]
+    pub mime_type: Option<String>,
+}
+
+/// One entry of a `resources/read` result; binary contents come as base64 `blob`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpResourceContents {
+    pub uri: String,
+    #[serde(rename = "mimeType", default)]
+    pub mime_type: Option<String>,
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub blob: Option<String>,
+}
+
+/// A prompt template advertised by `prompts/list`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpPrompt {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub arguments: Vec<McpPromptArgument>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpPromptArgument {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// An entry of `mcp_servers.json`: either `command` (stdio) or `url` (streamable HTTP)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct McpServerConfig {
+    pub name: String,
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Extra HTTP headers, e.g. `Authorization`
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+impl McpServerConfig {
+    pub fn stdio(name: &str, command: &str, args: &[String]) -> Self {
+        Self {
+            name: name.to_string(),
+            command: Some(command.to_string()),
+            args: args.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    pub fn http(name: &str, url: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            url: Some(url.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Server-side changes the client has been notified of
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum McpEvent {
+    ToolsChanged,
+    PromptsChanged,
+    ResourcesChanged,
+    ResourceUpdated(String),
+    /// The server was respawned or its HTTP session re-established
+    Restarted,
+}
+
+/// State shared by a server handle and its background readers
+struct Shared {
     name: String,
-    stdin: Mutex<ChildStdin>,
-    stdout: Mutex<BufReader<ChildStdout>>,
-    request_counter: Mutex<u64>,
+    next_id: AtomicU64,
     roots: Mutex<Vec<String>>,
-    _child: Mutex<Child>, // Keep child alive
+    capabilities: std::sync::Mutex<Value>,
+    resources: std::sync::Mutex<Option<Vec<McpResource>>>,
+    events: broadcast::Sender<McpEvent>,
 }
 
-impl McpServer {
-    pub async fn spawn(name: &str, command: &str, args: &[String]) -> anyhow::Result<Arc<Self>> {
-        info!("Spawning MCP server '{}' via {} {:?}...", name, command, args);
-        
-        let mut child = Command::new(command)
-            .args(args)
+impl Shared {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            next_id: AtomicU64::new(1),
+            roots: Mutex::new(Vec::new()),
+            capabilities: std::sync::Mutex::new(Value::Null),
+            resources: std::sync::Mutex::new(None),
+            events: broadcast::channel(64).0,
+        }
+    }
+
+    fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn emit(&self, event: McpEvent) {
+        if matches!(event, McpEvent::ResourcesChanged | McpEvent::Restarted) {
+            *self.resources.lock().unwrap() = None;
+        }
+        debug!("MCP server '{}': {:?}", self.name, event);
+        let _ = self.events.send(event);
+    }
+
+    /// Handle a server-initiated request or notification, returning the reply for requests
+    async fn handle_inbound(&self, message: &Value) -> Option<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let Some(id) = message.get("id").filter(|id| !id.is_null()) else {
+            let event = match method {
+                "notifications/tools/list_changed" => Some(McpEvent::ToolsChanged),
+                "notifications/prompts/list_changed" => Some(McpEvent::PromptsChanged),
+                "notifications/resources/list_changed" => Some(McpEvent::ResourcesChanged),
+                "notifications/resources/updated" => Some(McpEvent::ResourceUpdated(
+                    message["params"]["uri"].as_str().unwrap_or_default().to_string(),
+                )),
+                _ => None,
+            };
+            match event {
+                Some(event) => self.emit(event),
+                None => debug!("Ignoring MCP notification '{}' from {}", method, self.name),
+            }
+            return None;
+        };
+
+        let result = match method {
+            "roots/list" => {
+                let roots = self.roots.lock().await;
+                json!({ "roots": roots.iter().map(|r| json!({ "uri": r })).collect::<Vec<_>>() })
+            }
+            "ping" => json!({}),
+            _ => {
+                return Some(json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": { "code": -32601, "message": format!("Method not found: {}", method) }
+                }))
+            }
+        };
+        Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+    }
+}
+
+fn initialize_params() -> Value {
+    json!({
+        "protocolVersion": PROTOCOL_VERSION,
+        "capabilities": {
+            "roots": {
+                "listChanged": true
+            }
+        },
+        "clientInfo": {
+            "name": "rust_agency",
+            "version": env!("CARGO_PKG_VERSION")
+        }
+    })
+}
+
+fn notification(method: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method })
+}
+
+// ──────────────────────────────────────────────────────────────────────────
+// stdio transport
+// ──────────────────────────────────────────────────────────────────────────
+
+type Pending = Arc<std::sync::Mutex<HashMap<u64, oneshot::Sender<anyhow::Result<Value>>>>>;
+
+/// Handle on a running stdio server
+#[derive(Clone)]
+struct StdioConn {
+    stdin: Arc<Mutex<ChildStdin>>,
+    pending: Pending,
+    alive: Arc<AtomicBool>,
+}
+
+impl StdioConn {
+    async fn send(&self, message: &Value) -> anyhow::Result<()> {
+        let line = serde_json::to_string(message)? + "\n";
+        let mut stdin = self.stdin.lock().await;
+        stdin.write_all(line.as_bytes()).await?;
+        stdin.flush().await?;
+        Ok(())
+    }
+
+    async fn request(&self, id: u64, method: &str, params: Option<Value>) -> anyhow::Result<Value> {
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, tx);
+        let request = serde_json::to_value(JsonRpcRequest::new(id, method, params))?;
+        if let Err(e) = self.send(&request).await {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(e.context("MCP server disconnected"));
+        }
+        match tokio::time::timeout(REQUEST_TIMEOUT, rx).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(anyhow!("MCP server disconnected")),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&id);
+                Err(anyhow!("MCP request '{}' timed out after {:?}", method, REQUEST_TIMEOUT))
+            }
+        }
+    }
+}
+
+struct StdioProcess {
+    conn: StdioConn,
+    started: Instant,
+    _child: Child, // Killed on drop
+}
+
+struct StdioTransport {
+    command: String,
+    args: Vec<String>,
+    env: HashMap<String, String>,
+    process: Mutex<Option<StdioProcess>>,
+    restarts: AtomicU32,
+}
+
+impl StdioTransport {
+    fn spawn(&self, shared: &Arc<Shared>) -> anyhow::Result<StdioProcess> {
+        let mut child = Command::new(&self.command)
+            .args(&self.args)
+            .envs(&self.env)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::inherit()) // Forward stderr to main logs
+            .kill_on_drop(true)
             .spawn()
             .context("Failed to spawn MCP server process")?;
 
         let stdin = child.stdin.take().context("Failed to open stdin")?;
         let stdout = child.stdout.take().context("Failed to open stdout")?;
+        let conn = StdioConn {
+            stdin: Arc::new(Mutex::new(stdin)),
+            pending: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            alive: Arc::new(AtomicBool::new(true)),
+        };
+        tokio::spawn(read_stdio(BufReader::new(stdout), conn.clone(), shared.clone()));
+        Ok(StdioProcess { conn, started: Instant::now(), _child: child })
+    }
+
+    /// The live connection, spawning and initializing the server first if it
+    /// has not started or has crashed. Also returns whether it was restarted.
+    async fn connection(&self, shared: &Arc<Shared>) -> anyhow::Result<(StdioConn, bool)> {
+        let mut process = self.process.lock().await;
+        let restarting = match process.as_ref() {
+            Some(p) if p.conn.alive.load(Ordering::SeqCst) => return Ok((p.conn.clone(), false)),
+            Some(p) => {
+                if p.started.elapsed() >= STABLE_AFTER {
+                    self.restarts.store(0, Ordering::SeqCst);
+                }
+                true
+            }
+            None => false,
+        };
+        if restarting {
+            let attempt = self.restarts.fetch_add(1, Ordering::SeqCst);
+            if attempt >= MAX_RESTARTS {
+                bail!("MCP server '{}' crashed {} times in a row; not restarting", shared.name, attempt);
+            }
+            let backoff = Duration::from_millis(250 << attempt.min(5));
+            warn!("MCP server '{}' crashed; restarting in {:?} (attempt {})", shared.name, backoff, attempt + 1);
+            tokio::time::sleep(backoff).await;
+        }
+
+        let fresh = self.spawn(shared)?;
+        let result = fresh.conn.request(shared.next_id(), "initialize", Some(initialize_params())).await?;
+        *shared.capabilities.lock().unwrap() = result["capabilities"].clone();
+        fresh.conn.send(&notification("notifications/initialized")).await?;
+
+        let conn = fresh.conn.clone();
+        *process = Some(fresh);
+        Ok((conn, restarting))
+    }
+}
+
+async fn read_stdio(mut reader: BufReader<ChildStdout>, conn: StdioConn, shared: Arc<Shared>) {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match reader.read_line(&mut line).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        let data = line.trim();
+        if data.is_empty() {
+            continue;
+        }
+        debug!("MCP Data from {}: {}", shared.name, data);
+        let Ok(message) = serde_json::from_str::<Value>(data) else {
+            warn!("MCP server '{}' wrote a non-JSON line: {}", shared.name, data);
+            continue;
+        };
+
+        if message.get("method").is_some() {
+            // Server-initiated request (e.g. roots/list) or notification
+            if let Some(reply) = shared.handle_inbound(&message).await {
+                if let Err(e) = conn.send(&reply).await {
+                    warn!("Failed to reply to MCP server '{}': {}", shared.name, e);
+                }
+            }
+        } else if let Some(id) = message["id"].as_u64() {
+            if let Some(tx) = conn.pending.lock().unwrap().remove(&id) {
+                let _ = tx.send(into_result(message));
+            }
+        }
+    }
+
+    conn.alive.store(false, Ordering::SeqCst);
+    for (_, tx) in conn.pending.lock().unwrap().drain() {
+        let _ = tx.send(Err(anyhow!("MCP server disconnected")));
+    }
+    warn!("MCP server '{}' disconnected", shared.name);
+}
+
+// ──────────────────────────────────────────────────────────────────────────
+// Streamable HTTP transport
+// ──────────────────────────────────────────────────────────────────────────
+
+/// The server no longer recognizes our session and expects a new `initialize`
+#[derive(Debug)]
+struct SessionExpired;
+
+impl std::fmt::Display for SessionExpired {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MCP session expired")
+    }
+}
+
+impl std::error::Error for SessionExpired {}
+
+type Session = Arc<std::sync::Mutex<Option<String>>>;
+
+struct HttpTransport {
+    client: reqwest::Client,
+    url: String,
+    headers: HeaderMap,
+    session: Session,
+    listener: std::sync::Mutex<Option<tokio::task::JoinHandle<()>>>,
+}
+
+fn post_message(client: &reqwest::Client, url: &str, headers: &HeaderMap, session: &Session, body: &Value) -> reqwest::RequestBuilder {
+    let mut request = client
+        .post(url)
+        .headers(headers.clone())
+        .header(ACCEPT, "application/json, text/event-stream")
+        .json(body);
+    if let Some(id) = session.lock().unwrap().clone() {
+        request = request.header(SESSION_HEADER, id);
+    }
+    request
+}
+
+impl HttpTransport {
+    fn new(url: &str, headers: &HashMap<String, String>) -> anyhow::Result<Self> {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.insert(
+                HeaderName::from_bytes(name.as_bytes()).with_context(|| format!("Invalid header name '{}'", name))?,
+                HeaderValue::from_str(value).with_context(|| format!("Invalid value for header '{}'", name))?,
+            );
+        }
+        Ok(Self {
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+            headers: map,
+            session: Arc::new(std::sync::Mutex::new(None)),
+            listener: std::sync::Mutex::new(None),
+        })
+    }
+
+    async fn notify(&self, message: &Value) -> anyhow::Result<()> {
+        let response = post_message(&self.client, &self.url, &self.headers, &self.session, message).send().await?;
+        if !response.status().is_success() {
+            bail!("MCP server rejected notification: HTTP {}", response.status());
+        }
+        Ok(())
+    }
+
+    async fn request(&self, shared: &Arc<Shared>, id: u64, method: &str, params: Option<Value>) -> anyhow::Result<Value> {
+        let body = serde_json::to_value(JsonRpcRequest::new(id, method, params))?;
+        let response = post_message(&self.client, &self.url, &self.headers, &self.session, &body)
+            .timeout(REQUEST_TIMEOUT)
+            .send()
+            .await
+            .with_context(|| format!("MCP server '{}' is unreachable", shared.name))?;
+
+        if let Some(id) = response.headers().get(SESSION_HEADER).and_then(|v| v.to_str().ok()) {
+            *self.session.lock().unwrap() = Some(id.to_string());
+        }
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND && self.session.lock().unwrap().is_some() {
+            return Err(SessionExpired.into());
+        }
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            bail!("MCP server '{}' returned HTTP {}: {}", shared.name, status, text.trim());
+        }
+
+        let is_stream = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+        if !is_stream {
+            return into_result(response.json().await.context("Malformed MCP response body")?);
+        }
+
+        // The answer arrives on the stream, possibly after requests and notifications
+        let mut stream = response.bytes_stream();
+        let mut parser = SseParser::default();
+        while let Some(chunk) = stream.next().await {
+            for data in parser.push(&chunk?) {
+                let Ok(message) = serde_json::from_str::<Value>(&data) else { continue };
+                if message.get("method").is_some() {
+                    if let Some(reply) = shared.handle_inbound(&message).await {
+                        if let Err(e) = self.notify(&reply).await {
+                            warn!("Failed to reply to MCP server '{}': {}", shared.name, e);
+                        }
+                    }
+                } else if message["id"].as_u64() == Some(id) {
+                    return into_result(message);
+                }
+            }
+        }
+        bail!("MCP server '{}' closed the stream before answering '{}'", shared.name, method)
+    }
+
+    /// Start a new session and open the server's notification stream
+    async fn handshake(&self, shared: &Arc<Shared>) -> anyhow::Result<()> {
+        *self.session.lock().unwrap() = None;
+        let result = self.request(shared, shared.next_id(), "initialize", Some(initialize_params())).await?;
+        *shared.capabilities.lock().unwrap() = result["capabilities"].clone();
+        self.notify(&notification("notifications/initialized")).await?;
+        self.listen(shared);
+        Ok(())
+    }
+
+    /// Listen on the optional GET stream for server-initiated messages
+    fn listen(&self, shared: &Arc<Shared>) {
+        let (client, url, headers, session, shared) =
+            (self.client.clone(), self.url.clone(), self.headers.clone(), self.session.clone(), shared.clone());
+        let handle = tokio::spawn(async move {
+            let mut request = client.get(&url).headers(headers.clone()).header(ACCEPT, "text/event-stream");
+            if let Some(id) = session.lock().unwrap().clone() {
+                request = request.header(SESSION_HEADER, id);
+            }
+            let response = match request.send().await {
+                Ok(r) if r.status().is_success() => r,
+                Ok(r) => {
+                    debug!("MCP server '{}' offers no notification stream (HTTP {})", shared.name, r.status());
+                    return;
+                }
+                Err(e) => {
+                    debug!("MCP server '{}' notification stream unavailable: {}", shared.name, e);
+                    return;
+                }
+            };
+            let mut stream = response.bytes_stream();
+            let mut parser = SseParser::default();
+            while let Some(Ok(chunk)) = stream.next().await {
+                for data in parser.push(&chunk) {
+                    let Ok(message) = serde_json::from_str::<Value>(&data) else { continue };
+                    if let Some(reply) = shared.handle_inbound(&message).await {
+                        let _ = post_message(&client, &url, &headers, &session, &reply).send().await;
+                    }
+                }
+            }
+            debug!("MCP server '{}' closed its notification stream", shared.name);
+        });
+        if let Some(old) = self.listener.lock().unwrap().replace(handle) {
+            old.abort();
+        }
+    }
+}
+
+impl Drop for HttpTransport {
+    fn drop(&mut self) {
+        if let Some(listener) = self.listener.lock().unwrap().take() {
+            listener.abort();
+        }
+    }
+}
+
+/// Incremental `text/event-stream` parser yielding each event's data
+#[derive(Default)]
+struct SseParser {
+    buffer: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let raw: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&raw);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(self.data.join("\n"));
+                    self.data.clear();
+                }
+            } else if let Some(value) = line.strip_prefix("data:") {
+                self.data.push(value.strip_prefix(' ').unwrap_or(value).to_string());
+            }
+            // `event:`, `id:`, `retry:` and comments carry nothing we need
+        }
+        events
+    }
+}
+
+// ──────────────────────────────────────────────────────────────────────────
+// Server handle
+// ──────────────────────────────────────────────────────────────────────────
+
+enum Transport {
+    Stdio(StdioTransport),
+    Http(HttpTransport),
+}
+
+/// MCP Server Manager
+pub struct McpServer {
+    name: String,
+    transport: Transport,
+    shared: Arc<Shared>,
+}
+
+impl McpServer {
+    /// Connect to the server described by `config` and complete the handshake
+    pub async fn connect(config: &McpServerConfig) -> anyhow::Result<Arc<Self>> {
+        let transport = match (&config.url, &config.command) {
+            (Some(url), _) => {
+                info!("Connecting to MCP server '{}' at {}...", config.name, url);
+                Transport::Http(HttpTransport::new(url, &config.headers)?)
+            }
+            (None, Some(command)) => {
+                info!("Spawning MCP server '{}' via {} {:?}...", config.name, command, config.args);
+                Transport::Stdio(StdioTransport {
+                    command: command.clone(),
+                    args: config.args.clone(),
+                    env: config.env.clone(),
+                    process: Mutex::new(None),
+                    restarts: AtomicU32::new(0),
+                })
+            }
+            (None, None) => bail!("MCP server '{}' needs a `command` or a `url`", config.name),
+        };
 
         let server = Arc::new(Self {
-            name: name.to_string(),
-            stdin: Mutex::new(stdin),
-            stdout: Mutex::new(BufReader::new(stdout)),
-            request_counter: Mutex::new(0),
-            roots: Mutex::new(Vec::new()),
-            _child: Mutex::new(child),
+            name: config.name.clone(),
+            transport,
+            shared: Arc::new(Shared::new(&config.name)),
         });
 
         // Initialize MCP
-        server.initialize().await?;
-        
+        match &server.transport {
+            Transport::Stdio(t) => {
+                t.connection(&server.shared).await?;
+            }
+            Transport::Http(t) => t.handshake(&server.shared).await?,
+        }
         Ok(server)
     }
 
+    pub async fn spawn(name: &str, command: &str, args: &[String]) -> anyhow::Result<Arc<Self>> {
+        Self::connect(&McpServerConfig::stdio(name, command, args)).await
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Notifications about server-side changes and restarts
+    pub fn subscribe(&self) -> broadcast::Receiver<McpEvent> {
+        self.shared.events.subscribe()
+    }
+
+    /// Whether the server declared `capability` (e.g. "resources") at initialization
+    pub fn supports(&self, capability: &str) -> bool {
+        self.shared.capabilities.lock().unwrap()[capability].is_object()
+    }
+
     /// Add a root directory to this server
     pub async fn add_root(&self, path: &str) -> anyhow::Result<()> {
-        let mut roots = self.roots.lock().await;
         // URI format: file:///path/to/dir
         let uri = if path.starts_with("file://") {
             path.to_string()
         } else {
             format!("file://{}", path)
         };
-        
-        if !roots.contains(&uri) {
+
+        {
+            let mut roots = self.shared.roots.lock().await;
+            if roots.contains(&uri) {
+                return Ok(());
+            }
             roots.push(uri);
         }
-        Ok(())
+        self.notify(&notification("notifications/roots/list_changed")).await
     }
 
-    async fn call(&self, method: &str, params: Option<Value>) -> anyhow::Result<Value> {
-        let mut id_lock = self.request_counter.lock().await;
-        *id_lock += 1;
-        let id = *id_lock;
-        drop(id_lock);
-
-        let request = JsonRpcRequest {
-            jsonrpc: "2.0".to_string(),
-            method: method.to_string(),
-            params,
-            id: json!(id),
-        };
-
-        let request_str = serde_json::to_string(&request)? + "\n";
-        debug!("MCP Request to {}: {}", self.name, request_str.trim());
-
-        // Send request
-        {
-            let mut stdin = self.stdin.lock().await;
-            stdin.write_all(request_str.as_bytes()).await?;
-            stdin.flush().await?;
-        }
-
-        // Listen for response
-        let mut reader = self.stdout.lock().await;
-        
-        loop {
-            let mut line = String::new();
-            reader.read_line(&mut line).await?;
-            if line.is_empty() { return Err(anyhow!("MCP server disconnected")); }
-            
-            debug!("MCP Data from {}: {}", self.name, line.trim());
-            let response: Value = serde_json::from_str(&line)?;
-
-            // Case 1: Response to our request
-            if response["id"] == json!(id) {
-                if let Some(error) = response.get("error") {
-                    let err: JsonRpcError = serde_json::from_value(error.clone())?;
-                    return Err(anyhow!("MCP Error: {} (code {})", err.message, err.code));
-                }
-                return Ok(response["result"].clone());
-            }
+    async fn stdio_connection(&self, transport: &StdioTransport) -> anyhow::Result<StdioConn> {
+        let (conn, restarted) = transport.connection(&self.shared).await?;
+        if restarted {
+            self.shared.emit(McpEvent::Restarted);
+        }
+        Ok(conn)
+    }
 
-            // Case 2: Server-initiated request (e.g. roots/list)
-            if response.get("method").is_some() && response.get("id").is_some() {
-                let method = response["method"].as_str().unwrap_or("");
-                let req_id = response["id"].clone();
-                
-                if method == "roots/list" {
-                    let roots_guard = self.roots.lock().await;
-                    let roots_list: Vec<Value> = roots_guard.iter().map(|r| json!({ "uri": r })).collect();
-                    let res = json!({
-                        "jsonrpc": "2.0",
-                        "id": req_id,
-                        "result": { "roots": roots_list }
-                    });
-                    
-                    // Reply to server
-                    let mut stdin = self.stdin.lock().await;
-                    stdin.write_all((serde_json::to_string(&res)? + "\n").as_bytes()).await?;
-                    stdin.flush().await?;
-                    continue;
-                }
-            }
+    async fn notify(&self, message: &Value) -> anyhow::Result<()> {
+        match &self.transport {
+            Transport::Stdio(t) => self.stdio_connection(t).await?.send(message).await,
+            Transport::Http(t) => t.notify(message).await,
         }
     }
 
-    async fn initialize(&self) -> anyhow::Result<()> {
-        let params = json!({
-            "protocolVersion": "2024-11-05",
-            "capabilities": {
-                "roots": {
-                    "listChanged": true
+    async fn call(&self, method: &str, params: Option<Value>) -> anyhow::Result<Value> {
+        let id = self.shared.next_id();
+        debug!("MCP Request to {}: {} (id {})", self.name, method, id);
+        match &self.transport {
+            Transport::Stdio(t) => self.stdio_connection(t).await?.request(id, method, params).await,
+            Transport::Http(t) => match t.request(&self.shared, id, method, params.clone()).await {
+                Err(e) if e.is::<SessionExpired>() => {
+                    warn!("MCP session with '{}' expired; re-initializing", self.name);
+                    t.handshake(&self.shared).await?;
+                    self.shared.emit(McpEvent::Restarted);
+                    t.request(&self.shared, self.shared.next_id(), method, params).await
                 }
+                other => other,
             },
-            "clientInfo": {
-                "name": "rust_agency",
-                "version": "0.1.0"
-            }
-        });
-
-        self.call("initialize", Some(params)).await?;
-        
-        // Send initialized notification
-        let notification = json!({
-            "jsonrpc": "2.0",
-            "method": "notifications/initialized"
-        });
-        
-        let mut stdin = self.stdin.lock().await;
-        stdin.write_all((serde_json::to_string(&notification)? + "\n").as_bytes()).await?;
-        stdin.flush().await?;
+        }
+    }
 
-        Ok(())
+    /// Every item of a paginated `*/list` result
+    async fn list_all(&self, method: &str, key: &str) -> anyhow::Result<Vec<Value>> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+        for _ in 0..MAX_PAGES {
+            let params = cursor.take().map(|c| json!({ "cursor": c }));
+            let result = self.call(method, params).await?;
+            items.extend(result[key].as_array().cloned().unwrap_or_default());
+            match result["nextCursor"].as_str() {
+                Some(next) if !next.is_empty() => cursor = Some(next.to_string()),
+                _ => return Ok(items),
+            }
+        }
+        bail!("MCP server '{}' returned more than {} pages for {}", self.name, MAX_PAGES, method)
     }
 
     pub async fn list_tools(&self) -> anyhow::Result<Vec<McpToolDefinition>> {
-        let result = self.call("tools/list", None).await?;
-        let tools: Vec<McpToolDefinition> = serde_json::from_value(result["tools"].clone())?;
-        Ok(tools)
+        let tools = self.list_all("tools/list", "tools").await?;
+        Ok(serde_json::from_value(Value::Array(tools))?)
     }
 
     pub async fn call_tool(&self, name: &str, arguments: Value) -> anyhow::Result<Value> {
@@ -217,6 +781,38 @@ impl McpServer {
         });
         self.call("tools/call", Some(params)).await
     }
+
+    /// Advertised resources, cached until the server reports a change
+    pub async fn list_resources(&self) -> anyhow::Result<Vec<McpResource>> {
+        if let Some(resources) = self.shared.resources.lock().unwrap().clone() {
+            return Ok(resources);
+        }
+        let resources: Vec<McpResource> =
+            serde_json::from_value(Value::Array(self.list_all("resources/list", "resources").await?))?;
+        *self.shared.resources.lock().unwrap() = Some(resources.clone());
+        Ok(resources)
+    }
+
+    pub async fn read_resource(&self, uri: &str) -> anyhow::Result<Vec<McpResourceContents>> {
+        let result = self.call("resources/read", Some(json!({ "uri": uri }))).await?;
+        Ok(serde_json::from_value(result["contents"].clone())?)
+    }
+
+    /// Ask for `notifications/resources/updated` when `uri` changes
+    pub async fn subscribe_resource(&self, uri: &str) -> anyhow::Result<()> {
+        self.call("resources/subscribe", Some(json!({ "uri": uri }))).await?;
+        Ok(())
+    }
+
+    pub async fn list_prompts(&self) -> anyhow::Result<Vec<McpPrompt>> {
+        let prompts = self.list_all("prompts/list", "prompts").await?;
+        Ok(serde_json::from_value(Value::Array(prompts))?)
+    }
+
+    /// Render a prompt; the result holds `description` and `messages`
+    pub async fn get_prompt(&self, name: &str, arguments: Map<String, Value>) -> anyhow::Result<Value> {
+        self.call("prompts/get", Some(json!({ "name": name, "arguments": arguments }))).await
+    }
 }
 
 /// A Tool implementation that proxies to an MCP server
@@ -250,10 +846,10 @@ impl Tool for McpProxyTool {
         info!("Executing MCP tool {}...", self.name());
         let result = self.server.call_tool(&self.definition.name, params).await
             .map_err(|e| AgentError::Tool(format!("MCP call failed: {}", e)))?;
-        
+
         // MCP tools/call result has a 'content' field which is an array of blocks
         let content = result["content"].as_array().ok_or_else(|| AgentError::Tool("Invalid MCP response: missing content array".to_string()))?;
-        
+
         let mut summary = String::new();
         for block in content {
             if let Some(text) = block["text"].as_str() {
@@ -269,4 +865,199 @@ impl Tool for McpProxyTool {
             Ok(ToolOutput::success(result, summary))
         }
     }
-}
\ No newline at end of file
+}
+
+/// An MCP prompt exposed as a skill: invoking it renders the prompt and hands
+/// the messages back as instructions, like a Markdown skill
+pub struct McpPromptSkill {
+    server: Arc<McpServer>,
+    prompt: McpPrompt,
+}
+
+impl McpPromptSkill {
+    pub fn new(server: Arc<McpServer>, prompt: McpPrompt) -> Self {
+        Self { server, prompt }
+    }
+}
+
+#[async_trait]
+impl Tool for McpPromptSkill {
+    fn name(&self) -> String {
+        format!("skill__{}__{}", self.server.name, self.prompt.name.to_lowercase().replace(' ', "_"))
+    }
+
+    fn description(&self) -> String {
+        let description = self.prompt.description.clone().unwrap_or_else(|| self.prompt.name.clone());
+        format!("{} (Skill from MCP server '{}')", description, self.server.name)
+    }
+
+    fn parameters(&self) -> Value {
+        let properties: Map<String, Value> = self
+            .prompt
+            .arguments
+            .iter()
+            .map(|a| {
+                let description = a.description.clone().unwrap_or_default();
+                (a.name.clone(), json!({ "type": "string", "description": description }))
+            })
+            .collect();
+        let required: Vec<&str> = self.prompt.arguments.iter().filter(|a| a.required).map(|a| a.name.as_str()).collect();
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": required
+        })
+    }
+
+    async fn execute(&self, params: Value) -> AgentResult<ToolOutput> {
+        // Prompt arguments are strings on the wire
+        let arguments: Map<String, Value> = params
+            .as_object()
+            .map(|args| {
+                args.iter()
+                    .map(|(k, v)| (k.clone(), Value::String(v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string()))))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let result = self.server.get_prompt(&self.prompt.name, arguments).await
+            .map_err(|e| AgentError::Tool(format!("MCP prompt failed: {}", e)))?;
+
+        let instructions = result["messages"]
+            .as_array()
+            .map(|messages| {
+                messages
+                    .iter()
+                    .filter_map(|m| {
+                        let content = &m["content"];
+                        content["text"].as_str().or_else(|| content["resource"]["text"].as_str())
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n\n")
+            })
+            .unwrap_or_default();
+
+        Ok(ToolOutput::success(
+            json!({ "status": "skill_invoked", "skill": self.prompt.name, "prompt": result }),
+            format!("Skill '{}' invoked. Please follow these specialized instructions: \n\n{}",
+                self.prompt.name, instructions)
+        ))
+    }
+}
+
+/// A resource matching a query, with its text
+#[derive(Debug, Clone, Serialize)]
+pub struct McpResourceHit {
+    pub server: String,
+    pub uri: String,
+    pub name: String,
+    pub text: String,
+    /// Fraction of query terms found in the resource's name, URI and description
+    pub score: f32,
+}
+
+/// Resources of every registered server, searchable by keyword for
+/// `memory_query` and turn context
+#[derive(Clone, Default)]
+pub struct McpResourceIndex {
+    servers: Arc<RwLock<Vec<Arc<McpServer>>>>,
+}
+
+impl McpResourceIndex {
+    pub async fn add(&self, server: Arc<McpServer>) {
+        let mut servers = self.servers.write().await;
+        servers.retain(|s| s.name != server.name);
+        servers.push(server);
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.servers.read().await.is_empty()
+    }
+
+    /// Best `limit` resources by term overlap with `query`, read in full
+    pub async fn search(&self, query: &str, limit: usize) -> Vec<McpResourceHit> {
+        let mut terms: Vec<String> = query
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|t| t.len() > 2)
+            .map(str::to_string)
+            .collect();
+        terms.sort_unstable();
+        terms.dedup();
+        if terms.is_empty() || limit == 0 {
+            return Vec::new();
+        }
+
+        let servers = self.servers.read().await.clone();
+        let mut candidates = Vec::new();
+        for server in servers.iter().filter(|s| s.supports("resources")) {
+            match server.list_resources().await {
+                Ok(resources) => {
+                    for resource in resources {
+                        let haystack = format!(
+                            "{} {} {}",
+                            resource.name,
+                            resource.uri,
+                            resource.description.as_deref().unwrap_or_default()
+                        )
+                        .to_lowercase();
+                        let matched = terms.iter().filter(|t| haystack.contains(t.as_str())).count();
+                        if matched > 0 {
+                            candidates.push((matched as f32 / terms.len() as f32, server.clone(), resource));
+                        }
+                    }
+                }
+                Err(e) => warn!("Failed to list resources of MCP server '{}': {}", server.name, e),
+            }
+        }
+        candidates.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut hits = Vec::new();
+        for (score, server, resource) in candidates.into_iter().take(limit) {
+            match server.read_resource(&resource.uri).await {
+                Ok(contents) => {
+                    let text = contents.iter().filter_map(|c| c.text.as_deref()).collect::<Vec<_>>().join("\n");
+                    hits.push(McpResourceHit {
+                        server: server.name.clone(),
+                        uri: resource.uri,
+                        name: resource.name,
+                        text: text.chars().take(MAX_RESOURCE_CHARS).collect(),
+                        score,
+                    });
+                }
+                Err(e) => warn!("Failed to read MCP resource {}: {}", resource.uri, e),
+            }
+        }
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_parser_handles_split_chunks() {
+        let mut parser = SseParser::default();
+        assert!(parser.push(b"event: message\ndata: {\"id\"").is_empty());
+        let events = parser.push(b": 1}\r\n\r\n: keep-alive\n\ndata: a\ndata: b\n\n");
+        assert_eq!(events, vec!["{\"id\": 1}".to_string(), "a\nb".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_inbound_requests_and_notifications() {
+        let shared = Shared::new("stub");
+        shared.roots.lock().await.push("file:///work".to_string());
+        let mut events = shared.events.subscribe();
+
+        let reply = shared.handle_inbound(&json!({ "jsonrpc": "2.0", "id": 7, "method": "roots/list" })).await.unwrap();
+        assert_eq!(reply["id"], 7);
+        assert_eq!(reply["result"]["roots"][0]["uri"], "file:///work");
+
+        let reply = shared.handle_inbound(&json!({ "jsonrpc": "2.0", "id": 8, "method": "sampling/createMessage" })).await.unwrap();
+        assert_eq!(reply["error"]["code"], -32601);
+
+        let note = json!({ "jsonrpc": "2.0", "method": "notifications/tools/list_changed" });
+        assert!(shared.handle_inbound(&note).await.is_none());
+        assert_eq!(events.recv().await.unwrap(), McpEvent::ToolsChanged);
+    }
+}
@@ -3,22 +3,46 @@
 //! Allows agents to search their own memory.
 
 use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde_json::{json, Value};
 use std::sync::Arc;
 use tracing::debug;
 
 use crate::agent::{AgentResult, AgentError};
-use super::{Tool, ToolOutput};
-use crate::memory::Memory;
+use super::{McpResourceHit, McpResourceIndex, Tool, ToolOutput};
+use crate::memory::{Memory, SearchMode, SearchOptions};
+
+/// MCP resources returned alongside memories
+const MAX_RESOURCE_HITS: usize = 2;
 
 /// Tool for querying the memory system
 pub struct MemoryQueryTool {
     memory: Arc<dyn Memory>,
+    resources: Option<McpResourceIndex>,
 }
 
 impl MemoryQueryTool {
     pub fn new(memory: Arc<dyn Memory>) -> Self {
-        Self { memory }
+        Self { memory, resources: None }
+    }
+
+    /// Also search the resources of connected MCP servers
+    pub fn with_mcp_resources(mut self, resources: McpResourceIndex) -> Self {
+        self.resources = Some(resources);
+        self
+    }
+
+    /// RFC 3339 timestamp or `YYYY-MM-DD` (start of day, or end of day for `until`)
+    fn parse_time(params: &Value, key: &str) -> AgentResult<Option<DateTime<Utc>>> {
+        let Some(raw) = params[key].as_str() else { return Ok(None) };
+        if let Ok(time) = DateTime::parse_from_rfc3339(raw) {
+            return Ok(Some(time.with_timezone(&Utc)));
+        }
+        let date = NaiveDate::parse_from_str(raw, "%Y-%m-%d").map_err(|_| {
+            AgentError::Validation(format!("Invalid {}: '{}' (use RFC 3339 or YYYY-MM-DD)", key, raw))
+        })?;
+        let time = if key == "until" { date.and_hms_opt(23, 59, 59) } else { date.and_hms_opt(0, 0, 0) };
+        Ok(time.map(|t| t.and_utc()))
     }
 }
 
@@ -31,7 +55,8 @@ impl Tool for MemoryQueryTool {
     fn description(&self) -> String {
         "Search your memory for past interactions, learned information, or context. \
          Use this when you need to recall previous conversations or find relevant information \
-         from past interactions.".to_string()
+         from past interactions. Use mode 'lexical' (or the default 'hybrid') for exact identifiers, \
+         error codes and file names. Matching resources from connected MCP servers are included.".to_string()
     }
 
     fn parameters(&self) -> Value {
@@ -46,6 +71,25 @@ impl Tool for MemoryQueryTool {
                     "type": "integer",
                     "description": "Number of results to return (default: 3, max: 10)",
                     "default": 3
+                },
+                "mode": {
+                    "type": "string",
+                    "enum": ["semantic", "lexical", "hybrid"],
+                    "description": "semantic (meaning), lexical (exact words/identifiers, BM25) or hybrid (both, rank-fused)",
+                    "default": "hybrid"
+                },
+                "tags": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Only memories carrying all of these tags"
+                },
+                "since": {
+                    "type": "string",
+                    "description": "Only memories from this time on (RFC 3339 or YYYY-MM-DD)"
+                },
+                "until": {
+                    "type": "string",
+                    "description": "Only memories up to this time (RFC 3339 or YYYY-MM-DD)"
                 }
             },
             "required": ["query"]
@@ -56,7 +100,7 @@ impl Tool for MemoryQueryTool {
         json!({
             "status": "constrained",
             "environment": "internal vector database",
-            "search_mode": "semantic similarity",
+            "search_mode": "semantic, lexical (BM25) or hybrid (reciprocal rank fusion)",
             "data_scope": "local long-term memory"
         })
     }
@@ -85,11 +129,50 @@ impl Tool for MemoryQueryTool {
             None
         };
 
-        debug!("Querying memory for: {} (top {}, kind: {:?})", query, top_k, kind);
+        let mode = match params["mode"].as_str() {
+            Some(m) => SearchMode::parse(m)
+                .ok_or_else(|| AgentError::Validation(format!("Unknown mode: {} (use semantic, lexical or hybrid)", m)))?,
+            None => SearchMode::Hybrid,
+        };
+        let tags: Vec<String> = params["tags"]
+            .as_array()
+            .map(|tags| tags.iter().filter_map(|t| t.as_str().map(str::to_string)).collect())
+            .unwrap_or_default();
+        let options = SearchOptions {
+            mode,
+            context: None,
+            kind,
+            tags,
+            since: Self::parse_time(&params, "since")?,
+            until: Self::parse_time(&params, "until")?,
+        };
+
+        debug!("Querying memory for: {} (top {}, {:?})", query, top_k, options);
 
-        match self.memory.search(query, top_k, None, kind).await {
+        let resources: Vec<McpResourceHit> = match &self.resources {
+            Some(index) => index.search(query, MAX_RESOURCE_HITS).await,
+            None => Vec::new(),
+        };
+        let resource_section = if resources.is_empty() {
+            String::new()
+        } else {
+            let listed = resources
+                .iter()
+                .map(|r| format!("- [{}] {} ({})\n   {}", r.server, r.name, r.uri, r.text.chars().take(500).collect::<String>()))
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!("\n\nRelated MCP resources:\n{}", listed)
+        };
+
+        match self.memory.search_with(query, top_k, &options).await {
             Ok(entries) => {
                 if entries.is_empty() {
+                    if !resources.is_empty() {
+                        return Ok(ToolOutput::success(
+                            json!({ "query": query, "num_results": 0, "memories": [], "resources": resources }),
+                            format!("No relevant memories found for this query.{}", resource_section)
+                        ));
+                    }
                     return Ok(ToolOutput::success_str(
                         "No relevant memories found for this query."
                     ));
@@ -116,9 +199,10 @@ impl Tool for MemoryQueryTool {
                     .join("\n\n");
 
                 let summary = format!(
-                    "Found {} relevant memories:\n\n{}",
+                    "Found {} relevant memories:\n\n{}{}",
                     entries.len(),
-                    formatted
+                    formatted,
+                    resource_section
                 );
 
                 Ok(ToolOutput::success(
@@ -130,8 +214,11 @@ impl Tool for MemoryQueryTool {
                             "content": e.content,
                             "agent": e.metadata.agent,
                             "timestamp": e.timestamp.to_rfc3339(),
+                            "tags": e.metadata.tags,
                             "similarity": e.similarity
-                        })).collect::<Vec<_>>()
+                        })).collect::<Vec<_>>(),
+                        "mode": options.mode,
+                        "resources": resources
                     }),
                     summary
                 ))
@@ -142,3 +229,56 @@ impl Tool for MemoryQueryTool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::entry::MemorySource;
+    use crate::memory::MemoryEntry;
+
+    /// Returns every entry for any query, like a semantic backend without a lexical index
+    struct FixedMemory(Vec<MemoryEntry>);
+
+    #[async_trait]
+    impl Memory for FixedMemory {
+        async fn store(&self, _entry: MemoryEntry) -> anyhow::Result<String> { Ok(String::new()) }
+        async fn search(&self, _query: &str, top_k: usize, _context: Option<&str>, _kind: Option<crate::orchestrator::Kind>) -> anyhow::Result<Vec<MemoryEntry>> {
+            Ok(self.0.iter().take(top_k).cloned().collect())
+        }
+        async fn get_recent(&self, _limit: usize) -> anyhow::Result<Vec<MemoryEntry>> { Ok(Vec::new()) }
+        async fn count(&self) -> anyhow::Result<usize> { Ok(self.0.len()) }
+        async fn persist(&self) -> anyhow::Result<()> { Ok(()) }
+        async fn consolidate(&self) -> anyhow::Result<usize> { Ok(0) }
+        async fn get_cold_memories(&self, _limit: usize) -> anyhow::Result<Vec<MemoryEntry>> { Ok(Vec::new()) }
+        async fn prune(&self, _ids: Vec<String>) -> anyhow::Result<()> { Ok(()) }
+        async fn clear_cache(&self) -> anyhow::Result<()> { Ok(()) }
+        async fn hibernate(&self) -> anyhow::Result<()> { Ok(()) }
+        async fn wake(&self) -> anyhow::Result<()> { Ok(()) }
+    }
+
+    #[tokio::test]
+    async fn test_tag_and_time_filters() {
+        let mut old = MemoryEntry::new("Deploy notes from last year", "ops", MemorySource::Agent);
+        old.timestamp = "2024-03-01T10:00:00Z".parse().unwrap();
+        old.metadata.tags = vec!["deploy".to_string()];
+        let mut recent = MemoryEntry::new("Deploy notes from this week", "ops", MemorySource::Agent);
+        recent.metadata.tags = vec!["deploy".to_string(), "prod".to_string()];
+        let untagged = MemoryEntry::new("Unrelated chatter", "ops", MemorySource::User);
+        let tool = MemoryQueryTool::new(Arc::new(FixedMemory(vec![old, recent, untagged])));
+
+        let tagged = tool.execute(json!({ "query": "deploy", "tags": ["deploy"], "top_k": 10 })).await.unwrap();
+        assert_eq!(tagged.data["num_results"], 2);
+        assert_eq!(tagged.data["mode"], "hybrid");
+
+        let since = tool.execute(json!({ "query": "deploy", "tags": ["deploy"], "since": "2025-01-01" })).await.unwrap();
+        assert_eq!(since.data["num_results"], 1);
+        assert!(since.summary.contains("this week"));
+
+        let until = tool.execute(json!({ "query": "deploy", "until": "2024-03-01", "mode": "semantic" })).await.unwrap();
+        assert_eq!(until.data["num_results"], 1);
+        assert_eq!(until.data["mode"], "semantic");
+
+        assert!(tool.execute(json!({ "query": "deploy", "mode": "fuzzy" })).await.is_err());
+        assert!(tool.execute(json!({ "query": "deploy", "since": "last tuesday" })).await.is_err());
+    }
+}
@@ -4,13 +4,16 @@
 //! and result caching for high performance.
 
 mod web_search;
+mod web_fetch;
 mod code_exec;
 mod memory_query;
 mod artifact;
+mod artifact_store;
 mod sandbox;
 mod codebase;
 mod system;
 mod dynamic;
+mod history;
 mod knowledge_graph;
 mod agency_control;
 mod visualization;
@@ -32,12 +35,21 @@ mod provider;
 pub use provider::ProviderTool;
 mod wasm_compiler;
 mod wasm_executor;
+pub mod schema;
+mod cache;
+pub use cache::{file_resource, CachePolicy, DEFAULT_CACHE_CAPACITY};
+use cache::ToolCache;
 
-pub use web_search::WebSearchTool;
+pub use web_search::{BraveBackend, DuckDuckGoBackend, SearchBackend, SearchResult, SearxngBackend, TavilyBackend, WebSearchTool};
+pub use web_fetch::WebFetchTool;
 pub use speaker_rs::SpeakerRsTool;
 pub use code_exec::CodeExecTool;
 pub use memory_query::MemoryQueryTool;
 pub use artifact::ArtifactTool;
+pub use artifact_store::{
+    ArtifactContext, ArtifactFilter, ArtifactOwner, ArtifactProvenance, ArtifactRecord, ArtifactStore, ArtifactVersion,
+    StoredArtifact, DEFAULT_ARTIFACT_DIR,
+};
 pub use sandbox::SandboxTool;
 pub use codebase::CodebaseTool;
 pub use system::SystemTool;
@@ -47,9 +59,12 @@ pub use visualization::VisualizationTool;
 pub use science::ScienceTool;
 pub use models::ModelManager;
 pub use vision::VisionTool;
-pub use dynamic::{DynamicTool, ForgeTool};
+pub use dynamic::{DynamicTool, DynamicToolMetadata, DynamicToolTest, ForgeTool};
 pub use a2a::{PeerAgentTool, RemoteAgencyTool, AnonymousAgencyTool};
-pub use mcp::{McpServer, McpProxyTool};
+pub use mcp::{
+    McpEvent, McpPrompt, McpPromptSkill, McpProxyTool, McpResource, McpResourceHit, McpResourceIndex, McpServer,
+    McpServerConfig,
+};
 pub use skills::{MarkdownSkill, SkillLoader};
 pub use task_spawner::TaskSpawnerTool;
 pub use watchdog::WatchdogTool;
@@ -61,16 +76,17 @@ pub use hands::HandsTool;
 pub use wasm_compiler::WasmCompilerTool;
 pub use wasm_executor::WasmExecutorTool;
 
-use crate::agent::{AgentResult, LadeQuadrant};
+use crate::agent::{AgentError, AgentResult, LadeQuadrant};
 use crate::orchestrator::AgencyEvent;
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::path::{Path, PathBuf};
 use tokio::sync::{Mutex, RwLock};
+use tokio_util::sync::CancellationToken;
 
 /// Output from a tool execution
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -83,6 +99,9 @@ pub struct ToolOutput {
     pub summary: String,
     /// Optional error message if success is false
     pub error: Option<String>,
+    /// Age in seconds when the registry served this output from its cache
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_age_secs: Option<u64>,
 }
 
 impl ToolOutput {
@@ -93,6 +112,7 @@ impl ToolOutput {
             data: data.into(),
             summary: summary.into(),
             error: None,
+            cache_age_secs: None,
         }
     }
 
@@ -104,6 +124,7 @@ impl ToolOutput {
             summary: content.clone(),
             data: Value::String(content),
             error: None,
+            cache_age_secs: None,
         }
     }
 
@@ -115,6 +136,7 @@ impl ToolOutput {
             data: Value::Null,
             summary: format!("Error: {}", error),
             error: Some(error),
+            cache_age_secs: None,
         }
     }
 }
@@ -128,6 +150,17 @@ pub struct ToolCall {
     pub parameters: Value,
 }
 
+/// A tool schema handed to providers with native (structured) tool calling
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ToolDefinition {
+    /// Name of the tool
+    pub name: String,
+    /// What the tool does
+    pub description: String,
+    /// JSON schema for the tool's parameters
+    pub parameters: Value,
+}
+
 /// Trait for tools that can be executed by agents
 #[async_trait]
 pub trait Tool: Send + Sync {
@@ -163,37 +196,88 @@ pub trait Tool: Send + Sync {
     fn requires_confirmation(&self) -> bool {
         false
     }
+
+    /// Whether this tool runs code forged by an agent (dynamic tools)
+    fn is_forged(&self) -> bool {
+        false
+    }
+
+    /// Whether the registry may reuse a successful result for these parameters.
+    /// Defaults to never, so live-state and side-effecting tools always run.
+    fn cache_policy(&self, _params: &Value) -> CachePolicy {
+        CachePolicy::Never
+    }
+
+    /// Whether results depend on the current `ArtifactContext` owner (task and
+    /// session); cached results are then only reused by the same owner
+    fn owner_scoped(&self) -> bool {
+        false
+    }
+
+    /// Resources this call reads, e.g. `file:src/main.rs` or `artifact:` for
+    /// the whole artifact store. A cached result is dropped as soon as another
+    /// call writes an overlapping resource.
+    fn reads(&self, _params: &Value) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Resources this call mutates
+    fn writes(&self, _params: &Value) -> Vec<String> {
+        Vec::new()
+    }
 }
 
+/// Consecutive failures after which a promoted dynamic tool is rolled back
+pub const ROLLBACK_AFTER_FAILURES: u32 = 3;
+
+/// How long the dynamic tool watcher waits for related writes to settle
+const RELOAD_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(200);
+
 /// Registry for available tools with built-in caching
 pub struct ToolRegistry {
-    tools: RwLock<HashMap<String, Arc<dyn Tool>>>,
-    cache: Arc<Mutex<HashMap<String, ToolOutput>>>,
+    tools: Arc<RwLock<HashMap<String, Arc<dyn Tool>>>>,
+    cache: Arc<Mutex<ToolCache>>,
+    mcp_resources: McpResourceIndex,
     custom_tools_dir: PathBuf,
     standard_tools_dir: PathBuf,
+    /// Dynamic tools currently registered from `standard_tools_dir`, i.e.
+    /// promoted and not shadowed by a laboratory copy
+    promoted: Arc<Mutex<HashSet<String>>>,
+    /// Consecutive failures of promoted dynamic tools
+    failures: Mutex<HashMap<String, u32>>,
+    /// Laboratory version of each tool whose tests already failed
+    rejected: Mutex<HashMap<String, String>>,
+}
+
+/// Keeps the dynamic tool directories watched; dropping it stops hot reload
+pub struct DynamicToolWatcher {
+    _watcher: ::notify::RecommendedWatcher,
 }
 
 impl ToolRegistry {
     /// Create a new empty registry
     pub fn new(custom_dir: impl Into<PathBuf>, standard_dir: impl Into<PathBuf>) -> Self {
         Self {
-            tools: RwLock::new(HashMap::new()),
-            cache: Arc::new(Mutex::new(HashMap::new())),
+            tools: Arc::new(RwLock::new(HashMap::new())),
+            cache: Arc::new(Mutex::new(ToolCache::new(DEFAULT_CACHE_CAPACITY))),
+            mcp_resources: McpResourceIndex::default(),
             custom_tools_dir: custom_dir.into(),
             standard_tools_dir: standard_dir.into(),
+            promoted: Arc::new(Mutex::new(HashSet::new())),
+            failures: Mutex::new(HashMap::new()),
+            rejected: Mutex::new(HashMap::new()),
         }
     }
 
     /// Register a tool
     #[allow(dead_code)]
     pub async fn register<T: Tool + 'static + Default>(&self) {
-        let tool = T::default();
-        let mut tools = self.tools.write().await;
-        tools.insert(tool.name().to_string(), Arc::new(tool));
+        self.register_instance(T::default()).await;
     }
 
     /// Register a tool instance
     pub async fn register_instance<T: Tool + 'static>(&self, tool: T) {
+        self.promoted.lock().await.remove(&tool.name());
         let mut tools = self.tools.write().await;
         tools.insert(tool.name().to_string(), Arc::new(tool));
     }
@@ -205,6 +289,7 @@ impl ToolRegistry {
             return Ok(0);
         }
 
+        let promoted = path == self.standard_tools_dir;
         let mut count = 0;
         for entry in std::fs::read_dir(path)? {
             let entry = entry?;
@@ -213,6 +298,11 @@ impl ToolRegistry {
                 match DynamicTool::from_file(&path) {
                     Ok(tool) => {
                         tracing::info!("Loaded dynamic tool: {}", tool.name());
+                        if promoted {
+                            self.promoted.lock().await.insert(tool.name().to_string());
+                        } else {
+                            self.promoted.lock().await.remove(&tool.name());
+                        }
                         let mut tools = self.tools.write().await;
                         tools.insert(tool.name().to_string(), Arc::new(tool));
                         count += 1;
@@ -224,19 +314,116 @@ impl ToolRegistry {
         Ok(count)
     }
 
-    /// Register all tools from an MCP server
-    pub async fn register_mcp_server(&self, server: Arc<McpServer>) -> Result<usize> {
-        let tools = server.list_tools().await?;
-        let mut count = 0;
-        for tool_def in tools {
-            let proxy = Arc::new(McpProxyTool::new(server.clone(), tool_def));
-            let mut tools = self.tools.write().await;
-            tools.insert(proxy.name(), proxy);
-            count += 1;
+    /// Watch `standard_tools` and `custom_tools`, (re)loading a dynamic tool
+    /// whenever its metadata or script changes and unloading it when its
+    /// metadata is removed. A laboratory copy shadows the standard one, as
+    /// at startup.
+    pub fn watch_dynamic_tools(&self) -> Result<DynamicToolWatcher> {
+        use ::notify::{Event, RecursiveMode, Watcher};
+
+        let dirs = [self.standard_tools_dir.clone(), self.custom_tools_dir.clone()];
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<PathBuf>();
+        let mut watcher = ::notify::RecommendedWatcher::new(move |res: ::notify::Result<Event>| {
+            if let Ok(event) = res {
+                if !event.kind.is_access() {
+                    for path in event.paths {
+                        let _ = tx.send(path);
+                    }
+                }
+            }
+        }, ::notify::Config::default())?;
+
+        // Only tools that came from these directories may be unloaded
+        let mut loaded = HashSet::new();
+        for dir in &dirs {
+            std::fs::create_dir_all(dir)?;
+            watcher.watch(dir, RecursiveMode::NonRecursive)?;
+            for entry in std::fs::read_dir(dir)?.flatten() {
+                if let Some(name) = dynamic_tool_name(&entry.path()) {
+                    loaded.insert(name);
+                }
+            }
         }
+
+        let tools = self.tools.clone();
+        let promoted = self.promoted.clone();
+        tokio::spawn(async move {
+            // Ends when the watcher (and with it the sender) is dropped
+            while let Some(path) = rx.recv().await {
+                // Forging writes a script and its metadata; reload once both landed
+                tokio::time::sleep(RELOAD_DEBOUNCE).await;
+                let mut names = HashSet::new();
+                names.extend(dynamic_tool_name(&path));
+                while let Ok(path) = rx.try_recv() {
+                    names.extend(dynamic_tool_name(&path));
+                }
+                for name in names {
+                    let found = dirs.iter().rev().map(|dir| dir.join(format!("{}.json", name))).find(|p| p.exists());
+                    match found {
+                        Some(path) => match DynamicTool::from_file(&path) {
+                            Ok(tool) => {
+                                tracing::info!("🔄 Reloaded dynamic tool {} v{}", name, tool.metadata().version);
+                                tools.write().await.insert(name.clone(), Arc::new(tool));
+                                if path.starts_with(&dirs[0]) {
+                                    promoted.lock().await.insert(name.clone());
+                                } else {
+                                    promoted.lock().await.remove(&name);
+                                }
+                                loaded.insert(name);
+                            }
+                            // Likely a half-written file; the next event retries
+                            Err(e) => tracing::warn!("Keeping previous '{}': {}", name, e),
+                        },
+                        None if loaded.remove(&name) => {
+                            tracing::info!("Unloaded dynamic tool {}", name);
+                            tools.write().await.remove(&name);
+                            promoted.lock().await.remove(&name);
+                        }
+                        None => {}
+                    }
+                }
+            }
+        });
+
+        Ok(DynamicToolWatcher { _watcher: watcher })
+    }
+
+    /// Register all tools and prompts (as skills) from an MCP server and its
+    /// resources for `mcp_resources()`. Proxies are re-synced whenever the
+    /// server reports a changed tool or prompt list, or is restarted.
+    pub async fn register_mcp_server(&self, server: Arc<McpServer>) -> Result<usize> {
+        // Subscribe first so a change during the initial sync isn't missed
+        let mut events = server.subscribe();
+        let mut owned = HashSet::new();
+        let count = sync_mcp_server(&self.tools, &server, &mut owned).await?;
+        self.mcp_resources.add(server.clone()).await;
+
+        let tools = self.tools.clone();
+        let server = Arc::downgrade(&server);
+        tokio::spawn(async move {
+            use tokio::sync::broadcast::error::RecvError;
+            loop {
+                match events.recv().await {
+                    Ok(McpEvent::ToolsChanged | McpEvent::PromptsChanged | McpEvent::Restarted) | Err(RecvError::Lagged(_)) => {
+                        let Some(server) = server.upgrade() else { break };
+                        match sync_mcp_server(&tools, &server, &mut owned).await {
+                            Ok(count) => tracing::info!("Re-synced {} tools from MCP server '{}'", count, server.name()),
+                            Err(e) => tracing::warn!("Failed to re-sync MCP server '{}': {}", server.name(), e),
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
         Ok(count)
     }
 
+    /// Resources of registered MCP servers, for memory and context lookups
+    pub fn mcp_resources(&self) -> McpResourceIndex {
+        self.mcp_resources.clone()
+    }
+
     /// Get all tool names
     pub async fn tool_names(&self) -> Vec<String> {
         let tools = self.tools.read().await;
@@ -281,6 +468,23 @@ impl ToolRegistry {
         prompt
     }
 
+    /// Build native function definitions for specific tools
+    pub async fn tool_definitions(&self, allowed_names: &[String]) -> Vec<ToolDefinition> {
+        let tools = self.tools.read().await;
+        let mut names: Vec<_> = allowed_names.iter().filter(|n| tools.contains_key(*n)).collect();
+        names.sort();
+        names.dedup();
+
+        names.into_iter().map(|name| {
+            let tool = &tools[name];
+            ToolDefinition {
+                name: name.clone(),
+                description: tool.description(),
+                parameters: tool.parameters(),
+            }
+        }).collect()
+    }
+
     /// Get a specific tool by name
     pub async fn get_tool(&self, name: &str) -> Option<Arc<dyn Tool>> {
         let tools = self.tools.read().await;
@@ -289,43 +493,80 @@ impl ToolRegistry {
 
     /// Execute a tool call with caching
     pub async fn execute(&self, call: &ToolCall) -> AgentResult<ToolOutput> {
-        let cache_key = format!("{}:{}", call.name, serde_json::to_string(&call.parameters)?);
-        
-        // Check cache
-        {
-            let cache = self.cache.lock().await;
-            if let Some(output) = cache.get(&cache_key) {
-                tracing::debug!("Cache Hit for tool: {}", call.name);
-                return Ok(output.clone());
-            }
-        }
-
         let tool = {
             let tools = self.tools.read().await;
             tools.get(&call.name).cloned()
         };
+        let Some(tool) = tool else {
+            return Ok(ToolOutput::failure(format!("Unknown tool: {}", call.name)));
+        };
 
-        let result = match tool {
-            Some(tool) => {
-                // SOTA Security Check
-                if !tool.security_oracle(&call.parameters).await? {
-                    // Emit FPF Boundary Crossing (A.6.B - Quadrant A: Admissibility)
-                    crate::emit_event!(AgencyEvent::BoundaryCrossing(crate::orchestrator::event_bus::FPFBoundClaim {
-                        quadrant: LadeQuadrant::A,
-                        claim_id: format!("GF-{}", call.name),
-                        content: format!("Security Oracle blocked execution of tool '{}'", call.name),
-                    }));
-                    return Ok(ToolOutput::failure(format!("Security Oracle blocked execution of tool '{}'", call.name)));
-                }
-                tool.execute(call.parameters.clone()).await?
-            },
-            None => ToolOutput::failure(format!("Unknown tool: {}", call.name)),
+        // Validate against the declared schema; the model gets the violations
+        // back as its observation and can retry with corrected arguments
+        let schema = tool.parameters();
+        let validation = schema::validate(&schema, &call.parameters);
+        if !validation.is_valid() {
+            tracing::debug!("Rejected call to {}: {}", call.name, validation.describe());
+            let summary = format!("Invalid parameters for '{}': {}", call.name, validation.describe());
+            return Ok(ToolOutput {
+                success: false,
+                data: validation.to_observation(&call.name, &schema),
+                summary: summary.clone(),
+                error: Some(summary),
+                cache_age_secs: None,
+            });
+        }
+        let params = validation.params;
+
+        let policy = tool.cache_policy(&params);
+        let cache_key = if tool.owner_scoped() {
+            let owner = ArtifactContext::current().owner;
+            format!("{}:{}:{}", call.name, serde_json::to_string(&owner)?, serde_json::to_string(&params)?)
+        } else {
+            format!("{}:{}", call.name, serde_json::to_string(&params)?)
         };
+        
+        // Check cache
+        if policy != CachePolicy::Never {
+            let mut cache = self.cache.lock().await;
+            if let Some((mut output, age)) = cache.get(&cache_key) {
+                tracing::debug!("Cache Hit for tool: {} ({}s old)", call.name, age.as_secs());
+                output.cache_age_secs = Some(age.as_secs());
+                return Ok(output);
+            }
+        }
+
+        // SOTA Security Check
+        if !tool.security_oracle(&params).await? {
+            // Emit FPF Boundary Crossing (A.6.B - Quadrant A: Admissibility)
+            crate::emit_event!(AgencyEvent::BoundaryCrossing(crate::orchestrator::event_bus::FPFBoundClaim {
+                quadrant: LadeQuadrant::A,
+                claim_id: format!("GF-{}", call.name),
+                content: format!("Security Oracle blocked execution of tool '{}'", call.name),
+            }));
+            return Ok(ToolOutput::failure(format!("Security Oracle blocked execution of tool '{}'", call.name)));
+        }
+        let reads = tool.reads(&params);
+        let writes = tool.writes(&params);
+        let since = self.cache.lock().await.generation();
+        let result = tool.execute(params).await;
+        // Only execution errors and failed runs count; rejected arguments say
+        // nothing about whether the tool works
+        if !matches!(&result, Err(AgentError::Validation(_))) {
+            self.track_health(&call.name, matches!(&result, Ok(output) if output.success)).await;
+        }
 
-        // Update cache if successful or specific failure
+        let mut cache = self.cache.lock().await;
+        // Even a failed write may have partially applied
+        if !writes.is_empty() {
+            let evicted = cache.invalidate(&writes);
+            if evicted > 0 {
+                tracing::debug!("{} wrote {:?}; evicted {} cached results", call.name, writes, evicted);
+            }
+        }
+        let result = result?;
         if result.success {
-            let mut cache = self.cache.lock().await;
-            cache.insert(cache_key, result.clone());
+            cache.insert(cache_key, result.clone(), policy, reads, since);
         }
 
         Ok(result)
@@ -333,10 +574,32 @@ impl ToolRegistry {
 
     /// Execute multiple tool calls in parallel
     pub async fn execute_parallel(&self, calls: &[ToolCall]) -> Vec<AgentResult<ToolOutput>> {
-        let mut futures = Vec::new();
-        for call in calls {
-            futures.push(self.execute(call));
-        }
+        self.execute_parallel_with(calls, &CancellationToken::new(), None).await
+    }
+
+    /// Execute multiple tool calls in parallel under a cancellation token and an
+    /// optional per-call deadline. Calls still running when `cancel` fires are
+    /// dropped and report `AgentError::Cancelled`; calls that finished keep their results.
+    pub async fn execute_parallel_with(
+        &self,
+        calls: &[ToolCall],
+        cancel: &CancellationToken,
+        timeout: Option<std::time::Duration>,
+    ) -> Vec<AgentResult<ToolOutput>> {
+        let futures = calls.iter().map(|call| async move {
+            let run = async {
+                match timeout {
+                    Some(limit) => tokio::time::timeout(limit, self.execute(call)).await
+                        .unwrap_or_else(|_| Err(AgentError::Timeout(format!("tool '{}' exceeded {}s", call.name, limit.as_secs())))),
+                    None => self.execute(call).await,
+                }
+            };
+            tokio::select! {
+                biased;
+                _ = cancel.cancelled() => Err(AgentError::Cancelled(format!("tool '{}' was cancelled", call.name))),
+                result = run => result,
+            }
+        });
         futures_util::future::join_all(futures).await
     }
 
@@ -347,42 +610,114 @@ impl ToolRegistry {
         cache.clear();
     }
 
-    /// Promote a custom tool to the standard set
+    /// Promote a laboratory tool to the standard set once its declared tests
+    /// pass in the sandbox. The standard version it replaces goes to history.
+    /// Each version is tested once; after a failure only a newly forged
+    /// version is tried again.
     pub async fn promote_tool(&self, name: &str) -> Result<()> {
-        let tools = self.tools.read().await;
-        if let Some(_tool) = tools.get(name) {
-            // Check if it's a dynamic tool in the custom directory
-            let metadata_path = self.custom_tools_dir.join(format!("{}.json", name));
-            if metadata_path.exists() {
-                tracing::info!("🚀 Promoting tool '{}' to standard set.", name);
-                
-                // Ensure standard directory exists
-                if !self.standard_tools_dir.exists() {
-                    std::fs::create_dir_all(&self.standard_tools_dir)?;
-                }
+        if !self.tools.read().await.contains_key(name) {
+            return Err(anyhow::anyhow!("Tool not found for promotion"));
+        }
+        // Only dynamic tools in the custom directory are promoted;
+        // standard and built-in tools need nothing
+        let metadata_path = self.custom_tools_dir.join(format!("{}.json", name));
+        if !metadata_path.exists() {
+            return Ok(());
+        }
 
-                // Move metadata
-                let new_metadata_path = self.standard_tools_dir.join(format!("{}.json", name));
-                std::fs::rename(&metadata_path, &new_metadata_path)?;
-
-                // Read metadata to find script path
-                let content = std::fs::read_to_string(&new_metadata_path)?;
-                let metadata: serde_json::Value = serde_json::from_str(&content)?;
-                
-                if let Some(script_path) = metadata["script_path"].as_str() {
-                    let old_script = self.custom_tools_dir.join(script_path);
-                    let new_script = self.standard_tools_dir.join(script_path);
-                    if old_script.exists() {
-                        std::fs::rename(old_script, new_script)?;
-                    }
-                }
+        let candidate = DynamicTool::from_file(&metadata_path)?;
+        let version = candidate.metadata().version.clone();
+        if self.rejected.lock().await.get(name) == Some(&version) {
+            return Err(anyhow::anyhow!("'{}' v{} already failed its tests", name, version));
+        }
+        let passed = match candidate.run_tests().await {
+            Ok(passed) => passed,
+            Err(e) => {
+                self.rejected.lock().await.insert(name.to_string(), version);
+                return Err(e);
+            }
+        };
+        let metadata = candidate.metadata().clone();
+        tracing::info!("🚀 Promoting tool '{}' v{} to standard set ({} tests passed).", name, metadata.version, passed);
+
+        // Ensure standard directory exists
+        if !self.standard_tools_dir.exists() {
+            std::fs::create_dir_all(&self.standard_tools_dir)?;
+        }
+        let new_metadata_path = self.standard_tools_dir.join(format!("{}.json", name));
+        if let Ok(current) = DynamicTool::from_file(&new_metadata_path) {
+            history::ToolHistory::new(&self.standard_tools_dir).archive(current.metadata())?;
+            let _ = std::fs::remove_file(self.standard_tools_dir.join(&current.metadata().script_path));
+        }
 
-                return Ok(());
+        // Move metadata and script
+        std::fs::rename(&metadata_path, &new_metadata_path)?;
+        let old_script = self.custom_tools_dir.join(&metadata.script_path);
+        if old_script.exists() {
+            std::fs::rename(old_script, self.standard_tools_dir.join(&metadata.script_path))?;
+        }
+
+        // The script moved; re-register so execution uses the new location
+        self.tools.write().await.insert(name.to_string(), Arc::new(DynamicTool::from_file(&new_metadata_path)?));
+        self.promoted.lock().await.insert(name.to_string());
+        self.failures.lock().await.remove(name);
+        self.rejected.lock().await.remove(name);
+        crate::emit_event!(AgencyEvent::ToolPromoted { name: name.to_string(), version: metadata.version });
+        Ok(())
+    }
+
+    /// Replace a promoted tool with the newest earlier version from history.
+    /// The failing version is archived too. Returns the restored version.
+    pub async fn rollback_tool(&self, name: &str) -> Result<semver::Version> {
+        let metadata_path = self.standard_tools_dir.join(format!("{}.json", name));
+        let current = DynamicTool::from_file(&metadata_path)
+            .map_err(|_| anyhow::anyhow!("'{}' is not a promoted dynamic tool", name))?;
+        let current_version = current.metadata().semver();
+
+        let history = history::ToolHistory::new(&self.standard_tools_dir);
+        let previous = history
+            .versions(name)?
+            .into_iter()
+            .rfind(|v| *v < current_version)
+            .ok_or_else(|| anyhow::anyhow!("No version of '{}' older than {} to roll back to", name, current_version))?;
+
+        history.archive(current.metadata())?;
+        let _ = std::fs::remove_file(self.standard_tools_dir.join(&current.metadata().script_path));
+        history.restore(name, &previous)?;
+        self.tools.write().await.insert(name.to_string(), Arc::new(DynamicTool::from_file(&metadata_path)?));
+        self.failures.lock().await.remove(name);
+
+        tracing::warn!("⏪ Rolled back '{}' from v{} to v{}", name, current_version, previous);
+        crate::emit_event!(AgencyEvent::ToolRolledBack {
+            name: name.to_string(),
+            from: current_version.to_string(),
+            to: previous.to_string(),
+        });
+        Ok(previous)
+    }
+
+    /// Count consecutive failures of promoted dynamic tools and roll one
+    /// back after `ROLLBACK_AFTER_FAILURES`
+    async fn track_health(&self, name: &str, success: bool) {
+        if !self.promoted.lock().await.contains(name) {
+            return;
+        }
+        let failures = {
+            let mut failures = self.failures.lock().await;
+            if success {
+                failures.remove(name);
+                return;
+            }
+            let count = failures.entry(name.to_string()).or_insert(0);
+            *count += 1;
+            *count
+        };
+        if failures >= ROLLBACK_AFTER_FAILURES {
+            if let Err(e) = self.rollback_tool(name).await {
+                tracing::warn!("'{}' failed {} times in a row but cannot be rolled back: {}", name, failures, e);
+                self.failures.lock().await.remove(name);
             }
-            // If it's already in standard or built-in, do nothing
-            return Ok(());
         }
-        Err(anyhow::anyhow!("Tool not found for promotion"))
     }
 }
 
@@ -392,6 +727,49 @@ impl Default for ToolRegistry {
     }
 }
 
+/// Tool name for a changed file in a dynamic tool directory (`<name>.json`,
+/// `<name>.py`, ...), ignoring hidden and editor swap files
+fn dynamic_tool_name(path: &Path) -> Option<String> {
+    let file_name = path.file_name()?.to_str()?;
+    if file_name.starts_with('.') || file_name.ends_with('~') {
+        return None;
+    }
+    path.file_stem()?.to_str().map(str::to_string)
+}
+
+/// Replace the proxies previously registered for `server` (tracked in
+/// `owned`) with its current tools and prompts
+async fn sync_mcp_server(
+    tools: &RwLock<HashMap<String, Arc<dyn Tool>>>,
+    server: &Arc<McpServer>,
+    owned: &mut HashSet<String>,
+) -> Result<usize> {
+    let mut proxies: Vec<Arc<dyn Tool>> = server
+        .list_tools()
+        .await?
+        .into_iter()
+        .map(|def| Arc::new(McpProxyTool::new(server.clone(), def)) as Arc<dyn Tool>)
+        .collect();
+    if server.supports("prompts") {
+        match server.list_prompts().await {
+            Ok(prompts) => proxies.extend(
+                prompts.into_iter().map(|p| Arc::new(McpPromptSkill::new(server.clone(), p)) as Arc<dyn Tool>),
+            ),
+            Err(e) => tracing::warn!("Failed to list prompts of MCP server '{}': {}", server.name(), e),
+        }
+    }
+
+    let mut tools = tools.write().await;
+    for name in owned.drain() {
+        tools.remove(&name);
+    }
+    for proxy in proxies {
+        owned.insert(proxy.name());
+        tools.insert(proxy.name(), proxy);
+    }
+    Ok(owned.len())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -447,4 +825,185 @@ mod tests {
         assert!(prompt.contains("mock_tool"));
         assert!(prompt.contains("A mock tool for testing"));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_tool_definitions() {
+        let registry = ToolRegistry::default();
+        registry.register::<MockTool>().await;
+
+        let defs = registry.tool_definitions(&["mock_tool".to_string(), "missing".to_string()]).await;
+        assert_eq!(defs.len(), 1);
+        assert_eq!(defs[0].name, "mock_tool");
+        assert_eq!(defs[0].parameters, json!({"type": "object"}));
+    }
+
+    #[derive(Default)]
+    struct SearchTool;
+
+    #[async_trait]
+    impl Tool for SearchTool {
+        fn name(&self) -> String { "search".to_string() }
+        fn description(&self) -> String { "Search with a limit".to_string() }
+        fn parameters(&self) -> Value {
+            json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string" },
+                    "limit": { "type": "integer", "default": 5 }
+                },
+                "required": ["query"]
+            })
+        }
+        async fn execute(&self, params: Value) -> AgentResult<ToolOutput> {
+            Ok(ToolOutput::success(params, "searched"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_validates_parameters() {
+        let registry = ToolRegistry::default();
+        registry.register::<SearchTool>().await;
+
+        let bad = ToolCall { name: "search".to_string(), parameters: json!({ "limit": "many" }) };
+        let res = registry.execute(&bad).await.unwrap();
+        assert!(!res.success);
+        assert_eq!(res.data["error"], "invalid_parameters");
+        let paths: Vec<&str> = res.data["violations"].as_array().unwrap().iter().map(|v| v["path"].as_str().unwrap()).collect();
+        assert_eq!(paths, vec!["query", "limit"]);
+        assert!(res.summary.contains("missing required field"));
+
+        // Defaults are filled and quoted integers coerced before execution
+        let good = ToolCall { name: "search".to_string(), parameters: json!({ "query": "rust" }) };
+        let res = registry.execute(&good).await.unwrap();
+        assert!(res.success);
+        assert_eq!(res.data, json!({ "query": "rust", "limit": 5 }));
+        let quoted = ToolCall { name: "search".to_string(), parameters: json!({ "query": "rust", "limit": "5" }) };
+        assert_eq!(registry.execute(&quoted).await.unwrap().data["limit"], 5);
+    }
+
+    #[derive(Default)]
+    struct CountingTool {
+        runs: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Tool for CountingTool {
+        fn name(&self) -> String { "counter".to_string() }
+        fn description(&self) -> String { "Counts its runs".to_string() }
+        fn parameters(&self) -> Value { json!({"type": "object"}) }
+        fn cache_policy(&self, params: &Value) -> CachePolicy {
+            match params["op"].as_str() {
+                Some("read") => CachePolicy::Pure,
+                _ => CachePolicy::Never,
+            }
+        }
+        fn reads(&self, params: &Value) -> Vec<String> {
+            vec![format!("doc:{}", params["doc"].as_str().unwrap_or_default())]
+        }
+        fn writes(&self, params: &Value) -> Vec<String> {
+            match params["op"].as_str() {
+                Some("write") => vec![format!("doc:{}", params["doc"].as_str().unwrap_or_default())],
+                _ => Vec::new(),
+            }
+        }
+        async fn execute(&self, _params: Value) -> AgentResult<ToolOutput> {
+            let n = self.runs.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+            Ok(ToolOutput::success(json!({ "run": n }), format!("run {}", n)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache_policy_and_invalidation() {
+        let registry = ToolRegistry::default();
+        registry.register::<CountingTool>().await;
+        let call = |op: &str, doc: &str| ToolCall { name: "counter".to_string(), parameters: json!({ "op": op, "doc": doc }) };
+
+        // Uncached calls always run
+        assert_eq!(registry.execute(&call("status", "a")).await.unwrap().data["run"], 1);
+        assert_eq!(registry.execute(&call("status", "a")).await.unwrap().data["run"], 2);
+
+        // Pure reads are served from the cache and flagged as such
+        let first = registry.execute(&call("read", "a")).await.unwrap();
+        assert_eq!(first.cache_age_secs, None);
+        let hit = registry.execute(&call("read", "a")).await.unwrap();
+        assert_eq!(hit.data, first.data);
+        assert_eq!(hit.cache_age_secs, Some(0));
+
+        // A write to another resource leaves it; a write to the same one evicts it
+        registry.execute(&call("write", "b")).await.unwrap();
+        assert!(registry.execute(&call("read", "a")).await.unwrap().cache_age_secs.is_some());
+        registry.execute(&call("write", "a")).await.unwrap();
+        let fresh = registry.execute(&call("read", "a")).await.unwrap();
+        assert_eq!(fresh.cache_age_secs, None);
+        assert_eq!(fresh.data["run"], 6);
+    }
+
+    #[derive(Default)]
+    struct SlowTool;
+
+    #[async_trait]
+    impl Tool for SlowTool {
+        fn name(&self) -> String { "slow_tool".to_string() }
+        fn description(&self) -> String { "Never finishes".to_string() }
+        fn parameters(&self) -> Value { json!({"type": "object"}) }
+        async fn execute(&self, _params: Value) -> AgentResult<ToolOutput> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_parallel_deadline_and_cancel() {
+        let registry = ToolRegistry::default();
+        registry.register::<MockTool>().await;
+        registry.register::<SlowTool>().await;
+        let calls = vec![
+            ToolCall { name: "mock_tool".to_string(), parameters: json!({}) },
+            ToolCall { name: "slow_tool".to_string(), parameters: json!({}) },
+        ];
+
+        let timed = registry.execute_parallel_with(&calls, &CancellationToken::new(), Some(std::time::Duration::from_millis(50))).await;
+        assert!(timed[0].is_ok());
+        assert!(matches!(timed[1], Err(AgentError::Timeout(_))));
+
+        let cancel = CancellationToken::new();
+        let trigger = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            trigger.cancel();
+        });
+        let cancelled = registry.execute_parallel_with(&calls, &cancel, None).await;
+        assert!(cancelled[0].is_ok(), "finished calls keep their result");
+        assert!(matches!(cancelled[1], Err(AgentError::Cancelled(_))));
+    }
+
+    #[tokio::test]
+    async fn test_dynamic_tools_hot_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let custom = dir.path().join("custom");
+        let registry = ToolRegistry::new(&custom, dir.path().join("standard"));
+        let _watcher = registry.watch_dynamic_tools().unwrap();
+
+        let metadata = |description: &str| {
+            json!({ "name": "hot", "description": description, "parameters": {"type": "object"}, "language": "shell", "script_path": "hot.sh" })
+        };
+        async fn wait_for(registry: &ToolRegistry, expected: Option<&str>) {
+            for _ in 0..100 {
+                if registry.get_tool("hot").await.map(|t| t.description()).as_deref() == expected {
+                    return;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+            panic!("timed out waiting for description {:?}", expected);
+        }
+
+        std::fs::write(custom.join("hot.sh"), "echo hot").unwrap();
+        std::fs::write(custom.join("hot.json"), metadata("v1").to_string()).unwrap();
+        wait_for(&registry, Some("v1")).await;
+
+        std::fs::write(custom.join("hot.json"), metadata("v2").to_string()).unwrap();
+        wait_for(&registry, Some("v2")).await;
+
+        std::fs::remove_file(custom.join("hot.json")).unwrap();
+        wait_for(&registry, None).await;
+    }
+}
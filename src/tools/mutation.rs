@@ -13,7 +13,7 @@ use tracing::{info, warn, error};
 
 use crate::agent::{AgentResult, AgentError};
 use crate::utils::sandbox::TOOL_SANDBOX_POLICY;
-use super::{Tool, ToolOutput};
+use super::{file_resource, Tool, ToolOutput};
 
 pub struct MutationTool {
     src_dir: PathBuf,
@@ -158,6 +158,13 @@ impl Tool for MutationTool {
         true // Critical safety: Mutation always requires approval
     }
 
+    fn writes(&self, params: &Value) -> Vec<String> {
+        match (params["action"].as_str(), params["path"].as_str()) {
+            (Some("apply_change"), Some(path)) => vec![file_resource(&self.src_dir.join(path))],
+            _ => Vec::new(),
+        }
+    }
+
     async fn execute(&self, params: Value) -> AgentResult<ToolOutput> {
         let action = params["action"].as_str().unwrap_or("verify");
 
@@ -194,6 +201,7 @@ impl Tool for MutationTool {
                             data: json!({"status": "invalid", "exit_code": code}),
                             summary: format!("Mutation FAILED verification. The build is broken. Reverting is recommended.\n\n{}", output),
                             error: Some("Build broken".to_string()),
+                            cache_age_secs: None,
                         })
                     }
                     Err(e) => Ok(ToolOutput::failure(format!("Verification system error: {}", e))),
@@ -5,7 +5,7 @@
 use async_trait::async_trait;
 use serde_json::{json, Value};
 use std::sync::Arc;
-use crate::agent::{AgentResult, AgentError, provider::{SwitchableProvider, create_provider_by_type}};
+use crate::agent::{AgentResult, AgentError, provider::{SwitchableProvider, create_provider_with_fallbacks}};
 use crate::tools::{Tool, ToolOutput};
 
 pub struct ProviderTool {
@@ -54,7 +54,8 @@ impl Tool for ProviderTool {
                 let target = params["provider_type"].as_str()
                     .ok_or_else(|| AgentError::Tool("Missing 'provider_type' for switch action".to_string()))?;
                 
-                let new_inner = create_provider_by_type(target);
+                // Switching keeps the configured fallback chain behind the new primary
+                let new_inner = create_provider_with_fallbacks(target);
                 self.provider.switch_to(new_inner).await;
                 
                 Ok(ToolOutput::success(
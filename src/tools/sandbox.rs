@@ -2,7 +2,8 @@
 //! 
 //! Provides a unified interface for executing code and managing files
 //! across different backends (Local Docker, Daytona, E2B).
-//! Now leverages the centralized Immune System (Seatbelt).
+//! Now leverages the centralized Immune System (Seatbelt on macOS,
+//! bubblewrap namespaces on Linux).
 
 use async_trait::async_trait;
 use bollard::container::LogOutput;
@@ -18,6 +19,7 @@ use serde_json::{json, Value};
 use tracing::{info, warn};
 
 use crate::agent::{AgentResult, AgentError};
+#[cfg(target_os = "macos")]
 use crate::utils::sandbox::TOOL_SANDBOX_POLICY;
 use super::{Tool, ToolOutput};
 
@@ -27,6 +29,8 @@ use super::{Tool, ToolOutput};
 pub enum SandboxProvider {
     Local,
     MacOSNative,
+    /// Bubblewrap namespaces + rlimits (see `utils::linux_sandbox`)
+    LinuxNative,
     Daytona,
     E2B,
 }
@@ -104,6 +108,62 @@ impl SandboxTool {
         }
     }
 
+    #[cfg(target_os = "linux")]
+    async fn execute_linux_native(&self, code: &str, language: &str) -> AgentResult<ToolOutput> {
+        use crate::utils::{linux_sandbox, sandbox::SandboxPolicy};
+
+        info!("Initializing Linux Native sandbox (bubblewrap) for {}...", language);
+        let Some(bwrap) = linux_sandbox::usable_bwrap() else {
+            return Ok(ToolOutput::failure("LinuxNative provider requires bubblewrap (bwrap) with unprivileged user namespaces"));
+        };
+
+        let temp_dir = tempfile::tempdir()
+            .map_err(AgentError::Io)?;
+        let script_path = temp_dir.path().join(match language {
+            "python" => "script.py",
+            "javascript" => "script.js",
+            "rust" => "main.rs",
+            _ => "script.sh",
+        });
+        std::fs::write(&script_path, code)
+            .map_err(AgentError::Io)?;
+
+        // Only the scratch directory is writable; no network; limits as in work_scope
+        let policy = SandboxPolicy::isolated(temp_dir.path()).with_memory_bytes(1024 * 1024 * 1024);
+        let script = script_path.to_string_lossy().to_string();
+        let (program, args) = match language {
+            "python" => ("python3", vec![script]),
+            "javascript" => ("node", vec![script]),
+            "rust" => ("sh", vec!["-c".to_string(), "rustc main.rs -o main && ./main".to_string()]),
+            _ => ("sh", vec![script]),
+        };
+
+        let output = tokio::time::timeout(
+            std::time::Duration::from_secs(policy.cpu_secs),
+            linux_sandbox::command(bwrap, program, &args, &policy)
+                .map_err(|e| AgentError::Tool(format!("Failed to prepare bwrap: {}", e)))?
+                .kill_on_drop(true)
+                .stdin(std::process::Stdio::null())
+                .output(),
+        )
+        .await
+        .map_err(|_| AgentError::Timeout(format!("Sandboxed {} run exceeded {}s", language, policy.cpu_secs)))?
+        .map_err(|e| AgentError::Tool(format!("Failed to execute bwrap: {}", e)))?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+        if output.status.success() {
+            Ok(ToolOutput::success(
+                json!({ "stdout": stdout, "stderr": stderr }),
+                format!("Native Execution Output:\n{}", stdout)
+            ))
+        } else {
+            Ok(ToolOutput::failure(format!("Native Execution Error (Status {}):\nSTDOUT: {}\nSTDERR: {}",
+                output.status, stdout, stderr)))
+        }
+    }
+
     async fn execute_local_docker(&self, code: &str, language: &str) -> AgentResult<ToolOutput> {
         info!("Initializing local Docker/Podman sandbox for {}...", language);
         
@@ -229,7 +289,15 @@ impl Default for SandboxTool {
         {
             Self::new(SandboxProvider::MacOSNative)
         }
-        #[cfg(not(target_os = "macos"))]
+        #[cfg(target_os = "linux")]
+        {
+            if crate::utils::linux_sandbox::usable_bwrap().is_some() {
+                Self::new(SandboxProvider::LinuxNative)
+            } else {
+                Self::new(SandboxProvider::Local)
+            }
+        }
+        #[cfg(not(any(target_os = "macos", target_os = "linux")))]
         {
             Self::new(SandboxProvider::Local)
         }
@@ -273,6 +341,7 @@ impl Tool for SandboxTool {
     fn work_scope(&self) -> Value {
         let env = match self.provider {
             SandboxProvider::MacOSNative => "native macos seatbelt (ultra-low latency)",
+            SandboxProvider::LinuxNative => "linux namespaces via bubblewrap (read-only root, no network)",
             SandboxProvider::Local => "isolated Docker/Podman container",
             _ => "remote sandbox",
         };
@@ -286,10 +355,19 @@ impl Tool for SandboxTool {
                 "timeout": "60s"
             },
             "side_effects": "none (stateless)",
-            "requirements": if self.provider == SandboxProvider::Local { vec!["active docker daemon"] } else { vec![] }
+            "requirements": match self.provider {
+                SandboxProvider::Local => vec!["active docker daemon"],
+                SandboxProvider::LinuxNative => vec!["bubblewrap (bwrap) with unprivileged user namespaces"],
+                _ => vec![],
+            }
         })
     }
 
+    fn writes(&self, _params: &Value) -> Vec<String> {
+        // Scripts run against the workspace and may write to it
+        vec!["file:".to_string()]
+    }
+
     async fn execute(&self, params: Value) -> AgentResult<ToolOutput> {
         let action = params["action"].as_str().unwrap_or("run");
         
@@ -303,6 +381,10 @@ impl Tool for SandboxTool {
                     SandboxProvider::MacOSNative => self.execute_macos_native(code, lang).await,
                     #[cfg(not(target_os = "macos"))]
                     SandboxProvider::MacOSNative => Ok(ToolOutput::failure("MacOSNative provider only available on macOS")),
+                    #[cfg(target_os = "linux")]
+                    SandboxProvider::LinuxNative => self.execute_linux_native(code, lang).await,
+                    #[cfg(not(target_os = "linux"))]
+                    SandboxProvider::LinuxNative => Ok(ToolOutput::failure("LinuxNative provider only available on Linux")),
                     
                     SandboxProvider::Local => self.execute_local_docker(code, lang).await,
                     SandboxProvider::Daytona => self.execute_daytona(code, lang).await,
@@ -0,0 +1,303 @@
+//! Tool Parameter Validation
+//!
+//! Checks an LLM's tool arguments against the JSON schema the tool declares
+//! in `Tool::parameters()`. Covers the subset tool schemas actually use:
+//! `type`, `required`, `properties`, `items`, `enum`, `default`, `minimum`
+//! and `maximum`. Unknown keywords and type names are ignored rather than
+//! rejected.
+//!
+//! Validation also normalizes: missing fields with a `default` are filled in,
+//! and scalars the model quoted by mistake (`"5"` for an integer, `"true"` for
+//! a boolean) are converted when the conversion is lossless.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+/// A single place where the arguments disagree with the schema
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaViolation {
+    /// Location of the offending value, e.g. `limit` or `files[2].path`
+    pub path: String,
+    pub message: String,
+}
+
+/// Result of validating one set of tool arguments
+#[derive(Debug, Clone)]
+pub struct ParamValidation {
+    /// Arguments with defaults filled in and lossless coercions applied
+    pub params: Value,
+    pub violations: Vec<SchemaViolation>,
+    checks: usize,
+    passed: usize,
+}
+
+impl ParamValidation {
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// Fraction of schema checks the arguments satisfied (1.0 when the
+    /// schema constrains nothing)
+    pub fn conformance(&self) -> f32 {
+        if self.checks == 0 {
+            1.0
+        } else {
+            self.passed as f32 / self.checks as f32
+        }
+    }
+
+    /// One-line description of every violation, for the observation summary
+    pub fn describe(&self) -> String {
+        self.violations
+            .iter()
+            .map(|v| format!("{}: {}", v.path, v.message))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    /// Structured observation returned to the model instead of executing
+    pub fn to_observation(&self, tool: &str, schema: &Value) -> Value {
+        json!({
+            "error": "invalid_parameters",
+            "tool": tool,
+            "violations": self.violations,
+            "expected": schema,
+            "hint": "Fix the listed fields and call the tool again.",
+        })
+    }
+}
+
+/// Validate `params` against `schema`, returning normalized arguments
+pub fn validate(schema: &Value, params: &Value) -> ParamValidation {
+    let mut v = Validator { violations: Vec::new(), checks: 0, passed: 0 };
+    // Models often send `null` for "no arguments"
+    let params = match (params, schema.get("type").and_then(Value::as_str)) {
+        (Value::Null, Some("object")) => Value::Object(Map::new()),
+        _ => params.clone(),
+    };
+    let params = v.check(schema, params, "");
+    ParamValidation { params, violations: v.violations, checks: v.checks, passed: v.passed }
+}
+
+struct Validator {
+    violations: Vec<SchemaViolation>,
+    checks: usize,
+    passed: usize,
+}
+
+impl Validator {
+    fn record(&mut self, ok: bool, path: &str, message: impl FnOnce() -> String) -> bool {
+        self.checks += 1;
+        if ok {
+            self.passed += 1;
+        } else {
+            let path = if path.is_empty() { "(root)".to_string() } else { path.to_string() };
+            self.violations.push(SchemaViolation { path, message: message() });
+        }
+        ok
+    }
+
+    fn check(&mut self, schema: &Value, value: Value, path: &str) -> Value {
+        let Some(schema) = schema.as_object() else {
+            return value;
+        };
+
+        let value = match schema.get("type") {
+            Some(expected) => {
+                let names: Vec<&str> = match expected {
+                    Value::String(s) => vec![s.as_str()],
+                    Value::Array(a) => a.iter().filter_map(Value::as_str).collect(),
+                    _ => Vec::new(),
+                };
+                if names.is_empty() {
+                    value
+                } else {
+                    let value = coerce(value, &names);
+                    let ok = names.iter().any(|t| type_matches(t, &value));
+                    if !self.record(ok, path, || format!("expected {}, got {}", names.join(" or "), type_name(&value))) {
+                        return value;
+                    }
+                    value
+                }
+            }
+            None => value,
+        };
+
+        if let Some(Value::Array(allowed)) = schema.get("enum") {
+            let ok = allowed.contains(&value);
+            self.record(ok, path, || {
+                let options: Vec<String> = allowed.iter().map(Value::to_string).collect();
+                format!("must be one of [{}], got {}", options.join(", "), value)
+            });
+        }
+
+        if let Some(n) = value.as_f64() {
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+                self.record(n >= min, path, || format!("must be >= {}, got {}", min, value));
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+                self.record(n <= max, path, || format!("must be <= {}, got {}", max, value));
+            }
+        }
+
+        match value {
+            Value::Object(mut fields) => {
+                let properties = schema.get("properties").and_then(Value::as_object);
+                if let Some(Value::Array(required)) = schema.get("required") {
+                    for name in required.iter().filter_map(Value::as_str) {
+                        let has_default = properties
+                            .and_then(|p| p.get(name))
+                            .is_some_and(|p| p.get("default").is_some());
+                        let present = fields.get(name).is_some_and(|f| !f.is_null()) || has_default;
+                        self.record(present, &join(path, name), || "missing required field".to_string());
+                    }
+                }
+                if let Some(properties) = properties {
+                    for (name, prop) in properties {
+                        match fields.remove(name) {
+                            Some(Value::Null) | None => {
+                                if let Some(default) = prop.get("default") {
+                                    fields.insert(name.clone(), default.clone());
+                                }
+                            }
+                            Some(field) => {
+                                let field = self.check(prop, field, &join(path, name));
+                                fields.insert(name.clone(), field);
+                            }
+                        }
+                    }
+                }
+                Value::Object(fields)
+            }
+            Value::Array(items) => match schema.get("items") {
+                Some(item_schema) => Value::Array(
+                    items
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, item)| self.check(item_schema, item, &format!("{}[{}]", path, i)))
+                        .collect(),
+                ),
+                None => Value::Array(items),
+            },
+            other => other,
+        }
+    }
+}
+
+fn join(path: &str, field: &str) -> String {
+    if path.is_empty() {
+        field.to_string()
+    } else {
+        format!("{}.{}", path, field)
+    }
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "null" => value.is_null(),
+        // Not a JSON-Schema type; nothing to enforce
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Lossless scalar conversions for values the model quoted or over-typed
+fn coerce(value: Value, expected: &[&str]) -> Value {
+    if expected.iter().any(|t| type_matches(t, &value)) {
+        return value;
+    }
+    for target in expected {
+        let converted = match (*target, &value) {
+            ("integer", Value::String(s)) => s.trim().parse::<i64>().ok().map(Value::from),
+            ("integer", Value::Number(n)) => n
+                .as_f64()
+                .filter(|f| f.fract() == 0.0 && f.abs() < i64::MAX as f64)
+                .map(|f| Value::from(f as i64)),
+            ("number", Value::String(s)) => s.trim().parse::<f64>().ok().filter(|f| f.is_finite()).map(Value::from),
+            ("boolean", Value::String(s)) => match s.trim() {
+                "true" => Some(Value::Bool(true)),
+                "false" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            ("string", Value::Number(n)) => Some(Value::String(n.to_string())),
+            _ => None,
+        };
+        if let Some(converted) = converted {
+            return converted;
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "action": { "type": "string", "enum": ["read", "write"] },
+                "path": { "type": "string" },
+                "limit": { "type": "integer", "default": 10, "minimum": 1 },
+                "recursive": { "type": "boolean" },
+                "tags": { "type": "array", "items": { "type": "string" } }
+            },
+            "required": ["action", "path"]
+        })
+    }
+
+    #[test]
+    fn test_valid_params_get_defaults() {
+        let v = validate(&schema(), &json!({ "action": "read", "path": "src/lib.rs" }));
+        assert!(v.is_valid(), "{:?}", v.violations);
+        assert_eq!(v.params["limit"], 10);
+        assert_eq!(v.conformance(), 1.0);
+    }
+
+    #[test]
+    fn test_violations_are_located() {
+        let v = validate(&schema(), &json!({ "action": "delete", "limit": 0, "tags": ["a", 3, {}] }));
+        let paths: Vec<&str> = v.violations.iter().map(|v| v.path.as_str()).collect();
+        assert_eq!(paths, vec!["path", "action", "limit", "tags[2]"]);
+        assert!(v.violations[1].message.contains("\"read\""));
+        assert!(v.conformance() < 0.7);
+    }
+
+    #[test]
+    fn test_lossless_coercion() {
+        let v = validate(&schema(), &json!({ "action": "read", "path": "a", "limit": "25", "recursive": "true", "tags": [7] }));
+        assert!(v.is_valid(), "{:?}", v.violations);
+        assert_eq!(v.params["limit"], 25);
+        assert_eq!(v.params["recursive"], true);
+        assert_eq!(v.params["tags"][0], "7");
+
+        let v = validate(&schema(), &json!({ "action": "read", "path": "a", "limit": "lots" }));
+        assert_eq!(v.violations[0].message, "expected integer, got string");
+    }
+
+    #[test]
+    fn test_unconstrained_schema() {
+        let v = validate(&json!({ "type": "object" }), &Value::Null);
+        assert!(v.is_valid());
+        assert_eq!(v.params, json!({}));
+        assert_eq!(v.conformance(), 1.0);
+    }
+}
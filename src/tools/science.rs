@@ -10,7 +10,7 @@ use serde_json::{json, Value};
 use tracing::debug;
 
 use crate::agent::{AgentResult, AgentError};
-use super::{Tool, ToolOutput};
+use super::{CachePolicy, Tool, ToolOutput};
 
 #[derive(Debug, Deserialize)]
 struct GithubContent {
@@ -119,6 +119,11 @@ impl Tool for ScienceTool {
         })
     }
 
+    fn cache_policy(&self, _params: &Value) -> CachePolicy {
+        // Published articles change rarely
+        CachePolicy::Ttl(std::time::Duration::from_secs(3600))
+    }
+
     async fn execute(&self, params: Value) -> AgentResult<ToolOutput> {
         let action = params["action"].as_str().unwrap_or("list_categories");
 
@@ -30,6 +30,8 @@ impl Tool for WasmCompilerTool {
 
     fn description(&self) -> String {
         "Compiles Rust code into a WASM module. Returns the path to the .wasm file.
+        target 'library' builds a cdylib of exported functions; 'wasi' builds a program
+        that reads stdin and writes stdout (run it with wasm_executor mode 'wasi').
         Use this to create new, high-performance, sandboxed tools on the fly.".to_string()
     }
 
@@ -39,7 +41,13 @@ impl Tool for WasmCompilerTool {
             "properties": {
                 "source_code": {
                     "type": "string",
-                    "description": "The Rust source code to compile. Must be a library (cdylib)."
+                    "description": "The Rust source code to compile. A library (cdylib) or, for the wasi target, a program with `fn main`."
+                },
+                "target": {
+                    "type": "string",
+                    "enum": ["library", "wasi"],
+                    "default": "library",
+                    "description": "library: wasm32-unknown-unknown cdylib; wasi: wasm32-wasip1 command"
                 },
                 "filename": {
                     "type": "string",
@@ -54,13 +62,22 @@ impl Tool for WasmCompilerTool {
         json!({
             "status": "evolutionary",
             "safety": "High (Compiles code)",
-            "requirements": ["rustc", "wasm32-unknown-unknown"]
+            "requirements": ["rustc", "wasm32-unknown-unknown", "wasm32-wasip1"]
         })
     }
 
+    fn writes(&self, _params: &Value) -> Vec<String> {
+        // Build output lands on disk; treat it as a workspace write
+        vec!["file:".to_string()]
+    }
+
     async fn execute(&self, params: Value) -> AgentResult<ToolOutput> {
         let source_code = params["source_code"].as_str().ok_or_else(|| AgentError::Validation("Missing source_code".to_string()))?;
         let filename = params["filename"].as_str().unwrap_or("module");
+        let (target, crate_type) = match params["target"].as_str().unwrap_or("library") {
+            "wasi" => ("wasm32-wasip1", "bin"),
+            _ => ("wasm32-unknown-unknown", "cdylib"),
+        };
         
         if !self.work_dir.exists() {
             tokio::fs::create_dir_all(&self.work_dir).await.map_err(|e| AgentError::Io(e))?;
@@ -73,12 +90,12 @@ impl Tool for WasmCompilerTool {
         tokio::fs::write(&src_path, source_code).await.map_err(|e| AgentError::Io(e))?;
 
         // Compile using rustc
-        // rustc --target wasm32-unknown-unknown --crate-type cdylib -O source.rs -o output.wasm
+        // rustc --target <target> --crate-type <cdylib|bin> -O source.rs -o output.wasm
         let output = Command::new("rustc")
             .arg("--target")
-            .arg("wasm32-unknown-unknown")
+            .arg(target)
             .arg("--crate-type")
-            .arg("cdylib")
+            .arg(crate_type)
             .arg("-O") // Optimize
             .arg(&src_path)
             .arg("-o")
@@ -91,6 +108,7 @@ impl Tool for WasmCompilerTool {
             Ok(ToolOutput::success(
                 json!({
                     "wasm_path": wasm_path.to_string_lossy(),
+                    "target": target,
                     "size_bytes": wasm_path.metadata().map(|m| m.len()).unwrap_or(0)
                 }), 
                 format!("Successfully compiled WASM module to {}", wasm_path.display())
@@ -1,26 +1,38 @@
 //! WASM Executor Tool
-//! 
+//!
 //! Executes compiled WASM modules in a safe runtime.
 //! Part of the Self-Correction Loop.
 
 use async_trait::async_trait;
 use serde_json::{json, Value};
 use std::path::PathBuf;
-use std::sync::Mutex;
+use std::sync::Arc;
 use crate::agent::{AgentResult, AgentError};
 use crate::runtime::wasm::WasmRuntime;
 use super::{Tool, ToolOutput};
 
 pub struct WasmExecutorTool {
-    runtime: Mutex<WasmRuntime>,
+    runtime: Arc<WasmRuntime>,
 }
 
 impl WasmExecutorTool {
     pub fn new() -> Self {
         Self {
-            runtime: Mutex::new(WasmRuntime::new()),
+            runtime: Arc::new(WasmRuntime::new()),
         }
     }
+
+    /// Use a runtime with custom limits or a workspace preopened for WASI
+    pub fn with_runtime(mut self, runtime: WasmRuntime) -> Self {
+        self.runtime = Arc::new(runtime);
+        self
+    }
+}
+
+impl Default for WasmExecutorTool {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[async_trait]
@@ -30,8 +42,10 @@ impl Tool for WasmExecutorTool {
     }
 
     fn description(&self) -> String {
-        "Executes a function inside a compiled WASM module. 
-        Currently supports functions signature: fn(i32, i32) -> i32.
+        "Runs a compiled WASM module under fuel, memory and time limits. Modes: \
+        'wasi' runs a WASI program with `input` on stdin and returns its stdout; \
+        'json' calls an exported function with `input` as JSON through the alloc/ptr ABI; \
+        'numeric' calls an exported function with numeric `args`. \
         Use this to test and run your compiled capabilities.".to_string()
     }
 
@@ -43,53 +57,185 @@ impl Tool for WasmExecutorTool {
                     "type": "string",
                     "description": "Path to the .wasm file"
                 },
+                "mode": {
+                    "type": "string",
+                    "enum": ["wasi", "json", "numeric"],
+                    "description": "Calling convention (default: wasi without function_name, json with input, else numeric)"
+                },
                 "function_name": {
                     "type": "string",
-                    "description": "Name of the exported function to call"
+                    "description": "Exported function to call (json and numeric modes)"
                 },
                 "args": {
                     "type": "array",
-                    "items": { "type": "integer" },
-                    "description": "List of integer arguments (max 2)"
+                    "items": { "type": "number" },
+                    "description": "Numeric arguments, converted to the function's signature"
+                },
+                "input": {
+                    "description": "JSON value passed to the module (stdin for wasi, argument for json)"
+                },
+                "argv": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Command-line arguments for a WASI program"
                 }
             },
-            "required": ["wasm_path", "function_name", "args"]
+            "required": ["wasm_path"]
         })
     }
 
     fn work_scope(&self) -> Value {
+        let limits = self.runtime.limits();
         json!({
             "status": "runtime",
             "safety": "Sandboxed (WASM)",
-            "requirements": ["wasmer"]
+            "limits": {
+                "fuel": limits.fuel,
+                "memory_bytes": limits.memory_bytes,
+                "timeout_secs": limits.timeout.as_secs_f64(),
+                "max_output_bytes": limits.max_output_bytes
+            },
+            "requirements": ["wasmtime"]
         })
     }
 
+    fn writes(&self, _params: &Value) -> Vec<String> {
+        // WASI programs may write to the preopened workspace
+        vec!["file:".to_string()]
+    }
+
     async fn execute(&self, params: Value) -> AgentResult<ToolOutput> {
         let wasm_path_str = params["wasm_path"].as_str().ok_or_else(|| AgentError::Validation("Missing wasm_path".to_string()))?;
-        let function_name = params["function_name"].as_str().unwrap_or("run");
-        let args_json = params["args"].as_array().ok_or_else(|| AgentError::Validation("Missing args array".to_string()))?;
-        
-        let mut args = Vec::new();
-        for arg in args_json {
-            if let Some(i) = arg.as_i64() {
-                args.push(i as i32);
-            }
-        }
+        let function_name = params["function_name"].as_str().map(str::to_string);
+        let input = params.get("input").cloned().unwrap_or(Value::Null);
+        let mode = match params["mode"].as_str() {
+            Some(mode) => mode.to_string(),
+            None if function_name.is_none() => "wasi".to_string(),
+            None if !input.is_null() => "json".to_string(),
+            None => "numeric".to_string(),
+        };
 
         let wasm_path = PathBuf::from(wasm_path_str);
         if !wasm_path.exists() {
             return Ok(ToolOutput::failure(format!("WASM file not found: {}", wasm_path.display())));
         }
 
-        let result = {
-            let mut runtime = self.runtime.lock().unwrap();
-            runtime.execute(&wasm_path, function_name, &args)
-        };
+        // wasmtime calls are synchronous; keep them off the async workers
+        let runtime = self.runtime.clone();
+        let args = params["args"].as_array().cloned().unwrap_or_default();
+        let argv: Vec<String> = params["argv"]
+            .as_array()
+            .map(|a| a.iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+            .unwrap_or_default();
+        let result = tokio::task::spawn_blocking(move || -> anyhow::Result<ToolOutput> {
+            let function_name = function_name.as_deref().unwrap_or("run");
+            Ok(match mode.as_str() {
+                "wasi" => {
+                    let stdin = match &input {
+                        Value::Null => Vec::new(),
+                        Value::String(s) => s.clone().into_bytes(),
+                        other => serde_json::to_vec(other)?,
+                    };
+                    let out = runtime.run_wasi(&wasm_path, &stdin, &argv)?;
+                    let output = serde_json::from_str::<Value>(out.stdout.trim()).unwrap_or_else(|_| Value::String(out.stdout.clone()));
+                    let data = json!({
+                        "output": output,
+                        "stderr": out.stderr,
+                        "exit_code": out.exit_code,
+                        "fuel_used": out.fuel_used
+                    });
+                    if out.exit_code == 0 {
+                        ToolOutput::success(data, format!("WASI program finished. Output: {}", out.stdout.trim()))
+                    } else {
+                        let mut failed = ToolOutput::failure(format!("WASI program exited with code {}: {}", out.exit_code, out.stderr.trim()));
+                        failed.data = data;
+                        failed
+                    }
+                }
+                "json" => {
+                    let (result, fuel_used) = runtime.call_json(&wasm_path, function_name, &input)?;
+                    ToolOutput::success(
+                        json!({"result": result, "fuel_used": fuel_used}),
+                        format!("Execution successful. Result: {}", result),
+                    )
+                }
+                _ => {
+                    let (results, fuel_used) = runtime.call_numeric(&wasm_path, function_name, &args)?;
+                    let result = if results.len() == 1 { results[0].clone() } else { Value::Array(results) };
+                    ToolOutput::success(
+                        json!({"result": result, "fuel_used": fuel_used}),
+                        format!("Execution successful. Result: {}", result),
+                    )
+                }
+            })
+        })
+        .await
+        .map_err(|e| AgentError::Execution(format!("WASM task failed: {}", e)))?;
 
         match result {
-            Ok(val) => Ok(ToolOutput::success(json!({"result": val}), format!("Execution successful. Result: {}", val))),
+            Ok(output) => Ok(output),
             Err(e) => Ok(ToolOutput::failure(format!("Runtime error: {}", e))),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(dir: &tempfile::TempDir, name: &str, wat: &str) -> String {
+        let path = dir.path().join(name);
+        std::fs::write(&path, wat).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_wasi_mode_round_trips_json() {
+        let dir = tempfile::tempdir().unwrap();
+        // Copies up to 1KiB of stdin to stdout
+        let path = module(&dir, "cat.wat", r#"(module
+            (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+            (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (func (export "_start")
+                (i32.store (i32.const 0) (i32.const 64))
+                (i32.store (i32.const 4) (i32.const 1024))
+                (drop (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8)))
+                (i32.store (i32.const 4) (i32.load (i32.const 8)))
+                (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 12)))))"#);
+        let tool = WasmExecutorTool::new().with_runtime(WasmRuntime::new().with_workspace(dir.path()));
+
+        let input = json!({ "n": 7, "tags": ["a", "b"] });
+        let out = tool.execute(json!({ "wasm_path": path, "input": input })).await.unwrap();
+        assert!(out.success, "{}", out.summary);
+        assert_eq!(out.data["output"], input);
+        assert_eq!(out.data["exit_code"], 0);
+
+        // Plain text stdin comes back as a string
+        let out = tool.execute(json!({ "wasm_path": path, "mode": "wasi", "input": "hello" })).await.unwrap();
+        assert_eq!(out.data["output"], "hello");
+    }
+
+    #[tokio::test]
+    async fn test_json_mode_and_runtime_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        // Echoes its input through the alloc/ptr ABI
+        let path = module(&dir, "echo.wat", r#"(module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) i32.const 1024)
+            (func (export "echo") (param i32 i32) (result i64)
+                local.get 0 i64.extend_i32_u i64.const 32 i64.shl
+                local.get 1 i64.extend_i32_u i64.or))"#);
+        let tool = WasmExecutorTool::new();
+
+        // function_name plus input selects json mode
+        let input = json!({ "skill": "sum", "values": [1, 2, 3] });
+        let out = tool.execute(json!({ "wasm_path": path, "function_name": "echo", "input": input })).await.unwrap();
+        assert!(out.success, "{}", out.summary);
+        assert_eq!(out.data["result"], input);
+
+        let missing = tool.execute(json!({ "wasm_path": path, "mode": "json", "function_name": "nope", "input": 1 })).await.unwrap();
+        assert!(!missing.success);
+        assert!(missing.error.unwrap().starts_with("Runtime error"));
+    }
+}
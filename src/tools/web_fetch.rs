@@ -0,0 +1,712 @@
+//! Web Fetch Tool
+//!
+//! Downloads a page and reduces it to readable markdown (or plain text) plus
+//! the list of links it contains. Honors robots.txt, caps the download size,
+//! and lets the registry cache results for a while.
+//!
+//! Only public addresses are fetched: loopback, link-local (cloud metadata),
+//! private and unique-local addresses are refused, whether given literally or
+//! resolved from a name. Redirects are followed by hand so every hop is
+//! checked against that policy and the target's robots.txt.
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::{redirect, Client, Response};
+use scraper::{ElementRef, Html, Node, Selector};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::agent::{AgentResult, AgentError};
+use super::{CachePolicy, Tool, ToolOutput};
+
+/// Product token matched against robots.txt `User-agent` lines
+const ROBOTS_AGENT: &str = "rust_agency";
+const DEFAULT_MAX_BYTES: usize = 2 * 1024 * 1024;
+/// Parsers must read at least 500 KiB of robots.txt (RFC 9309); the rest is ignored
+const MAX_ROBOTS_BYTES: usize = 512 * 1024;
+const MAX_REDIRECTS: usize = 10;
+const DEFAULT_MAX_CHARS: usize = 20_000;
+/// Links listed in the summary; `data.links` keeps them all
+const SUMMARY_LINKS: usize = 40;
+
+/// Elements that never hold the readable content of a page
+const SKIPPED_TAGS: [&str; 13] = [
+    "script", "style", "noscript", "template", "svg", "iframe", "nav", "header", "footer", "aside", "form", "button", "select",
+];
+const BLOCK_TAGS: [&str; 14] = [
+    "p", "div", "section", "article", "main", "table", "tr", "ul", "ol", "dl", "dt", "dd", "figure", "figcaption",
+];
+
+pub struct WebFetchTool {
+    client: Client,
+    max_bytes: usize,
+    /// Whether loopback and private addresses may be fetched
+    allow_internal: bool,
+    robots: Mutex<HashMap<String, Arc<RobotsRules>>>,
+}
+
+/// Whether `ip` is somewhere a fetch must not reach: the host itself, its
+/// link-local neighbours (169.254.169.254 serves cloud credentials), private
+/// networks, or no routable address at all
+pub fn is_internal_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || a == 0
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (a == 100 && (b & 0xc0) == 64)
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_internal_address(IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                // Unique local fc00::/7 and link-local fe80::/10
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+        }
+    }
+}
+
+/// Resolves names like the system resolver, but fails when any address is internal
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0)).await?.collect();
+            if let Some(internal) = addrs.iter().find(|addr| is_internal_address(addr.ip())) {
+                return Err(format!("{} resolves to internal address {}", name.as_str(), internal.ip()).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Refuse non-http(s) URLs and, unless `allow_internal`, literal internal
+/// addresses (names are checked by `PublicResolver` when connecting)
+fn check_target(url: &Url, allow_internal: bool) -> anyhow::Result<()> {
+    if !matches!(url.scheme(), "http" | "https") {
+        anyhow::bail!("Unsupported URL scheme: {}", url.scheme());
+    }
+    if allow_internal {
+        return Ok(());
+    }
+    let internal = match url.host() {
+        Some(url::Host::Ipv4(ip)) => is_internal_address(IpAddr::V4(ip)),
+        Some(url::Host::Ipv6(ip)) => is_internal_address(IpAddr::V6(ip)),
+        Some(url::Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost") || domain.to_ascii_lowercase().ends_with(".localhost"),
+        None => true,
+    };
+    if internal {
+        anyhow::bail!("Refusing to fetch internal address {}", url.host_str().unwrap_or_default());
+    }
+    Ok(())
+}
+
+/// Read at most `max_bytes` of the body; also reports whether it was cut
+async fn read_capped(response: Response, max_bytes: usize) -> anyhow::Result<(Vec<u8>, bool)> {
+    let mut body = Vec::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        let room = max_bytes - body.len();
+        if chunk.len() > room {
+            body.extend_from_slice(&chunk[..room]);
+            return Ok((body, true));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok((body, false))
+}
+
+impl WebFetchTool {
+    pub fn new() -> Self {
+        Self {
+            client: Self::client(false),
+            max_bytes: DEFAULT_MAX_BYTES,
+            allow_internal: false,
+            robots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn client(allow_internal: bool) -> Client {
+        let builder = Client::builder()
+            .user_agent(format!("Mozilla/5.0 (compatible; {}/{})", ROBOTS_AGENT, env!("CARGO_PKG_VERSION")))
+            .timeout(std::time::Duration::from_secs(30))
+            .redirect(redirect::Policy::none());
+        let builder = if allow_internal { builder } else { builder.dns_resolver(Arc::new(PublicResolver)) };
+        builder.build().unwrap_or_default()
+    }
+
+    /// Let fetches reach loopback and private addresses, e.g. a local test
+    /// server. Off by default.
+    pub fn with_allow_internal(mut self, allow_internal: bool) -> Self {
+        self.client = Self::client(allow_internal);
+        self.allow_internal = allow_internal;
+        self
+    }
+
+    /// Stop downloading after this many bytes (the page is marked truncated)
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// robots.txt rules for the URL's origin, fetched once per origin.
+    /// A missing or unreachable robots.txt allows everything.
+    async fn robots_for(&self, url: &Url) -> Arc<RobotsRules> {
+        let origin = url.origin().ascii_serialization();
+        if let Some(rules) = self.robots.lock().await.get(&origin) {
+            return rules.clone();
+        }
+        let rules = match self.client.get(format!("{}/robots.txt", origin)).send().await {
+            Ok(response) if response.status().is_success() => {
+                let (body, _) = read_capped(response, MAX_ROBOTS_BYTES).await.unwrap_or_default();
+                RobotsRules::parse(&String::from_utf8_lossy(&body), ROBOTS_AGENT)
+            }
+            Ok(_) => RobotsRules::default(),
+            Err(e) => {
+                debug!("No robots.txt for {}: {}", origin, e);
+                RobotsRules::default()
+            }
+        };
+        let rules = Arc::new(rules);
+        self.robots.lock().await.insert(origin, rules.clone());
+        rules
+    }
+
+    /// Body (at most `max_bytes`), final URL after redirects, content type, and
+    /// whether it was cut. Each hop must be public and allowed by its robots.txt.
+    async fn download(&self, url: &Url) -> anyhow::Result<(Vec<u8>, Url, String, bool)> {
+        let mut current = url.clone();
+        let mut hops = 0;
+        let response = loop {
+            check_target(&current, self.allow_internal)?;
+            let path = match current.query() {
+                Some(query) => format!("{}?{}", current.path(), query),
+                None => current.path().to_string(),
+            };
+            if !self.robots_for(&current).await.allows(&path) {
+                anyhow::bail!("Fetching {} is disallowed by the site's robots.txt", current);
+            }
+
+            let response = self.client.get(current.clone()).send().await?;
+            if !response.status().is_redirection() {
+                break response.error_for_status()?;
+            }
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .ok_or_else(|| anyhow::anyhow!("Redirect from {} without a Location", current))?;
+            hops += 1;
+            if hops > MAX_REDIRECTS {
+                anyhow::bail!("Too many redirects (more than {})", MAX_REDIRECTS);
+            }
+            let next = current.join(location)?;
+            debug!("Following redirect {} -> {}", current, next);
+            current = next;
+        };
+
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let (body, truncated) = read_capped(response, self.max_bytes).await?;
+        Ok((body, current, content_type, truncated))
+    }
+}
+
+impl Default for WebFetchTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for WebFetchTool {
+    fn name(&self) -> String {
+        "web_fetch".to_string()
+    }
+
+    fn description(&self) -> String {
+        "Download a web page and return its readable content as markdown (or plain text) with a list of its links. \
+        Use after `web_search` to read a result. Respects robots.txt and size limits; only public addresses can be fetched.".to_string()
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "url": {
+                    "type": "string",
+                    "description": "http(s) URL to fetch"
+                },
+                "format": {
+                    "type": "string",
+                    "enum": ["markdown", "text"],
+                    "default": "markdown",
+                    "description": "Output format of the extracted content"
+                },
+                "max_chars": {
+                    "type": "integer",
+                    "default": DEFAULT_MAX_CHARS,
+                    "minimum": 500,
+                    "maximum": 200000,
+                    "description": "Truncate the extracted content to this many characters"
+                }
+            },
+            "required": ["url"]
+        })
+    }
+
+    fn work_scope(&self) -> Value {
+        json!({
+            "status": "constrained",
+            "environment": "external internet",
+            "network": "required (active internet connection)",
+            "limits": { "max_bytes": self.max_bytes, "robots_txt": "honored", "addresses": "public only" },
+            "side_effects": "none"
+        })
+    }
+
+    fn cache_policy(&self, _params: &Value) -> CachePolicy {
+        CachePolicy::Ttl(std::time::Duration::from_secs(900))
+    }
+
+    async fn execute(&self, params: Value) -> AgentResult<ToolOutput> {
+        let raw_url = params["url"]
+            .as_str()
+            .ok_or_else(|| AgentError::Validation("Missing required parameter: url".to_string()))?;
+        let markdown = params["format"].as_str() != Some("text");
+        let max_chars = params["max_chars"].as_u64().map(|n| n as usize).unwrap_or(DEFAULT_MAX_CHARS);
+
+        let url = match Url::parse(raw_url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => url,
+            Ok(url) => return Ok(ToolOutput::failure(format!("Unsupported URL scheme: {}", url.scheme()))),
+            Err(e) => return Ok(ToolOutput::failure(format!("Invalid URL '{}': {}", raw_url, e))),
+        };
+
+        let (body, final_url, content_type, truncated_download) = match self.download(&url).await {
+            Ok(page) => page,
+            Err(e) => {
+                warn!("web_fetch failed for {}: {}", url, e);
+                return Ok(ToolOutput::failure(format!("Failed to fetch {}: {}", url, e)));
+            }
+        };
+        let html = String::from_utf8_lossy(&body);
+
+        let page = if content_type.contains("html") || (content_type.is_empty() && html.trim_start().starts_with('<')) {
+            Readable::extract(&html, &final_url, markdown)
+        } else if content_type.starts_with("text/") || content_type.contains("json") || content_type.contains("xml") {
+            Readable { title: String::new(), content: html.trim().to_string(), links: Vec::new() }
+        } else {
+            return Ok(ToolOutput::failure(format!("Unsupported content type '{}' at {}", content_type, final_url)));
+        };
+
+        let truncated_text = page.content.chars().count() > max_chars;
+        let content: String = if truncated_text {
+            page.content.chars().take(max_chars).collect()
+        } else {
+            page.content
+        };
+        let truncated = truncated_download || truncated_text;
+
+        let mut summary = String::new();
+        if !page.title.is_empty() {
+            summary.push_str(&format!("# {}\n", page.title));
+        }
+        summary.push_str(&format!("Source: {}\n\n{}", final_url, content));
+        if truncated {
+            summary.push_str("\n\n[content truncated]");
+        }
+        if !page.links.is_empty() {
+            summary.push_str("\n\nLinks:\n");
+            for (i, (text, href)) in page.links.iter().take(SUMMARY_LINKS).enumerate() {
+                summary.push_str(&format!("{}. [{}]({})\n", i + 1, text, href));
+            }
+            if page.links.len() > SUMMARY_LINKS {
+                summary.push_str(&format!("... and {} more\n", page.links.len() - SUMMARY_LINKS));
+            }
+        }
+
+        Ok(ToolOutput::success(
+            json!({
+                "url": url.as_str(),
+                "final_url": final_url.as_str(),
+                "title": page.title,
+                "content_type": content_type,
+                "format": if markdown { "markdown" } else { "text" },
+                "content": content,
+                "links": page.links.iter().map(|(text, href)| json!({ "text": text, "url": href })).collect::<Vec<_>>(),
+                "bytes": body.len(),
+                "truncated": truncated
+            }),
+            summary,
+        ))
+    }
+}
+
+/// Readable content of a page
+struct Readable {
+    title: String,
+    content: String,
+    links: Vec<(String, String)>,
+}
+
+impl Readable {
+    /// Pick the main content (`article`, `main`, else `body`), drop chrome
+    /// like navigation and scripts, and render the rest
+    fn extract(html: &str, base: &Url, markdown: bool) -> Self {
+        let document = Html::parse_document(html);
+        let select = |css: &str| Selector::parse(css).ok().and_then(|s| document.select(&s).next());
+
+        let title = select("title")
+            .or_else(|| select("h1"))
+            .map(|el| collapse_whitespace(&el.text().collect::<String>()))
+            .unwrap_or_default();
+        let root = ["article", "main", "[role=main]", "body"]
+            .iter()
+            .find_map(|css| select(css))
+            .unwrap_or_else(|| document.root_element());
+
+        let mut writer = Writer { out: String::new(), links: Vec::new(), base, markdown };
+        writer.element(root);
+        Self { title, content: tidy(&writer.out), links: writer.links }
+    }
+}
+
+struct Writer<'a> {
+    out: String,
+    links: Vec<(String, String)>,
+    base: &'a Url,
+    markdown: bool,
+}
+
+impl Writer<'_> {
+    fn block_break(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push_str(if self.out.ends_with('\n') { "\n" } else { "\n\n" });
+        }
+    }
+
+    fn line_break(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        let collapsed = collapse_whitespace(text);
+        if collapsed.is_empty() {
+            if text.chars().any(char::is_whitespace) && !self.out.ends_with([' ', '\n']) && !self.out.is_empty() {
+                self.out.push(' ');
+            }
+            return;
+        }
+        if text.starts_with(char::is_whitespace) && !self.out.ends_with([' ', '\n']) && !self.out.is_empty() {
+            self.out.push(' ');
+        }
+        self.out.push_str(&collapsed);
+        if text.ends_with(char::is_whitespace) {
+            self.out.push(' ');
+        }
+    }
+
+    fn children(&mut self, element: ElementRef) {
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => self.text(text),
+                Node::Element(_) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        self.element(child);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn element(&mut self, element: ElementRef) {
+        let el = element.value();
+        let tag = el.name();
+        if SKIPPED_TAGS.contains(&tag) || el.attr("hidden").is_some() || el.attr("aria-hidden") == Some("true") {
+            return;
+        }
+
+        match tag {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.block_break();
+                if self.markdown {
+                    let level = tag[1..].parse().unwrap_or(1);
+                    self.out.push_str(&format!("{} ", "#".repeat(level)));
+                }
+                self.children(element);
+                self.block_break();
+            }
+            "li" => {
+                self.line_break();
+                self.out.push_str("- ");
+                self.children(element);
+                self.line_break();
+            }
+            "br" => self.out.push('\n'),
+            "hr" => {
+                self.block_break();
+                if self.markdown {
+                    self.out.push_str("---");
+                    self.block_break();
+                }
+            }
+            "pre" => {
+                self.block_break();
+                let code: String = element.text().collect();
+                if self.markdown {
+                    self.out.push_str(&format!("```\n{}\n```", code.trim_end()));
+                } else {
+                    self.out.push_str(code.trim_end());
+                }
+                self.block_break();
+            }
+            "code" if self.markdown => {
+                let code = collapse_whitespace(&element.text().collect::<String>());
+                self.out.push_str(&format!("`{}`", code));
+            }
+            "blockquote" => {
+                self.block_break();
+                let start = self.out.len();
+                self.children(element);
+                if self.markdown {
+                    let quoted = self.out[start..].trim().lines().map(|l| format!("> {}", l.trim())).collect::<Vec<_>>().join("\n");
+                    self.out.truncate(start);
+                    self.out.push_str(&quoted);
+                }
+                self.block_break();
+            }
+            "a" => {
+                let start = self.out.len();
+                self.children(element);
+                let text = self.out[start..].trim().to_string();
+                let href = el.attr("href").and_then(|href| self.base.join(href).ok());
+                if let Some(href) = href.filter(|u| matches!(u.scheme(), "http" | "https")) {
+                    if !text.is_empty() {
+                        if self.markdown {
+                            self.out.truncate(start);
+                            self.out.push_str(&format!("[{}]({})", text, href));
+                        }
+                        if !self.links.iter().any(|(_, known)| *known == href.as_str()) {
+                            self.links.push((text, href.to_string()));
+                        }
+                    }
+                }
+            }
+            "img" => {
+                if let Some(alt) = el.attr("alt").map(str::trim).filter(|alt| !alt.is_empty()) {
+                    self.text(alt);
+                }
+            }
+            "td" | "th" => {
+                self.children(element);
+                self.out.push(' ');
+            }
+            _ if BLOCK_TAGS.contains(&tag) => {
+                self.block_break();
+                self.children(element);
+                self.block_break();
+            }
+            _ => self.children(element),
+        }
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Trim every line and keep at most one blank line between blocks
+fn tidy(text: &str) -> String {
+    let mut out = String::new();
+    let mut blank = false;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() {
+            blank = !out.is_empty();
+            continue;
+        }
+        if blank {
+            out.push('\n');
+            blank = false;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.trim_end().to_string()
+}
+
+/// (allow, path pattern)
+type RobotsRule = (bool, String);
+
+/// The `Allow`/`Disallow` rules of the robots.txt group that applies to us
+#[derive(Debug, Default)]
+struct RobotsRules {
+    rules: Vec<RobotsRule>,
+}
+
+impl RobotsRules {
+    /// Use the group naming `agent`, else the `*` group
+    fn parse(body: &str, agent: &str) -> Self {
+        let agent = agent.to_ascii_lowercase();
+        let mut groups: Vec<(Vec<String>, Vec<RobotsRule>)> = Vec::new();
+        let mut in_rules = false;
+
+        for line in body.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else { continue };
+            let (key, value) = (key.trim().to_ascii_lowercase(), value.trim());
+            match key.as_str() {
+                "user-agent" => {
+                    // A user-agent line after rules starts a new group
+                    if in_rules || groups.is_empty() {
+                        groups.push((Vec::new(), Vec::new()));
+                        in_rules = false;
+                    }
+                    if let Some((agents, _)) = groups.last_mut() {
+                        agents.push(value.to_ascii_lowercase());
+                    }
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    // An empty Disallow allows everything
+                    if let (Some((_, rules)), false) = (groups.last_mut(), value.is_empty()) {
+                        rules.push((key == "allow", value.to_string()));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // `User-agent` names a product token (`rust_agency`, maybe with a
+        // version); it must be ours exactly
+        let names_us = |a: &String| a.split('/').next().unwrap_or_default().trim() == agent;
+        let specific = groups.iter().find(|(agents, _)| agents.iter().any(names_us));
+        let wildcard = groups.iter().find(|(agents, _)| agents.iter().any(|a| a == "*"));
+        Self { rules: specific.or(wildcard).map(|(_, rules)| rules.clone()).unwrap_or_default() }
+    }
+
+    /// The longest matching rule decides; `Allow` wins ties
+    fn allows(&self, path: &str) -> bool {
+        let decisive = self.rules
+            .iter()
+            .filter(|(_, pattern)| robots_match(pattern, path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow));
+        match decisive {
+            Some((allow, _)) => *allow,
+            None => true,
+        }
+    }
+}
+
+/// robots.txt path patterns: prefix match with `*` wildcards and a `$` end anchor
+fn robots_match(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+    let regex = format!(
+        "^{}{}",
+        pattern.split('*').map(regex::escape).collect::<Vec<_>>().join(".*"),
+        if anchored { "$" } else { "" }
+    );
+    regex::Regex::new(&regex).map(|re| re.is_match(path)).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_robots_groups_and_precedence() {
+        let robots = "User-agent: *\nDisallow: /private\nAllow: /private/open\n\nUser-agent: rust_agency\nDisallow: /agents-only-block\nDisallow: /*.pdf$\n";
+        let ours = RobotsRules::parse(robots, ROBOTS_AGENT);
+        assert!(ours.allows("/private"), "our own group replaces the * group");
+        assert!(!ours.allows("/agents-only-block/x"));
+        assert!(!ours.allows("/files/report.pdf"));
+        assert!(ours.allows("/files/report.pdf?download=1"));
+
+        // Only our exact product token selects our group
+        let short = RobotsRules::parse("User-agent: rust\nDisallow: /\n", ROBOTS_AGENT);
+        assert!(short.allows("/page"));
+        assert!(!RobotsRules::parse("User-agent: Rust_Agency/2.0\nDisallow: /\n", ROBOTS_AGENT).allows("/page"));
+
+        let others = RobotsRules::parse(robots, "otherbot");
+        assert!(!others.allows("/private/secret"));
+        assert!(others.allows("/private/open/page"), "longer Allow wins");
+        assert!(RobotsRules::parse("User-agent: *\nDisallow:\n", "x").allows("/anything"));
+    }
+
+    #[test]
+    fn test_internal_addresses_are_refused() {
+        for internal in ["127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "100.64.0.1", "0.0.0.0", "::1", "fd00::1", "fe80::1", "::ffff:127.0.0.1"] {
+            assert!(is_internal_address(internal.parse().unwrap()), "{}", internal);
+        }
+        for public in ["93.184.216.34", "2606:2800:220:1::1"] {
+            assert!(!is_internal_address(public.parse().unwrap()), "{}", public);
+        }
+
+        for url in ["http://169.254.169.254/latest/meta-data/", "http://[::1]:8080/", "http://localhost/admin", "file:///etc/passwd"] {
+            assert!(check_target(&Url::parse(url).unwrap(), false).is_err(), "{}", url);
+        }
+        assert!(check_target(&Url::parse("https://example.com/page").unwrap(), false).is_ok());
+        assert!(check_target(&Url::parse("http://127.0.0.1:8080/").unwrap(), true).is_ok());
+        assert!(check_target(&Url::parse("file:///etc/passwd").unwrap(), true).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_names_resolving_to_internal_addresses_are_refused() {
+        let Ok(resolved) = PublicResolver.resolve("localhost".parse().unwrap()).await else { return };
+        panic!("localhost resolved to {:?}", resolved.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_readable_extraction() {
+        let html = r#"<html><head><title>Deploy Guide</title><script>var x = 1;</script></head><body>
+            <nav><a href="/home">Home</a></nav>
+            <article>
+              <h1>Deploying</h1>
+              <p>Run   <code>deploy.sh</code> from the
+                 <a href="/docs/ops">ops docs</a>.</p>
+              <ul><li>Build</li><li>Ship</li></ul>
+              <pre>cargo build --release</pre>
+            </article>
+            <footer>Copyright</footer></body></html>"#;
+        let base = Url::parse("https://example.com/guide").unwrap();
+
+        let page = Readable::extract(html, &base, true);
+        assert_eq!(page.title, "Deploy Guide");
+        assert_eq!(
+            page.content,
+            "# Deploying\n\nRun `deploy.sh` from the [ops docs](https://example.com/docs/ops).\n\n- Build\n- Ship\n\n```\ncargo build --release\n```"
+        );
+        assert_eq!(page.links, vec![("ops docs".to_string(), "https://example.com/docs/ops".to_string())]);
+
+        let text = Readable::extract(html, &base, false);
+        assert!(text.content.starts_with("Deploying\n\nRun deploy.sh from the ops docs."), "{}", text.content);
+    }
+}
@@ -1,88 +1,242 @@
 //! Web Search Tool
-//! 
-//! Performs web searches using DuckDuckGo HTML API (no API key required).
+//!
+//! Performs web searches through pluggable `SearchBackend`s: a self-hosted
+//! SearxNG instance, the Brave or Tavily APIs, and the DuckDuckGo HTML page
+//! (no API key required). Backends are tried in order until one answers.
 
 use anyhow::Context;
 use async_trait::async_trait;
 use reqwest::Client;
 use serde_json::{json, Value};
+use std::sync::Arc;
 use tracing::{debug, warn};
 
 use crate::agent::{AgentResult, AgentError};
-use super::{Tool, ToolOutput};
+use super::{CachePolicy, Tool, ToolOutput};
 
-/// Web search tool using DuckDuckGo
-pub struct WebSearchTool {
+const USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    pub title: String,
+    pub snippet: String,
+    pub url: String,
+}
+
+/// A source of web search results
+#[async_trait]
+pub trait SearchBackend: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Up to `max_results` results; an empty list means the backend found nothing
+    async fn search(&self, query: &str, max_results: usize) -> anyhow::Result<Vec<SearchResult>>;
+}
+
+fn default_client() -> Client {
+    Client::builder()
+        .user_agent(USER_AGENT)
+        .timeout(std::time::Duration::from_secs(20))
+        .build()
+        .unwrap_or_default()
+}
+
+/// Read `[title, url, snippet]` fields from each object of a JSON result list
+fn results_from_json(items: Option<&Vec<Value>>, fields: [&str; 3], max_results: usize) -> Vec<SearchResult> {
+    let [title, url, snippet] = fields;
+    items
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| {
+                    let url = item[url].as_str()?.to_string();
+                    Some(SearchResult {
+                        title: item[title].as_str().unwrap_or(&url).to_string(),
+                        snippet: item[snippet].as_str().unwrap_or_default().trim().to_string(),
+                        url,
+                    })
+                })
+                .take(max_results)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Self-hosted SearxNG instance (requires `json` in its `search.formats`)
+pub struct SearxngBackend {
     client: Client,
+    base_url: String,
 }
 
-impl WebSearchTool {
-    pub fn new() -> Self {
+impl SearxngBackend {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self { client: default_client(), base_url: base_url.into().trim_end_matches('/').to_string() }
+    }
+}
+
+#[async_trait]
+impl SearchBackend for SearxngBackend {
+    fn name(&self) -> &str {
+        "searxng"
+    }
+
+    async fn search(&self, query: &str, max_results: usize) -> anyhow::Result<Vec<SearchResult>> {
+        let body: Value = self.client
+            .get(format!("{}/search", self.base_url))
+            .query(&[("q", query), ("format", "json")])
+            .send()
+            .await
+            .context("Failed to reach SearxNG")?
+            .error_for_status()?
+            .json()
+            .await
+            .context("SearxNG returned invalid JSON")?;
+        Ok(results_from_json(body["results"].as_array(), ["title", "url", "content"], max_results))
+    }
+}
+
+/// Brave Search API (`BRAVE_API_KEY`)
+pub struct BraveBackend {
+    client: Client,
+    api_key: String,
+    base_url: String,
+}
+
+impl BraveBackend {
+    pub fn new(api_key: impl Into<String>) -> Self {
         Self {
-            client: Client::builder()
-                .user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36")
-                .build()
-                .unwrap_or_default(),
+            client: default_client(),
+            api_key: api_key.into(),
+            base_url: "https://api.search.brave.com/res/v1/web/search".to_string(),
         }
     }
 
-    async fn search_ddg(&self, query: &str, num_results: usize) -> anyhow::Result<Vec<SearchResult>> {
-        // Use DuckDuckGo HTML search (more reliable than API for simple uses)
-        let url = format!(
-            "https://html.duckduckgo.com/html/?q={}",
-            urlencoding::encode(query)
-        );
+    /// Point at a compatible endpoint (proxies, test servers)
+    pub fn with_base_url(mut self, url: impl Into<String>) -> Self {
+        self.base_url = url.into();
+        self
+    }
+}
 
-        debug!("Searching DuckDuckGo: {}", query);
+#[async_trait]
+impl SearchBackend for BraveBackend {
+    fn name(&self) -> &str {
+        "brave"
+    }
 
-        let response = self.client
-            .get(&url)
+    async fn search(&self, query: &str, max_results: usize) -> anyhow::Result<Vec<SearchResult>> {
+        let body: Value = self.client
+            .get(&self.base_url)
+            .query(&[("q", query), ("count", &max_results.to_string())])
+            .header("X-Subscription-Token", &self.api_key)
+            .header("Accept", "application/json")
             .send()
             .await
-            .context("Failed to send search request")?;
+            .context("Failed to reach Brave Search")?
+            .error_for_status()?
+            .json()
+            .await
+            .context("Brave Search returned invalid JSON")?;
+        Ok(results_from_json(body["web"]["results"].as_array(), ["title", "url", "description"], max_results))
+    }
+}
 
-        let html = response.text().await.context("Failed to read response")?;
-        
-        // Parse results from HTML (simple extraction)
-        let results = self.parse_ddg_html(&html, num_results);
-        
-        Ok(results)
+/// Tavily search API (`TAVILY_API_KEY`)
+pub struct TavilyBackend {
+    client: Client,
+    api_key: String,
+    base_url: String,
+}
+
+impl TavilyBackend {
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            client: default_client(),
+            api_key: api_key.into(),
+            base_url: "https://api.tavily.com/search".to_string(),
+        }
+    }
+
+    /// Point at a compatible endpoint (proxies, test servers)
+    pub fn with_base_url(mut self, url: impl Into<String>) -> Self {
+        self.base_url = url.into();
+        self
+    }
+}
+
+#[async_trait]
+impl SearchBackend for TavilyBackend {
+    fn name(&self) -> &str {
+        "tavily"
+    }
+
+    async fn search(&self, query: &str, max_results: usize) -> anyhow::Result<Vec<SearchResult>> {
+        let body: Value = self.client
+            .post(&self.base_url)
+            .bearer_auth(&self.api_key)
+            .json(&json!({ "api_key": self.api_key, "query": query, "max_results": max_results }))
+            .send()
+            .await
+            .context("Failed to reach Tavily")?
+            .error_for_status()?
+            .json()
+            .await
+            .context("Tavily returned invalid JSON")?;
+        Ok(results_from_json(body["results"].as_array(), ["title", "url", "content"], max_results))
+    }
+}
+
+/// DuckDuckGo HTML results page (no API key required)
+pub struct DuckDuckGoBackend {
+    client: Client,
+    base_url: String,
+}
+
+impl DuckDuckGoBackend {
+    pub fn new() -> Self {
+        Self { client: default_client(), base_url: "https://html.duckduckgo.com/html/".to_string() }
     }
 
-    fn parse_ddg_html(&self, html: &str, max_results: usize) -> Vec<SearchResult> {
+    /// Point at a compatible endpoint (proxies, test servers)
+    pub fn with_base_url(mut self, url: impl Into<String>) -> Self {
+        self.base_url = url.into();
+        self
+    }
+
+    fn parse_html(html: &str, max_results: usize) -> Vec<SearchResult> {
         let mut results = Vec::new();
-        
+
         // Simple HTML parsing - look for result snippets
         // DuckDuckGo HTML uses class="result__snippet" for snippets
         // and class="result__a" for links
-        
+
         let snippet_pattern = regex::Regex::new(r#"class="result__snippet"[^>]*>([^<]+)"#).ok();
-        let title_pattern = regex::Regex::new(r#"class="result__a"[^>]*>([^<]+)"#).ok();
-        let url_pattern = regex::Regex::new(r#"class="result__url"[^>]*>([^<]+)"#).ok();
+        let title_pattern = regex::Regex::new(r#"<a\s[^>]*class="result__a"[^>]*>([^<]+)"#).ok();
+        let href_pattern = regex::Regex::new(r#"href="([^"]*)""#).ok();
 
-        if let (Some(snippet_re), Some(title_re), Some(url_re)) = 
-            (snippet_pattern, title_pattern, url_pattern) 
+        if let (Some(snippet_re), Some(title_re), Some(href_re)) =
+            (snippet_pattern, title_pattern, href_pattern)
         {
             let snippets: Vec<_> = snippet_re.captures_iter(html).collect();
             let titles: Vec<_> = title_re.captures_iter(html).collect();
-            let urls: Vec<_> = url_re.captures_iter(html).collect();
 
             let count = snippets.len().min(titles.len()).min(max_results);
-            
+
             for i in 0..count {
                 let title = titles.get(i)
                     .and_then(|c| c.get(1))
                     .map(|m| html_escape::decode_html_entities(m.as_str()).to_string())
                     .unwrap_or_default();
-                
+
                 let snippet = snippets.get(i)
                     .and_then(|c| c.get(1))
                     .map(|m| html_escape::decode_html_entities(m.as_str()).to_string())
                     .unwrap_or_default();
-                
-                let url = urls.get(i)
+
+                // The title anchor links through DuckDuckGo's redirector
+                let url = titles.get(i)
+                    .and_then(|c| href_re.captures(c.get(0)?.as_str()))
                     .and_then(|c| c.get(1))
-                    .map(|m| m.as_str().trim().to_string())
+                    .and_then(|m| Self::result_url(&html_escape::decode_html_entities(m.as_str())))
                     .unwrap_or_default();
 
                 if !title.is_empty() && !snippet.is_empty() {
@@ -91,32 +245,110 @@ impl WebSearchTool {
             }
         }
 
-        // If HTML parsing failed, return a simple acknowledgment
-        if results.is_empty() {
-            warn!("Could not parse DuckDuckGo results, returning placeholder");
-            results.push(SearchResult {
-                title: "Search Completed".to_string(),
-                snippet: format!("Search was performed but results could not be parsed. Query: {}", 
-                    html.len()),
-                url: String::new(),
-            });
-        }
-
         results
     }
+
+    /// Target of a result link: the `uddg` parameter of a `/l/?uddg=...`
+    /// redirect, or the href itself when it already is an http(s) URL
+    fn result_url(href: &str) -> Option<String> {
+        let base = url::Url::parse("https://duckduckgo.com/").ok()?;
+        let link = base.join(href.trim()).ok()?;
+        let target = match link.query_pairs().find(|(key, _)| key == "uddg") {
+            Some((_, target)) => url::Url::parse(&target).ok()?,
+            None if link.host_str() != base.host_str() => link,
+            None => return None,
+        };
+        matches!(target.scheme(), "http" | "https").then(|| target.to_string())
+    }
 }
 
-impl Default for WebSearchTool {
+impl Default for DuckDuckGoBackend {
     fn default() -> Self {
         Self::new()
     }
 }
 
-#[derive(Debug, Clone)]
-struct SearchResult {
-    title: String,
-    snippet: String,
-    url: String,
+#[async_trait]
+impl SearchBackend for DuckDuckGoBackend {
+    fn name(&self) -> &str {
+        "duckduckgo"
+    }
+
+    async fn search(&self, query: &str, max_results: usize) -> anyhow::Result<Vec<SearchResult>> {
+        let html = self.client
+            .get(&self.base_url)
+            .query(&[("q", query)])
+            .send()
+            .await
+            .context("Failed to send search request")?
+            .error_for_status()?
+            .text()
+            .await
+            .context("Failed to read response")?;
+
+        let results = Self::parse_html(&html, max_results);
+        if results.is_empty() && html.contains("result__") {
+            // Results were there but the markup changed under us
+            anyhow::bail!("Could not parse DuckDuckGo results ({} bytes of HTML)", html.len());
+        }
+        Ok(results)
+    }
+}
+
+/// Web search tool over one or more backends
+pub struct WebSearchTool {
+    backends: Vec<Arc<dyn SearchBackend>>,
+}
+
+impl WebSearchTool {
+    /// Backends configured by the environment (`AGENCY_SEARXNG_URL`,
+    /// `BRAVE_API_KEY`, `TAVILY_API_KEY`), with DuckDuckGo as the last resort
+    pub fn new() -> Self {
+        let mut backends: Vec<Arc<dyn SearchBackend>> = Vec::new();
+        if let Ok(url) = std::env::var("AGENCY_SEARXNG_URL") {
+            backends.push(Arc::new(SearxngBackend::new(url)));
+        }
+        if let Ok(key) = std::env::var("BRAVE_API_KEY") {
+            backends.push(Arc::new(BraveBackend::new(key)));
+        }
+        if let Ok(key) = std::env::var("TAVILY_API_KEY") {
+            backends.push(Arc::new(TavilyBackend::new(key)));
+        }
+        backends.push(Arc::new(DuckDuckGoBackend::new()));
+        Self { backends }
+    }
+
+    /// Replace the backends; they are tried in the given order
+    pub fn with_backends(mut self, backends: Vec<Arc<dyn SearchBackend>>) -> Self {
+        self.backends = backends;
+        self
+    }
+
+    /// First non-empty answer, with the name of the backend that gave it
+    async fn search(&self, query: &str, max_results: usize) -> anyhow::Result<(String, Vec<SearchResult>)> {
+        let mut errors = Vec::new();
+        for backend in &self.backends {
+            debug!("Searching {} for: {}", backend.name(), query);
+            match backend.search(query, max_results).await {
+                Ok(results) if !results.is_empty() => return Ok((backend.name().to_string(), results)),
+                Ok(_) => debug!("{} found nothing for '{}'", backend.name(), query),
+                Err(e) => {
+                    warn!("Search backend {} failed: {}", backend.name(), e);
+                    errors.push(format!("{}: {}", backend.name(), e));
+                }
+            }
+        }
+        if errors.len() == self.backends.len() {
+            anyhow::bail!("All search backends failed ({})", errors.join("; "));
+        }
+        Ok((String::new(), Vec::new()))
+    }
+}
+
+impl Default for WebSearchTool {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[async_trait]
@@ -126,7 +358,7 @@ impl Tool for WebSearchTool {
     }
 
     fn description(&self) -> String {
-        "Search the web for current information, news, or technical documentation. \n        Returns a list of search results with titles, snippets, and URLs.".to_string()
+        "Search the web for current information, news, or technical documentation. \n        Returns a list of search results with titles, snippets, and URLs. Use `web_fetch` to read a result.".to_string()
     }
 
     fn parameters(&self) -> Value {
@@ -153,23 +385,32 @@ impl Tool for WebSearchTool {
             "status": "constrained",
             "environment": "external internet",
             "network": "required (active internet connection)",
+            "backends": self.backends.iter().map(|b| b.name()).collect::<Vec<_>>(),
             "side_effects": "none",
             "data_freshness": "real-time"
         })
     }
 
+    fn cache_policy(&self, _params: &Value) -> CachePolicy {
+        CachePolicy::Ttl(std::time::Duration::from_secs(600))
+    }
+
     async fn execute(&self, params: Value) -> AgentResult<ToolOutput> {
         let query = params["query"]
             .as_str()
             .ok_or_else(|| AgentError::Validation("Missing required parameter: query".to_string()))?;
-        
-        let num_results = params["num_results"]
+
+        let num_results = params["max_results"]
             .as_u64()
             .unwrap_or(5)
-            .min(10) as usize;
+            .clamp(1, 10) as usize;
 
-        match self.search_ddg(query, num_results).await {
-            Ok(results) => {
+        match self.search(query, num_results).await {
+            Ok((_, results)) if results.is_empty() => Ok(ToolOutput::success(
+                json!({ "query": query, "num_results": 0, "results": [] }),
+                format!("No results found for '{}'", query),
+            )),
+            Ok((backend, results)) => {
                 let formatted = results
                     .iter()
                     .enumerate()
@@ -195,6 +436,7 @@ impl Tool for WebSearchTool {
                 Ok(ToolOutput::success(
                     json!({
                         "query": query,
+                        "backend": backend,
                         "num_results": results.len(),
                         "results": results.iter().map(|r| json!({
                             "title": r.title,
@@ -212,3 +454,27 @@ impl Tool for WebSearchTool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duckduckgo_result_urls_are_decoded() {
+        let html = r#"
+            <a rel="nofollow" class="result__a" href="//duckduckgo.com/l/?uddg=https%3A%2F%2Fwww.rust-lang.org%2Flearn%3Fa%3D1&amp;rut=abc">Learn Rust</a>
+            <a class="result__url" href="//duckduckgo.com/l/?uddg=x">www.rust-lang.org/learn</a>
+            <a class="result__snippet">The official book.</a>
+            <a class="result__a" href="https://doc.rust-lang.org/std/">std</a>
+            <a class="result__snippet">Standard library docs.</a>
+            <a class="result__a" href="/l/?uddg=javascript%3Aalert(1)">Bad</a>
+            <a class="result__snippet">Not a web page.</a>
+        "#;
+        let results = DuckDuckGoBackend::parse_html(html, 10);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].title, "Learn Rust");
+        assert_eq!(results[0].url, "https://www.rust-lang.org/learn?a=1");
+        assert_eq!(results[1].url, "https://doc.rust-lang.org/std/");
+        assert_eq!(results[2].url, "");
+    }
+}
@@ -0,0 +1,321 @@
+//! Linux Confinement (Bubblewrap)
+//!
+//! Linux counterpart of the Seatbelt `TOOL_SANDBOX_POLICY`. The child runs
+//! under `bwrap` in fresh user, mount, PID, IPC and UTS namespaces, plus a
+//! network namespace with no interfaces unless the policy allows network
+//! access. The host root is mounted read-only, `/tmp` is a private tmpfs, and
+//! only the paths the policy makes writable are bound read-write.
+//!
+//! A seccomp filter (handed to `bwrap --seccomp`) makes kernel-administration,
+//! tracing and namespace syscalls fail with `EPERM`. CPU, memory and
+//! file-size rlimits are applied just before exec and inherited through
+//! `bwrap`; the process limit is set inside the sandbox, where it counts only
+//! the sandboxed processes rather than every process of the host user.
+
+use super::sandbox::SandboxPolicy;
+use anyhow::Context;
+use std::io::{Seek, Write};
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tokio::process::Command;
+
+/// Locate `bwrap`, honouring `AGENCY_BWRAP` for non-standard installs
+pub fn bwrap_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("AGENCY_BWRAP").map(PathBuf::from) {
+        return path.is_file().then_some(path);
+    }
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).map(|dir| dir.join("bwrap")).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .find(|p| p.is_file())
+}
+
+/// `bwrap`, if installed and able to create namespaces on this host (some
+/// distributions disable unprivileged user namespaces). Probed once.
+pub fn usable_bwrap() -> Option<&'static Path> {
+    static USABLE: OnceLock<Option<PathBuf>> = OnceLock::new();
+    USABLE
+        .get_or_init(|| {
+            let bwrap = bwrap_path()?;
+            let probe = std::process::Command::new(&bwrap)
+                .args(["--unshare-user", "--unshare-net", "--ro-bind", "/", "/", "true"])
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .status();
+            match probe {
+                Ok(status) if status.success() => Some(bwrap),
+                _ => {
+                    tracing::warn!("{:?} cannot create namespaces on this host; Linux sandbox disabled", bwrap);
+                    None
+                }
+            }
+        })
+        .as_deref()
+}
+
+/// `bwrap` arguments confining `program args` to `policy`
+pub fn bwrap_args(policy: &SandboxPolicy, program: &str, args: &[String]) -> Vec<String> {
+    let mut out: Vec<String> = [
+        "--die-with-parent",
+        "--new-session",
+        "--unshare-user",
+        "--unshare-ipc",
+        "--unshare-pid",
+        "--unshare-uts",
+        "--unshare-cgroup-try",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    if !policy.allow_network {
+        out.push("--unshare-net".to_string());
+    }
+
+    // Read-only view of the host, fresh /dev, /proc and private scratch /tmp
+    out.extend(["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc", "--tmpfs", "/tmp", "--setenv", "TMPDIR", "/tmp"].map(String::from));
+
+    // Writable binds come last so they override the read-only root
+    let workspace = resolve(&policy.workspace);
+    let writable = policy.writable_workspace.then(|| workspace.clone()).into_iter().chain(policy.writable.iter().map(|p| resolve(p)));
+    for path in writable.filter(|p| p.exists()) {
+        let p = path.to_string_lossy().to_string();
+        out.extend(["--bind".to_string(), p.clone(), p]);
+    }
+    out.extend(["--chdir".to_string(), workspace.to_string_lossy().to_string(), "--".to_string()]);
+
+    // The process limit is set by a shell inside the namespaces (bash spells
+    // it `-u`, dash `-p`), which then execs the program
+    let limit_processes = format!(
+        "ulimit -u {n} 2>/dev/null || ulimit -p {n} 2>/dev/null || exit 126; exec \"$0\" \"$@\"",
+        n = policy.max_processes
+    );
+    out.extend(["/bin/sh".to_string(), "-c".to_string(), limit_processes, program.to_string()]);
+    out.extend(args.iter().cloned());
+    out
+}
+
+fn resolve(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Syscalls the sandboxed program may not make: kernel administration,
+/// tracing other processes, and creating or joining namespaces
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+const DENIED_SYSCALLS: &[libc::c_long] = &[
+    libc::SYS_mount,
+    libc::SYS_umount2,
+    libc::SYS_pivot_root,
+    libc::SYS_swapon,
+    libc::SYS_swapoff,
+    libc::SYS_reboot,
+    libc::SYS_acct,
+    libc::SYS_kexec_load,
+    libc::SYS_kexec_file_load,
+    libc::SYS_init_module,
+    libc::SYS_finit_module,
+    libc::SYS_delete_module,
+    libc::SYS_ptrace,
+    libc::SYS_process_vm_readv,
+    libc::SYS_process_vm_writev,
+    libc::SYS_bpf,
+    libc::SYS_perf_event_open,
+    libc::SYS_userfaultfd,
+    libc::SYS_keyctl,
+    libc::SYS_add_key,
+    libc::SYS_request_key,
+    libc::SYS_unshare,
+    libc::SYS_setns,
+    libc::SYS_open_by_handle_at,
+];
+
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: u32 = 0xc000_003e;
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+/// Classic BPF program (as read by `bwrap --seccomp`) returning `EPERM` for
+/// `DENIED_SYSCALLS` and killing the process on a foreign syscall ABI
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+pub fn seccomp_program() -> Vec<u8> {
+    const LD_W_ABS: u16 = 0x20;
+    const JEQ_K: u16 = 0x15;
+    const JGE_K: u16 = 0x35;
+    const RET_K: u16 = 0x06;
+    const RET_ALLOW: u32 = 0x7fff_0000;
+    const RET_KILL_PROCESS: u32 = 0x8000_0000;
+    const RET_EPERM: u32 = 0x0005_0000 | libc::EPERM as u32;
+    // Offsets into `struct seccomp_data`
+    const NR: u32 = 0;
+    const ARCH: u32 = 4;
+
+    let mut program: Vec<(u16, u8, u8, u32)> = vec![
+        (LD_W_ABS, 0, 0, ARCH),
+        (JEQ_K, 1, 0, AUDIT_ARCH),
+        (RET_K, 0, 0, RET_KILL_PROCESS),
+        (LD_W_ABS, 0, 0, NR),
+    ];
+    if cfg!(target_arch = "x86_64") {
+        // x32 syscalls share the x86_64 arch value; refuse them all
+        program.extend([(JGE_K, 0, 1, 0x4000_0000), (RET_K, 0, 0, RET_EPERM)]);
+    }
+    for &nr in DENIED_SYSCALLS {
+        program.extend([(JEQ_K, 0, 1, nr as u32), (RET_K, 0, 0, RET_EPERM)]);
+    }
+    program.push((RET_K, 0, 0, RET_ALLOW));
+
+    let mut bytes = Vec::with_capacity(program.len() * 8);
+    for (code, jt, jf, k) in program {
+        bytes.extend(code.to_ne_bytes());
+        bytes.extend([jt, jf]);
+        bytes.extend(k.to_ne_bytes());
+    }
+    bytes
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+pub fn seccomp_program() -> Vec<u8> {
+    Vec::new()
+}
+
+/// Command running `program args` under `bwrap` with the policy's rlimits
+/// and the seccomp filter
+pub fn command(bwrap: &Path, program: &str, args: &[String], policy: &SandboxPolicy) -> anyhow::Result<Command> {
+    let mut command = Command::new(bwrap);
+
+    let program_bytes = seccomp_program();
+    let seccomp = if program_bytes.is_empty() {
+        tracing::warn!("No seccomp filter for this architecture; relying on namespaces alone");
+        None
+    } else {
+        let mut file = tempfile::tempfile().context("Failed to create the seccomp filter file")?;
+        file.write_all(&program_bytes)?;
+        file.rewind()?;
+        command.arg("--seccomp").arg(file.as_raw_fd().to_string());
+        Some(file)
+    };
+    command.args(bwrap_args(policy, program, args));
+
+    let limits = [
+        (libc::RLIMIT_CPU, policy.cpu_secs),
+        (libc::RLIMIT_DATA, policy.memory_bytes),
+        (libc::RLIMIT_FSIZE, policy.max_file_bytes),
+        (libc::RLIMIT_CORE, 0),
+    ];
+    // SAFETY: the closure only calls fcntl and setrlimit, which are
+    // async-signal-safe, on data copied before the fork. The filter file is
+    // owned by the closure, so its descriptor outlives the command.
+    unsafe {
+        command.pre_exec(move || {
+            if let Some(file) = &seccomp {
+                // Let `bwrap` inherit the filter descriptor
+                if libc::fcntl(file.as_raw_fd(), libc::F_SETFD, 0) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+            for (resource, value) in limits {
+                let limit = libc::rlimit { rlim_cur: value as libc::rlim_t, rlim_max: value as libc::rlim_t };
+                if libc::setrlimit(resource, &limit) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+    Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_args(policy: &SandboxPolicy) -> Vec<String> {
+        bwrap_args(policy, "python3", &["-c".to_string(), "print(1)".to_string()])
+    }
+
+    #[test]
+    fn test_args_mirror_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path().canonicalize().unwrap().to_string_lossy().to_string();
+        let policy = SandboxPolicy::isolated(dir.path());
+        let args = run_args(&policy);
+
+        assert!(args.contains(&"--unshare-net".to_string()));
+        let root = args.iter().position(|a| a == "--ro-bind").unwrap();
+        assert_eq!(&args[root..root + 3], ["--ro-bind", "/", "/"]);
+        let bind = args.iter().position(|a| a == "--bind").unwrap();
+        assert!(bind > root, "writable binds must follow the read-only root");
+        assert_eq!(&args[bind + 1..bind + 3], [workspace.as_str(), workspace.as_str()]);
+        assert_eq!(args.iter().filter(|a| *a == "--bind").count(), 1);
+        let exec = args.iter().position(|a| a == "--").unwrap();
+        assert_eq!(&args[exec + 1..exec + 3], ["/bin/sh", "-c"]);
+        assert!(args[exec + 3].contains(&format!("ulimit -u {}", policy.max_processes)));
+        assert_eq!(&args[exec + 4..], ["python3", "-c", "print(1)"]);
+
+        let networked = run_args(&policy.clone().with_network(true));
+        assert!(!networked.contains(&"--unshare-net".to_string()));
+
+        // The default policy only reads the workspace; /tmp is private scratch
+        let read_only = run_args(&SandboxPolicy::default().with_workspace(dir.path()));
+        assert!(!read_only.contains(&"--bind".to_string()));
+        let tmp = read_only.iter().position(|a| a == "--tmpfs").unwrap();
+        assert_eq!(read_only[tmp + 1], "/tmp");
+    }
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    #[test]
+    fn test_seccomp_program_denies_listed_syscalls() {
+        let program = seccomp_program();
+        assert_eq!(program.len() % 8, 0);
+        let instruction = |i: usize| {
+            let at = i * 8;
+            (u16::from_ne_bytes([program[at], program[at + 1]]), u32::from_ne_bytes(program[at + 4..at + 8].try_into().unwrap()))
+        };
+        assert_eq!(instruction(1).1, AUDIT_ARCH);
+        assert_eq!(instruction(program.len() / 8 - 1), (0x06, 0x7fff_0000), "allows by default");
+        let checked: Vec<u32> = (0..program.len() / 8).map(instruction).filter(|i| i.0 == 0x15).map(|i| i.1).collect();
+        assert!(checked.contains(&(libc::SYS_ptrace as u32)));
+        assert!(checked.contains(&(libc::SYS_unshare as u32)));
+    }
+
+    #[test]
+    fn test_refuses_to_run_unconfined_by_default() {
+        if usable_bwrap().is_some() || crate::utils::sandbox::unsandboxed_allowed() {
+            return;
+        }
+        let result = crate::utils::sandbox::confined_command("true", &[], &SandboxPolicy::default());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_partial_policy_keeps_defaults() {
+        // As written in a dynamic tool's metadata file
+        let policy: SandboxPolicy = serde_json::from_value(serde_json::json!({ "allow_network": true })).unwrap();
+        assert!(policy.allow_network);
+        assert_eq!(policy.cpu_secs, SandboxPolicy::default().cpu_secs);
+        assert!(!policy.writable_workspace);
+        assert!(policy.writable.is_empty());
+    }
+
+    /// Exercises the real backend where bubblewrap and user namespaces are available
+    #[tokio::test]
+    async fn test_confinement_when_available() {
+        let Some(bwrap) = usable_bwrap() else {
+            return;
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let policy = SandboxPolicy::isolated(dir.path());
+        let script = "echo ok > inside.txt && (touch /etc/agency_probe 2>/dev/null && echo rw-root || echo ro-root) && ulimit -t";
+        let output = command(bwrap, "sh", &["-c".to_string(), script.to_string()], &policy)
+            .unwrap()
+            .output()
+            .await
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("ro-root"), "{}", stdout);
+        assert!(stdout.contains(&policy.cpu_secs.to_string()), "{}", stdout);
+        assert!(dir.path().join("inside.txt").exists());
+    }
+}
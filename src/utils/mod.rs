@@ -1,5 +1,7 @@
 //! Utils Module
 pub mod sandbox;
+#[cfg(target_os = "linux")]
+pub mod linux_sandbox;
 pub mod hardening;
 pub mod otel;
 pub mod toon;
@@ -1,6 +1,10 @@
 //! Sandbox Utilities (Seatbelt)
 //! 
-//! Centralizes macOS Seatbelt (sandbox-exec) policies and helpers.
+//! Centralizes macOS Seatbelt (sandbox-exec) policies and helpers, and the
+//! portable `SandboxPolicy` used by the Linux backend (`linux_sandbox`).
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
 pub const TOOL_SANDBOX_POLICY: &str = r#"
 (version 1)
@@ -30,3 +34,110 @@ pub const TOOL_SANDBOX_POLICY: &str = r#"
 
 (allow sysctl-read)
 "#;
+
+/// Portable description of what a confined tool process may do.
+///
+/// Mirrors `TOOL_SANDBOX_POLICY` for backends that are configured from data
+/// rather than a Seatbelt profile (the Linux backend), except that network
+/// access is denied and the workspace is read-only unless a tool opts in.
+/// A private, empty `/tmp` is always available as scratch space.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SandboxPolicy {
+    /// Directory the process starts in
+    pub workspace: PathBuf,
+    /// Whether `workspace` is bound read-write
+    pub writable_workspace: bool,
+    /// Further read-write paths; everything else is read-only
+    pub writable: Vec<PathBuf>,
+    pub allow_network: bool,
+    /// RLIMIT_CPU, in seconds of CPU time
+    pub cpu_secs: u64,
+    /// RLIMIT_DATA, in bytes (heap and private mappings)
+    pub memory_bytes: u64,
+    /// RLIMIT_NPROC, set inside the sandbox so it only counts sandboxed processes
+    pub max_processes: u64,
+    /// RLIMIT_FSIZE, in bytes
+    pub max_file_bytes: u64,
+}
+
+impl Default for SandboxPolicy {
+    /// Current directory as a read-only workspace, private scratch `/tmp`, no network
+    fn default() -> Self {
+        Self {
+            workspace: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+            writable_workspace: false,
+            writable: Vec::new(),
+            allow_network: false,
+            cpu_secs: 60,
+            memory_bytes: 2 * 1024 * 1024 * 1024,
+            max_processes: 256,
+            max_file_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+impl SandboxPolicy {
+    /// Policy with `dir` as the only writable location
+    pub fn isolated(dir: impl Into<PathBuf>) -> Self {
+        Self { workspace: dir.into(), writable_workspace: true, writable: Vec::new(), ..Self::default() }
+    }
+
+    pub fn with_workspace(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.workspace = dir.into();
+        self
+    }
+
+    pub fn with_writable_workspace(mut self, writable: bool) -> Self {
+        self.writable_workspace = writable;
+        self
+    }
+
+    /// Also bind `path` read-write
+    pub fn with_writable(mut self, path: impl Into<PathBuf>) -> Self {
+        self.writable.push(path.into());
+        self
+    }
+
+    pub fn with_network(mut self, allow: bool) -> Self {
+        self.allow_network = allow;
+        self
+    }
+
+    pub fn with_cpu_secs(mut self, secs: u64) -> Self {
+        self.cpu_secs = secs;
+        self
+    }
+
+    pub fn with_memory_bytes(mut self, bytes: u64) -> Self {
+        self.memory_bytes = bytes;
+        self
+    }
+}
+
+/// Whether tools may run unconfined on hosts without a sandbox backend
+/// (`AGENCY_ALLOW_UNSANDBOXED=1`). Off by default.
+pub fn unsandboxed_allowed() -> bool {
+    std::env::var("AGENCY_ALLOW_UNSANDBOXED").is_ok_and(|v| v == "1")
+}
+
+/// Build a command running `program` confined by `policy` on hosts without
+/// Seatbelt. Linux uses the bubblewrap backend when it works on this host.
+/// Without a backend the command is refused, unless running unconfined was
+/// explicitly allowed (`unsandboxed_allowed`).
+#[cfg(not(target_os = "macos"))]
+pub fn confined_command(program: &str, args: &[String], policy: &SandboxPolicy) -> anyhow::Result<tokio::process::Command> {
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(bwrap) = super::linux_sandbox::usable_bwrap() {
+            return super::linux_sandbox::command(bwrap, program, args, policy);
+        }
+    }
+    if !unsandboxed_allowed() {
+        anyhow::bail!("No sandbox backend available (install bubblewrap and enable unprivileged user namespaces); refusing to run {} unconfined. Set AGENCY_ALLOW_UNSANDBOXED=1 to allow it.", program);
+    }
+    tracing::warn!("No sandbox backend on this host. Running {} unconfined (AGENCY_ALLOW_UNSANDBOXED=1).", program);
+    let mut command = tokio::process::Command::new(program);
+    command.args(args).current_dir(&policy.workspace);
+    Ok(command)
+}
@@ -46,7 +46,7 @@ async fn test_crystallizer_wiring() {
 #[tokio::test]
 async fn test_runtime_isolation() {
     // Verify that the runtime module is accessible and compiles
-    let mut runtime = rust_agency::runtime::wasm::WasmRuntime::new();
+    let runtime = rust_agency::runtime::wasm::WasmRuntime::new();
     // We can't easily test execution without a wasm file, but instantiation proves the dependency links are correct.
     assert!(std::any::type_name_of_val(&runtime).contains("WasmRuntime"));
 }
\ No newline at end of file
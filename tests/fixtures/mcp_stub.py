@@ -0,0 +1,84 @@
+#!/usr/bin/env python3
+"""Minimal stdio MCP server used by tests/mcp_client.rs."""
+import json
+import sys
+
+tools = ["echo", "add_tool", "crash", "roots"]
+
+
+def send(message):
+    sys.stdout.write(json.dumps(message) + "\n")
+    sys.stdout.flush()
+
+
+def read():
+    line = sys.stdin.readline()
+    if not line:
+        sys.exit(0)
+    return json.loads(line)
+
+
+def tool(name):
+    return {"name": name, "description": "stub " + name, "inputSchema": {"type": "object"}}
+
+
+def text(value):
+    return {"content": [{"type": "text", "text": value}]}
+
+
+def call_tool(name, args):
+    if name == "echo":
+        return text(args.get("text", ""))
+    if name == "add_tool":
+        tools.append("extra")
+        send({"jsonrpc": "2.0", "method": "notifications/tools/list_changed"})
+        return text("added")
+    if name == "crash":
+        sys.exit(1)
+    if name == "roots":
+        send({"jsonrpc": "2.0", "id": "roots-1", "method": "roots/list"})
+        while True:
+            reply = read()
+            if reply.get("id") == "roots-1":
+                return text(" ".join(r["uri"] for r in reply["result"]["roots"]))
+    raise KeyError(name)
+
+
+def handle(method, params):
+    if method == "initialize":
+        return {
+            "protocolVersion": params.get("protocolVersion"),
+            "capabilities": {"tools": {"listChanged": True}, "resources": {}, "prompts": {}},
+            "serverInfo": {"name": "stub", "version": "0.0.0"},
+        }
+    if method == "tools/list":
+        # Two pages to exercise cursors
+        if params.get("cursor") == "2":
+            return {"tools": [tool(n) for n in tools[1:]]}
+        return {"tools": [tool(tools[0])], "nextCursor": "2"}
+    if method == "tools/call":
+        return call_tool(params["name"], params.get("arguments") or {})
+    if method == "resources/list":
+        return {"resources": [
+            {"uri": "memo://deploy-guide", "name": "Deployment guide", "description": "How to deploy the service"},
+            {"uri": "memo://style", "name": "Style guide"},
+        ]}
+    if method == "resources/read":
+        return {"contents": [{"uri": params["uri"], "mimeType": "text/plain", "text": "Run deploy.sh, then check /health."}]}
+    if method == "prompts/list":
+        return {"prompts": [{"name": "code_review", "description": "Review a diff",
+                             "arguments": [{"name": "diff", "required": True}]}]}
+    if method == "prompts/get":
+        diff = params["arguments"]["diff"]
+        return {"messages": [{"role": "user", "content": {"type": "text", "text": "Review this diff carefully: " + diff}}]}
+    raise KeyError(method)
+
+
+while True:
+    message = read()
+    if "id" not in message:
+        continue
+    try:
+        send({"jsonrpc": "2.0", "id": message["id"], "result": handle(message["method"], message.get("params") or {})})
+    except KeyError as missing:
+        send({"jsonrpc": "2.0", "id": message["id"], "error": {"code": -32601, "message": "unknown: %s" % missing}})
@@ -0,0 +1,154 @@
+//! Golden conversation through `Supervisor::handle`: routing → ReAct → tool
+//! → Pareto selection, offline.
+//!
+//! The supervisor runs against a `ReplayProvider` serving the checked-in
+//! `tests/fixtures/golden/bridge_wind.json`. Any prompt the fixture does not
+//! contain fails the test with the unexpected prompt and its closest match,
+//! and so does a missing fixture.
+//!
+//! To (re-)record after an intentional prompt change, run
+//! `AGENCY_RECORD_GOLDEN=1 cargo test --test golden_pipeline`; the scripted
+//! backend below then answers and `RecordingProvider` rewrites the fixture.
+//! Review the diff and commit it.
+
+use async_trait::async_trait;
+use futures_util::stream::BoxStream;
+use rust_agency::agent::{
+    AgentResult, ChatMessage, ChatRole, GenerationOptions, LLMProvider, RecordingProvider, ReplayProvider,
+    TokenUsage, ToolCompletion,
+};
+use rust_agency::orchestrator::Supervisor;
+use rust_agency::tools::{Tool, ToolCall, ToolDefinition, ToolOutput, ToolRegistry};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+const QUERY: &str = "Why do suspension bridges sway more in strong wind than arch bridges, per bridges.org?";
+
+fn fixture_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/golden/bridge_wind.json")
+}
+
+/// Deterministic stand-in for a live model, used only when recording
+struct ScriptedBackend;
+
+#[async_trait]
+impl LLMProvider for ScriptedBackend {
+    async fn generate(&self, _model: &str, prompt: String, _system: Option<String>) -> anyhow::Result<String> {
+        if prompt.contains("classify(") {
+            return Ok(r#"{"agent": "reasoner", "memory": "no", "reason": "structural engineering explanation"}"#.to_string());
+        }
+        Ok("[ANSWER] Suspension decks are light and flexible.".to_string())
+    }
+
+    async fn generate_stream(&self, model: &str, prompt: String, system: Option<String>) -> anyhow::Result<BoxStream<'static, anyhow::Result<String>>> {
+        let text = self.generate(model, prompt, system).await?;
+        Ok(Box::pin(futures_util::stream::iter(vec![Ok(text)])))
+    }
+
+    fn supports_native_tools(&self) -> bool {
+        true
+    }
+
+    async fn chat_with_tools(&self, _model: &str, messages: Vec<ChatMessage>, _options: GenerationOptions, _tools: &[ToolDefinition]) -> anyhow::Result<ToolCompletion> {
+        let observation = messages.iter().rev().find(|m| m.role == ChatRole::Tool);
+        Ok(match observation {
+            None => ToolCompletion {
+                content: "⚡ I need stiffness figures for both bridge types.".to_string(),
+                tool_calls: vec![ToolCall { name: "bridge_facts".to_string(), parameters: json!({ "topic": "wind" }) }],
+                usage: TokenUsage::new(400, 20),
+            },
+            Some(obs) => ToolCompletion {
+                content: format!(
+                    "⚡ The facts explain the difference.\n🎯 Suspension bridges sway more because {}",
+                    obs.content.trim()
+                ),
+                tool_calls: Vec::new(),
+                usage: TokenUsage::new(450, 40),
+            },
+        })
+    }
+
+    fn get_lock(&self) -> Arc<Mutex<()>> {
+        Arc::new(Mutex::new(()))
+    }
+}
+
+/// Offline knowledge tool the agents consult; counts its calls
+struct BridgeFacts(Arc<AtomicUsize>);
+
+#[async_trait]
+impl Tool for BridgeFacts {
+    fn name(&self) -> String {
+        "bridge_facts".to_string()
+    }
+
+    fn description(&self) -> String {
+        "Look up structural facts about bridge types.".to_string()
+    }
+
+    fn parameters(&self) -> Value {
+        json!({ "type": "object", "properties": { "topic": { "type": "string" } }, "required": ["topic"] })
+    }
+
+    async fn execute(&self, _params: Value) -> AgentResult<ToolOutput> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Ok(ToolOutput::success_str(
+            "their decks hang from flexible cables with low torsional stiffness, while arches carry load in compression through a rigid rib.",
+        ))
+    }
+}
+
+/// Replay the checked-in fixture, or record it when explicitly asked to
+fn golden_provider() -> (Arc<dyn LLMProvider>, Option<Arc<ReplayProvider>>) {
+    let path = fixture_path();
+    if std::env::var("AGENCY_RECORD_GOLDEN").is_ok_and(|v| v == "1") {
+        eprintln!("Recording golden fixture to {:?}", path);
+        return (Arc::new(RecordingProvider::new(Arc::new(ScriptedBackend), path)), None);
+    }
+    let replay = ReplayProvider::load(&path).unwrap_or_else(|e| {
+        panic!("golden fixture unavailable ({:#}); record it with AGENCY_RECORD_GOLDEN=1 and commit it", e)
+    });
+    let replay = Arc::new(replay);
+    (replay.clone(), Some(replay))
+}
+
+#[tokio::test]
+async fn test_golden_routing_react_tool_pareto() {
+    let (provider, replay) = golden_provider();
+
+    // Everything the supervisor persists (task queue, LLM cache, history,
+    // PAI journal) lands in a scratch directory, with no project context
+    // files above it to leak into the prompts
+    let scratch = tempfile::tempdir().expect("scratch dir");
+    std::env::set_current_dir(scratch.path()).expect("enter scratch dir");
+    std::fs::create_dir_all("data").expect("identity dir");
+    std::env::set_var("AGENCY_TASK_DB", scratch.path().join("tasks.db"));
+    std::env::set_var("AGENCY_LLM_CACHE_DB", scratch.path().join("llm_cache.db"));
+    std::env::set_var("PAI_DIR", scratch.path().join("pai"));
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let tools = Arc::new(ToolRegistry::default());
+    tools.register_instance(BridgeFacts(calls.clone())).await;
+    let mut supervisor = Supervisor::new_with_provider(provider, tools).await;
+
+    let result = supervisor.handle(QUERY).await.expect("supervisor turn");
+
+    // Routing: no heuristic matches, so the LLM classifies; the URL marks the
+    // query high-complexity and the portfolio gains a second candidate, each
+    // running ReAct with a tool call before Pareto selection picks the answer
+    let report = supervisor.usage_report();
+    assert!(report.by_agent.contains_key("Router"), "routing call not metered: {}", report);
+    let candidates = report.by_agent.keys().filter(|agent| *agent != "Router").count();
+    assert_eq!(candidates, 2, "expected two candidates: {}", report);
+    assert!(calls.load(Ordering::SeqCst) >= 2, "candidates never used the tool");
+    assert!(result.success, "turn failed: {}", result.answer);
+    assert!(result.answer.contains("torsional stiffness"), "unexpected answer: {}", result.answer);
+    assert!(result.usage.total_tokens() > 0);
+
+    if let Some(replay) = replay {
+        assert!(replay.unused().is_empty(), "fixture has requests the pipeline no longer makes: {:#?}", replay.unused());
+    }
+}
@@ -0,0 +1,180 @@
+//! MCP client against local stub servers: a Python stdio server
+//! (tests/fixtures/mcp_stub.py) and an in-process streamable HTTP server.
+
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use futures::StreamExt;
+use rust_agency::tools::{McpServer, McpServerConfig, ToolCall, ToolRegistry};
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+fn call(name: &str, parameters: Value) -> ToolCall {
+    ToolCall { name: name.to_string(), parameters }
+}
+
+/// Poll until `check` holds, failing after five seconds
+async fn eventually<F, Fut>(what: &str, mut check: F)
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = bool>,
+{
+    for _ in 0..100 {
+        if check().await {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("timed out waiting for {}", what);
+}
+
+async fn has_tool(registry: &ToolRegistry, name: &str) -> bool {
+    registry.tool_names().await.iter().any(|n| n == name)
+}
+
+#[tokio::test]
+async fn test_stdio_server_capabilities_and_restart() {
+    if std::process::Command::new("python3").arg("--version").output().is_err() {
+        eprintln!("python3 not available; skipping");
+        return;
+    }
+    let stub = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/mcp_stub.py");
+    let server = McpServer::spawn("stub", "python3", &[stub.to_string()]).await.unwrap();
+    server.add_root("/work/project").await.unwrap();
+
+    let registry = ToolRegistry::default();
+    // Four tools across two pages, plus the prompt as a skill
+    assert_eq!(registry.register_mcp_server(server.clone()).await.unwrap(), 5);
+
+    let echoed = registry.execute(&call("stub__echo", json!({ "text": "hi" }))).await.unwrap();
+    assert_eq!(echoed.summary, "hi");
+
+    let roots = registry.execute(&call("stub__roots", json!({}))).await.unwrap();
+    assert_eq!(roots.summary, "file:///work/project");
+
+    let skill = registry.execute(&call("skill__stub__code_review", json!({ "diff": "+fn main() {}" }))).await.unwrap();
+    assert!(skill.summary.contains("Review this diff carefully: +fn main() {}"), "{}", skill.summary);
+    let missing = registry.execute(&call("skill__stub__code_review", json!({}))).await.unwrap();
+    assert!(!missing.success, "required prompt arguments are validated");
+
+    let hits = registry.mcp_resources().search("how do we deploy?", 2).await;
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].uri, "memo://deploy-guide");
+    assert!(hits[0].text.contains("deploy.sh"));
+
+    // tools/list_changed re-syncs the proxies
+    registry.execute(&call("stub__add_tool", json!({}))).await.unwrap();
+    eventually("the new tool to be registered", || has_tool(&registry, "stub__extra")).await;
+
+    // A crash fails the in-flight call; the next call respawns the server,
+    // whose fresh tool list replaces the stale proxies
+    assert!(registry.execute(&call("stub__crash", json!({}))).await.is_err());
+    let echoed = registry.execute(&call("stub__echo", json!({ "text": "back" }))).await.unwrap();
+    assert_eq!(echoed.summary, "back");
+    eventually("the stale tool to be removed", || async { !has_tool(&registry, "stub__extra").await }).await;
+}
+
+#[derive(Default)]
+struct StubState {
+    session: String,
+    initializations: usize,
+    tools: Vec<&'static str>,
+    notifier: Option<mpsc::UnboundedSender<Value>>,
+}
+
+type Stub = Arc<Mutex<StubState>>;
+
+async fn stub_post(State(stub): State<Stub>, headers: HeaderMap, Json(message): Json<Value>) -> Response {
+    let method = message["method"].as_str().unwrap_or_default().to_string();
+    let Some(id) = message.get("id").cloned() else {
+        return StatusCode::ACCEPTED.into_response(); // notification
+    };
+    if method.is_empty() {
+        return StatusCode::ACCEPTED.into_response(); // reply to our ping
+    }
+
+    let mut state = stub.lock().unwrap();
+    if method == "initialize" {
+        state.initializations += 1;
+        state.session = format!("session-{}", state.initializations);
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": {
+                "protocolVersion": "2025-03-26",
+                "capabilities": { "tools": { "listChanged": true } },
+                "serverInfo": { "name": "http-stub", "version": "0.0.0" }
+            }
+        });
+        return ([("mcp-session-id", state.session.clone())], Json(body)).into_response();
+    }
+    if headers.get("mcp-session-id").and_then(|v| v.to_str().ok()) != Some(state.session.as_str()) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let result = match method.as_str() {
+        "tools/list" => json!({
+            "tools": state.tools.iter().map(|n| json!({ "name": n, "inputSchema": { "type": "object" } })).collect::<Vec<_>>()
+        }),
+        "tools/call" => json!({
+            "content": [{ "type": "text", "text": format!("echo: {}", message["params"]["arguments"]["text"].as_str().unwrap_or_default()) }]
+        }),
+        _ => {
+            return Json(json!({ "jsonrpc": "2.0", "id": id, "error": { "code": -32601, "message": "unknown method" } }))
+                .into_response()
+        }
+    };
+    // Answer as an SSE stream, after a server request the client must handle
+    let body = format!(
+        "event: message\ndata: {}\n\nevent: message\ndata: {}\n\n",
+        json!({ "jsonrpc": "2.0", "id": "srv-1", "method": "ping" }),
+        json!({ "jsonrpc": "2.0", "id": id, "result": result })
+    );
+    ([(header::CONTENT_TYPE, "text/event-stream")], body).into_response()
+}
+
+async fn stub_get(State(stub): State<Stub>) -> Response {
+    let (tx, rx) = mpsc::unbounded_channel::<Value>();
+    stub.lock().unwrap().notifier = Some(tx);
+    let events = UnboundedReceiverStream::new(rx).map(|m| Ok::<_, Infallible>(format!("data: {}\n\n", m)));
+    ([(header::CONTENT_TYPE, "text/event-stream")], Body::from_stream(events)).into_response()
+}
+
+#[tokio::test]
+async fn test_streamable_http_server() {
+    let stub: Stub = Arc::new(Mutex::new(StubState { tools: vec!["echo"], ..Default::default() }));
+    let app = Router::new().route("/mcp", post(stub_post).get(stub_get)).with_state(stub.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let server = McpServer::connect(&McpServerConfig::http("web", &url)).await.unwrap();
+    let registry = ToolRegistry::default();
+    assert_eq!(registry.register_mcp_server(server).await.unwrap(), 1);
+
+    let echoed = registry.execute(&call("web__echo", json!({ "text": "over http" }))).await.unwrap();
+    assert_eq!(echoed.summary, "echo: over http");
+
+    // Notifications arrive on the GET stream
+    eventually("the notification stream", || async { stub.lock().unwrap().notifier.is_some() }).await;
+    {
+        let mut state = stub.lock().unwrap();
+        state.tools.push("later");
+        let notifier = state.notifier.as_ref().unwrap();
+        notifier.send(json!({ "jsonrpc": "2.0", "method": "notifications/tools/list_changed" })).unwrap();
+    }
+    eventually("the new tool to be registered", || has_tool(&registry, "web__later")).await;
+
+    // An expired session is re-established transparently
+    stub.lock().unwrap().session = "expired".to_string();
+    let echoed = registry.execute(&call("web__echo", json!({ "text": "again" }))).await.unwrap();
+    assert_eq!(echoed.summary, "echo: again");
+    assert_eq!(stub.lock().unwrap().initializations, 2);
+}
@@ -0,0 +1,212 @@
+//! `web_search` backends and `web_fetch` against a local HTTP fixture server.
+
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use rust_agency::tools::{
+    BraveBackend, DuckDuckGoBackend, SearchBackend, SearxngBackend, TavilyBackend, Tool, ToolCall, ToolRegistry,
+    WebFetchTool, WebSearchTool,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+const ARTICLE: &str = r#"<!doctype html>
+<html><head><title>Release Notes</title><style>body { color: red }</style></head>
+<body>
+  <header><a href="/">Home</a> | <a href="/blog">Blog</a></header>
+  <main>
+    <h2>Version 2.0</h2>
+    <p>The <strong>new</strong> scheduler is described in the
+       <a href="/docs/scheduler">scheduler guide</a> and on
+       <a href="https://example.org/rfc">the RFC page</a>.</p>
+    <ol><li>Faster startup</li><li>Smaller binaries</li></ol>
+  </main>
+  <footer>© fixtures</footer>
+</body></html>"#;
+
+type Hits = Arc<AtomicUsize>;
+
+async fn article(State(hits): State<Hits>) -> Html<&'static str> {
+    hits.fetch_add(1, Ordering::SeqCst);
+    Html(ARTICLE)
+}
+
+async fn searxng(Query(query): Query<HashMap<String, String>>) -> Response {
+    if query.get("format").map(String::as_str) != Some("json") {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    let q = query.get("q").cloned().unwrap_or_default();
+    Json(json!({
+        "query": q,
+        "results": [
+            { "title": format!("{} handbook", q), "url": "https://docs.example.com/handbook", "content": "  The handbook.  " },
+            { "title": "Second", "url": "https://docs.example.com/second", "content": "Another page" },
+            { "title": "No URL" }
+        ]
+    }))
+    .into_response()
+}
+
+async fn brave(headers: HeaderMap, Query(query): Query<HashMap<String, String>>) -> Response {
+    if headers.get("x-subscription-token").and_then(|v| v.to_str().ok()) != Some("brave-key") {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let count: usize = query.get("count").and_then(|c| c.parse().ok()).unwrap_or(20);
+    let results: Vec<Value> = (1..=5)
+        .map(|i| json!({ "title": format!("Brave {}", i), "url": format!("https://brave.example/{}", i), "description": "From Brave" }))
+        .take(count)
+        .collect();
+    Json(json!({ "web": { "results": results } })).into_response()
+}
+
+async fn tavily(Json(body): Json<Value>) -> Json<Value> {
+    Json(json!({
+        "query": body["query"],
+        "results": [{ "title": "Tavily hit", "url": "https://tavily.example/a", "content": format!("max {}", body["max_results"]) }]
+    }))
+}
+
+async fn ddg(Query(query): Query<HashMap<String, String>>) -> Html<String> {
+    let q = query.get("q").cloned().unwrap_or_default();
+    if q == "nothing" {
+        return Html("<html><body><div class=\"no-results\">No results.</div></body></html>".to_string());
+    }
+    Html(format!(
+        r#"<div class="result"><a class="result__a" href="https://a.example">First &amp; best {q}</a>
+        <a class="result__url" href="https://a.example"> a.example </a>
+        <a class="result__snippet" href="https://a.example">Snippet one</a></div>"#
+    ))
+}
+
+async fn serve() -> (String, Hits) {
+    let hits = Hits::default();
+    let app = Router::new()
+        .route("/robots.txt", get(|| async { "User-agent: *\nDisallow: /private\n" }))
+        .route("/article", get(article))
+        .route("/private/page", get(|| async { Html("<p>secret</p>") }))
+        .route("/notes.txt", get(|| async { "plain notes" }))
+        .route("/big", get(|| async { Html(format!("<p>{}</p>", "word ".repeat(20_000))) }))
+        .route("/binary", get(|| async { ([(header::CONTENT_TYPE, "application/octet-stream")], vec![0u8; 16]) }))
+        .route("/search", get(searxng))
+        .route("/brave", get(brave))
+        .route("/tavily", post(tavily))
+        .route("/ddg", get(ddg))
+        .with_state(hits.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (base, hits)
+}
+
+#[tokio::test]
+async fn test_search_backends() {
+    let (base, _) = serve().await;
+
+    let searxng = SearxngBackend::new(format!("{}/", base)).search("rust", 5).await.unwrap();
+    assert_eq!(searxng.len(), 2, "results without a URL are skipped");
+    assert_eq!(searxng[0].title, "rust handbook");
+    assert_eq!(searxng[0].snippet, "The handbook.");
+
+    let brave = BraveBackend::new("brave-key").with_base_url(format!("{}/brave", base));
+    assert_eq!(brave.search("rust", 3).await.unwrap().len(), 3);
+    assert!(BraveBackend::new("wrong").with_base_url(format!("{}/brave", base)).search("rust", 3).await.is_err());
+
+    let tavily = TavilyBackend::new("t-key").with_base_url(format!("{}/tavily", base)).search("rust", 4).await.unwrap();
+    assert_eq!(tavily[0].snippet, "max 4");
+
+    let ddg = DuckDuckGoBackend::new().with_base_url(format!("{}/ddg", base));
+    let results = ddg.search("rust", 5).await.unwrap();
+    assert_eq!(results[0].title, "First & best rust");
+    assert_eq!(results[0].url, "https://a.example/");
+    assert!(ddg.search("nothing", 5).await.unwrap().is_empty(), "no placeholder results");
+}
+
+#[tokio::test]
+async fn test_web_search_falls_back_between_backends() {
+    let (base, _) = serve().await;
+    let broken: Arc<dyn SearchBackend> = Arc::new(SearxngBackend::new(format!("{}/missing", base)));
+    let brave: Arc<dyn SearchBackend> = Arc::new(BraveBackend::new("brave-key").with_base_url(format!("{}/brave", base)));
+    let ddg: Arc<dyn SearchBackend> = Arc::new(DuckDuckGoBackend::new().with_base_url(format!("{}/ddg", base)));
+
+    let tool = WebSearchTool::new().with_backends(vec![broken.clone(), brave]);
+    let output = tool.execute(json!({ "query": "rust", "max_results": 2 })).await.unwrap();
+    assert!(output.success);
+    assert_eq!(output.data["backend"], "brave");
+    assert_eq!(output.data["num_results"], 2);
+
+    let empty = WebSearchTool::new().with_backends(vec![ddg]);
+    let output = empty.execute(json!({ "query": "nothing" })).await.unwrap();
+    assert!(output.success);
+    assert_eq!(output.data["num_results"], 0);
+
+    let failing = WebSearchTool::new().with_backends(vec![broken]);
+    assert!(!failing.execute(json!({ "query": "rust" })).await.unwrap().success);
+}
+
+#[tokio::test]
+async fn test_web_fetch_extracts_readable_content() {
+    let (base, hits) = serve().await;
+    let tool = WebFetchTool::new().with_allow_internal(true);
+
+    let page = tool.execute(json!({ "url": format!("{}/article", base) })).await.unwrap();
+    assert!(page.success, "{}", page.summary);
+    assert_eq!(page.data["title"], "Release Notes");
+    let content = page.data["content"].as_str().unwrap();
+    assert!(content.starts_with("## Version 2.0"), "{}", content);
+    assert!(content.contains(&format!("[scheduler guide]({}/docs/scheduler)", base)), "{}", content);
+    assert!(content.contains("- Faster startup\n- Smaller binaries"), "{}", content);
+    assert!(!content.contains("Home") && !content.contains("color"), "chrome and styles are dropped: {}", content);
+    let links: Vec<&str> = page.data["links"].as_array().unwrap().iter().map(|l| l["url"].as_str().unwrap()).collect();
+    assert_eq!(links, vec![format!("{}/docs/scheduler", base).as_str(), "https://example.org/rfc"]);
+    assert!(page.summary.contains("Links:\n1. [scheduler guide]"));
+
+    let text = tool.execute(json!({ "url": format!("{}/article", base), "format": "text" })).await.unwrap();
+    assert!(text.data["content"].as_str().unwrap().starts_with("Version 2.0\n\nThe new scheduler is described in the scheduler guide"));
+
+    let plain = tool.execute(json!({ "url": format!("{}/notes.txt", base) })).await.unwrap();
+    assert_eq!(plain.data["content"], "plain notes");
+
+    let binary = tool.execute(json!({ "url": format!("{}/binary", base) })).await.unwrap();
+    assert!(!binary.success);
+    assert!(!tool.execute(json!({ "url": "file:///etc/passwd" })).await.unwrap().success);
+
+    // Results are cached by the registry
+    let registry = ToolRegistry::default();
+    registry.register_instance(WebFetchTool::new().with_allow_internal(true)).await;
+    let before = hits.load(Ordering::SeqCst);
+    let call = ToolCall { name: "web_fetch".to_string(), parameters: json!({ "url": format!("{}/article", base) }) };
+    registry.execute(&call).await.unwrap();
+    let cached = registry.execute(&call).await.unwrap();
+    assert!(cached.cache_age_secs.is_some());
+    assert_eq!(hits.load(Ordering::SeqCst), before + 1);
+}
+
+#[tokio::test]
+async fn test_web_fetch_honors_robots_and_size_limits() {
+    let (base, _) = serve().await;
+    let tool = WebFetchTool::new().with_allow_internal(true).with_max_bytes(4096);
+
+    let blocked = tool.execute(json!({ "url": format!("{}/private/page", base) })).await.unwrap();
+    assert!(!blocked.success);
+    assert!(blocked.summary.contains("robots.txt"));
+
+    let big = tool.execute(json!({ "url": format!("{}/big", base), "max_chars": 1000 })).await.unwrap();
+    assert!(big.success);
+    assert_eq!(big.data["truncated"], true);
+    assert_eq!(big.data["bytes"], 4096);
+    assert_eq!(big.data["content"].as_str().unwrap().chars().count(), 1000);
+}
+
+#[tokio::test]
+async fn test_web_fetch_refuses_loopback_by_default() {
+    let (base, hits) = serve().await;
+
+    let refused = WebFetchTool::new().execute(json!({ "url": format!("{}/article", base) })).await.unwrap();
+    assert!(!refused.success);
+    assert!(refused.summary.contains("internal address"), "{}", refused.summary);
+    assert_eq!(hits.load(Ordering::SeqCst), 0, "the fixture server was never contacted");
+}